@@ -0,0 +1,297 @@
+//! What the commands report, and how.
+//!
+//! Every problem a command finds is a [`Diagnostic`]. In the human format
+//! each is a line in the `file:line:column: severity: message` shape that
+//! editors and CI log matchers pick up; in the JSON format the whole
+//! [`Report`] is one document, for tools that annotate pull requests.
+
+use std::io::{self, Write};
+
+use concerto_core::ConcertoError;
+use concerto_core::compare::{Change, Severity as Bump};
+use concerto_cto::CtoError;
+use serde::Serialize;
+
+/// How the process exits. Each class of failure has its own code, so that a
+/// script can tell them apart; when a run finds several, the highest wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Exit {
+    /// Everything checked out.
+    #[default]
+    Ok = 0,
+    /// An instance does not conform to its type.
+    InvalidInstance = 1,
+    /// The command line is wrong. clap exits with this itself.
+    Usage = 2,
+    /// A file could not be read or written, or is not JSON.
+    Io = 3,
+    /// A CTO file does not parse.
+    Syntax = 4,
+    /// A model is malformed, or clashes with another.
+    IllegalModel = 5,
+    /// The models do not hang together: a type or namespace is missing, or
+    /// a semantic rule is broken.
+    ValidationFailed = 6,
+    /// A diff found a change that needs a major version, and the new
+    /// version's namespace is not one.
+    Breaking = 7,
+    /// A file is not formatted, and `fmt --check` was asked.
+    Unformatted = 8,
+}
+
+impl Exit {
+    /// The process exit code.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+/// How bad a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The run fails.
+    Error,
+    /// Worth a look; the run still passes.
+    Warning,
+    /// For information.
+    Note,
+}
+
+/// One problem, or one change a diff found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// How bad it is.
+    pub severity: Severity,
+    /// What it is, in kebab-case, such as `syntax` or `invalid-instance`.
+    pub kind: String,
+    /// A description, for people.
+    pub message: String,
+    /// The file it is in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// The line it is on, counting from 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// The column it is at, counting from 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    /// For an instance, a JSON Pointer to the offending value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// How the process exits because of it.
+    #[serde(skip)]
+    pub exit: Exit,
+}
+
+impl Diagnostic {
+    /// An error of `kind`, in no particular file.
+    pub fn error(kind: &str, exit: Exit, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            kind: kind.to_string(),
+            message: message.into(),
+            file: None,
+            line: None,
+            column: None,
+            path: None,
+            exit,
+        }
+    }
+
+    /// A file that could not be read or written.
+    pub fn io(file: &str, error: &io::Error) -> Self {
+        Self::error("io", Exit::Io, error.to_string()).in_file(file)
+    }
+
+    /// A file that is not JSON.
+    pub fn json(file: &str, error: &serde_json::Error) -> Self {
+        let mut diagnostic =
+            Self::error("json", Exit::Io, format!("invalid JSON: {error}")).in_file(file);
+        if error.line() > 0 {
+            diagnostic = diagnostic.at(error.line(), error.column());
+        }
+        diagnostic
+    }
+
+    /// A CTO file that does not parse, or a model that cannot be printed.
+    pub fn cto(file: &str, error: &CtoError) -> Self {
+        let mut diagnostic = match error {
+            CtoError::Syntax { message, .. } => {
+                Self::error("syntax", Exit::Syntax, message.clone())
+            }
+            CtoError::Unprintable { .. } => {
+                Self::error("illegal-model", Exit::IllegalModel, error.to_string())
+            }
+        };
+        if let Some(position) = error.position() {
+            diagnostic = diagnostic.at(position.line, position.column);
+        }
+        diagnostic.in_file(file)
+    }
+
+    /// An error from the core.
+    pub fn concerto(error: &ConcertoError) -> Self {
+        let message = error.to_string();
+        match error {
+            ConcertoError::TypeNotFound { .. } => {
+                Self::error("type-not-found", Exit::ValidationFailed, message)
+            }
+            ConcertoError::NamespaceNotFound { .. } => {
+                Self::error("namespace-not-found", Exit::ValidationFailed, message)
+            }
+            ConcertoError::ValidationFailed { .. } => {
+                Self::error("validation-failed", Exit::ValidationFailed, message)
+            }
+            ConcertoError::IllegalModel { file_name, .. } => {
+                let mut diagnostic = Self::error("illegal-model", Exit::IllegalModel, message);
+                diagnostic.file = file_name.clone();
+                diagnostic
+            }
+            // The pointer is kept apart from the message, so that a record's
+            // position can be put in front of it.
+            ConcertoError::InvalidInstance { path, message, .. } => {
+                let mut diagnostic =
+                    Self::error("invalid-instance", Exit::InvalidInstance, message.clone());
+                diagnostic.path = Some(path.clone());
+                diagnostic
+            }
+        }
+    }
+
+    /// A change a diff found, in a new version whose namespace makes the
+    /// `declared` bump. Only a major change fails the run, and only if the
+    /// new version is not a major one.
+    pub fn change(file: &str, change: &Change, declared: Option<Bump>) -> Self {
+        let (severity, exit) = match change.severity {
+            Bump::Major if declared < Some(Bump::Major) => (Severity::Error, Exit::Breaking),
+            Bump::Major | Bump::Minor => (Severity::Warning, Exit::Ok),
+            Bump::Patch => (Severity::Note, Exit::Ok),
+        };
+        Self {
+            severity,
+            kind: kebab_case(&format!("{:?}", change.kind)),
+            message: format!("{} ({})", change.message, change.severity),
+            file: Some(file.to_string()),
+            line: None,
+            column: None,
+            path: None,
+            exit,
+        }
+    }
+
+    /// The same diagnostic, in `file`.
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    /// The same diagnostic, at `line` and `column` of its file.
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.line = u32::try_from(line).ok();
+        self.column = u32::try_from(column).ok();
+        self
+    }
+
+    /// The same diagnostic, about the record at `record` in its file: the
+    /// record's index goes in front of its JSON Pointer.
+    pub fn at_record(mut self, record: usize) -> Self {
+        let path = self.path.take().unwrap_or_default();
+        self.path = Some(format!("/{record}{path}"));
+        self
+    }
+}
+
+/// The outcome of a command.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// Whether the run passed.
+    ok: bool,
+    /// One line saying what was checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    /// What was found.
+    diagnostics: Vec<Diagnostic>,
+    #[serde(skip)]
+    exit: Exit,
+}
+
+impl Report {
+    /// Adds a diagnostic.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.exit = self.exit.max(diagnostic.exit);
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Sets the summary.
+    pub fn summarize(&mut self, summary: impl Into<String>) {
+        self.summary = Some(summary.into());
+    }
+
+    /// How the process exits.
+    pub fn exit(&self) -> Exit {
+        self.exit
+    }
+
+    /// Writes the report in `format`.
+    pub fn write(&mut self, format: Format, out: &mut dyn Write) -> io::Result<()> {
+        self.ok = self.exit == Exit::Ok;
+        match format {
+            Format::Json => {
+                serde_json::to_writer_pretty(&mut *out, self)?;
+                writeln!(out)
+            }
+            Format::Human => {
+                for diagnostic in &self.diagnostics {
+                    writeln!(out, "{}", human(diagnostic))?;
+                }
+                if let Some(summary) = &self.summary {
+                    writeln!(out, "{summary}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The formats a report is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Format {
+    /// A line per diagnostic.
+    #[default]
+    Human,
+    /// One JSON document.
+    Json,
+}
+
+fn human(diagnostic: &Diagnostic) -> String {
+    let mut line = String::new();
+    if let Some(file) = &diagnostic.file {
+        line.push_str(file);
+        if let (Some(row), Some(column)) = (diagnostic.line, diagnostic.column) {
+            line.push_str(&format!(":{row}:{column}"));
+        }
+        line.push_str(": ");
+    }
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+    };
+    line.push_str(&format!("{severity}: {}", diagnostic.message));
+    if let Some(path) = diagnostic.path.as_deref().filter(|path| !path.is_empty()) {
+        line.push_str(&format!(" [at {path}]"));
+    }
+    line
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && !kebab.is_empty() {
+            kebab.push('-');
+        }
+        kebab.push(c.to_ascii_lowercase());
+    }
+    kebab
+}
@@ -0,0 +1,816 @@
+//! # concerto-cli
+//!
+//! The `concerto` command, a single binary for build agents that checks and
+//! converts Concerto models:
+//!
+//! ```text
+//! concerto validate models/                          # every .cto and .json model below
+//! concerto instance -m models/ -t org.acme@1.0.0.Order orders.jsonl
+//! concerto parse person.cto > person.json
+//! concerto print person.json > person.cto
+//! concerto compile models/ --target jsonschema --root org.acme@1.0.0.Order
+//! concerto compile models/ --target typescript --output src/models/
+//! concerto compile models/ --target openapi --paths > openapi.json
+//! concerto compile models/ --target protobuf --output proto/
+//! concerto compile models/ --target avro --output schemas/
+//! concerto diff v1/person.cto v2/person.cto
+//! concerto fmt --check models/
+//! ```
+//!
+//! `validate`, `instance`, `diff` and `fmt` report what they find on standard
+//! output. `parse`, `print` and `compile` write what they produce there, or to
+//! `--output`, and report any problem on standard error. With
+//! `--format json` the report is one JSON document, with a diagnostic per
+//! problem giving its file, line and column, or its JSON Pointer within an
+//! instance; see [`diagnostic`]. The exit code tells the classes of failure
+//! apart; see [`Exit`].
+
+pub mod diagnostic;
+mod load;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use concerto_codegen::GeneratedFile;
+use concerto_codegen::avro::{self, AvroOptions};
+use concerto_codegen::json_schema::{self, JsonSchemaOptions};
+use concerto_codegen::openapi::{self, OpenApiOptions};
+use concerto_codegen::protobuf::{self, FieldNumbers, ProtobufOptions};
+use concerto_codegen::typescript::{self, TypeScriptOptions};
+use concerto_core::compare::{Severity as Bump, compare};
+use concerto_core::{CompiledSchema, StreamOptions, StreamValidator};
+use concerto_cto::ParseOptions;
+
+pub use diagnostic::{Diagnostic, Exit, Format, Report};
+use load::{Records, cto_paths, load_models, open_instances, read_model, read_model_file};
+
+/// Validate, convert and compare Concerto models.
+#[derive(Debug, Parser)]
+#[command(name = "concerto", version)]
+pub struct Cli {
+    /// How to write the report.
+    #[arg(long, value_enum, default_value_t = Format::Human, global = true)]
+    pub format: Format,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// What to do.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Check that models are well formed and consistent with each other.
+    Validate {
+        /// Model files, in CTO or as JSON ASTs, or directories holding them.
+        #[arg(required = true)]
+        models: Vec<PathBuf>,
+    },
+
+    /// Check instances against the models.
+    Instance {
+        /// Model files or directories.
+        #[arg(long = "model", short, required = true)]
+        models: Vec<PathBuf>,
+        /// The type every instance is declared as. Without it, each instance
+        /// is checked against the type its `$class` names.
+        #[arg(long = "type", short = 't')]
+        type_name: Option<String>,
+        /// Instance files: a JSON instance, a JSON array of instances, or
+        /// JSON lines (`.jsonl` or `.ndjson`).
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Parse a CTO file into its JSON AST.
+    Parse {
+        /// The CTO file.
+        file: PathBuf,
+        /// Leave out where each construct is in the source.
+        #[arg(long)]
+        no_locations: bool,
+        /// Where to write the AST, instead of standard output.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Print a model's JSON AST as CTO.
+    Print {
+        /// The JSON AST.
+        file: PathBuf,
+        /// Where to write the CTO, instead of standard output.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Generate a schema or types from the models.
+    Compile {
+        /// Model files or directories.
+        #[arg(required = true)]
+        models: Vec<PathBuf>,
+        /// What to generate.
+        #[arg(long, short, value_enum)]
+        target: Target,
+        #[command(flatten)]
+        options: CompileOptions,
+        /// The directory to write the files to. A target that generates a
+        /// single file writes it to standard output without one.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Compare two versions of a model, and fail if a breaking change comes
+    /// without a new major version.
+    Diff {
+        /// The old version.
+        old: PathBuf,
+        /// The new version.
+        new: PathBuf,
+    },
+
+    /// Rewrite CTO files in the canonical layout, keeping their comments.
+    Fmt {
+        /// CTO files, or directories holding them.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Change nothing, and fail if a file is not formatted.
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+/// What `compile` generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Target {
+    /// A JSON Schema, draft 2020-12.
+    #[value(name = "jsonschema")]
+    JsonSchema,
+    /// TypeScript types, a module per namespace.
+    #[value(name = "typescript")]
+    TypeScript,
+    /// OpenAPI 3.1 components, and optionally REST paths.
+    #[value(name = "openapi")]
+    OpenApi,
+    /// proto3 files, a package per namespace, and the lock file of their
+    /// field numbers, which is read back from the output directory.
+    #[value(name = "protobuf")]
+    Protobuf,
+    /// Avro schemas, one per class.
+    #[value(name = "avro")]
+    Avro,
+}
+
+/// The options of the `compile` targets, each used by the targets it names.
+#[derive(Debug, Clone, Args)]
+pub struct CompileOptions {
+    /// jsonschema, avro: the type the schema is for, fully qualified.
+    #[arg(long)]
+    root: Option<String>,
+    /// typescript: the type of DateTime fields.
+    #[arg(long, value_enum, default_value = "string")]
+    date_time: DateTimeType,
+    /// openapi: a namespace to include, with what it refers to; all of
+    /// them without one.
+    #[arg(long = "namespace")]
+    namespaces: Vec<String>,
+    /// openapi: add paths to list, create, get, replace and delete the
+    /// assets and participants identified by a field.
+    #[arg(long)]
+    paths: bool,
+    /// openapi: the title of the API.
+    #[arg(long)]
+    title: Option<String>,
+    /// openapi: the version of the API.
+    #[arg(long)]
+    api_version: Option<String>,
+}
+
+/// How `compile --target typescript` types a DateTime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DateTimeType {
+    /// The ISO 8601 string of the JSON instance.
+    String,
+    /// A `Date`.
+    Date,
+}
+
+/// Runs a command, writing its output to `out` and any problems with it to
+/// `err`.
+pub fn run(cli: &Cli, out: &mut dyn Write, err: &mut dyn Write) -> Exit {
+    let mut report = Report::default();
+    let result = match &cli.command {
+        Command::Validate { models } => validate(models, &mut report),
+        Command::Instance {
+            models,
+            type_name,
+            files,
+        } => instance(models, type_name.as_deref(), files, &mut report),
+        Command::Parse {
+            file,
+            no_locations,
+            output,
+        } => parse(file, !no_locations, output.as_deref(), out),
+        Command::Print { file, output } => print(file, output.as_deref(), out),
+        Command::Compile {
+            models,
+            target,
+            options,
+            output,
+        } => compile(models, *target, options, output.as_deref(), out),
+        Command::Diff { old, new } => diff(old, new, &mut report),
+        Command::Fmt { files, check } => fmt(files, *check, &mut report),
+    };
+    if let Err(diagnostic) = result {
+        report.push(diagnostic);
+    }
+    let reports_to_out = matches!(
+        cli.command,
+        Command::Validate { .. }
+            | Command::Instance { .. }
+            | Command::Diff { .. }
+            | Command::Fmt { .. }
+    );
+    let written = match reports_to_out {
+        true => report.write(cli.format, out),
+        false if report.exit() != Exit::Ok => report.write(cli.format, err),
+        false => Ok(()),
+    };
+    match written {
+        Ok(()) => report.exit(),
+        Err(_) => Exit::Io,
+    }
+}
+
+fn validate(models: &[PathBuf], report: &mut Report) -> Result<(), Diagnostic> {
+    let (_, asts) = load_models(models)?;
+    report.summarize(match asts.len() {
+        1 => "1 model is valid".to_string(),
+        count => format!("{count} models are valid"),
+    });
+    Ok(())
+}
+
+fn instance(
+    models: &[PathBuf],
+    type_name: Option<&str>,
+    files: &[PathBuf],
+    report: &mut Report,
+) -> Result<(), Diagnostic> {
+    let (manager, _) = load_models(models)?;
+    if let Some(type_name) = type_name {
+        manager
+            .get_declaration(type_name)
+            .map_err(|error| Diagnostic::concerto(&error))?;
+    }
+    let schema = CompiledSchema::new(&manager).map_err(|error| Diagnostic::concerto(&error))?;
+    let options = StreamOptions {
+        record_type: type_name.map(str::to_string),
+        ..StreamOptions::default()
+    };
+    let validator = StreamValidator::with_options(&manager, options);
+    let (mut checked, mut invalid) = (0, 0);
+    for file in files {
+        let name = file.display().to_string();
+        let streamed = match open_instances(file) {
+            Ok(Records::One(value)) => {
+                checked += 1;
+                let result = match type_name {
+                    Some(type_name) => schema.validate_as(type_name, &value),
+                    None => schema.validate(&value),
+                };
+                if let Err(error) = result {
+                    invalid += 1;
+                    report.push(Diagnostic::concerto(&error).in_file(&name));
+                }
+                continue;
+            }
+            Ok(Records::Lines(reader)) => validator.validate_json_lines(reader),
+            Ok(Records::Array(reader)) => validator.validate_json_array(reader),
+            Err(diagnostic) => {
+                report.push(diagnostic);
+                continue;
+            }
+        };
+        let streamed = match streamed {
+            Ok(streamed) => streamed,
+            Err(error) => {
+                report.push(Diagnostic::io(&name, &error));
+                continue;
+            }
+        };
+        checked += streamed.records;
+        invalid += streamed.failures.len();
+        for failure in &streamed.failures {
+            let diagnostic = Diagnostic::concerto(&failure.error);
+            let diagnostic = match failure.line {
+                Some(line) => diagnostic.at(line, 1),
+                None => diagnostic.at_record(failure.record),
+            };
+            report.push(diagnostic.in_file(&name));
+        }
+    }
+    report.summarize(match checked {
+        1 => format!("1 instance checked, {invalid} invalid"),
+        checked => format!("{checked} instances checked, {invalid} invalid"),
+    });
+    Ok(())
+}
+
+fn parse(
+    file: &Path,
+    locations: bool,
+    output: Option<&Path>,
+    out: &mut dyn Write,
+) -> Result<(), Diagnostic> {
+    let name = file.display().to_string();
+    let source = fs::read_to_string(file).map_err(|error| Diagnostic::io(&name, &error))?;
+    let options = ParseOptions {
+        locations,
+        source_uri: locations.then(|| name.clone()),
+    };
+    let ast = concerto_cto::parse_with(&source, &options)
+        .map_err(|error| Diagnostic::cto(&name, &error))?;
+    let json = serde_json::to_string_pretty(&ast).expect("an AST serializes");
+    emit(&format!("{json}\n"), output, out)
+}
+
+fn print(file: &Path, output: Option<&Path>, out: &mut dyn Write) -> Result<(), Diagnostic> {
+    let name = file.display().to_string();
+    let ast = read_model(file)?;
+    let cto = concerto_cto::print(&ast).map_err(|error| Diagnostic::cto(&name, &error))?;
+    emit(&cto, output, out)
+}
+
+fn compile(
+    models: &[PathBuf],
+    target: Target,
+    options: &CompileOptions,
+    output: Option<&Path>,
+    out: &mut dyn Write,
+) -> Result<(), Diagnostic> {
+    let (manager, asts) = load_models(models)?;
+    let files = match target {
+        Target::JsonSchema => json_schema::generate(
+            &manager,
+            &JsonSchemaOptions {
+                root: options.root.clone(),
+            },
+        ),
+        Target::TypeScript => typescript::generate(
+            &manager,
+            &TypeScriptOptions {
+                date_time: match options.date_time {
+                    DateTimeType::String => typescript::DateTimeType::String,
+                    DateTimeType::Date => typescript::DateTimeType::Date,
+                },
+            },
+        ),
+        Target::OpenApi => {
+            let defaults = OpenApiOptions::default();
+            openapi::generate(
+                &manager,
+                &OpenApiOptions {
+                    title: options.title.clone().unwrap_or(defaults.title),
+                    version: options.api_version.clone().unwrap_or(defaults.version),
+                    namespaces: options.namespaces.clone(),
+                    paths: options.paths,
+                },
+            )
+        }
+        Target::Protobuf => {
+            let lock = match output.map(|directory| directory.join(protobuf::LOCK_FILE)) {
+                Some(path) if path.exists() => {
+                    let name = path.display().to_string();
+                    let source =
+                        fs::read_to_string(&path).map_err(|error| Diagnostic::io(&name, &error))?;
+                    serde_json::from_str(&source)
+                        .map_err(|error| Diagnostic::json(&name, &error))?
+                }
+                _ => FieldNumbers::new(),
+            };
+            let pinned = asts.iter().flat_map(protobuf::pinned_numbers).collect();
+            protobuf::generate(&manager, &ProtobufOptions { lock, pinned })
+        }
+        Target::Avro => avro::generate(
+            &manager,
+            &AvroOptions {
+                root: options.root.clone(),
+            },
+        ),
+    }
+    .map_err(|error| Diagnostic::concerto(&error))?;
+    match (output, files.as_slice()) {
+        (Some(directory), files) => write_files(directory, files),
+        (None, [file]) => emit(&file.contents, None, out),
+        (None, files) => Err(Diagnostic::error(
+            "usage",
+            Exit::Usage,
+            format!("the target generates {} files; give --output", files.len()),
+        )),
+    }
+}
+
+fn diff(old: &Path, new: &Path, report: &mut Report) -> Result<(), Diagnostic> {
+    let (before, after) = (read_model_file(old)?, read_model_file(new)?);
+    let comparison = compare(&before, &after);
+    let declared = Bump::between(before.version(), after.version());
+    let name = new.display().to_string();
+    for change in &comparison.changes {
+        report.push(Diagnostic::change(&name, change, declared));
+    }
+    let changes = match comparison.changes.len() {
+        1 => "1 change".to_string(),
+        count => format!("{count} changes"),
+    };
+    report.summarize(match comparison.severity() {
+        None => "no changes".to_string(),
+        Some(bump) if declared >= Some(bump) => {
+            format!("{changes}; the new version needs a {bump} version bump, and has one")
+        }
+        Some(bump) => format!("{changes}; the new version needs a {bump} version bump"),
+    });
+    Ok(())
+}
+
+fn fmt(files: &[PathBuf], check: bool, report: &mut Report) -> Result<(), Diagnostic> {
+    let paths = cto_paths(files)?;
+    let mut changed = 0;
+    for path in &paths {
+        let name = path.display().to_string();
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                report.push(Diagnostic::io(&name, &error));
+                continue;
+            }
+        };
+        let formatted = match concerto_cto::format(&source) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                report.push(Diagnostic::cto(&name, &error));
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        changed += 1;
+        if check {
+            report.push(
+                Diagnostic::error("unformatted", Exit::Unformatted, "not formatted").in_file(&name),
+            );
+        } else if let Err(error) = fs::write(path, formatted) {
+            report.push(Diagnostic::io(&name, &error));
+        }
+    }
+    report.summarize(match check {
+        true => format!("{changed} of {} files need formatting", paths.len()),
+        false => format!("{changed} of {} files formatted", paths.len()),
+    });
+    Ok(())
+}
+
+fn write_files(directory: &Path, files: &[GeneratedFile]) -> Result<(), Diagnostic> {
+    for file in files {
+        let path = directory.join(&file.path);
+        let name = path.display().to_string();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| Diagnostic::io(&name, &error))?;
+        }
+        fs::write(&path, &file.contents).map_err(|error| Diagnostic::io(&name, &error))?;
+    }
+    Ok(())
+}
+
+/// Writes `contents` to `output`, or to `out` if there is none.
+fn emit(contents: &str, output: Option<&Path>, out: &mut dyn Write) -> Result<(), Diagnostic> {
+    let written = match output {
+        Some(path) => fs::write(path, contents),
+        None => out.write_all(contents.as_bytes()),
+    };
+    written.map_err(|error: io::Error| {
+        let name = output.map_or("<stdout>".to_string(), |path| path.display().to_string());
+        Diagnostic::io(&name, &error)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use clap::Parser;
+    use serde_json::Value;
+
+    use super::{Cli, Exit, run};
+
+    const PERSON: &str = "namespace org.acme@1.0.0\n\nconcept Person {\n  o String name\n  o Integer age optional\n}\n";
+
+    /// A fresh directory holding `files`.
+    fn workspace(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("concerto-cli-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        for (name, contents) in files {
+            let path = directory.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        directory
+    }
+
+    /// Runs the command line, giving back the exit and what went to standard
+    /// output and standard error.
+    fn concerto(directory: &Path, args: &[&str]) -> (Exit, String, String) {
+        let args = args.iter().map(|arg| match arg.strip_prefix('@') {
+            Some(file) => directory.join(file).display().to_string(),
+            None => arg.to_string(),
+        });
+        let cli = Cli::try_parse_from(std::iter::once("concerto".to_string()).chain(args)).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let exit = run(&cli, &mut out, &mut err);
+        (
+            exit,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn validate_loads_cto_and_json_models_and_reports_syntax_errors() {
+        let directory = workspace(
+            "validate",
+            &[
+                ("models/person.cto", PERSON),
+                (
+                    "models/team.json",
+                    r#"{ "$class": "concerto.metamodel@1.0.0.Model", "namespace": "org.team@1.0.0",
+                         "imports": [{ "$class": "concerto.metamodel@1.0.0.ImportType", "name": "Person", "namespace": "org.acme@1.0.0" }],
+                         "declarations": [{ "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Team", "isAbstract": false,
+                           "properties": [{ "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "lead", "isArray": false, "isOptional": false,
+                             "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Person" } }] }] }"#,
+                ),
+                ("broken.cto", "namespace org.broken@1.0.0\nconcept {\n}\n"),
+                (
+                    "dangling.cto",
+                    "namespace org.dangling@1.0.0\nconcept A {\n  o Missing m\n}\n",
+                ),
+            ],
+        );
+        let (exit, out, _) = concerto(&directory, &["validate", "@models"]);
+        assert_eq!(exit, Exit::Ok);
+        assert_eq!(out, "2 models are valid\n");
+
+        let (exit, out, _) = concerto(&directory, &["validate", "--format", "json", "@broken.cto"]);
+        assert_eq!(exit, Exit::Syntax);
+        let report: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(report["ok"], false);
+        let diagnostic = &report["diagnostics"][0];
+        assert_eq!(diagnostic["kind"], "syntax");
+        assert_eq!(
+            (diagnostic["line"].as_u64(), diagnostic["column"].as_u64()),
+            (Some(2), Some(9))
+        );
+
+        let (exit, _, _) = concerto(&directory, &["validate", "@dangling.cto"]);
+        assert_eq!(exit, Exit::ValidationFailed);
+    }
+
+    #[test]
+    fn instance_reports_each_invalid_record() {
+        let directory = workspace(
+            "instance",
+            &[
+                ("person.cto", PERSON),
+                (
+                    "people.jsonl",
+                    "{\"$class\": \"org.acme@1.0.0.Person\", \"name\": \"Ada\"}\n\n{\"$class\": \"org.acme@1.0.0.Person\", \"name\": \"Bob\", \"age\": \"old\"}\n{\"name\": \n{\"$class\": \"org.acme@1.0.0.Person\", \"name\": \"Cy\"}\n",
+                ),
+                ("people.json", r#"[{ "name": "Cy" }, { "name": 7 }]"#),
+                ("one.json", r#"{ "name": "Di", "age": 1.5 }"#),
+            ],
+        );
+        let (exit, out, _) = concerto(
+            &directory,
+            &["instance", "-m", "@person.cto", "@people.jsonl"],
+        );
+        assert_eq!(exit, Exit::InvalidInstance);
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 3, "{out}");
+        assert!(
+            lines[0].contains("people.jsonl:3:1: error: ") && lines[0].ends_with(" [at /age]"),
+            "{}",
+            lines[0]
+        );
+        assert!(
+            lines[1].contains("people.jsonl:4:1: error: The record is not valid JSON"),
+            "{}",
+            lines[1]
+        );
+        assert_eq!(lines[2], "4 instances checked, 2 invalid");
+
+        let (exit, out, _) = concerto(
+            &directory,
+            &[
+                "--format",
+                "json",
+                "instance",
+                "-m",
+                "@person.cto",
+                "-t",
+                "org.acme@1.0.0.Person",
+                "@people.json",
+                "@one.json",
+            ],
+        );
+        assert_eq!(exit, Exit::InvalidInstance);
+        let report: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(report["diagnostics"][0]["path"], "/1/name");
+        assert!(
+            !report["diagnostics"][0]["message"]
+                .as_str()
+                .unwrap()
+                .contains("/name"),
+            "{out}"
+        );
+        assert_eq!(report["diagnostics"][1]["path"], "/age");
+        assert_eq!(report["summary"], "3 instances checked, 2 invalid");
+    }
+
+    #[test]
+    fn parse_print_and_compile_write_to_standard_output() {
+        let directory = workspace("convert", &[("person.cto", PERSON)]);
+        let (exit, ast, _) = concerto(&directory, &["parse", "--no-locations", "@person.cto"]);
+        assert_eq!(exit, Exit::Ok);
+        fs::write(directory.join("person.json"), &ast).unwrap();
+        let (exit, cto, _) = concerto(&directory, &["print", "@person.json"]);
+        assert_eq!(exit, Exit::Ok);
+        assert_eq!(cto, PERSON);
+
+        let (exit, schema, _) = concerto(
+            &directory,
+            &[
+                "compile",
+                "@person.json",
+                "--target",
+                "jsonschema",
+                "--root",
+                "org.acme@1.0.0.Person",
+            ],
+        );
+        assert_eq!(exit, Exit::Ok);
+        let schema: Value = serde_json::from_str(&schema).unwrap();
+        assert_eq!(schema["$ref"], "#/$defs/org.acme@1.0.0.Person");
+
+        let (exit, _, _) = concerto(
+            &directory,
+            &[
+                "compile",
+                "@person.cto",
+                "--target",
+                "typescript",
+                "--date-time",
+                "date",
+                "--output",
+                "@ts",
+            ],
+        );
+        assert_eq!(exit, Exit::Ok);
+        let module = fs::read_to_string(directory.join("ts/org.acme@1.0.0.ts")).unwrap();
+        assert!(module.starts_with("export interface Person {\n  $class: string;\n"));
+
+        let (exit, api, _) = concerto(
+            &directory,
+            &[
+                "compile",
+                "@person.cto",
+                "--target",
+                "openapi",
+                "--title",
+                "People",
+            ],
+        );
+        assert_eq!(exit, Exit::Ok);
+        let api: Value = serde_json::from_str(&api).unwrap();
+        assert_eq!(api["info"]["title"], "People");
+        assert!(api["components"]["schemas"]["org.acme-1.0.0.Person"].is_object());
+
+        let (exit, avro, _) = concerto(&directory, &["compile", "@person.cto", "--target", "avro"]);
+        assert_eq!(exit, Exit::Ok);
+        let avro: Value = serde_json::from_str(&avro).unwrap();
+        assert_eq!(avro["name"], "Person");
+        assert_eq!(avro["namespace"], "org.acme");
+
+        // The second run reads back the lock the first one wrote.
+        let protobuf = [
+            "compile",
+            "@person.cto",
+            "--target",
+            "protobuf",
+            "--output",
+            "@proto",
+        ];
+        assert_eq!(concerto(&directory, &protobuf).0, Exit::Ok);
+        fs::write(
+            directory.join("person.cto"),
+            PERSON.replace("o String name\n", ""),
+        )
+        .unwrap();
+        assert_eq!(concerto(&directory, &protobuf).0, Exit::Ok);
+        let proto = fs::read_to_string(directory.join("proto/org/acme/v1_0_0/acme.proto")).unwrap();
+        assert!(
+            proto.contains("  optional int32 age = 2;\n  reserved 1;\n"),
+            "{proto}"
+        );
+
+        let (exit, out, err) = concerto(&directory, &["print", "@missing.json"]);
+        assert_eq!(exit, Exit::Io);
+        assert!(out.is_empty());
+        assert!(err.contains("missing.json: error:"));
+    }
+
+    #[test]
+    fn diff_fails_on_a_breaking_change() {
+        let directory = workspace(
+            "diff",
+            &[
+                ("v1.cto", PERSON),
+                (
+                    "v2.cto",
+                    &PERSON.replace(
+                        "o Integer age optional",
+                        "o Integer age\n  o String email optional",
+                    ),
+                ),
+                (
+                    "v3.cto",
+                    &PERSON.replace("o String name", "o String name optional"),
+                ),
+                (
+                    "v4.cto",
+                    &PERSON
+                        .replace("@1.0.0", "@2.0.0")
+                        .replace("o Integer age optional", "o Integer age"),
+                ),
+            ],
+        );
+        let (exit, out, _) = concerto(&directory, &["diff", "@v1.cto", "@v2.cto"]);
+        assert_eq!(exit, Exit::Breaking);
+        assert!(
+            out.contains("error: org.acme@1.0.0.Person.age is now required (major)"),
+            "{out}"
+        );
+        assert!(out.ends_with("2 changes; the new version needs a major version bump\n"));
+
+        let (exit, out, _) = concerto(
+            &directory,
+            &["--format", "json", "diff", "@v1.cto", "@v3.cto"],
+        );
+        assert_eq!(exit, Exit::Ok);
+        let report: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            report["diagnostics"][0]["kind"],
+            "property-optionality-changed"
+        );
+        assert_eq!(report["diagnostics"][0]["severity"], "warning");
+
+        let (exit, out, _) = concerto(&directory, &["diff", "@v1.cto", "@v4.cto"]);
+        assert_eq!(exit, Exit::Ok);
+        assert!(out.contains("warning: "), "{out}");
+        assert!(
+            out.ends_with("1 change; the new version needs a major version bump, and has one\n"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn fmt_rewrites_files_and_check_fails_on_unformatted_ones() {
+        let messy =
+            "namespace org.acme@1.0.0\nconcept Person{o String name\n o Integer age optional}\n";
+        let tidy = "namespace org.acme@1.0.0\n\nconcept Person {\n  o String  name\n  o Integer age optional\n}\n";
+        let directory = workspace(
+            "fmt",
+            &[
+                ("models/person.cto", messy),
+                ("models/tidy.cto", &tidy.replace("org.acme", "org.tidy")),
+                ("models/notes.txt", "not a model"),
+            ],
+        );
+        let (exit, out, _) = concerto(&directory, &["fmt", "--check", "@models"]);
+        assert_eq!(exit, Exit::Unformatted);
+        assert!(out.contains("person.cto: error: not formatted"), "{out}");
+        assert!(out.ends_with("1 of 2 files need formatting\n"), "{out}");
+
+        let (exit, out, _) = concerto(&directory, &["fmt", "@models"]);
+        assert_eq!(exit, Exit::Ok);
+        assert_eq!(out, "1 of 2 files formatted\n");
+        assert_eq!(
+            fs::read_to_string(directory.join("models/person.cto")).unwrap(),
+            tidy
+        );
+
+        let (exit, _, _) = concerto(&directory, &["fmt", "--check", "@models"]);
+        assert_eq!(exit, Exit::Ok);
+    }
+}
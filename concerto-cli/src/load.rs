@@ -0,0 +1,153 @@
+//! Reading models and instances from disk.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use concerto_core::{ModelFile, ModelManager};
+use concerto_cto::ParseOptions;
+use serde_json::Value;
+
+use crate::diagnostic::Diagnostic;
+
+/// The model files under `paths`: each file as given, and the `.cto` and
+/// `.json` files anywhere below each directory, in name order.
+pub fn model_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Diagnostic> {
+    files_under(paths, &["cto", "json"])
+}
+
+/// The CTO files under `paths`: each file as given, and the `.cto` files
+/// anywhere below each directory, in name order.
+pub fn cto_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Diagnostic> {
+    files_under(paths, &["cto"])
+}
+
+fn files_under(paths: &[PathBuf], extensions: &[&str]) -> Result<Vec<PathBuf>, Diagnostic> {
+    let mut found = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, extensions, &mut found)?;
+        } else {
+            found.push(path.clone());
+        }
+    }
+    Ok(found)
+}
+
+fn walk(directory: &Path, extensions: &[&str], found: &mut Vec<PathBuf>) -> Result<(), Diagnostic> {
+    let name = directory.display().to_string();
+    let mut entries = fs::read_dir(directory)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|error| Diagnostic::io(&name, &error))?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            walk(&entry, extensions, found)?;
+        } else if extensions.contains(&extension(&entry)) {
+            found.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a model's JSON AST from a `.cto` file, which is parsed, or from any
+/// other file, which is read as JSON.
+pub fn read_model(path: &Path) -> Result<Value, Diagnostic> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path).map_err(|error| Diagnostic::io(&name, &error))?;
+    if extension(path) == "cto" {
+        let options = ParseOptions {
+            locations: true,
+            source_uri: Some(name.clone()),
+        };
+        concerto_cto::parse_with(&source, &options).map_err(|error| Diagnostic::cto(&name, &error))
+    } else {
+        serde_json::from_str(&source).map_err(|error| Diagnostic::json(&name, &error))
+    }
+}
+
+/// Reads a model into a [`ModelFile`], without loading it.
+pub fn read_model_file(path: &Path) -> Result<ModelFile, Diagnostic> {
+    let name = path.display().to_string();
+    let ast = read_model(path)?;
+    ModelFile::from_json(&ast, Some(name.clone()))
+        .map_err(|error| Diagnostic::concerto(&error).in_file(&name))
+}
+
+/// Loads the models under `paths` into a manager, and validates them.
+/// Returns the manager and the AST of each model loaded.
+pub fn load_models(paths: &[PathBuf]) -> Result<(ModelManager, Vec<Value>), Diagnostic> {
+    let mut manager = ModelManager::new().map_err(|error| Diagnostic::concerto(&error))?;
+    let mut asts = Vec::new();
+    for path in &model_paths(paths)? {
+        let name = path.display().to_string();
+        let ast = read_model(path)?;
+        manager
+            .add_model(&ast, Some(name.clone()))
+            .map_err(|error| Diagnostic::concerto(&error).in_file(&name))?;
+        asts.push(ast);
+    }
+    manager
+        .validate_models()
+        .map_err(|error| Diagnostic::concerto(&error))?;
+    Ok((manager, asts))
+}
+
+/// Opens an instance file: a `.jsonl` or `.ndjson` file is JSON lines, and
+/// any other file holds either a JSON array of instances or one instance.
+/// Lines and arrays are left to be read a record at a time; a single
+/// instance is read whole.
+pub fn open_instances(path: &Path) -> Result<Records, Diagnostic> {
+    let name = path.display().to_string();
+    let mut reader = File::open(path)
+        .map(BufReader::new)
+        .map_err(|error| Diagnostic::io(&name, &error))?;
+    if matches!(extension(path), "jsonl" | "ndjson") {
+        return Ok(Records::Lines(reader));
+    }
+    if first_byte(&mut reader).map_err(|error| Diagnostic::io(&name, &error))? == Some(b'[') {
+        return Ok(Records::Array(reader));
+    }
+    serde_json::from_reader(reader)
+        .map(Records::One)
+        .map_err(|error| Diagnostic::json(&name, &error))
+}
+
+/// The instances in a file.
+pub enum Records {
+    /// A JSON lines file.
+    Lines(BufReader<File>),
+    /// A JSON array of instances.
+    Array(BufReader<File>),
+    /// A single instance.
+    One(Value),
+}
+
+/// Skips leading whitespace, and looks at the byte after it without
+/// consuming it.
+fn first_byte(reader: &mut impl BufRead) -> io::Result<Option<u8>> {
+    loop {
+        let buffer = reader.fill_buf()?;
+        let Some(&byte) = buffer.first() else {
+            return Ok(None);
+        };
+        if !byte.is_ascii_whitespace() {
+            return Ok(Some(byte));
+        }
+        let blank = buffer
+            .iter()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+        reader.consume(blank);
+    }
+}
+
+fn extension(path: &Path) -> &str {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+}
@@ -0,0 +1,11 @@
+use std::io;
+use std::process::ExitCode;
+
+use clap::Parser;
+use concerto_cli::{Cli, run};
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let exit = run(&cli, &mut io::stdout().lock(), &mut io::stderr().lock());
+    ExitCode::from(exit.code())
+}
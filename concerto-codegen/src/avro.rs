@@ -0,0 +1,420 @@
+//! Apache Avro schemas (`.avsc`) for instances of the loaded models.
+//!
+//! Each concrete class gets a schema of its own, as a schema registry
+//! expects: a record, with the records, enums and maps it refers to defined
+//! in it where they are first used and named after that. A name is the
+//! declaration's, in the Avro namespace of its Concerto namespace without
+//! the version, `org.acme.Person`, so that a schema registry sees the class
+//! in the next version of the model as the next version of the same schema.
+//!
+//! A record has a field for each property the class declares or inherits,
+//! flattened as [`ModelManager::get_all_properties`] gives them. An optional
+//! property is a union with `null` that defaults to `null`, and an array an
+//! `array`. A property whose type has subtypes is a union of the records of
+//! its concrete subtypes. An enum is an `enum` of its values' names, a map a
+//! `map`, whose keys Avro always holds as strings, and a scalar its
+//! primitive's type. A DateTime is a `long` of logical type
+//! `timestamp-millis`, and a relationship the `string` that identifies what
+//! it points at.
+//!
+//! Avro has no validators, so those of a property, or of the scalar that is
+//! its type, are kept in a custom `validator` attribute of the field, with
+//! the keywords of JSON Schema: `pattern` and `flags`, `minLength` and
+//! `maxLength`, `minimum` and `maximum`.
+
+use std::collections::HashSet;
+
+use concerto_core::introspect::declaration::MapDeclaration;
+use concerto_core::model_util::{is_primitive_type, namespace_of, qualify, short_name};
+use concerto_core::{
+    ClassDeclaration, Declaration, ModelManager, Property, Result, ScalarDeclaration,
+};
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde_json::{Map, Value, json};
+
+use crate::GeneratedFile;
+use crate::model::{Field, concrete_subtypes, fields, resolve, user_models};
+
+/// What to generate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AvroOptions {
+    /// The fully-qualified name of the one type to generate a schema for.
+    /// With none, every concrete class gets one.
+    pub root: Option<String>,
+}
+
+/// Generates a schema for each type, `org.acme.Person.avsc` for
+/// `org.acme@1.0.0.Person`.
+pub fn generate(manager: &ModelManager, options: &AvroOptions) -> Result<Vec<GeneratedFile>> {
+    let roots = match &options.root {
+        Some(root) => {
+            manager.get_declaration(root)?;
+            vec![root.clone()]
+        }
+        None => user_models(manager)
+            .into_iter()
+            .flat_map(|model| {
+                model.declarations().iter().filter_map(|declaration| {
+                    let class = declaration.as_class()?;
+                    (!class.is_abstract()).then(|| qualify(model.namespace(), class.name()))
+                })
+            })
+            .collect(),
+    };
+    roots
+        .iter()
+        .map(|fqn| {
+            let schema = schema(manager, fqn)?;
+            Ok(GeneratedFile {
+                path: format!("{}.avsc", full_name(fqn)),
+                contents: format!(
+                    "{}\n",
+                    serde_json::to_string_pretty(&schema).expect("a schema serializes")
+                ),
+            })
+        })
+        .collect()
+}
+
+/// The schema of the type `fqn`, as JSON.
+pub fn schema(manager: &ModelManager, fqn: &str) -> Result<Value> {
+    let mut generator = Generator {
+        manager,
+        defined: HashSet::new(),
+    };
+    generator.declared(fqn)
+}
+
+/// `org.acme` for `org.acme@1.0.0`.
+fn avro_namespace(namespace: &str) -> &str {
+    namespace
+        .split_once('@')
+        .map_or(namespace, |(name, _)| name)
+}
+
+/// `org.acme.Person` for `org.acme@1.0.0.Person`.
+fn full_name(fqn: &str) -> String {
+    format!("{}.{}", avro_namespace(namespace_of(fqn)), short_name(fqn))
+}
+
+struct Generator<'m> {
+    manager: &'m ModelManager,
+    /// The names defined so far, which later uses refer to by name.
+    defined: HashSet<String>,
+}
+
+impl Generator<'_> {
+    /// The schema for a value declared as `fqn`: a class's record, or the
+    /// union of its subtypes', an enum, a map, or a scalar's primitive.
+    fn declared(&mut self, fqn: &str) -> Result<Value> {
+        if is_primitive_type(fqn) {
+            return Ok(primitive(fqn));
+        }
+        match self.manager.get_declaration(fqn)? {
+            Declaration::Class(class) => {
+                let subtypes = concrete_subtypes(self.manager, fqn)?;
+                match subtypes.as_slice() {
+                    // Nothing is an instance of it, but its record says what
+                    // one would hold.
+                    [] => self.record(fqn, class),
+                    [only] if only == fqn => self.record(fqn, class),
+                    subtypes => {
+                        let mut union = Vec::new();
+                        for subtype in subtypes {
+                            let Some(class) = self.manager.get_declaration(subtype)?.as_class()
+                            else {
+                                continue;
+                            };
+                            union.push(self.record(subtype, class)?);
+                        }
+                        Ok(Value::Array(union))
+                    }
+                }
+            }
+            Declaration::Enum(declaration) => Ok(self.enumeration(fqn, declaration)),
+            Declaration::Scalar(scalar) => Ok(primitive(scalar.scalar_type())),
+            Declaration::Map(map) => self.map(fqn, map),
+        }
+    }
+
+    fn record(&mut self, fqn: &str, class: &ClassDeclaration) -> Result<Value> {
+        let name = full_name(fqn);
+        if !self.defined.insert(name.clone()) {
+            return Ok(Value::String(name));
+        }
+        let mut record_fields = Vec::new();
+        for field in fields(self.manager, fqn)? {
+            record_fields.push(self.field(&field)?);
+        }
+        Ok(json!({
+            "type": "record",
+            "name": class.name(),
+            "namespace": avro_namespace(namespace_of(fqn)),
+            "fields": record_fields,
+        }))
+    }
+
+    fn field(&mut self, field: &Field<'_>) -> Result<Value> {
+        let property = field.property;
+        let (mut schema, validator) = match property {
+            Property::Object(p) => {
+                let fqn = resolve(self.manager, field.namespace, &p.type_)?;
+                let validator = match self.manager.get_declaration(&fqn) {
+                    Ok(Declaration::Scalar(scalar)) => scalar_validator(scalar),
+                    _ => None,
+                };
+                (self.declared(&fqn)?, validator)
+            }
+            Property::Relationship(_) | Property::Enum(_) => (primitive("String"), None),
+            Property::String(p) => (
+                primitive("String"),
+                validator(
+                    p.validator.as_ref(),
+                    p.length_validator.as_ref(),
+                    None,
+                    None,
+                ),
+            ),
+            Property::Integer(p) => (
+                primitive("Integer"),
+                range(p.validator.as_ref().map(|v| (v.lower, v.upper))),
+            ),
+            Property::Long(p) => (
+                primitive("Long"),
+                range(p.validator.as_ref().map(|v| (v.lower, v.upper))),
+            ),
+            Property::Double(p) => (
+                primitive("Double"),
+                range(p.validator.as_ref().map(|v| (v.lower, v.upper))),
+            ),
+            primitive_property => (
+                primitive(primitive_property.type_name().unwrap_or("String")),
+                None,
+            ),
+        };
+        if property.is_array() {
+            schema = json!({ "type": "array", "items": schema });
+        }
+        let mut avro_field = Map::new();
+        avro_field.insert("name".into(), property.name().into());
+        if property.is_optional() {
+            let mut union = vec![Value::from("null")];
+            match schema {
+                Value::Array(choices) => union.extend(choices),
+                schema => union.push(schema),
+            }
+            avro_field.insert("type".into(), Value::Array(union));
+            avro_field.insert("default".into(), Value::Null);
+        } else {
+            avro_field.insert("type".into(), schema);
+        }
+        if let Some(validator) = validator {
+            avro_field.insert("validator".into(), validator);
+        }
+        Ok(Value::Object(avro_field))
+    }
+
+    fn enumeration(&mut self, fqn: &str, declaration: &mm::EnumDeclaration) -> Value {
+        let name = full_name(fqn);
+        if !self.defined.insert(name.clone()) {
+            return Value::String(name);
+        }
+        json!({
+            "type": "enum",
+            "name": declaration.name,
+            "namespace": avro_namespace(namespace_of(fqn)),
+            "symbols": declaration
+                .properties
+                .iter()
+                .map(|member| member.name.as_str())
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn map(&mut self, fqn: &str, map: &MapDeclaration) -> Result<Value> {
+        let namespace = namespace_of(fqn);
+        let values = match (map.value_kind(), map.value_type()) {
+            ("RelationshipMapValueType", _) => primitive("String"),
+            (_, Some(declared)) => {
+                let declared = resolve(self.manager, namespace, declared)?;
+                self.declared(&declared)?
+            }
+            (kind, None) => primitive(kind.trim_end_matches("MapValueType")),
+        };
+        Ok(json!({ "type": "map", "values": values }))
+    }
+}
+
+fn primitive(primitive: &str) -> Value {
+    match primitive {
+        "Boolean" => json!("boolean"),
+        "Integer" => json!("int"),
+        "Long" => json!("long"),
+        "Double" => json!("double"),
+        "DateTime" => json!({ "type": "long", "logicalType": "timestamp-millis" }),
+        _ => json!("string"),
+    }
+}
+
+fn scalar_validator(scalar: &ScalarDeclaration) -> Option<Value> {
+    match scalar {
+        ScalarDeclaration::String(s) => validator(
+            s.validator.as_ref(),
+            s.length_validator.as_ref(),
+            None,
+            None,
+        ),
+        ScalarDeclaration::Integer(s) => range(s.validator.as_ref().map(|v| (v.lower, v.upper))),
+        ScalarDeclaration::Long(s) => range(s.validator.as_ref().map(|v| (v.lower, v.upper))),
+        ScalarDeclaration::Double(s) => range(s.validator.as_ref().map(|v| (v.lower, v.upper))),
+        ScalarDeclaration::Boolean(_) | ScalarDeclaration::DateTime(_) => None,
+    }
+}
+
+fn range<T: Into<Value>>(bounds: Option<(Option<T>, Option<T>)>) -> Option<Value> {
+    let (lower, upper) = bounds?;
+    validator(None, None, lower.map(Into::into), upper.map(Into::into))
+}
+
+/// The `validator` attribute of a field, if it has any validator.
+fn validator(
+    regex: Option<&mm::StringRegexValidator>,
+    length: Option<&mm::StringLengthValidator>,
+    lower: Option<Value>,
+    upper: Option<Value>,
+) -> Option<Value> {
+    let mut validator = Map::new();
+    if let Some(regex) = regex {
+        validator.insert("pattern".into(), regex.pattern.clone().into());
+        if !regex.flags.is_empty() {
+            validator.insert("flags".into(), regex.flags.clone().into());
+        }
+    }
+    if let Some(length) = length {
+        if let Some(min) = length.min_length {
+            validator.insert("minLength".into(), min.into());
+        }
+        if let Some(max) = length.max_length {
+            validator.insert("maxLength".into(), max.into());
+        }
+    }
+    if let Some(lower) = lower {
+        validator.insert("minimum".into(), lower);
+    }
+    if let Some(upper) = upper {
+        validator.insert("maximum".into(), upper);
+    }
+    (!validator.is_empty()).then_some(Value::Object(validator))
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::{ConcertoError, ModelManager};
+    use serde_json::json;
+
+    use super::{AvroOptions, generate, schema};
+
+    fn manager() -> ModelManager {
+        let ast = concerto_cto::parse(
+            r#"
+            namespace org.acme@1.0.0
+            enum Grade { o JUNIOR o SENIOR }
+            scalar Email extends String regex=/^[^@]+@[^@]+$/ length=[3,254]
+            abstract participant Person identified by email {
+              o Email email
+              o String[] nicknames optional
+            }
+            participant Employee extends Person {
+              o Grade grade
+              o Integer age range=[18,] optional
+              --> Person manager optional
+              o DateTime hired
+            }
+            participant Contractor extends Person {
+              o Grade grade
+            }
+            concept Team {
+              o Person lead
+              o Person[] members
+              o Roster roster optional
+            }
+            map Roster { o String o Double }
+            "#,
+        )
+        .unwrap();
+        let mut manager = ModelManager::new().unwrap();
+        manager.add_model(&ast, None).unwrap();
+        manager.validate_models().unwrap();
+        manager
+    }
+
+    #[test]
+    fn a_record_flattens_inheritance_and_keeps_validators() {
+        let schema = schema(&manager(), "org.acme@1.0.0.Employee").unwrap();
+        assert_eq!(
+            schema,
+            json!({
+                "type": "record",
+                "name": "Employee",
+                "namespace": "org.acme",
+                "fields": [
+                    {
+                        "name": "grade",
+                        "type": { "type": "enum", "name": "Grade", "namespace": "org.acme", "symbols": ["JUNIOR", "SENIOR"] },
+                    },
+                    { "name": "age", "type": ["null", "int"], "default": null, "validator": { "minimum": 18 } },
+                    { "name": "manager", "type": ["null", "string"], "default": null },
+                    { "name": "hired", "type": { "type": "long", "logicalType": "timestamp-millis" } },
+                    {
+                        "name": "email",
+                        "type": "string",
+                        "validator": { "pattern": "^[^@]+@[^@]+$", "minLength": 3, "maxLength": 254 },
+                    },
+                    { "name": "nicknames", "type": ["null", { "type": "array", "items": "string" }], "default": null },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn subtypes_are_unions_and_names_are_defined_once() {
+        let schema = schema(&manager(), "org.acme@1.0.0.Team").unwrap();
+        let fields = schema["fields"].as_array().unwrap();
+        let lead = fields[0]["type"].as_array().unwrap();
+        assert_eq!(lead[0]["name"], "Employee");
+        assert_eq!(lead[1]["name"], "Contractor");
+        // Contractor's grade refers to the enum Employee's defined.
+        assert_eq!(lead[1]["fields"][0]["type"], "org.acme.Grade");
+        assert_eq!(
+            fields[1]["type"],
+            json!({ "type": "array", "items": ["org.acme.Employee", "org.acme.Contractor"] })
+        );
+        assert_eq!(
+            fields[2]["type"],
+            json!(["null", { "type": "map", "values": "double" }])
+        );
+    }
+
+    #[test]
+    fn each_concrete_class_gets_a_file() {
+        let manager = manager();
+        let files = generate(&manager, &AvroOptions::default()).unwrap();
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "org.acme.Employee.avsc",
+                "org.acme.Contractor.avsc",
+                "org.acme.Team.avsc"
+            ]
+        );
+
+        let options = AvroOptions {
+            root: Some("org.acme@1.0.0.Nobody".into()),
+        };
+        assert!(matches!(
+            generate(&manager, &options),
+            Err(ConcertoError::TypeNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,467 @@
+//! JSON Schema for instances of the loaded models.
+//!
+//! The schema is draft 2020-12. Every declaration gets a definition under
+//! `$defs`, keyed by its fully-qualified name, and the schema itself accepts
+//! an instance of [`JsonSchemaOptions::root`], or of any concrete class when
+//! no root is given.
+//!
+//! A class is an object whose `$class` is the class's name, with a property
+//! for each field it declares or inherits; the fields that are not optional
+//! are required, and nothing else is allowed but `$identifier` and
+//! `$timestamp`. An abstract class is the `anyOf` of its concrete subtypes,
+//! and a field typed by a class that has subtypes accepts any of them, told
+//! apart by `$class`, just as instance validation does. Scalars and enums
+//! are definitions of their own, and a map is an object whose `$class`
+//! names it and whose other entries are its values.
+//!
+//! Validators carry over as `pattern`, `minLength`, `maxLength`, `minimum`
+//! and `maximum`. JSON Schema has no regex flags, so a pattern with any flag
+//! other than `u` is left out rather than checked differently. A
+//! relationship is a string, since the schema cannot know which identifiers
+//! exist.
+
+use concerto_core::introspect::declaration::MapDeclaration;
+use concerto_core::model_util::{is_primitive_type, qualify};
+use concerto_core::{
+    ClassDeclaration, Declaration, ModelManager, Property, Result, ScalarDeclaration,
+};
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde_json::{Map, Value, json};
+
+use crate::GeneratedFile;
+use crate::model::{Field, concrete_subtypes, fields, resolve, user_models};
+
+/// The JSON Schema dialect generated.
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// What to generate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonSchemaOptions {
+    /// The fully-qualified name of the type the schema accepts. With none,
+    /// the schema accepts an instance of any concrete class.
+    pub root: Option<String>,
+}
+
+/// Generates `schema.json`.
+pub fn generate(manager: &ModelManager, options: &JsonSchemaOptions) -> Result<Vec<GeneratedFile>> {
+    let schema = schema(manager, options)?;
+    Ok(vec![GeneratedFile {
+        path: "schema.json".to_string(),
+        contents: format!(
+            "{}\n",
+            serde_json::to_string_pretty(&schema).expect("a schema serializes")
+        ),
+    }])
+}
+
+/// The schema, as JSON.
+pub fn schema(manager: &ModelManager, options: &JsonSchemaOptions) -> Result<Value> {
+    let generator = Generator { manager };
+    let definitions = definitions(manager)?;
+    let mut classes = Vec::new();
+    for model in user_models(manager) {
+        for declaration in model.declarations() {
+            if let Declaration::Class(class) = declaration
+                && !class.is_abstract()
+            {
+                classes.push(reference(&qualify(model.namespace(), declaration.name())));
+            }
+        }
+    }
+
+    let mut schema = Map::new();
+    schema.insert("$schema".into(), DIALECT.into());
+    match &options.root {
+        Some(root) => {
+            manager.get_declaration(root)?;
+            if let Value::Object(root) = generator.type_reference(root)? {
+                schema.extend(root);
+            }
+        }
+        None => {
+            schema.insert("anyOf".into(), Value::Array(classes));
+        }
+    }
+    schema.insert("$defs".into(), Value::Object(definitions));
+    Ok(Value::Object(schema))
+}
+
+/// The definition of every declaration, keyed by its fully-qualified name,
+/// with references of the form `#/$defs/<name>`.
+pub(crate) fn definitions(manager: &ModelManager) -> Result<Map<String, Value>> {
+    let generator = Generator { manager };
+    let mut definitions = Map::new();
+    for model in user_models(manager) {
+        for declaration in model.declarations() {
+            let fqn = qualify(model.namespace(), declaration.name());
+            let definition = generator.declaration(model.namespace(), &fqn, declaration)?;
+            definitions.insert(fqn, definition);
+        }
+    }
+    Ok(definitions)
+}
+
+struct Generator<'m> {
+    manager: &'m ModelManager,
+}
+
+impl Generator<'_> {
+    fn declaration(&self, namespace: &str, fqn: &str, declaration: &Declaration) -> Result<Value> {
+        match declaration {
+            Declaration::Class(class) => self.class(fqn, class),
+            Declaration::Enum(declaration) => Ok(json!({
+                "type": "string",
+                "enum": declaration
+                    .properties
+                    .iter()
+                    .map(|member| member.name.as_str())
+                    .collect::<Vec<_>>(),
+            })),
+            Declaration::Scalar(scalar) => Ok(scalar_schema(scalar)),
+            Declaration::Map(map) => self.map(namespace, fqn, map),
+        }
+    }
+
+    fn class(&self, fqn: &str, class: &ClassDeclaration) -> Result<Value> {
+        if class.is_abstract() {
+            let subtypes = concrete_subtypes(self.manager, fqn)?;
+            if subtypes.is_empty() {
+                // Nothing can be an instance of it.
+                return Ok(Value::Bool(false));
+            }
+            return Ok(any_of(&subtypes));
+        }
+        let mut properties = Map::new();
+        properties.insert("$class".into(), json!({ "const": fqn }));
+        properties.insert("$identifier".into(), json!({ "type": "string" }));
+        properties.insert(
+            "$timestamp".into(),
+            json!({ "type": "string", "format": "date-time" }),
+        );
+        let mut required = vec![Value::from("$class")];
+        for field in fields(self.manager, fqn)? {
+            let name = field.property.name();
+            if !field.property.is_optional() {
+                required.push(name.into());
+            }
+            properties.insert(name.to_string(), self.field(&field)?);
+        }
+        Ok(json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        }))
+    }
+
+    fn field(&self, field: &Field<'_>) -> Result<Value> {
+        let (mut schema, default) = match field.property {
+            Property::Boolean(p) => (
+                json!({ "type": "boolean" }),
+                p.default_value.map(Value::from),
+            ),
+            Property::String(p) => (
+                string_schema(p.validator.as_ref(), p.length_validator.as_ref()),
+                p.default_value.clone().map(Value::from),
+            ),
+            Property::Integer(p) => (
+                number_schema(
+                    "integer",
+                    p.validator.as_ref().and_then(|v| v.lower).map(Value::from),
+                    p.validator.as_ref().and_then(|v| v.upper).map(Value::from),
+                ),
+                p.default_value.map(Value::from),
+            ),
+            Property::Long(p) => (
+                number_schema(
+                    "integer",
+                    p.validator.as_ref().and_then(|v| v.lower).map(Value::from),
+                    p.validator.as_ref().and_then(|v| v.upper).map(Value::from),
+                ),
+                p.default_value.map(Value::from),
+            ),
+            Property::Double(p) => (
+                number_schema(
+                    "number",
+                    p.validator.as_ref().and_then(|v| v.lower).map(Value::from),
+                    p.validator.as_ref().and_then(|v| v.upper).map(Value::from),
+                ),
+                p.default_value.map(Value::from),
+            ),
+            Property::DateTime(_) => (primitive_schema("DateTime"), None),
+            Property::Object(p) => (
+                self.type_reference(&resolve(self.manager, field.namespace, &p.type_)?)?,
+                p.default_value.clone().map(Value::from),
+            ),
+            Property::Relationship(p) => (
+                relationship_schema(&resolve(self.manager, field.namespace, &p.type_)?),
+                None,
+            ),
+            Property::Enum(_) => (json!({ "type": "string" }), None),
+        };
+        if field.property.is_array() {
+            return Ok(json!({ "type": "array", "items": schema }));
+        }
+        if let (Some(default), Value::Object(schema)) = (default, &mut schema) {
+            schema.insert("default".into(), default);
+        }
+        Ok(schema)
+    }
+
+    fn map(&self, namespace: &str, fqn: &str, map: &MapDeclaration) -> Result<Value> {
+        let value = match (map.value_kind(), map.value_type()) {
+            ("RelationshipMapValueType", Some(target)) => {
+                relationship_schema(&resolve(self.manager, namespace, target)?)
+            }
+            (_, Some(declared)) => {
+                self.type_reference(&resolve(self.manager, namespace, declared)?)?
+            }
+            (kind, None) => primitive_schema(kind.trim_end_matches("MapValueType")),
+        };
+        let mut schema = json!({
+            "type": "object",
+            "properties": { "$class": { "const": fqn } },
+            "required": ["$class"],
+            "additionalProperties": value,
+        });
+        let key = match map.key_type() {
+            Some(declared) => {
+                Some(self.type_reference(&resolve(self.manager, namespace, declared)?)?)
+            }
+            None if map.key_kind() == "DateTimeMapKeyType" => {
+                Some(json!({ "format": "date-time" }))
+            }
+            None => None,
+        };
+        if let Some(key) = key {
+            schema["propertyNames"] = key;
+        }
+        Ok(schema)
+    }
+
+    /// The schema for a value declared as `fqn`: a reference to its
+    /// definition, or, for a class with subtypes, to any of them.
+    fn type_reference(&self, fqn: &str) -> Result<Value> {
+        if is_primitive_type(fqn) {
+            return Ok(primitive_schema(fqn));
+        }
+        if let Declaration::Class(class) = self.manager.get_declaration(fqn)?
+            && !class.is_abstract()
+        {
+            let subtypes = concrete_subtypes(self.manager, fqn)?;
+            if subtypes.len() > 1 {
+                return Ok(any_of(&subtypes));
+            }
+        }
+        Ok(reference(fqn))
+    }
+}
+
+fn scalar_schema(scalar: &ScalarDeclaration) -> Value {
+    let (mut schema, default) = match scalar {
+        ScalarDeclaration::Boolean(s) => (
+            primitive_schema("Boolean"),
+            s.default_value.map(Value::from),
+        ),
+        ScalarDeclaration::String(s) => (
+            string_schema(s.validator.as_ref(), s.length_validator.as_ref()),
+            s.default_value.clone().map(Value::from),
+        ),
+        ScalarDeclaration::Integer(s) => (
+            number_schema(
+                "integer",
+                s.validator.as_ref().and_then(|v| v.lower).map(Value::from),
+                s.validator.as_ref().and_then(|v| v.upper).map(Value::from),
+            ),
+            s.default_value.map(Value::from),
+        ),
+        ScalarDeclaration::Long(s) => (
+            number_schema(
+                "integer",
+                s.validator.as_ref().and_then(|v| v.lower).map(Value::from),
+                s.validator.as_ref().and_then(|v| v.upper).map(Value::from),
+            ),
+            s.default_value.map(Value::from),
+        ),
+        ScalarDeclaration::Double(s) => (
+            number_schema(
+                "number",
+                s.validator.as_ref().and_then(|v| v.lower).map(Value::from),
+                s.validator.as_ref().and_then(|v| v.upper).map(Value::from),
+            ),
+            s.default_value.map(Value::from),
+        ),
+        ScalarDeclaration::DateTime(s) => (
+            primitive_schema("DateTime"),
+            s.default_value.clone().map(Value::from),
+        ),
+    };
+    if let Some(default) = default {
+        schema["default"] = default;
+    }
+    schema
+}
+
+fn primitive_schema(primitive: &str) -> Value {
+    match primitive {
+        "Boolean" => json!({ "type": "boolean" }),
+        "Integer" | "Long" => json!({ "type": "integer" }),
+        "Double" => json!({ "type": "number" }),
+        "DateTime" => json!({ "type": "string", "format": "date-time" }),
+        _ => json!({ "type": "string" }),
+    }
+}
+
+fn string_schema(
+    regex: Option<&mm::StringRegexValidator>,
+    length: Option<&mm::StringLengthValidator>,
+) -> Value {
+    let mut schema = json!({ "type": "string" });
+    if let Some(regex) = regex
+        && regex.flags.chars().all(|flag| flag == 'u')
+    {
+        schema["pattern"] = regex.pattern.clone().into();
+    }
+    if let Some(length) = length {
+        if let Some(min) = length.min_length {
+            schema["minLength"] = min.into();
+        }
+        if let Some(max) = length.max_length {
+            schema["maxLength"] = max.into();
+        }
+    }
+    schema
+}
+
+fn number_schema(kind: &str, lower: Option<Value>, upper: Option<Value>) -> Value {
+    let mut schema = json!({ "type": kind });
+    if let Some(lower) = lower {
+        schema["minimum"] = lower;
+    }
+    if let Some(upper) = upper {
+        schema["maximum"] = upper;
+    }
+    schema
+}
+
+fn relationship_schema(target: &str) -> Value {
+    json!({
+        "type": "string",
+        "description": format!("A relationship to a {target}"),
+    })
+}
+
+fn reference(fqn: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{fqn}") })
+}
+
+fn any_of(fqns: &[String]) -> Value {
+    json!({ "anyOf": fqns.iter().map(|fqn| reference(fqn)).collect::<Vec<_>>() })
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::{ConcertoError, ModelManager};
+    use serde_json::json;
+
+    use super::{JsonSchemaOptions, generate, schema};
+
+    fn manager() -> ModelManager {
+        let ast = concerto_cto::parse(
+            r#"
+            namespace org.acme@1.0.0
+            enum Grade { o JUNIOR o SENIOR }
+            scalar Email extends String regex=/^[^@]+@[^@]+$/ length=[3,254]
+            abstract participant Person identified by email {
+              o Email email
+              o String[] nicknames optional
+            }
+            participant Employee extends Person {
+              o Grade grade default="JUNIOR"
+              o Integer age range=[18,] optional
+              --> Person manager optional
+            }
+            participant Contractor extends Person {
+              o DateTime until
+            }
+            concept Team {
+              o Person lead
+              o Roster roster
+            }
+            map Roster { o DateTime o Double }
+            "#,
+        )
+        .unwrap();
+        let mut manager = ModelManager::new().unwrap();
+        manager.add_model(&ast, None).unwrap();
+        manager.validate_models().unwrap();
+        manager
+    }
+
+    #[test]
+    fn each_declaration_gets_a_definition() {
+        let schema = schema(&manager(), &JsonSchemaOptions::default()).unwrap();
+        let definitions = &schema["$defs"];
+        assert_eq!(
+            definitions["org.acme@1.0.0.Email"],
+            json!({ "type": "string", "pattern": "^[^@]+@[^@]+$", "minLength": 3, "maxLength": 254 })
+        );
+        assert_eq!(
+            definitions["org.acme@1.0.0.Person"],
+            json!({ "anyOf": [
+                { "$ref": "#/$defs/org.acme@1.0.0.Employee" },
+                { "$ref": "#/$defs/org.acme@1.0.0.Contractor" },
+            ] })
+        );
+        assert_eq!(
+            definitions["org.acme@1.0.0.Employee"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "$class": { "const": "org.acme@1.0.0.Employee" },
+                    "$identifier": { "type": "string" },
+                    "$timestamp": { "type": "string", "format": "date-time" },
+                    "grade": { "$ref": "#/$defs/org.acme@1.0.0.Grade", "default": "JUNIOR" },
+                    "age": { "type": "integer", "minimum": 18 },
+                    "manager": { "type": "string", "description": "A relationship to a org.acme@1.0.0.Person" },
+                    "email": { "$ref": "#/$defs/org.acme@1.0.0.Email" },
+                    "nicknames": { "type": "array", "items": { "type": "string" } },
+                },
+                "required": ["$class", "grade", "email"],
+                "additionalProperties": false,
+            })
+        );
+        assert_eq!(
+            definitions["org.acme@1.0.0.Roster"],
+            json!({
+                "type": "object",
+                "properties": { "$class": { "const": "org.acme@1.0.0.Roster" } },
+                "required": ["$class"],
+                "additionalProperties": { "type": "number" },
+                "propertyNames": { "format": "date-time" },
+            })
+        );
+        assert_eq!(schema["anyOf"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn a_root_narrows_the_schema_to_one_type() {
+        let manager = manager();
+        let options = JsonSchemaOptions {
+            root: Some("org.acme@1.0.0.Team".into()),
+        };
+        let files = generate(&manager, &options).unwrap();
+        assert_eq!(files[0].path, "schema.json");
+        let generated: serde_json::Value = serde_json::from_str(&files[0].contents).unwrap();
+        assert_eq!(generated["$ref"], "#/$defs/org.acme@1.0.0.Team");
+        assert!(generated.get("anyOf").is_none());
+
+        let options = JsonSchemaOptions {
+            root: Some("org.acme@1.0.0.Nobody".into()),
+        };
+        assert!(matches!(
+            schema(&manager, &options),
+            Err(ConcertoError::TypeNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+//! # concerto-codegen
+//!
+//! Turns the models loaded into a [`ModelManager`](concerto_core::ModelManager)
+//! into the schemas and types of other languages. Each target is a module
+//! with a `generate` function, which takes the manager and the target's
+//! options and gives back the files to write:
+//!
+//! - [`avro`]: an Apache Avro schema for each class, for a schema
+//!   registry.
+//! - [`json_schema`]: a JSON Schema (draft 2020-12) for instances of the
+//!   models.
+//! - [`openapi`]: OpenAPI 3.1 components, and optionally the paths of a
+//!   REST API over the identified assets and participants.
+//! - [`protobuf`]: proto3 packages, a file per namespace, with field numbers
+//!   kept stable by decorators and a lock file.
+//! - [`typescript`]: TypeScript interfaces, enums and type aliases, one
+//!   module per namespace.
+//!
+//! The generators read the models as loaded, so they can assume the models
+//! are well formed; run [`validate_models`](concerto_core::ModelManager::validate_models)
+//! first. A name that does not resolve is reported as the core's
+//! [`TypeNotFound`](concerto_core::ConcertoError::TypeNotFound). The system
+//! model is generated only by [`typescript`], and only when a model refers
+//! to one of its types: its types have no fields, and the other targets
+//! leave them out.
+
+pub mod avro;
+pub mod json_schema;
+mod model;
+pub mod openapi;
+pub mod protobuf;
+pub mod typescript;
+
+/// A file a generator produces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    /// Where the file goes, relative to the output directory, with `/`
+    /// separators.
+    pub path: String,
+    /// The file's contents.
+    pub contents: String,
+}
@@ -0,0 +1,98 @@
+//! What the generators need to know about the loaded models, beyond what the
+//! manager answers directly.
+
+use concerto_core::model_util::{namespace_of, qualify};
+use concerto_core::{ClassDeclaration, Declaration, ModelFile, ModelManager, Property, Result};
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+
+/// A field of a class, with the class that declares it and that class's
+/// namespace, in which its type is resolved.
+pub(crate) struct Field<'m> {
+    pub(crate) namespace: &'m str,
+    pub(crate) owner: &'m ClassDeclaration,
+    pub(crate) property: &'m Property,
+}
+
+/// The models to generate, in namespace order, without the system model.
+pub(crate) fn user_models(manager: &ModelManager) -> Vec<&ModelFile> {
+    let mut models: Vec<_> = manager
+        .model_files()
+        .filter(|model| !model.is_system_namespace())
+        .collect();
+    models.sort_by(|a, b| a.namespace().cmp(b.namespace()));
+    models
+}
+
+/// A type named in `namespace`, fully qualified. Primitives come back as
+/// they are.
+pub(crate) fn resolve(
+    manager: &ModelManager,
+    namespace: &str,
+    type_identifier: &mm::TypeIdentifier,
+) -> Result<String> {
+    match (&type_identifier.namespace, &type_identifier.resolved_name) {
+        (Some(declared_in), _) => Ok(qualify(declared_in, &type_identifier.name)),
+        (None, Some(resolved)) => Ok(resolved.clone()),
+        (None, None) => manager.resolve_type_name(namespace, &type_identifier.name),
+    }
+}
+
+/// The class `fqn` and its super types, from the class up, each with its
+/// fully-qualified name.
+pub(crate) fn lineage<'m>(
+    manager: &'m ModelManager,
+    fqn: &str,
+) -> Result<Vec<(String, &'m ClassDeclaration)>> {
+    // Resolves the whole lineage first, so the walk below cannot fail on a
+    // cycle.
+    manager.get_all_properties(fqn)?;
+    let mut lineage = Vec::new();
+    let mut next = Some(fqn.to_string());
+    while let Some(fqn) = next {
+        let Some(class) = manager.get_declaration(&fqn)?.as_class() else {
+            break;
+        };
+        next = match class.super_type() {
+            Some(super_type) => Some(resolve(manager, namespace_of(&fqn), super_type)?),
+            None => None,
+        };
+        lineage.push((fqn, class));
+    }
+    Ok(lineage)
+}
+
+/// Every field of the class `fqn`, in the order
+/// [`ModelManager::get_all_properties`] gives them.
+pub(crate) fn fields<'m>(manager: &'m ModelManager, fqn: &str) -> Result<Vec<Field<'m>>> {
+    let lineage = lineage(manager, fqn)?;
+    let mut fields = Vec::new();
+    for (owner, class) in lineage {
+        let namespace = manager
+            .model_file(namespace_of(&owner))
+            .map_or("", ModelFile::namespace);
+        fields.extend(class.own_properties().iter().map(|property| Field {
+            namespace,
+            owner: class,
+            property,
+        }));
+    }
+    Ok(fields)
+}
+
+/// The concrete classes an instance of `fqn` may be, `fqn` itself included
+/// unless it is abstract, in model order.
+pub(crate) fn concrete_subtypes(manager: &ModelManager, fqn: &str) -> Result<Vec<String>> {
+    let mut subtypes = Vec::new();
+    for model in user_models(manager) {
+        for declaration in model.declarations() {
+            let Declaration::Class(class) = declaration else {
+                continue;
+            };
+            let candidate = qualify(model.namespace(), class.name());
+            if !class.is_abstract() && manager.is_assignable_to(&candidate, fqn)? {
+                subtypes.push(candidate);
+            }
+        }
+    }
+    Ok(subtypes)
+}
@@ -0,0 +1,428 @@
+//! OpenAPI 3.1 components for the loaded models, and optionally the paths
+//! of a REST API over them.
+//!
+//! OpenAPI 3.1 schemas are JSON Schema 2020-12, so each component is the
+//! [`json_schema`](crate::json_schema) definition of a declaration. A
+//! component's name is the declaration's fully-qualified name with its `@`
+//! made a `-`, as component names allow no `@`: `org.acme-1.0.0.Person`.
+//! Where the JSON Schema accepts any of a class's subtypes, the component
+//! has a `oneOf` with a `discriminator` on `$class` that maps each class name
+//! to its component.
+//!
+//! The components can be limited to some namespaces; the declarations of
+//! other namespaces they refer to come along. With
+//! [`OpenApiOptions::paths`], each concrete asset or participant identified
+//! by a field gets a collection, `/<namespace>/<Name>`, to list and create
+//! instances, and an item, `/<namespace>/<Name>/{<field>}`, to get, replace
+//! and delete one.
+
+use std::collections::BTreeSet;
+
+use concerto_core::introspect::declaration::ClassKind;
+use concerto_core::model_util::qualify;
+use concerto_core::{ConcertoError, Declaration, ModelFile, ModelManager, Result};
+use serde_json::{Map, Value, json};
+
+use crate::GeneratedFile;
+use crate::json_schema::definitions;
+use crate::model::{lineage, user_models};
+
+/// The OpenAPI version generated.
+const OPENAPI: &str = "3.1.0";
+
+/// The prefix of the references between JSON Schema definitions.
+const DEFS: &str = "#/$defs/";
+
+/// What to generate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenApiOptions {
+    /// The title of the API.
+    pub title: String,
+    /// The version of the API.
+    pub version: String,
+    /// The namespaces whose declarations to include. With none, every
+    /// namespace's are.
+    pub namespaces: Vec<String>,
+    /// Whether to generate the paths of a REST API over the identified
+    /// assets and participants.
+    pub paths: bool,
+}
+
+impl Default for OpenApiOptions {
+    fn default() -> Self {
+        Self {
+            title: "Concerto models".to_string(),
+            version: "1.0.0".to_string(),
+            namespaces: Vec::new(),
+            paths: false,
+        }
+    }
+}
+
+/// Generates `openapi.json`.
+pub fn generate(manager: &ModelManager, options: &OpenApiOptions) -> Result<Vec<GeneratedFile>> {
+    let document = document(manager, options)?;
+    Ok(vec![GeneratedFile {
+        path: "openapi.json".to_string(),
+        contents: format!(
+            "{}\n",
+            serde_json::to_string_pretty(&document).expect("a document serializes")
+        ),
+    }])
+}
+
+/// The OpenAPI document, as JSON.
+pub fn document(manager: &ModelManager, options: &OpenApiOptions) -> Result<Value> {
+    let definitions = definitions(manager)?;
+    let models = selected_models(manager, &options.namespaces)?;
+
+    // The selected declarations, and all they refer to.
+    let mut included = BTreeSet::new();
+    let mut pending: Vec<String> = models
+        .iter()
+        .flat_map(|model| {
+            model
+                .declarations()
+                .iter()
+                .map(|declaration| qualify(model.namespace(), declaration.name()))
+        })
+        .collect();
+    while let Some(fqn) = pending.pop() {
+        if let Some(definition) = definitions.get(&fqn)
+            && included.insert(fqn)
+        {
+            references(definition, &mut pending);
+        }
+    }
+    let schemas: Map<String, Value> = definitions
+        .iter()
+        .filter(|(fqn, _)| included.contains(*fqn))
+        .map(|(fqn, definition)| (component_name(fqn), component(definition.clone())))
+        .collect();
+
+    let mut document = Map::new();
+    document.insert("openapi".into(), OPENAPI.into());
+    document.insert(
+        "info".into(),
+        json!({ "title": options.title, "version": options.version }),
+    );
+    if options.paths {
+        document.insert("paths".into(), paths(manager, &models, &schemas)?.into());
+    }
+    document.insert("components".into(), json!({ "schemas": schemas }));
+    Ok(Value::Object(document))
+}
+
+/// The models of `namespaces`, or all of them if there are none.
+fn selected_models<'m>(
+    manager: &'m ModelManager,
+    namespaces: &[String],
+) -> Result<Vec<&'m ModelFile>> {
+    if namespaces.is_empty() {
+        return Ok(user_models(manager));
+    }
+    namespaces
+        .iter()
+        .map(|namespace| {
+            manager
+                .model_file(namespace)
+                .ok_or_else(|| ConcertoError::NamespaceNotFound {
+                    namespace: namespace.clone(),
+                })
+        })
+        .collect()
+}
+
+/// Adds the names of the definitions `schema` refers to.
+fn references(schema: &Value, found: &mut Vec<String>) {
+    match schema {
+        Value::Object(schema) => {
+            for (key, value) in schema {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(reference)) => {
+                        found.extend(reference.strip_prefix(DEFS).map(str::to_string));
+                    }
+                    _ => references(value, found),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| references(item, found)),
+        _ => {}
+    }
+}
+
+/// A JSON Schema definition as a component: its references point at the
+/// other components, and a choice between classes is discriminated by
+/// `$class`.
+fn component(schema: Value) -> Value {
+    match schema {
+        Value::Object(mut schema) => {
+            if let Some(Value::Array(choices)) = schema.get("anyOf")
+                && let Some(classes) = choices
+                    .iter()
+                    .map(|choice| choice["$ref"].as_str()?.strip_prefix(DEFS))
+                    .collect::<Option<Vec<_>>>()
+            {
+                let mapping: Map<String, Value> = classes
+                    .iter()
+                    .map(|fqn| (fqn.to_string(), component_reference(fqn).into()))
+                    .collect();
+                let choices = schema.remove("anyOf").expect("the choices are there");
+                schema.insert("oneOf".into(), choices);
+                schema.insert(
+                    "discriminator".into(),
+                    json!({ "propertyName": "$class", "mapping": mapping }),
+                );
+            }
+            Value::Object(
+                schema
+                    .into_iter()
+                    .map(|(key, value)| match (key.as_str(), value) {
+                        ("$ref", Value::String(reference)) => {
+                            let reference = match reference.strip_prefix(DEFS) {
+                                Some(fqn) => component_reference(fqn),
+                                None => reference,
+                            };
+                            (key, Value::String(reference))
+                        }
+                        (_, value) => (key, component(value)),
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(component).collect()),
+        schema => schema,
+    }
+}
+
+fn component_name(fqn: &str) -> String {
+    fqn.replacen('@', "-", 1)
+}
+
+fn component_reference(fqn: &str) -> String {
+    format!("#/components/schemas/{}", component_name(fqn))
+}
+
+/// The collection and item paths of each concrete asset and participant of
+/// `models` that is identified by a field.
+fn paths(
+    manager: &ModelManager,
+    models: &[&ModelFile],
+    schemas: &Map<String, Value>,
+) -> Result<Map<String, Value>> {
+    let mut paths = Map::new();
+    for model in models {
+        for declaration in model.declarations() {
+            let Declaration::Class(class) = declaration else {
+                continue;
+            };
+            if class.is_abstract()
+                || !matches!(class.kind(), ClassKind::Asset | ClassKind::Participant)
+            {
+                continue;
+            }
+            let fqn = qualify(model.namespace(), class.name());
+            // The field may be named by a super type.
+            let Some(field) = lineage(manager, &fqn)?
+                .into_iter()
+                .find_map(|(_, class)| class.identifier_field_name())
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let name = class.name();
+            let instance = json!({ "$ref": component_reference(&fqn) });
+            let body = json!({ "content": { "application/json": { "schema": instance } } });
+            let not_found = json!({ "description": format!("No {name} has that {field}") });
+            let parameter = json!({
+                "name": field,
+                "in": "path",
+                "required": true,
+                "schema": schemas[&component_name(&fqn)]["properties"][&field],
+            });
+            let collection = format!("/{}/{name}", model.namespace());
+            paths.insert(
+                collection.clone(),
+                json!({
+                    "get": {
+                        "summary": format!("List the {name} instances"),
+                        "responses": {
+                            "200": {
+                                "description": format!("Every {name}"),
+                                "content": { "application/json": { "schema": { "type": "array", "items": instance } } },
+                            },
+                        },
+                    },
+                    "post": {
+                        "summary": format!("Create a {name}"),
+                        "requestBody": { "required": true, "content": body["content"] },
+                        "responses": {
+                            "201": { "description": format!("The {name} as created"), "content": body["content"] },
+                        },
+                    },
+                }),
+            );
+            paths.insert(
+                format!("{collection}/{{{field}}}"),
+                json!({
+                    "parameters": [parameter],
+                    "get": {
+                        "summary": format!("Get a {name} by its {field}"),
+                        "responses": {
+                            "200": { "description": format!("The {name}"), "content": body["content"] },
+                            "404": not_found,
+                        },
+                    },
+                    "put": {
+                        "summary": format!("Replace a {name}"),
+                        "requestBody": { "required": true, "content": body["content"] },
+                        "responses": {
+                            "200": { "description": format!("The {name} as replaced"), "content": body["content"] },
+                            "404": not_found,
+                        },
+                    },
+                    "delete": {
+                        "summary": format!("Delete a {name}"),
+                        "responses": {
+                            "204": { "description": format!("The {name} is deleted") },
+                            "404": not_found,
+                        },
+                    },
+                }),
+            );
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::{ConcertoError, ModelManager};
+    use serde_json::json;
+
+    use super::{OpenApiOptions, document, generate};
+
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        for source in [
+            r#"
+            namespace org.people@1.0.0
+            abstract participant Person identified by email {
+              o String email
+            }
+            participant Employee extends Person {
+              o Integer level
+            }
+            participant Contractor extends Person {
+              o DateTime until
+            }
+            "#,
+            r#"
+            namespace org.hr@1.0.0
+            import org.people@1.0.0.{Person}
+            asset Review identified by reviewId {
+              o String reviewId
+              o Person subject
+            }
+            concept Note {
+              o String text
+            }
+            "#,
+        ] {
+            let ast = concerto_cto::parse(source).unwrap();
+            manager.add_model(&ast, None).unwrap();
+        }
+        manager.validate_models().unwrap();
+        manager
+    }
+
+    #[test]
+    fn abstract_hierarchies_are_discriminated_by_class() {
+        let document = document(&manager(), &OpenApiOptions::default()).unwrap();
+        assert_eq!(document["openapi"], "3.1.0");
+        assert!(document.get("paths").is_none());
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(
+            schemas["org.people-1.0.0.Person"],
+            json!({
+                "oneOf": [
+                    { "$ref": "#/components/schemas/org.people-1.0.0.Employee" },
+                    { "$ref": "#/components/schemas/org.people-1.0.0.Contractor" },
+                ],
+                "discriminator": {
+                    "propertyName": "$class",
+                    "mapping": {
+                        "org.people@1.0.0.Employee": "#/components/schemas/org.people-1.0.0.Employee",
+                        "org.people@1.0.0.Contractor": "#/components/schemas/org.people-1.0.0.Contractor",
+                    },
+                },
+            })
+        );
+        assert_eq!(
+            schemas["org.hr-1.0.0.Review"]["properties"]["subject"],
+            json!({ "$ref": "#/components/schemas/org.people-1.0.0.Person" })
+        );
+    }
+
+    #[test]
+    fn a_subset_brings_what_it_refers_to_and_paths_are_keyed_by_identifier() {
+        let manager = manager();
+        let options = OpenApiOptions {
+            namespaces: vec!["org.hr@1.0.0".into()],
+            paths: true,
+            ..OpenApiOptions::default()
+        };
+        let api = document(&manager, &options).unwrap();
+        let schemas = api["components"]["schemas"].as_object().unwrap();
+        let names: Vec<&str> = schemas.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "org.hr-1.0.0.Review",
+                "org.hr-1.0.0.Note",
+                "org.people-1.0.0.Person",
+                "org.people-1.0.0.Employee",
+                "org.people-1.0.0.Contractor",
+            ]
+        );
+
+        // Only the namespaces asked for get paths.
+        let paths = api["paths"].as_object().unwrap();
+        let paths: Vec<&str> = paths.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            ["/org.hr@1.0.0/Review", "/org.hr@1.0.0/Review/{reviewId}"]
+        );
+        let item = &api["paths"]["/org.hr@1.0.0/Review/{reviewId}"];
+        assert_eq!(
+            item["parameters"][0],
+            json!({ "name": "reviewId", "in": "path", "required": true, "schema": { "type": "string" } })
+        );
+        assert_eq!(
+            item["get"]["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/org.hr-1.0.0.Review"
+        );
+
+        // An identifier named by a super type keys the subtype's paths.
+        let options = OpenApiOptions {
+            paths: true,
+            ..OpenApiOptions::default()
+        };
+        let files = generate(&manager, &options).unwrap();
+        assert_eq!(files[0].path, "openapi.json");
+        assert!(
+            files[0]
+                .contents
+                .contains("\"/org.people@1.0.0/Employee/{email}\"")
+        );
+
+        let options = OpenApiOptions {
+            namespaces: vec!["org.nowhere@1.0.0".into()],
+            ..OpenApiOptions::default()
+        };
+        assert!(matches!(
+            document(&manager, &options),
+            Err(ConcertoError::NamespaceNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,777 @@
+//! Protocol Buffers (proto3) for the loaded models.
+//!
+//! Each namespace becomes a package named for it and its version, so
+//! `org.acme@1.0.0` is `org.acme.v1_0_0`, in a file laid out as `buf`
+//! expects, `org/acme/v1_0_0/acme.proto`.
+//!
+//! A concrete class is a message with a field for each property it declares
+//! or inherits, named in snake case; an optional property is an `optional`
+//! field and an array a `repeated` one. A property whose type is an abstract
+//! class is a `oneof` of its concrete subtypes, and an abstract class is a
+//! message holding that `oneof`, for the arrays and maps of it. An enum
+//! starts with the zero value proto3 requires, `<ENUM>_UNSPECIFIED`. A map
+//! is a message whose `entries` are a `map<string, …>` when the keys are
+//! strings, and `repeated` key and value pairs otherwise. A scalar is its
+//! primitive's type, a DateTime a `google.protobuf.Timestamp`, and a
+//! relationship the `string` that identifies what it points at.
+//!
+//! A field keeps its number from one generation to the next. A number
+//! pinned with a `@ProtoField(n)` decorator on the property or enum value
+//! wins, except for the fields of a `oneof`, which have no property of their
+//! own; otherwise the number recorded in the lock file,
+//! [`LOCK_FILE`], of an earlier generation is kept; a new field takes the
+//! next number never used. The lock file is generated with the `.proto`
+//! files, and a field that is gone leaves its number `reserved`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use concerto_core::introspect::declaration::MapDeclaration;
+use concerto_core::model_util::{
+    is_primitive_type, namespace_of, parse_namespace, qualify, short_name,
+};
+use concerto_core::{
+    ClassDeclaration, ConcertoError, Declaration, ModelFile, ModelManager, Property, Result,
+};
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde_json::Value;
+
+use crate::GeneratedFile;
+use crate::model::{Field, concrete_subtypes, fields, resolve, user_models};
+
+/// The name of the lock file of field numbers.
+pub const LOCK_FILE: &str = "proto.lock.json";
+
+/// The decorator that pins a field number.
+const DECORATOR: &str = "ProtoField";
+
+const TIMESTAMP: &str = "google.protobuf.Timestamp";
+const TIMESTAMP_FILE: &str = "google/protobuf/timestamp.proto";
+
+/// The largest field number protobuf allows.
+const MAX_NUMBER: u32 = 536_870_911;
+
+/// The field numbers protobuf keeps for itself.
+const RESERVED_NUMBERS: std::ops::RangeInclusive<u32> = 19_000..=19_999;
+
+/// Field numbers, by message or enum and then by field. A message or enum
+/// is named by its namespace without the version, so that its numbers carry
+/// over to the next version: `org.acme.Person`. A field is named by its
+/// property, or enum value; a field of a `oneof` by its property and the
+/// subtype, `subject.Employee`; and a field of an abstract class's message
+/// by the subtype.
+pub type FieldNumbers = BTreeMap<String, BTreeMap<String, u32>>;
+
+/// What to generate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtobufOptions {
+    /// The numbers of an earlier generation, from its lock file.
+    pub lock: FieldNumbers,
+    /// The numbers pinned by decorators; see [`pinned_numbers`].
+    pub pinned: FieldNumbers,
+}
+
+/// The numbers pinned by `@ProtoField(n)` decorators in a model's JSON AST.
+///
+/// The loaded models do not keep the values of decorator arguments, so the
+/// pins are read from the AST the model was loaded from. A pin is recorded
+/// under the class that declares the property, and holds in the message of
+/// every class that inherits it.
+pub fn pinned_numbers(ast: &Value) -> FieldNumbers {
+    let mut pinned = FieldNumbers::new();
+    let Some(namespace) = ast["namespace"]
+        .as_str()
+        .and_then(|namespace| parse_namespace(namespace).ok())
+    else {
+        return pinned;
+    };
+    for declaration in ast["declarations"].as_array().into_iter().flatten() {
+        let Some(name) = declaration["name"].as_str() else {
+            continue;
+        };
+        for property in declaration["properties"].as_array().into_iter().flatten() {
+            let number = property["decorators"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|decorator| decorator["name"] == DECORATOR)
+                .find_map(|decorator| decorator["arguments"][0]["value"].as_f64());
+            if let (Some(number), Some(field)) = (number, property["name"].as_str())
+                && number.fract() == 0.0
+                && (0.0..=f64::from(u32::MAX)).contains(&number)
+            {
+                pinned
+                    .entry(format!("{}.{name}", namespace.name))
+                    .or_default()
+                    .insert(field.to_string(), number as u32);
+            }
+        }
+    }
+    pinned
+}
+
+/// Generates a `.proto` file for each namespace, and the lock file of the
+/// field numbers they use.
+pub fn generate(manager: &ModelManager, options: &ProtobufOptions) -> Result<Vec<GeneratedFile>> {
+    let mut lock = options.lock.clone();
+    let mut files = Vec::new();
+    for model in user_models(manager) {
+        let mut generator = Generator {
+            manager,
+            model,
+            options,
+            lock: &mut lock,
+            imports: BTreeSet::new(),
+        };
+        let body = generator.declarations()?;
+        let mut contents = format!("syntax = \"proto3\";\n\npackage {};\n", package(model));
+        if !generator.imports.is_empty() {
+            contents.push('\n');
+            for import in &generator.imports {
+                contents.push_str(&format!("import \"{import}\";\n"));
+            }
+        }
+        for declaration in body {
+            contents.push('\n');
+            contents.push_str(&declaration);
+        }
+        files.push(GeneratedFile {
+            path: file_path(model),
+            contents,
+        });
+    }
+    files.push(GeneratedFile {
+        path: LOCK_FILE.to_string(),
+        contents: format!(
+            "{}\n",
+            serde_json::to_string_pretty(&lock).expect("a lock serializes")
+        ),
+    });
+    Ok(files)
+}
+
+/// `org.acme.v1_0_0` for `org.acme@1.0.0`.
+fn package(model: &ModelFile) -> String {
+    let name = namespace_of_model(model);
+    let version: String = model
+        .version()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{name}.v{version}")
+}
+
+/// `org/acme/v1_0_0/acme.proto` for `org.acme@1.0.0`.
+fn file_path(model: &ModelFile) -> String {
+    let package = package(model);
+    let name = namespace_of_model(model);
+    let last = name.rsplit('.').next().unwrap_or(name);
+    format!("{}/{last}.proto", package.replace('.', "/"))
+}
+
+/// The namespace of `model` without its version.
+fn namespace_of_model(model: &ModelFile) -> &str {
+    unversioned(model.namespace())
+}
+
+/// `org.acme` for `org.acme@1.0.0`.
+fn unversioned(namespace: &str) -> &str {
+    namespace
+        .split_once('@')
+        .map_or(namespace, |(name, _)| name)
+}
+
+/// A field of a message: one that holds a value, or a `oneof` of fields
+/// each holding one subtype.
+enum Slot {
+    Value {
+        key: String,
+        label: &'static str,
+        type_name: String,
+        /// The number a decorator on the property pins it to.
+        pinned: Option<u32>,
+    },
+    OneOf {
+        name: String,
+        choices: Vec<(String, String)>,
+    },
+}
+
+struct Generator<'m, 'l> {
+    manager: &'m ModelManager,
+    model: &'m ModelFile,
+    options: &'m ProtobufOptions,
+    lock: &'l mut FieldNumbers,
+    imports: BTreeSet<String>,
+}
+
+impl Generator<'_, '_> {
+    fn declarations(&mut self) -> Result<Vec<String>> {
+        let mut declarations = Vec::new();
+        for declaration in self.model.declarations() {
+            let fqn = qualify(self.model.namespace(), declaration.name());
+            declarations.push(match declaration {
+                Declaration::Class(class) => self.class(&fqn, class)?,
+                Declaration::Enum(declaration) => self.enumeration(declaration)?,
+                Declaration::Map(map) => self.map(map)?,
+                // A scalar is its primitive wherever it is used.
+                Declaration::Scalar(_) => continue,
+            });
+        }
+        Ok(declarations)
+    }
+
+    fn class(&mut self, fqn: &str, class: &ClassDeclaration) -> Result<String> {
+        let mut slots = Vec::new();
+        if class.is_abstract() {
+            let choices = concrete_subtypes(self.manager, fqn)?
+                .iter()
+                .map(|subtype| (short_name(subtype).to_string(), self.message_type(subtype)))
+                .collect();
+            slots.push(Slot::OneOf {
+                name: "value".to_string(),
+                choices,
+            });
+            return self.message(class.name(), &slots, true);
+        }
+        for field in fields(self.manager, fqn)? {
+            slots.push(self.slot(&field)?);
+        }
+        self.message(class.name(), &slots, false)
+    }
+
+    fn slot(&mut self, field: &Field<'_>) -> Result<Slot> {
+        let property = field.property;
+        let name = property.name().to_string();
+        let label = match (property.is_array(), property.is_optional()) {
+            (true, _) => "repeated ",
+            (false, true) => "optional ",
+            (false, false) => "",
+        };
+        let type_name = match property {
+            Property::Object(p) => {
+                let fqn = resolve(self.manager, field.namespace, &p.type_)?;
+                if !property.is_array()
+                    && let Some(class) = self.manager.get_declaration(&fqn)?.as_class()
+                    && class.is_abstract()
+                {
+                    let choices = concrete_subtypes(self.manager, &fqn)?
+                        .iter()
+                        .map(|subtype| {
+                            (
+                                format!("{name}.{}", short_name(subtype)),
+                                self.message_type(subtype),
+                            )
+                        })
+                        .collect();
+                    return Ok(Slot::OneOf { name, choices });
+                }
+                self.type_name(&fqn)?
+            }
+            Property::Relationship(_) | Property::Enum(_) => "string".to_string(),
+            primitive => self.primitive(primitive.type_name().unwrap_or("String")),
+        };
+        // A pin is recorded under the class that declares the property, and
+        // holds in every message that inherits it.
+        let pinned = self
+            .options
+            .pinned
+            .get(&format!(
+                "{}.{}",
+                unversioned(field.namespace),
+                field.owner.name()
+            ))
+            .and_then(|pins| pins.get(&name))
+            .copied();
+        Ok(Slot::Value {
+            key: name,
+            label,
+            type_name,
+            pinned,
+        })
+    }
+
+    /// A message of `slots`. The fields of a `oneof` are named for their
+    /// types; `by_type` leaves the `oneof`'s own name out of theirs.
+    fn message(&mut self, name: &str, slots: &[Slot], by_type: bool) -> Result<String> {
+        let keys: Vec<&str> = slots
+            .iter()
+            .flat_map(|slot| match slot {
+                Slot::Value { key, .. } => vec![key.as_str()],
+                Slot::OneOf { choices, .. } => {
+                    choices.iter().map(|(key, _)| key.as_str()).collect()
+                }
+            })
+            .collect();
+        let pinned = slots
+            .iter()
+            .filter_map(|slot| match slot {
+                Slot::Value {
+                    key,
+                    pinned: Some(number),
+                    ..
+                } => Some((key.as_str(), *number)),
+                _ => None,
+            })
+            .collect();
+        let (numbers, reserved) = self.numbers(name, &keys, &pinned)?;
+        let mut body = String::new();
+        for slot in slots {
+            match slot {
+                Slot::Value {
+                    key,
+                    label,
+                    type_name,
+                    ..
+                } => body.push_str(&format!(
+                    "  {label}{type_name} {} = {};\n",
+                    snake_case(key),
+                    numbers[key]
+                )),
+                Slot::OneOf { name, choices } => {
+                    body.push_str(&format!("  oneof {} {{\n", snake_case(name)));
+                    for (key, type_name) in choices {
+                        let field = match by_type {
+                            true => snake_case(key),
+                            false => {
+                                format!("{}_{}", snake_case(name), snake_case(short_name(key)))
+                            }
+                        };
+                        body.push_str(&format!("    {type_name} {field} = {};\n", numbers[key]));
+                    }
+                    body.push_str("  }\n");
+                }
+            }
+        }
+        body.push_str(&reserved_line(&reserved));
+        Ok(format!("message {name} {{\n{body}}}\n"))
+    }
+
+    fn enumeration(&mut self, declaration: &mm::EnumDeclaration) -> Result<String> {
+        let keys: Vec<&str> = declaration
+            .properties
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect();
+        let lock_key = format!("{}.{}", namespace_of_model(self.model), declaration.name);
+        let pinned = self
+            .options
+            .pinned
+            .get(&lock_key)
+            .map(|pins| {
+                pins.iter()
+                    .map(|(key, &number)| (key.as_str(), number))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (numbers, reserved) = self.numbers(&declaration.name, &keys, &pinned)?;
+        let prefix = upper_snake_case(&declaration.name);
+        let mut body = format!("  {prefix}_UNSPECIFIED = 0;\n");
+        for key in keys {
+            body.push_str(&format!(
+                "  {prefix}_{} = {};\n",
+                upper_snake_case(key),
+                numbers[key]
+            ));
+        }
+        body.push_str(&reserved_line(&reserved));
+        Ok(format!("enum {} {{\n{body}}}\n", declaration.name))
+    }
+
+    fn map(&mut self, map: &MapDeclaration) -> Result<String> {
+        let namespace = self.model.namespace();
+        let key = match map.key_type() {
+            Some(declared) => {
+                let fqn = resolve(self.manager, namespace, declared)?;
+                self.type_name(&fqn)?
+            }
+            None => self.primitive(map.key_kind().trim_end_matches("MapKeyType")),
+        };
+        let value = match (map.value_kind(), map.value_type()) {
+            ("RelationshipMapValueType", _) => "string".to_string(),
+            (_, Some(declared)) => {
+                let fqn = resolve(self.manager, namespace, declared)?;
+                self.type_name(&fqn)?
+            }
+            (kind, None) => self.primitive(kind.trim_end_matches("MapValueType")),
+        };
+        let body = match key.as_str() {
+            "string" => format!("  map<string, {value}> entries = 1;\n"),
+            _ => format!(
+                "  message Entry {{\n    {key} key = 1;\n    {value} value = 2;\n  }}\n  repeated Entry entries = 1;\n"
+            ),
+        };
+        Ok(format!("message {} {{\n{body}}}\n", map.name()))
+    }
+
+    /// The type of a value declared as `fqn`: a scalar's primitive, or the
+    /// message or enum.
+    fn type_name(&mut self, fqn: &str) -> Result<String> {
+        if is_primitive_type(fqn) {
+            return Ok(self.primitive(fqn));
+        }
+        Ok(match self.manager.get_declaration(fqn)?.as_scalar() {
+            Some(scalar) => self.primitive(scalar.scalar_type()),
+            None => self.message_type(fqn),
+        })
+    }
+
+    /// The name of the message or enum `fqn`, qualified by its package if it
+    /// is in another one, which is then imported.
+    fn message_type(&mut self, fqn: &str) -> String {
+        let namespace = namespace_of(fqn);
+        if namespace == self.model.namespace() {
+            return short_name(fqn).to_string();
+        }
+        match self.manager.model_file(namespace) {
+            Some(model) => {
+                self.imports.insert(file_path(model));
+                format!(".{}.{}", package(model), short_name(fqn))
+            }
+            None => short_name(fqn).to_string(),
+        }
+    }
+
+    fn primitive(&mut self, primitive: &str) -> String {
+        match primitive {
+            "Boolean" => "bool",
+            "Integer" => "int32",
+            "Long" => "int64",
+            "Double" => "double",
+            "DateTime" => {
+                self.imports.insert(TIMESTAMP_FILE.to_string());
+                TIMESTAMP
+            }
+            _ => "string",
+        }
+        .to_string()
+    }
+
+    /// The numbers of the fields `keys` of the message or enum `name`, given
+    /// those `pinned` by decorators, and the numbers of the fields it no
+    /// longer has. Records both in the lock.
+    fn numbers(
+        &mut self,
+        name: &str,
+        keys: &[&str],
+        pinned: &BTreeMap<&str, u32>,
+    ) -> Result<(BTreeMap<String, u32>, Vec<u32>)> {
+        let lock_key = format!("{}.{name}", namespace_of_model(self.model));
+        let empty = BTreeMap::new();
+        let locked = self.options.lock.get(&lock_key).unwrap_or(&empty);
+        let illegal = |message: String| ConcertoError::IllegalModel {
+            message,
+            file_name: self.model.file_name().map(str::to_string),
+            location: None,
+        };
+
+        let mut numbers = BTreeMap::new();
+        let mut taken = BTreeMap::new();
+        for &key in keys {
+            let Some(&number) = pinned.get(key) else {
+                continue;
+            };
+            if number == 0 || number > MAX_NUMBER || RESERVED_NUMBERS.contains(&number) {
+                return Err(illegal(format!(
+                    "{lock_key}.{key} cannot be field number {number}"
+                )));
+            }
+            if let Some(other) = taken.insert(number, key) {
+                return Err(illegal(format!(
+                    "{lock_key}.{other} and {lock_key}.{key} are both field number {number}"
+                )));
+            }
+            numbers.insert(key.to_string(), number);
+        }
+        for &key in keys {
+            if let Some(&number) = locked.get(key)
+                && !numbers.contains_key(key)
+                && !taken.contains_key(&number)
+            {
+                taken.insert(number, key);
+                numbers.insert(key.to_string(), number);
+            }
+        }
+        let mut next = taken
+            .keys()
+            .chain(locked.values())
+            .max()
+            .map_or(1, |n| n + 1);
+        for &key in keys {
+            if numbers.contains_key(key) {
+                continue;
+            }
+            while RESERVED_NUMBERS.contains(&next) {
+                next += 1;
+            }
+            taken.insert(next, key);
+            numbers.insert(key.to_string(), next);
+            next += 1;
+        }
+
+        let mut record = numbers.clone();
+        let mut reserved = Vec::new();
+        for (key, &number) in locked {
+            if !numbers.contains_key(key) && !taken.contains_key(&number) {
+                record.insert(key.clone(), number);
+                reserved.push(number);
+            }
+        }
+        reserved.sort_unstable();
+        self.lock.insert(lock_key, record);
+        Ok((numbers, reserved))
+    }
+}
+
+fn reserved_line(reserved: &[u32]) -> String {
+    if reserved.is_empty() {
+        return String::new();
+    }
+    let numbers: Vec<String> = reserved.iter().map(u32::to_string).collect();
+    format!("  reserved {};\n", numbers.join(", "))
+}
+
+/// `employee_id` for `employeeId`, and `http_url` for `HTTPUrl`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+fn upper_snake_case(name: &str) -> String {
+    snake_case(name).to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::{ConcertoError, ModelManager};
+
+    use super::{FieldNumbers, LOCK_FILE, ProtobufOptions, generate, pinned_numbers, snake_case};
+
+    const PEOPLE: &str = r#"
+        namespace org.people@1.0.0
+        enum Grade { o JUNIOR o SENIOR }
+        abstract participant Person identified by email {
+          o String email
+        }
+        participant Employee extends Person {
+          o Grade grade
+          o DateTime hiredOn optional
+        }
+        participant Contractor extends Person {
+          o Long dayRate
+        }
+        "#;
+
+    const HR: &str = r#"
+        namespace org.hr@1.0.0
+        import org.people@1.0.0.{Person}
+        scalar Score extends Double range=[0.0, 10.0]
+        concept Review {
+          o Person subject
+          @ProtoField(7)
+          --> Person reviewer
+          o Score[] scores
+          o Person[] witnesses optional
+          o Notes notes
+        }
+        map Notes { o String o String }
+        map Calendar { o DateTime o Review }
+        "#;
+
+    fn load(sources: &[&str]) -> (ModelManager, FieldNumbers) {
+        let mut manager = ModelManager::new().unwrap();
+        let mut pinned = FieldNumbers::new();
+        for source in sources {
+            let ast = concerto_cto::parse(source).unwrap();
+            pinned.extend(pinned_numbers(&ast));
+            manager.add_model(&ast, None).unwrap();
+        }
+        manager.validate_models().unwrap();
+        (manager, pinned)
+    }
+
+    #[test]
+    fn each_namespace_is_a_package() {
+        let (manager, pinned) = load(&[PEOPLE, HR]);
+        let options = ProtobufOptions {
+            pinned,
+            ..ProtobufOptions::default()
+        };
+        let files = generate(&manager, &options).unwrap();
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "org/hr/v1_0_0/hr.proto",
+                "org/people/v1_0_0/people.proto",
+                LOCK_FILE
+            ]
+        );
+        assert_eq!(
+            files[0].contents,
+            r#"syntax = "proto3";
+
+package org.hr.v1_0_0;
+
+import "google/protobuf/timestamp.proto";
+import "org/people/v1_0_0/people.proto";
+
+message Review {
+  oneof subject {
+    .org.people.v1_0_0.Employee subject_employee = 8;
+    .org.people.v1_0_0.Contractor subject_contractor = 9;
+  }
+  string reviewer = 7;
+  repeated double scores = 10;
+  repeated .org.people.v1_0_0.Person witnesses = 11;
+  Notes notes = 12;
+}
+
+message Notes {
+  map<string, string> entries = 1;
+}
+
+message Calendar {
+  message Entry {
+    google.protobuf.Timestamp key = 1;
+    Review value = 2;
+  }
+  repeated Entry entries = 1;
+}
+"#
+        );
+        assert_eq!(
+            files[1].contents,
+            r#"syntax = "proto3";
+
+package org.people.v1_0_0;
+
+import "google/protobuf/timestamp.proto";
+
+enum Grade {
+  GRADE_UNSPECIFIED = 0;
+  GRADE_JUNIOR = 1;
+  GRADE_SENIOR = 2;
+}
+
+message Person {
+  oneof value {
+    Employee employee = 1;
+    Contractor contractor = 2;
+  }
+}
+
+message Employee {
+  Grade grade = 1;
+  optional google.protobuf.Timestamp hired_on = 2;
+  string email = 3;
+}
+
+message Contractor {
+  int64 day_rate = 1;
+  string email = 2;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn numbers_carry_over_through_the_lock() {
+        let (manager, _) = load(&[PEOPLE]);
+        let files = generate(&manager, &ProtobufOptions::default()).unwrap();
+        let lock: FieldNumbers = serde_json::from_str(&files[1].contents).unwrap();
+        assert_eq!(lock["org.people.Employee"]["email"], 3);
+
+        // The next version drops a field and adds two, one of them to the
+        // super type.
+        let next = PEOPLE
+            .replace("@1.0.0", "@2.0.0")
+            .replace("o String email", "o String email\n  o String name")
+            .replace("o DateTime hiredOn optional", "o Boolean remote")
+            .replace("o JUNIOR o SENIOR", "o SENIOR o LEAD");
+        let (manager, _) = load(&[&next]);
+        let options = ProtobufOptions {
+            lock,
+            ..ProtobufOptions::default()
+        };
+        let files = generate(&manager, &options).unwrap();
+        let proto = &files[0].contents;
+        assert!(proto.contains("package org.people.v2_0_0;"));
+        assert!(proto.contains(
+            "message Employee {\n  Grade grade = 1;\n  bool remote = 4;\n  string email = 3;\n  string name = 5;\n  reserved 2;\n}"
+        ));
+        assert!(proto.contains("  GRADE_SENIOR = 2;\n  GRADE_LEAD = 3;\n  reserved 1;\n"));
+        let lock: FieldNumbers = serde_json::from_str(&files[1].contents).unwrap();
+        assert_eq!(lock["org.people.Employee"]["hiredOn"], 2);
+    }
+
+    #[test]
+    fn pinned_numbers_must_be_distinct() {
+        let (manager, pinned) = load(&[r#"
+            namespace org.acme@1.0.0
+            concept Pair {
+              @ProtoField(1)
+              o String left
+              @ProtoField(1)
+              o String right
+            }
+            "#]);
+        let options = ProtobufOptions {
+            pinned,
+            ..ProtobufOptions::default()
+        };
+        assert!(matches!(
+            generate(&manager, &options),
+            Err(ConcertoError::IllegalModel { .. })
+        ));
+    }
+
+    #[test]
+    fn an_inherited_field_keeps_its_pinned_number() {
+        let (manager, pinned) = load(&[r#"
+            namespace org.acme@1.0.0
+            concept Person {
+              o String name
+              @ProtoField(9)
+              o String email
+            }
+            concept Employee extends Person {
+              o String badge
+            }
+            "#]);
+        let options = ProtobufOptions {
+            pinned,
+            ..ProtobufOptions::default()
+        };
+        let files = generate(&manager, &options).unwrap();
+        let proto = &files[0].contents;
+        assert!(
+            proto.contains(
+                "message Person {\n  string name = 10;\n  string email = 9;\n}\n\n\
+                 message Employee {\n  string badge = 10;\n  string name = 11;\n  string email = 9;\n}\n"
+            ),
+            "{proto}"
+        );
+    }
+
+    #[test]
+    fn names_are_snake_case() {
+        assert_eq!(snake_case("employeeId"), "employee_id");
+        assert_eq!(snake_case("HTTPUrl"), "http_url");
+        assert_eq!(snake_case("address2Line"), "address2_line");
+        assert_eq!(snake_case("SENIOR"), "senior");
+    }
+}
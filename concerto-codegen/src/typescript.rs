@@ -0,0 +1,463 @@
+//! TypeScript types for instances of the loaded models.
+//!
+//! Each namespace becomes a module, `<namespace>.ts`, that imports from the
+//! modules of the other namespaces every type it refers to there, whether
+//! its model imports the type or names it by its namespace. An imported type
+//! keeps the name the model knows it by, alias included; one named by its
+//! namespace keeps its own name unless the module already uses it. The
+//! system namespace gets a module too, but only when a model refers to one
+//! of its types other than as a super type.
+//!
+//! A class is an interface that `extends` its super type's, with a field for
+//! each property it declares; an optional property is an optional field and
+//! an array an array. The interface of a class without a super type also has
+//! the `$class` every instance carries. An enum is a string enum whose
+//! values are the names of its members, a scalar is an alias of its
+//! primitive's type, and a map is a `Record` from string keys to its values.
+//! Integer, Long and Double are all `number`, and a relationship is the
+//! `string` that identifies what it points at. A DateTime is a `string`, as
+//! it is in JSON, or a `Date` if the instances are revived first.
+
+use std::collections::HashMap;
+
+use concerto_core::introspect::declaration::MapDeclaration;
+use concerto_core::model_util::{is_primitive_type, namespace_of, short_name};
+use concerto_core::{
+    ClassDeclaration, Declaration, ModelFile, ModelManager, Property, Result, ScalarDeclaration,
+};
+
+use crate::GeneratedFile;
+use crate::model::{resolve, user_models};
+
+/// What to generate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeScriptOptions {
+    /// The type of DateTime fields and scalars.
+    pub date_time: DateTimeType,
+}
+
+/// The TypeScript type of a DateTime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateTimeType {
+    /// `string`, the ISO 8601 text an instance holds in JSON.
+    #[default]
+    String,
+    /// `Date`.
+    Date,
+}
+
+/// Generates a module for each namespace, `org.acme@1.0.0.ts` for
+/// `org.acme@1.0.0`.
+pub fn generate(manager: &ModelManager, options: &TypeScriptOptions) -> Result<Vec<GeneratedFile>> {
+    let mut models = user_models(manager);
+    let mut generators = models
+        .iter()
+        .map(|&model| Generator::new(manager, model, options))
+        .collect::<Result<Vec<_>>>()?;
+    let system = generators
+        .iter()
+        .flat_map(|generator| &generator.imports)
+        .filter_map(|(namespace, _)| manager.model_file(namespace))
+        .find(|model| model.is_system_namespace());
+    if let Some(system) = system {
+        models.insert(0, system);
+        generators.insert(0, Generator::new(manager, system, options)?);
+    }
+    models
+        .into_iter()
+        .zip(generators)
+        .map(|(model, generator)| {
+            Ok(GeneratedFile {
+                path: format!("{}.ts", model.namespace()),
+                contents: generator.module()?,
+            })
+        })
+        .collect()
+}
+
+struct Generator<'m> {
+    manager: &'m ModelManager,
+    model: &'m ModelFile,
+    options: &'m TypeScriptOptions,
+    /// The name each type from another namespace goes by in this module.
+    names: HashMap<String, String>,
+    /// What the module imports: each namespace, with the names it imports
+    /// from there as `import type` writes them.
+    imports: Vec<(String, Vec<String>)>,
+}
+
+impl<'m> Generator<'m> {
+    fn new(
+        manager: &'m ModelManager,
+        model: &'m ModelFile,
+        options: &'m TypeScriptOptions,
+    ) -> Result<Self> {
+        let mut generator = Self {
+            manager,
+            model,
+            options,
+            names: HashMap::new(),
+            imports: Vec::new(),
+        };
+        for fqn in generator.foreign_types()? {
+            let (namespace, name) = (namespace_of(&fqn), short_name(&fqn));
+            let local = generator.imported_name(&fqn).unwrap_or_else(|| {
+                let taken = model
+                    .resolve_local_type(name)
+                    .is_some_and(|resolved| resolved != fqn)
+                    || generator.names.values().any(|local| local == name);
+                match taken {
+                    true => {
+                        let unversioned = namespace.split('@').next().unwrap_or(namespace);
+                        format!("{}_{name}", unversioned.replace('.', "_"))
+                    }
+                    false => name.to_string(),
+                }
+            });
+            let specifier = match local == name {
+                true => local.clone(),
+                false => format!("{name} as {local}"),
+            };
+            match generator
+                .imports
+                .iter_mut()
+                .find(|(imported_from, _)| imported_from == namespace)
+            {
+                Some((_, specifiers)) => specifiers.push(specifier),
+                None => generator
+                    .imports
+                    .push((namespace.to_string(), vec![specifier])),
+            }
+            generator.names.insert(fqn, local);
+        }
+        Ok(generator)
+    }
+
+    /// Every type from another namespace that the module refers to, in the
+    /// order it first refers to them. A super type from the system namespace
+    /// is left out, as the interface stands in for it.
+    fn foreign_types(&self) -> Result<Vec<String>> {
+        let namespace = self.model.namespace();
+        let mut referred = Vec::new();
+        for declaration in self.model.declarations() {
+            match declaration {
+                Declaration::Class(class) => {
+                    if let Some(super_type) = class.super_type() {
+                        let fqn = resolve(self.manager, namespace, super_type)?;
+                        if !self.is_system_type(&fqn) {
+                            referred.push(fqn);
+                        }
+                    }
+                    for property in class.own_properties() {
+                        if let Property::Object(p) = property {
+                            referred.push(resolve(self.manager, namespace, &p.type_)?);
+                        }
+                    }
+                }
+                Declaration::Map(map) if map.value_kind() != "RelationshipMapValueType" => {
+                    if let Some(declared) = map.value_type() {
+                        referred.push(resolve(self.manager, namespace, declared)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut foreign: Vec<String> = Vec::new();
+        for fqn in referred {
+            if !is_primitive_type(&fqn)
+                && namespace_of(&fqn) != namespace
+                && !foreign.contains(&fqn)
+            {
+                foreign.push(fqn);
+            }
+        }
+        Ok(foreign)
+    }
+
+    fn module(&self) -> Result<String> {
+        let mut sections = Vec::new();
+        let imports = self.imports();
+        if !imports.is_empty() {
+            sections.push(imports);
+        }
+        for declaration in self.model.declarations() {
+            sections.push(match declaration {
+                Declaration::Class(class) => self.class(class)?,
+                Declaration::Enum(declaration) => {
+                    let members: String = declaration
+                        .properties
+                        .iter()
+                        .map(|member| format!("  {0} = '{0}',\n", member.name))
+                        .collect();
+                    format!("export enum {} {{\n{members}}}\n", declaration.name)
+                }
+                Declaration::Scalar(scalar) => self.scalar(scalar),
+                Declaration::Map(map) => self.map(map)?,
+            });
+        }
+        Ok(sections.join("\n"))
+    }
+
+    /// An `import type` for each namespace the module imports from.
+    fn imports(&self) -> String {
+        self.imports
+            .iter()
+            .map(|(namespace, names)| {
+                format!(
+                    "import type {{ {} }} from './{namespace}';\n",
+                    names.join(", ")
+                )
+            })
+            .collect()
+    }
+
+    fn class(&self, class: &ClassDeclaration) -> Result<String> {
+        let mut header = format!("export interface {}", class.name());
+        let mut fields = String::new();
+        let super_type = match class.super_type() {
+            Some(super_type) => Some(resolve(self.manager, self.model.namespace(), super_type)?),
+            None => None,
+        };
+        match super_type.filter(|fqn| !self.is_system_type(fqn)) {
+            Some(fqn) => header.push_str(&format!(" extends {}", self.local_name(&fqn))),
+            None => fields.push_str("  $class: string;\n"),
+        }
+        for property in class.own_properties() {
+            let mut type_name = self.property_type(property)?;
+            if property.is_array() {
+                type_name.push_str("[]");
+            }
+            let optional = if property.is_optional() { "?" } else { "" };
+            fields.push_str(&format!("  {}{optional}: {type_name};\n", property.name()));
+        }
+        Ok(format!("{header} {{\n{fields}}}\n"))
+    }
+
+    fn property_type(&self, property: &Property) -> Result<String> {
+        Ok(match property {
+            Property::Object(p) => {
+                self.type_name(&resolve(self.manager, self.model.namespace(), &p.type_)?)
+            }
+            Property::Relationship(_) | Property::Enum(_) => "string".to_string(),
+            primitive => self.primitive(primitive.type_name().unwrap_or("String")),
+        })
+    }
+
+    fn scalar(&self, scalar: &ScalarDeclaration) -> String {
+        format!(
+            "export type {} = {};\n",
+            scalar.name(),
+            self.primitive(scalar.scalar_type())
+        )
+    }
+
+    fn map(&self, map: &MapDeclaration) -> Result<String> {
+        let value = match (map.value_kind(), map.value_type()) {
+            ("RelationshipMapValueType", _) => "string".to_string(),
+            (_, Some(declared)) => {
+                self.type_name(&resolve(self.manager, self.model.namespace(), declared)?)
+            }
+            (kind, None) => self.primitive(kind.trim_end_matches("MapValueType")),
+        };
+        Ok(format!(
+            "export type {} = Record<string, {value}>;\n",
+            map.name()
+        ))
+    }
+
+    /// The TypeScript type of a value declared as `fqn`.
+    fn type_name(&self, fqn: &str) -> String {
+        match is_primitive_type(fqn) {
+            true => self.primitive(fqn),
+            false => self.local_name(fqn),
+        }
+    }
+
+    fn primitive(&self, primitive: &str) -> String {
+        match primitive {
+            "Boolean" => "boolean",
+            "Integer" | "Long" | "Double" => "number",
+            "DateTime" if self.options.date_time == DateTimeType::Date => "Date",
+            _ => "string",
+        }
+        .to_string()
+    }
+
+    /// The name `fqn` goes by in this module: its own name, or the one it is
+    /// imported under.
+    fn local_name(&self, fqn: &str) -> String {
+        self.names
+            .get(fqn)
+            .cloned()
+            .unwrap_or_else(|| short_name(fqn).to_string())
+    }
+
+    /// The name the model imports `fqn` under, if it imports it.
+    fn imported_name(&self, fqn: &str) -> Option<String> {
+        let (namespace, name) = (namespace_of(fqn), short_name(fqn));
+        self.model
+            .imports()
+            .iter()
+            .filter(|import| import.namespace() == namespace)
+            .find_map(|import| {
+                import
+                    .imported_names()
+                    .iter()
+                    .zip(import.local_names())
+                    .find(|(imported, _)| *imported == name)
+                    .map(|(_, local)| local.to_string())
+            })
+    }
+
+    fn is_system_type(&self, fqn: &str) -> bool {
+        self.manager
+            .model_file(namespace_of(fqn))
+            .is_some_and(ModelFile::is_system_namespace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::ModelManager;
+
+    use super::{DateTimeType, TypeScriptOptions, generate};
+
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        for source in [
+            r#"
+            namespace org.people@1.0.0
+            enum Grade { o JUNIOR o SENIOR }
+            scalar Email extends String regex=/^[^@]+@[^@]+$/
+            abstract participant Person identified by email {
+              o Email email
+              o String[] nicknames optional
+              o DateTime born
+            }
+            "#,
+            r#"
+            namespace org.hr@1.0.0
+            import org.people@1.0.0.{Person as Human, Grade}
+            participant Employee extends Human {
+              o Grade grade
+              o Integer age optional
+              --> Human manager optional
+              o Salaries salaries
+            }
+            map Salaries { o DateTime o Double }
+            "#,
+        ] {
+            let ast = concerto_cto::parse(source).unwrap();
+            manager.add_model(&ast, None).unwrap();
+        }
+        manager.validate_models().unwrap();
+        manager
+    }
+
+    #[test]
+    fn each_namespace_is_a_module() {
+        let files = generate(&manager(), &TypeScriptOptions::default()).unwrap();
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["org.hr@1.0.0.ts", "org.people@1.0.0.ts"]);
+        assert_eq!(
+            files[0].contents,
+            "import type { Person as Human, Grade } from './org.people@1.0.0';
+
+export interface Employee extends Human {
+  grade: Grade;
+  age?: number;
+  manager?: string;
+  salaries: Salaries;
+}
+
+export type Salaries = Record<string, number>;
+"
+        );
+        assert_eq!(
+            files[1].contents,
+            "export enum Grade {
+  JUNIOR = 'JUNIOR',
+  SENIOR = 'SENIOR',
+}
+
+export type Email = string;
+
+export interface Person {
+  $class: string;
+  email: Email;
+  nicknames?: string[];
+  born: string;
+}
+"
+        );
+    }
+
+    #[test]
+    fn types_from_other_namespaces_are_imported() {
+        let mut manager = manager();
+        let mut ast = concerto_cto::parse(
+            "namespace org.payroll@1.0.0
+            import concerto@1.0.0.{Concept}
+            concept Person { o String name }
+            concept Payslip {
+              o Person clerk
+              o Person payee
+              o Grade grade
+              o Concept extra
+            }
+            ",
+        )
+        .unwrap();
+        // CTO has no syntax for a namespaced reference, but a JSON AST does.
+        let properties = &mut ast["declarations"][1]["properties"];
+        properties[1]["type"]["namespace"] = "org.people@1.0.0".into();
+        properties[2]["type"]["namespace"] = "org.people@1.0.0".into();
+        manager.add_model(&ast, None).unwrap();
+        manager.validate_models().unwrap();
+
+        let files = generate(&manager, &TypeScriptOptions::default()).unwrap();
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "concerto@1.0.0.ts",
+                "org.hr@1.0.0.ts",
+                "org.payroll@1.0.0.ts",
+                "org.people@1.0.0.ts"
+            ]
+        );
+        assert!(
+            files[0]
+                .contents
+                .starts_with("export interface Concept {\n  $class: string;\n}\n")
+        );
+        assert_eq!(
+            files[2].contents,
+            "import type { Person as org_people_Person, Grade } from './org.people@1.0.0';
+import type { Concept } from './concerto@1.0.0';
+
+export interface Person {
+  $class: string;
+  name: string;
+}
+
+export interface Payslip {
+  $class: string;
+  clerk: Person;
+  payee: org_people_Person;
+  grade: Grade;
+  extra: Concept;
+}
+"
+        );
+    }
+
+    #[test]
+    fn date_times_can_be_dates() {
+        let options = TypeScriptOptions {
+            date_time: DateTimeType::Date,
+        };
+        let files = generate(&manager(), &options).unwrap();
+        assert!(files[1].contents.contains("  born: Date;\n"));
+    }
+}
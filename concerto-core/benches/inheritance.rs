@@ -0,0 +1,101 @@
+//! Inheritance lookups over a deep class hierarchy.
+//!
+//! `org.deep@1.0.0` declares `Level0` and, for every depth up to `DEPTH`, a
+//! `LevelN` extending the level above it, each with a few properties of its
+//! own. The `cold` benchmarks ask a freshly loaded manager, which has to walk
+//! and resolve the chain; the `warm` ones ask a manager that has answered the
+//! same question before, and so reads its remembered lineages.
+
+use std::hint::black_box;
+
+use concerto_core::ModelManager;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use serde_json::{Value, json};
+
+const DEPTH: usize = 64;
+const LEAF: &str = "org.deep@1.0.0.Level64";
+const ROOT: &str = "org.deep@1.0.0.Level0";
+
+fn deep_model() -> Value {
+    let declarations: Vec<Value> = (0..=DEPTH)
+        .map(|level| {
+            let properties: Vec<Value> = (0..4)
+                .map(|index| {
+                    json!({ "$class": "concerto.metamodel@1.0.0.StringProperty",
+                            "name": format!("field{level}x{index}"),
+                            "isArray": false, "isOptional": true })
+                })
+                .collect();
+            let mut declaration = json!({
+                "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+                "name": format!("Level{level}"),
+                "isAbstract": false,
+                "properties": properties
+            });
+            if level > 0 {
+                declaration["superType"] = json!({
+                    "$class": "concerto.metamodel@1.0.0.TypeIdentifier",
+                    "name": format!("Level{}", level - 1)
+                });
+            }
+            declaration
+        })
+        .collect();
+    json!({
+        "$class": "concerto.metamodel@1.0.0.Model",
+        "namespace": "org.deep@1.0.0",
+        "declarations": declarations
+    })
+}
+
+fn manager(model: &Value) -> ModelManager {
+    let mut manager = ModelManager::new().unwrap();
+    manager.add_model(model, None).unwrap();
+    manager
+}
+
+fn inheritance(c: &mut Criterion) {
+    let model = deep_model();
+    let warm = manager(&model);
+    warm.validate_models().unwrap();
+
+    let mut group = c.benchmark_group("get_all_properties");
+    group.bench_function("cold", |b| {
+        b.iter_batched_ref(
+            || manager(&model),
+            |manager| black_box(manager.get_all_properties(LEAF).unwrap().len()),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("warm", |b| {
+        b.iter(|| black_box(warm.get_all_properties(LEAF).unwrap().len()))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("is_assignable_to");
+    group.bench_function("cold", |b| {
+        b.iter_batched_ref(
+            || manager(&model),
+            |manager| black_box(manager.is_assignable_to(LEAF, ROOT).unwrap()),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("warm", |b| {
+        b.iter(|| black_box(warm.is_assignable_to(LEAF, ROOT).unwrap()))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("validate_models");
+    group.bench_function("cold", |b| {
+        b.iter_batched_ref(
+            || manager(&model),
+            |manager| manager.validate_models().unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("warm", |b| b.iter(|| warm.validate_models().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, inheritance);
+criterion_main!(benches);
@@ -0,0 +1,718 @@
+//! Comparison of two versions of a model.
+//!
+//! [`compare`] lines up the declarations of an old and a new [`ModelFile`]
+//! by name, and their fields and enum members by name within each, and lists
+//! what changed. Each [`Change`] is graded by the semantic-version bump it
+//! calls for: a change that can make an instance of the old model invalid,
+//! such as a removed type or a field made required, is [`Severity::Major`];
+//! one that only lets more through, such as a new optional field, is
+//! [`Severity::Minor`]; and one no instance can notice, such as a changed
+//! decorator, is [`Severity::Patch`].
+//!
+//! Types are compared by the names the models write, so a field whose type
+//! is imported from a newer version of the same namespace is unchanged. A
+//! changed validator is major whichever way it moves, since telling a
+//! loosened pattern from a tightened one is beyond a diff.
+
+use std::fmt;
+
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::introspect::declaration::{ClassDeclaration, Declaration, ScalarDeclaration};
+use crate::introspect::model_file::ModelFile;
+use crate::introspect::property::Property;
+use crate::model_util::qualify;
+
+/// The version bump a change calls for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Nothing an instance can notice.
+    Patch,
+    /// Every old instance is still valid.
+    Minor,
+    /// Some old instances may no longer be valid.
+    Major,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Patch => "patch",
+            Self::Minor => "minor",
+            Self::Major => "major",
+        })
+    }
+}
+
+impl Severity {
+    /// The bump from version `old` to version `new`, both written
+    /// `major.minor.patch`, or `None` if `new` is not higher or either is not
+    /// such a version. A pre-release or build suffix is ignored.
+    pub fn between(old: &str, new: &str) -> Option<Self> {
+        let (old, new) = (release(old)?, release(new)?);
+        if new <= old {
+            None
+        } else if new[0] > old[0] {
+            Some(Self::Major)
+        } else if new[1] > old[1] {
+            Some(Self::Minor)
+        } else {
+            Some(Self::Patch)
+        }
+    }
+}
+
+/// The major, minor and patch numbers of a version.
+fn release(version: &str) -> Option<[u64; 3]> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse().ok());
+    let release = [parts.next()??, parts.next()??, parts.next()??];
+    parts.next().is_none().then_some(release)
+}
+
+/// What kind of change was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// A declaration is new.
+    DeclarationAdded,
+    /// A declaration is gone.
+    DeclarationRemoved,
+    /// A declaration is now a different kind of declaration, such as an
+    /// asset that was a concept.
+    DeclarationKindChanged,
+    /// A class now extends a different type, or none.
+    SuperTypeChanged,
+    /// A class is now abstract, or no longer is.
+    AbstractChanged,
+    /// A class is now identified differently.
+    IdentityChanged,
+    /// A field or enum member is new.
+    PropertyAdded,
+    /// A field or enum member is gone.
+    PropertyRemoved,
+    /// A field holds a different type, or is now or no longer an array or a
+    /// relationship.
+    PropertyTypeChanged,
+    /// A field is now optional, or now required.
+    PropertyOptionalityChanged,
+    /// A field's or scalar's validator was added, removed or changed.
+    ValidatorChanged,
+    /// A field's or scalar's default changed.
+    DefaultChanged,
+    /// A scalar now wraps a different primitive, or a map holds different
+    /// keys or values.
+    ScalarOrMapTypeChanged,
+    /// The decorators on the model, a declaration or a field changed.
+    DecoratorsChanged,
+}
+
+/// One difference between the two versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The bump it calls for.
+    pub severity: Severity,
+    /// What changed.
+    pub kind: ChangeKind,
+    /// The declaration it is in, by short name; `None` for the model itself.
+    pub declaration: Option<String>,
+    /// The field or enum member it is in.
+    pub property: Option<String>,
+    /// A description, for people.
+    pub message: String,
+}
+
+/// The differences between two versions of a model.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparison {
+    /// Each difference, in the order of the new model's declarations, with
+    /// removals after the declarations they were in.
+    pub changes: Vec<Change>,
+}
+
+impl Comparison {
+    /// The bump the changes call for together, or `None` if there are none.
+    pub fn severity(&self) -> Option<Severity> {
+        self.changes.iter().map(|change| change.severity).max()
+    }
+
+    /// Whether any change is major.
+    pub fn is_breaking(&self) -> bool {
+        self.severity() == Some(Severity::Major)
+    }
+}
+
+/// Compares two versions of a model.
+pub fn compare(old: &ModelFile, new: &ModelFile) -> Comparison {
+    let mut comparison = Comparer {
+        changes: Vec::new(),
+        namespace: new.namespace(),
+    };
+    if decorator_names(old.decorators()) != decorator_names(new.decorators()) {
+        comparison.push(
+            Severity::Patch,
+            ChangeKind::DecoratorsChanged,
+            None,
+            None,
+            "The model's decorators changed".to_string(),
+        );
+    }
+    for declaration in new.declarations() {
+        match old.local_declaration(declaration.name()) {
+            Some(previous) => comparison.declaration(previous, declaration),
+            None => comparison.push(
+                Severity::Minor,
+                ChangeKind::DeclarationAdded,
+                Some(declaration.name()),
+                None,
+                format!("{} was added", comparison.fqn(declaration.name())),
+            ),
+        }
+    }
+    for declaration in old.declarations() {
+        if new.local_declaration(declaration.name()).is_none() {
+            comparison.push(
+                Severity::Major,
+                ChangeKind::DeclarationRemoved,
+                Some(declaration.name()),
+                None,
+                format!("{} was removed", comparison.fqn(declaration.name())),
+            );
+        }
+    }
+    Comparison {
+        changes: comparison.changes,
+    }
+}
+
+struct Comparer<'a> {
+    changes: Vec<Change>,
+    namespace: &'a str,
+}
+
+impl Comparer<'_> {
+    fn push(
+        &mut self,
+        severity: Severity,
+        kind: ChangeKind,
+        declaration: Option<&str>,
+        property: Option<&str>,
+        message: String,
+    ) {
+        self.changes.push(Change {
+            severity,
+            kind,
+            declaration: declaration.map(str::to_string),
+            property: property.map(str::to_string),
+            message,
+        });
+    }
+
+    fn fqn(&self, name: &str) -> String {
+        qualify(self.namespace, name)
+    }
+
+    fn declaration(&mut self, old: &Declaration, new: &Declaration) {
+        let name = new.name();
+        let fqn = self.fqn(name);
+        // A scalar of another primitive is still a scalar; that change is
+        // reported below.
+        let both_scalars = matches!((old, new), (Declaration::Scalar(_), Declaration::Scalar(_)));
+        if old.declaration_kind() != new.declaration_kind() && !both_scalars {
+            self.push(
+                Severity::Major,
+                ChangeKind::DeclarationKindChanged,
+                Some(name),
+                None,
+                format!(
+                    "{fqn} was a {} and is now a {}",
+                    old.declaration_kind(),
+                    new.declaration_kind()
+                ),
+            );
+            return;
+        }
+        if decorator_names(old.decorators()) != decorator_names(new.decorators()) {
+            self.push(
+                Severity::Patch,
+                ChangeKind::DecoratorsChanged,
+                Some(name),
+                None,
+                format!("The decorators of {fqn} changed"),
+            );
+        }
+        match (old, new) {
+            (Declaration::Class(old), Declaration::Class(new)) => {
+                let super_name = |class: &ClassDeclaration| {
+                    class.super_type().map(|super_type| super_type.name.clone())
+                };
+                if super_name(old) != super_name(new) {
+                    self.push(
+                        Severity::Major,
+                        ChangeKind::SuperTypeChanged,
+                        Some(name),
+                        None,
+                        format!(
+                            "{fqn} extended {} and now extends {}",
+                            super_name(old).as_deref().unwrap_or("nothing"),
+                            super_name(new).as_deref().unwrap_or("nothing"),
+                        ),
+                    );
+                }
+                if old.is_abstract() != new.is_abstract() {
+                    let (severity, message) = match new.is_abstract() {
+                        true => (Severity::Major, format!("{fqn} is now abstract")),
+                        false => (Severity::Minor, format!("{fqn} is no longer abstract")),
+                    };
+                    self.push(
+                        severity,
+                        ChangeKind::AbstractChanged,
+                        Some(name),
+                        None,
+                        message,
+                    );
+                }
+                if (old.is_identified(), old.identifier_field_name())
+                    != (new.is_identified(), new.identifier_field_name())
+                {
+                    self.push(
+                        Severity::Major,
+                        ChangeKind::IdentityChanged,
+                        Some(name),
+                        None,
+                        format!("{fqn} is identified differently"),
+                    );
+                }
+                self.properties(name, old.own_properties(), new.own_properties());
+            }
+            (Declaration::Enum(old), Declaration::Enum(new)) => {
+                for member in &new.properties {
+                    if !old.properties.iter().any(|m| m.name == member.name) {
+                        self.push(
+                            Severity::Minor,
+                            ChangeKind::PropertyAdded,
+                            Some(name),
+                            Some(&member.name),
+                            format!("{fqn} has a new member {}", member.name),
+                        );
+                    }
+                }
+                for member in &old.properties {
+                    if !new.properties.iter().any(|m| m.name == member.name) {
+                        self.push(
+                            Severity::Major,
+                            ChangeKind::PropertyRemoved,
+                            Some(name),
+                            Some(&member.name),
+                            format!("{fqn} no longer has the member {}", member.name),
+                        );
+                    }
+                }
+            }
+            (Declaration::Scalar(old), Declaration::Scalar(new)) => {
+                if old.scalar_type() != new.scalar_type() {
+                    self.push(
+                        Severity::Major,
+                        ChangeKind::ScalarOrMapTypeChanged,
+                        Some(name),
+                        None,
+                        format!(
+                            "{fqn} extended {} and now extends {}",
+                            old.scalar_type(),
+                            new.scalar_type()
+                        ),
+                    );
+                    return;
+                }
+                let (old, new) = (scalar_constraints(old), scalar_constraints(new));
+                self.constraints(name, None, &fqn, old, new);
+            }
+            (Declaration::Map(old), Declaration::Map(new)) => {
+                let side = |kind: &str, declared: Option<&mm::TypeIdentifier>| {
+                    (kind.to_string(), declared.map(|t| t.name.clone()))
+                };
+                if side(old.key_kind(), old.key_type()) != side(new.key_kind(), new.key_type())
+                    || side(old.value_kind(), old.value_type())
+                        != side(new.value_kind(), new.value_type())
+                {
+                    self.push(
+                        Severity::Major,
+                        ChangeKind::ScalarOrMapTypeChanged,
+                        Some(name),
+                        None,
+                        format!("{fqn} holds different keys or values"),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn properties(&mut self, declaration: &str, old: &[Property], new: &[Property]) {
+        let fqn = self.fqn(declaration);
+        for property in new {
+            let field = property.name();
+            let Some(previous) = old.iter().find(|p| p.name() == field) else {
+                let (severity, required) = match property.is_optional() {
+                    true => (Severity::Minor, "optional"),
+                    false => (Severity::Major, "required"),
+                };
+                self.push(
+                    severity,
+                    ChangeKind::PropertyAdded,
+                    Some(declaration),
+                    Some(field),
+                    format!("{fqn} has a new {required} field {field}"),
+                );
+                continue;
+            };
+            let shape = |p: &Property| {
+                (
+                    p.type_name().map(str::to_string),
+                    p.is_array(),
+                    p.is_relationship(),
+                )
+            };
+            if shape(previous) != shape(property) {
+                self.push(
+                    Severity::Major,
+                    ChangeKind::PropertyTypeChanged,
+                    Some(declaration),
+                    Some(field),
+                    format!(
+                        "{fqn}.{field} was {} and is now {}",
+                        describe(previous),
+                        describe(property)
+                    ),
+                );
+                continue;
+            }
+            if previous.is_optional() != property.is_optional() {
+                let (severity, message) = match property.is_optional() {
+                    true => (Severity::Minor, format!("{fqn}.{field} is now optional")),
+                    false => (Severity::Major, format!("{fqn}.{field} is now required")),
+                };
+                self.push(
+                    severity,
+                    ChangeKind::PropertyOptionalityChanged,
+                    Some(declaration),
+                    Some(field),
+                    message,
+                );
+            }
+            let subject = format!("{fqn}.{field}");
+            self.constraints(
+                declaration,
+                Some(field),
+                &subject,
+                property_constraints(previous),
+                property_constraints(property),
+            );
+            if decorator_names(previous.decorators()) != decorator_names(property.decorators()) {
+                self.push(
+                    Severity::Patch,
+                    ChangeKind::DecoratorsChanged,
+                    Some(declaration),
+                    Some(field),
+                    format!("The decorators of {subject} changed"),
+                );
+            }
+        }
+        for property in old {
+            let field = property.name();
+            if !new.iter().any(|p| p.name() == field) {
+                self.push(
+                    Severity::Major,
+                    ChangeKind::PropertyRemoved,
+                    Some(declaration),
+                    Some(field),
+                    format!("{fqn} no longer has the field {field}"),
+                );
+            }
+        }
+    }
+
+    /// Compares the validators and defaults of a field or scalar, `subject`.
+    fn constraints(
+        &mut self,
+        declaration: &str,
+        property: Option<&str>,
+        subject: &str,
+        old: Constraints,
+        new: Constraints,
+    ) {
+        if old.validators != new.validators {
+            let (severity, message) = match (old.validators.is_empty(), new.validators.is_empty()) {
+                (false, true) => (Severity::Minor, format!("{subject} is no longer validated")),
+                (true, _) => (Severity::Major, format!("{subject} is now validated")),
+                (false, false) => (
+                    Severity::Major,
+                    format!("{subject} is validated differently"),
+                ),
+            };
+            self.push(
+                severity,
+                ChangeKind::ValidatorChanged,
+                Some(declaration),
+                property,
+                message,
+            );
+        }
+        if old.default != new.default {
+            self.push(
+                Severity::Patch,
+                ChangeKind::DefaultChanged,
+                Some(declaration),
+                property,
+                format!("The default of {subject} changed"),
+            );
+        }
+    }
+}
+
+/// The validators of a field or scalar, as JSON, and its default.
+#[derive(Default)]
+struct Constraints {
+    validators: Vec<Value>,
+    default: Option<Value>,
+}
+
+impl Constraints {
+    fn new<D: Serialize>(validators: [Option<Value>; 2], default: Option<&D>) -> Self {
+        Self {
+            validators: validators.into_iter().flatten().collect(),
+            default: to_json(default),
+        }
+    }
+}
+
+fn property_constraints(property: &Property) -> Constraints {
+    match property {
+        Property::String(p) => Constraints::new(
+            [
+                to_json(p.validator.as_ref()),
+                to_json(p.length_validator.as_ref()),
+            ],
+            p.default_value.as_ref(),
+        ),
+        Property::Integer(p) => Constraints::new(
+            [to_json(p.validator.as_ref()), None],
+            p.default_value.as_ref(),
+        ),
+        Property::Long(p) => Constraints::new(
+            [to_json(p.validator.as_ref()), None],
+            p.default_value.as_ref(),
+        ),
+        Property::Double(p) => Constraints::new(
+            [to_json(p.validator.as_ref()), None],
+            p.default_value.as_ref(),
+        ),
+        Property::Boolean(p) => Constraints::new([None, None], p.default_value.as_ref()),
+        Property::Object(p) => Constraints::new([None, None], p.default_value.as_ref()),
+        _ => Constraints::default(),
+    }
+}
+
+fn scalar_constraints(scalar: &ScalarDeclaration) -> Constraints {
+    match scalar {
+        ScalarDeclaration::String(s) => Constraints::new(
+            [
+                to_json(s.validator.as_ref()),
+                to_json(s.length_validator.as_ref()),
+            ],
+            s.default_value.as_ref(),
+        ),
+        ScalarDeclaration::Integer(s) => Constraints::new(
+            [to_json(s.validator.as_ref()), None],
+            s.default_value.as_ref(),
+        ),
+        ScalarDeclaration::Long(s) => Constraints::new(
+            [to_json(s.validator.as_ref()), None],
+            s.default_value.as_ref(),
+        ),
+        ScalarDeclaration::Double(s) => Constraints::new(
+            [to_json(s.validator.as_ref()), None],
+            s.default_value.as_ref(),
+        ),
+        ScalarDeclaration::Boolean(s) => Constraints::new([None, None], s.default_value.as_ref()),
+        ScalarDeclaration::DateTime(s) => Constraints::new([None, None], s.default_value.as_ref()),
+    }
+}
+
+fn to_json<T: Serialize>(value: Option<&T>) -> Option<Value> {
+    value.and_then(|value| serde_json::to_value(value).ok())
+}
+
+/// A field's type, as a model writes it, such as `String[]` or `--> Person`.
+fn describe(property: &Property) -> String {
+    let arrow = match property.is_relationship() {
+        true => "--> ",
+        false => "",
+    };
+    let array = match property.is_array() {
+        true => "[]",
+        false => "",
+    };
+    format!("{arrow}{}{array}", property.type_name().unwrap_or_default())
+}
+
+fn decorator_names(decorators: &[mm::Decorator]) -> Vec<&str> {
+    decorators
+        .iter()
+        .map(|decorator| decorator.name.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{ChangeKind, Severity, compare};
+    use crate::introspect::model_file::ModelFile;
+
+    fn model(declarations: serde_json::Value) -> ModelFile {
+        ModelFile::from_json(
+            &json!({
+                "$class": "concerto.metamodel@1.0.0.Model",
+                "namespace": "org.acme@1.0.0",
+                "declarations": declarations,
+            }),
+            None,
+        )
+        .unwrap()
+    }
+
+    fn person(properties: serde_json::Value) -> serde_json::Value {
+        json!({
+            "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+            "name": "Person", "isAbstract": false, "properties": properties,
+        })
+    }
+
+    fn string(name: &str, optional: bool) -> serde_json::Value {
+        json!({
+            "$class": "concerto.metamodel@1.0.0.StringProperty",
+            "name": name, "isArray": false, "isOptional": optional,
+        })
+    }
+
+    #[test]
+    fn identical_models_have_no_changes() {
+        let old = model(json!([person(json!([string("name", false)]))]));
+        let comparison = compare(&old, &old);
+        assert!(comparison.changes.is_empty());
+        assert_eq!(comparison.severity(), None);
+    }
+
+    #[test]
+    fn changes_are_graded_by_the_bump_they_need() {
+        let old = model(json!([
+            person(json!([string("name", false), string("email", true), string("phone", false)])),
+            { "$class": "concerto.metamodel@1.0.0.EnumDeclaration", "name": "Grade",
+              "properties": [
+                { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "JUNIOR" },
+                { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "SENIOR" }
+              ] }
+        ]));
+        let new = model(json!([
+            person(json!([
+                string("name", true),
+                string("email", false),
+                string("nickname", true),
+                {
+                    "$class": "concerto.metamodel@1.0.0.StringProperty",
+                    "name": "phone", "isArray": false, "isOptional": false,
+                    "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator", "pattern": "^\\+", "flags": "" }
+                }
+            ])),
+            { "$class": "concerto.metamodel@1.0.0.EnumDeclaration", "name": "Grade",
+              "properties": [
+                { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "JUNIOR" },
+                { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "PRINCIPAL" }
+              ] },
+            { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+              "name": "Team", "isAbstract": false, "properties": [] }
+        ]));
+        let comparison = compare(&old, &new);
+        let found: Vec<_> = comparison
+            .changes
+            .iter()
+            .map(|change| (change.severity, change.kind, change.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    Severity::Minor,
+                    ChangeKind::PropertyOptionalityChanged,
+                    "org.acme@1.0.0.Person.name is now optional"
+                ),
+                (
+                    Severity::Major,
+                    ChangeKind::PropertyOptionalityChanged,
+                    "org.acme@1.0.0.Person.email is now required"
+                ),
+                (
+                    Severity::Minor,
+                    ChangeKind::PropertyAdded,
+                    "org.acme@1.0.0.Person has a new optional field nickname"
+                ),
+                (
+                    Severity::Major,
+                    ChangeKind::ValidatorChanged,
+                    "org.acme@1.0.0.Person.phone is now validated"
+                ),
+                (
+                    Severity::Minor,
+                    ChangeKind::PropertyAdded,
+                    "org.acme@1.0.0.Grade has a new member PRINCIPAL"
+                ),
+                (
+                    Severity::Major,
+                    ChangeKind::PropertyRemoved,
+                    "org.acme@1.0.0.Grade no longer has the member SENIOR"
+                ),
+                (
+                    Severity::Minor,
+                    ChangeKind::DeclarationAdded,
+                    "org.acme@1.0.0.Team was added"
+                ),
+            ]
+        );
+        assert!(comparison.is_breaking());
+    }
+
+    #[test]
+    fn a_removed_or_retyped_declaration_is_major() {
+        let old = model(json!([person(json!([string("name", false)]))]));
+        let new = model(json!([{
+            "$class": "concerto.metamodel@1.0.0.AssetDeclaration",
+            "name": "Person", "isAbstract": false, "properties": [],
+            "identified": { "$class": "concerto.metamodel@1.0.0.Identified" }
+        }]));
+        let comparison = compare(&old, &new);
+        assert_eq!(comparison.changes.len(), 1);
+        assert_eq!(
+            comparison.changes[0].kind,
+            ChangeKind::DeclarationKindChanged
+        );
+        assert_eq!(
+            compare(&old, &model(json!([]))).changes[0].kind,
+            ChangeKind::DeclarationRemoved
+        );
+    }
+
+    #[test]
+    fn the_bump_between_two_versions() {
+        assert_eq!(Severity::between("1.0.0", "2.0.0"), Some(Severity::Major));
+        assert_eq!(Severity::between("1.4.2", "1.5.0"), Some(Severity::Minor));
+        assert_eq!(
+            Severity::between("1.0.0", "1.0.1-rc.1"),
+            Some(Severity::Patch)
+        );
+        assert_eq!(Severity::between("2.0.0", "1.9.0"), None);
+        assert_eq!(Severity::between("1.0.0", "1.0.0"), None);
+        assert_eq!(Severity::between("1.0", "2.0"), None);
+    }
+}
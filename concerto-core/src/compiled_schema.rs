@@ -0,0 +1,1115 @@
+//! A validation plan compiled once from a [`ModelManager`].
+//!
+//! [`ModelManager::validate_instance`] works from the model as loaded: for
+//! every instance it walks each type's inheritance chain, resolves each type
+//! name through the imports of the namespace it is written in, and compiles
+//! each regular expression it meets. That is fine for a few instances, but
+//! not for a stream of millions.
+//!
+//! A [`CompiledSchema`] does all of that once. Each class gets a flat table
+//! of the fields it declares or inherits, in the order
+//! [`ModelManager::get_all_properties`] lists them, with the type of each
+//! field already resolved to an entry in the schema and its validators
+//! compiled. Type and field names are interned, so each distinct name is held
+//! once. Validating an instance is then a walk of the instance against those
+//! tables. Where a problem is found, it is reported exactly as
+//! [`ModelManager::validate_instance`] reports it; the JSON Pointer and the
+//! message are only built then.
+//!
+//! The schema owns everything it needs and does not borrow the manager. It
+//! is `Send` and `Sync`, so one schema can be shared between threads, behind
+//! an [`Arc`](std::sync::Arc) for instance.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use fancy_regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::error::{ConcertoError, Result};
+use crate::instance_validation::{
+    Checked, INSTANCE_SYSTEM_PROPERTIES, Identity, check_boolean, check_datetime, check_double,
+    check_integer, check_long, check_string, expected, invalid_instance, primitive_of,
+};
+use crate::introspect::compile_pattern;
+use crate::introspect::declaration::{Declaration, MapDeclaration, ScalarDeclaration};
+use crate::introspect::import::Import;
+use crate::introspect::property::Property;
+use crate::model_manager::ModelManager;
+use crate::model_util::{is_primitive_type, namespace_of, qualify};
+use crate::runtime::Relationship;
+use crate::symbol::Symbol;
+use crate::validation::resolve;
+
+/// The types of a [`ModelManager`], compiled for validating instances.
+#[derive(Debug, Clone)]
+pub struct CompiledSchema {
+    types: Vec<Entry>,
+    by_name: HashMap<Symbol, usize>,
+    scopes: Vec<Scope>,
+}
+
+impl CompiledSchema {
+    /// Compiles every type `manager` has loaded. Fails only if a class's
+    /// super types cannot be resolved or are circular, or if a regular
+    /// expression does not compile. The models are not otherwise checked: a
+    /// type a field names that is not declared is reported when an instance
+    /// reaches that field. Run [`ModelManager::validate_models`] first to
+    /// catch such problems up front.
+    pub fn new(manager: &ModelManager) -> Result<Self> {
+        Compiler::new(manager).compile()
+    }
+
+    /// Validates a JSON instance against the type its `$class` names, as
+    /// [`ModelManager::validate_instance`] does.
+    pub fn validate(&self, instance: &Value) -> Result<()> {
+        let object = instance.as_object().ok_or_else(|| {
+            invalid_instance(
+                "",
+                None,
+                None,
+                format!("An instance must be an object, not {instance}"),
+            )
+        })?;
+        let class_name = object
+            .get("$class")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                invalid_instance(
+                    "/$class",
+                    None,
+                    None,
+                    "The instance does not name its type in $class".into(),
+                )
+            })?;
+        let index = self.named(class_name, || {
+            invalid_instance(
+                "",
+                Some(class_name),
+                Some(class_name),
+                format!("{class_name} is not a declared type"),
+            )
+        })?;
+        self.class_instance(index, object, &Path::Root)
+    }
+
+    /// Validates a JSON instance that is declared to be a `type_name`, as
+    /// [`ModelManager::validate_instance_as`] does.
+    pub fn validate_as(&self, type_name: &str, instance: &Value) -> Result<()> {
+        let object = instance.as_object().ok_or_else(|| {
+            invalid_instance(
+                "",
+                Some(type_name),
+                None,
+                format!("An instance of {type_name} must be an object, not {instance}"),
+            )
+        })?;
+        let class = object.get("$class").and_then(Value::as_str);
+        let concrete = self.concrete(type_name, object, |problem| {
+            invalid_instance(
+                "",
+                Some(type_name),
+                class,
+                format!("The instance {problem}"),
+            )
+        })?;
+        self.class_instance(concrete, object, &Path::Root)
+    }
+
+    /// The number of types in the schema.
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    /// `true` if the schema has no types.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    fn index(&self, fqn: &str) -> Result<usize> {
+        self.by_name
+            .get(fqn)
+            .copied()
+            .ok_or_else(|| ConcertoError::TypeNotFound {
+                type_name: fqn.to_string(),
+            })
+    }
+
+    /// The index of a type that an instance names, in its `$class` or in a
+    /// relationship. A type that is not in the schema is a fault of the
+    /// instance, reported by `unknown`.
+    fn named(&self, fqn: &str, unknown: impl FnOnce() -> ConcertoError) -> Result<usize> {
+        self.by_name.get(fqn).copied().ok_or_else(unknown)
+    }
+
+    /// Works out which class an object declared as `declared` really is, from
+    /// its `$class`, as `concrete_type` does.
+    fn concrete(
+        &self,
+        declared: &str,
+        object: &Map<String, Value>,
+        invalid: impl Fn(String) -> ConcertoError,
+    ) -> Result<usize> {
+        match object.get("$class") {
+            None => {
+                let index = self.index(declared)?;
+                if matches!(&self.types[index], Entry::Class(class) if class.is_abstract) {
+                    return Err(invalid(format!(
+                        "is declared as the abstract type {declared}, so it must name a concrete subtype in $class"
+                    )));
+                }
+                Ok(index)
+            }
+            Some(Value::String(class_name)) => {
+                let index = self.named(class_name, || {
+                    invalid(format!(
+                        "has $class {class_name}, which is not a declared type"
+                    ))
+                })?;
+                if !self.is_assignable(index, declared) {
+                    return Err(invalid(format!(
+                        "has $class {class_name}, which is not a subtype of {declared}"
+                    )));
+                }
+                Ok(index)
+            }
+            Some(other) => Err(invalid(format!(
+                "has the $class {other}, which is not a type name"
+            ))),
+        }
+    }
+
+    /// `true` if the type at `index` is a class that is, or extends, the
+    /// type `fqn`.
+    fn is_assignable(&self, index: usize, fqn: &str) -> bool {
+        match &self.types[index] {
+            Entry::Class(class) => class.supertypes.contains(fqn),
+            _ => false,
+        }
+    }
+
+    /// Validates an object, at `path`, as an instance of the type at `index`.
+    fn class_instance(
+        &self,
+        index: usize,
+        object: &Map<String, Value>,
+        path: &Path<'_>,
+    ) -> Result<()> {
+        let entry = &self.types[index];
+        let fqn = entry.fqn();
+        let invalid = |message| invalid_instance(&path.render(), Some(fqn), Some(fqn), message);
+        let class = match entry {
+            Entry::Class(class) if class.is_abstract => {
+                return Err(invalid(format!(
+                    "Cannot instantiate the abstract type {fqn}"
+                )));
+            }
+            Entry::Class(class) => class,
+            _ => {
+                return Err(invalid(format!(
+                    "{fqn} is not a class and cannot be instantiated"
+                )));
+            }
+        };
+
+        for key in object.keys() {
+            if !class.fields_by_name.contains_key(key.as_str())
+                && !INSTANCE_SYSTEM_PROPERTIES.contains(&key.as_str())
+            {
+                return Err(invalid_instance(
+                    &Path::Key(path, key).render(),
+                    None,
+                    Some(fqn),
+                    format!("Instance of {fqn} has a property named {key}, which is not declared"),
+                ));
+            }
+        }
+        for field in &class.fields {
+            self.field(fqn, field, object.get(&*field.name), path)?;
+        }
+        class
+            .identity
+            .check(fqn, object, |key| Path::Key(path, key).render())
+    }
+
+    /// Checks one field of the instance of `fqn` at `path`.
+    fn field(
+        &self,
+        fqn: &str,
+        field: &Field,
+        value: Option<&Value>,
+        path: &Path<'_>,
+    ) -> Result<()> {
+        let path = Path::Key(path, &field.name);
+        let description = Description::Field(&field.description);
+        let spot = Spot {
+            path: &path,
+            description: &description,
+            class: fqn,
+            expected_type: field.expected_type.as_deref(),
+        };
+        let value = match value {
+            None | Some(Value::Null) if field.is_optional => return Ok(()),
+            None | Some(Value::Null) => {
+                return Err(spot.error(format!(
+                    "Instance of {fqn} is missing the required field {}",
+                    field.name
+                )));
+            }
+            Some(value) => value,
+        };
+        if !field.is_array {
+            return self.value(&field.check, value, &spot);
+        }
+        let items = value
+            .as_array()
+            .ok_or_else(|| spot.invalid(expected("an array", value)))?;
+        for (index, item) in items.iter().enumerate() {
+            let path = Path::Index(&path, index);
+            let element = Spot {
+                path: &path,
+                expected_type: field.element_type.as_deref(),
+                ..spot
+            };
+            self.value(&field.check, item, &element)?;
+        }
+        Ok(())
+    }
+
+    /// Checks a single value against a compiled check.
+    fn value(&self, check: &Check, value: &Value, spot: &Spot<'_>) -> Result<()> {
+        match check {
+            Check::Any => Ok(()),
+            Check::Primitive(primitive) => spot.check(primitive.check(value)),
+            Check::Declared(index) => self.declared(*index, value, spot),
+            Check::Relationship(relationship) => self.relationship(relationship, value, spot),
+            Check::Unresolved(name) => Err(spot.invalid(format_args!(
+                "is declared as {name}, which is not a declared type"
+            ))),
+            Check::Unsupported(problem) => Err(spot.invalid(problem)),
+        }
+    }
+
+    /// Checks a value whose type is the declared type at `index`.
+    fn declared(&self, index: usize, value: &Value, spot: &Spot<'_>) -> Result<()> {
+        match &self.types[index] {
+            Entry::Class(class) => {
+                let object = value.as_object().ok_or_else(|| {
+                    spot.invalid(expected(&format!("an instance of {}", class.fqn), value))
+                })?;
+                let concrete =
+                    self.concrete(&class.fqn, object, |problem| spot.invalid(problem))?;
+                self.class_instance(concrete, object, spot.path)
+            }
+            Entry::Enum(entry) => match value.as_str() {
+                Some(member) if entry.members.contains(member) => Ok(()),
+                _ => Err(spot.invalid(expected(
+                    &format!("a value of the enum {}", entry.fqn),
+                    value,
+                ))),
+            },
+            Entry::Scalar(entry) => spot.check(entry.primitive.check(value)),
+            Entry::Map(map) => self.map(map, value, spot),
+        }
+    }
+
+    /// Checks a map instance.
+    fn map(&self, map: &MapEntry, value: &Value, spot: &Spot<'_>) -> Result<()> {
+        let fqn = &*map.fqn;
+        let object = value.as_object().ok_or_else(|| {
+            spot.invalid(expected(&format!("an instance of the map {fqn}"), value))
+        })?;
+        match object.get("$class") {
+            Some(Value::String(class_name)) if class_name == fqn => {}
+            Some(other) => {
+                return Err(spot.invalid(format_args!(
+                    "has the $class {other}, but is declared as the map {fqn}"
+                )));
+            }
+            None => {
+                return Err(spot.invalid(format_args!(
+                    "is a map, so it must name the map {fqn} in $class"
+                )));
+            }
+        }
+        for (key, entry) in object {
+            if key == "$class" {
+                continue;
+            }
+            let path = Path::Key(spot.path, key);
+            let description = Description::Key(key, spot.description);
+            let key_spot = Spot {
+                path: &path,
+                description: &description,
+                class: fqn,
+                expected_type: map.key_type.as_deref(),
+            };
+            if !matches!(map.key, Check::Any) {
+                self.value(&map.key, &Value::String(key.clone()), &key_spot)?;
+            }
+            let description = Description::Value(key, spot.description);
+            let value_spot = Spot {
+                description: &description,
+                expected_type: map.value_type.as_deref(),
+                ..key_spot
+            };
+            self.value(&map.value, entry, &value_spot)?;
+        }
+        Ok(())
+    }
+
+    /// Checks a relationship value.
+    fn relationship(
+        &self,
+        check: &RelationshipCheck,
+        value: &Value,
+        spot: &Spot<'_>,
+    ) -> Result<()> {
+        let target = &*check.target;
+        let expectation = || format!("a relationship such as resource:{target}#ID");
+        let reference = value
+            .as_str()
+            .ok_or_else(|| spot.invalid(expected(&expectation(), value)))?;
+        let scope = &self.scopes[check.scope];
+        let relationship = Relationship::resolve_with(reference, |short| scope.resolve(short))
+            .map_err(|error| match error {
+                ConcertoError::ValidationFailed { .. } => {
+                    spot.invalid(expected(&expectation(), value))
+                }
+                ConcertoError::TypeNotFound { type_name } => spot.invalid(format_args!(
+                    "refers to {type_name}, which is not a declared type"
+                )),
+                other => other,
+            })?;
+        let referenced = relationship.fully_qualified_type();
+        let index = self.named(referenced, || {
+            spot.invalid(format_args!(
+                "refers to {referenced}, which is not a declared type"
+            ))
+        })?;
+        if !self.is_assignable(index, target) {
+            return Err(spot.invalid(format_args!(
+                "expects a relationship to {target}, not to {referenced}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// One compiled type.
+#[derive(Debug, Clone)]
+enum Entry {
+    Class(ClassEntry),
+    Enum(EnumEntry),
+    Scalar(ScalarEntry),
+    Map(MapEntry),
+}
+
+impl Entry {
+    fn fqn(&self) -> &str {
+        match self {
+            Self::Class(entry) => &entry.fqn,
+            Self::Enum(entry) => &entry.fqn,
+            Self::Scalar(entry) => &entry.fqn,
+            Self::Map(entry) => &entry.fqn,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ClassEntry {
+    fqn: Symbol,
+    is_abstract: bool,
+    /// Every field, own and inherited, in inheritance order.
+    fields: Vec<Field>,
+    fields_by_name: HashMap<Symbol, usize>,
+    /// The class and everything it extends.
+    supertypes: HashSet<Symbol>,
+    identity: Identity<Symbol>,
+}
+
+#[derive(Debug, Clone)]
+struct EnumEntry {
+    fqn: Symbol,
+    members: HashSet<Symbol>,
+}
+
+#[derive(Debug, Clone)]
+struct ScalarEntry {
+    fqn: Symbol,
+    primitive: Primitive,
+}
+
+#[derive(Debug, Clone)]
+struct MapEntry {
+    fqn: Symbol,
+    key: Check,
+    key_type: Option<Symbol>,
+    value: Check,
+    value_type: Option<Symbol>,
+}
+
+/// A field of a class, with everything needed to check and report on it.
+#[derive(Debug, Clone)]
+struct Field {
+    name: Symbol,
+    /// As in `field email of org.acme@1.0.0.Person`.
+    description: Box<str>,
+    is_optional: bool,
+    is_array: bool,
+    /// The declared type, with `[]` for an array.
+    expected_type: Option<Symbol>,
+    /// The declared type of each element of an array.
+    element_type: Option<Symbol>,
+    check: Check,
+}
+
+/// How a value is checked.
+#[derive(Debug, Clone)]
+enum Check {
+    /// Anything goes.
+    Any,
+    Primitive(Primitive),
+    /// The declared type at this index.
+    Declared(usize),
+    Relationship(Box<RelationshipCheck>),
+    /// A type name that does not resolve.
+    Unresolved(Symbol),
+    /// A value that cannot be checked, for this reason.
+    Unsupported(Box<str>),
+}
+
+/// A primitive, with its validators.
+#[derive(Debug, Clone)]
+enum Primitive {
+    Boolean,
+    String {
+        pattern: Option<Box<(Regex, mm::StringRegexValidator)>>,
+        length: Option<mm::StringLengthValidator>,
+    },
+    Integer(Option<(Option<i32>, Option<i32>)>),
+    Long(Option<(Option<i64>, Option<i64>)>),
+    Double(Option<(Option<f64>, Option<f64>)>),
+    DateTime,
+}
+
+impl Primitive {
+    fn check(&self, value: &Value) -> Checked {
+        match self {
+            Self::Boolean => check_boolean(value),
+            Self::String { pattern, length } => check_string(
+                value,
+                pattern
+                    .as_deref()
+                    .map(|(regex, validator)| (regex, validator)),
+                length.as_ref(),
+            ),
+            Self::Integer(domain) => check_integer(value, *domain),
+            Self::Long(domain) => check_long(value, *domain),
+            Self::Double(domain) => check_double(value, *domain),
+            Self::DateTime => check_datetime(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RelationshipCheck {
+    target: Symbol,
+    /// The namespace a short type name in a reference resolves in.
+    scope: usize,
+}
+
+/// The names visible in a namespace, for resolving short type names in
+/// relationship references the way the namespace's model file would.
+#[derive(Debug, Clone)]
+struct Scope {
+    namespace: Symbol,
+    locals: HashSet<Symbol>,
+    imports: Vec<Import>,
+}
+
+impl Scope {
+    fn resolve(&self, short: &str) -> Result<String> {
+        if is_primitive_type(short) {
+            return Ok(short.to_string());
+        }
+        if self.locals.contains(short) {
+            return Ok(qualify(&self.namespace, short));
+        }
+        self.imports
+            .iter()
+            .find_map(|import| import.resolve(short))
+            .ok_or_else(|| ConcertoError::TypeNotFound {
+                type_name: qualify(&self.namespace, short),
+            })
+    }
+}
+
+/// Where a value is, as a JSON Pointer built up on the stack and only
+/// written out when there is a problem to report.
+enum Path<'p> {
+    Root,
+    Key(&'p Path<'p>, &'p str),
+    Index(&'p Path<'p>, usize),
+}
+
+impl Path<'_> {
+    fn render(&self) -> String {
+        match self {
+            Self::Root => String::new(),
+            Self::Key(parent, key) => format!(
+                "{}/{}",
+                parent.render(),
+                key.replace('~', "~0").replace('/', "~1")
+            ),
+            Self::Index(parent, index) => format!("{}/{index}", parent.render()),
+        }
+    }
+}
+
+/// What a value is, for messages: a field, or a key or value of a map that
+/// is itself described.
+#[derive(Clone, Copy)]
+enum Description<'p> {
+    Field(&'p str),
+    Key(&'p str, &'p Description<'p>),
+    Value(&'p str, &'p Description<'p>),
+}
+
+impl fmt::Display for Description<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field(text) => f.write_str(text),
+            Self::Key(key, parent) => write!(f, "key {key:?} of {parent}"),
+            Self::Value(key, parent) => write!(f, "value at key {key:?} of {parent}"),
+        }
+    }
+}
+
+/// A value being checked, for reporting a problem with it.
+#[derive(Clone, Copy)]
+struct Spot<'p> {
+    path: &'p Path<'p>,
+    description: &'p Description<'p>,
+    class: &'p str,
+    expected_type: Option<&'p str>,
+}
+
+impl Spot<'_> {
+    fn error(&self, message: String) -> ConcertoError {
+        invalid_instance(
+            &self.path.render(),
+            self.expected_type,
+            Some(self.class),
+            message,
+        )
+    }
+
+    fn invalid(&self, problem: impl fmt::Display) -> ConcertoError {
+        self.error(format!("The {} {problem}", self.description))
+    }
+
+    fn check(&self, checked: Checked) -> Result<()> {
+        checked.map_err(|problem| self.invalid(problem))
+    }
+}
+
+/// Builds a [`CompiledSchema`] from the types of a [`ModelManager`].
+struct Compiler<'m> {
+    manager: &'m ModelManager,
+    by_name: HashMap<Symbol, usize>,
+    scopes: Vec<Scope>,
+    scope_index: HashMap<String, usize>,
+}
+
+impl<'m> Compiler<'m> {
+    fn new(manager: &'m ModelManager) -> Self {
+        Self {
+            manager,
+            by_name: HashMap::new(),
+            scopes: Vec::new(),
+            scope_index: HashMap::new(),
+        }
+    }
+
+    fn compile(mut self) -> Result<CompiledSchema> {
+        // Number every declaration first, so that a check can refer to a
+        // type that is compiled after it.
+        let mut declarations = Vec::new();
+        let mut model_files: Vec<_> = self.manager.model_files().collect();
+        model_files.sort_by(|a, b| a.namespace().cmp(b.namespace()));
+        for model_file in model_files {
+            for declaration in model_file.declarations() {
+                let fqn = Symbol::intern(&qualify(model_file.namespace(), declaration.name()));
+                self.by_name.insert(fqn.clone(), declarations.len());
+                declarations.push((fqn, declaration));
+            }
+        }
+        let types = declarations
+            .into_iter()
+            .map(|(fqn, declaration)| self.entry(fqn, declaration))
+            .collect::<Result<_>>()?;
+        Ok(CompiledSchema {
+            types,
+            by_name: self.by_name,
+            scopes: self.scopes,
+        })
+    }
+
+    fn entry(&mut self, fqn: Symbol, declaration: &'m Declaration) -> Result<Entry> {
+        Ok(match declaration {
+            Declaration::Class(class) => {
+                let chain = self.manager.super_chain(&fqn)?;
+                let mut fields = Vec::new();
+                for (owner, class) in &chain {
+                    for property in class.own_properties() {
+                        fields.push(self.field(&fqn, owner, property)?);
+                    }
+                }
+                let identity = match Identity::of(&chain) {
+                    Identity::None => Identity::None,
+                    Identity::System => Identity::System,
+                    Identity::Field(field_name) => Identity::Field(Symbol::intern(field_name)),
+                };
+                Entry::Class(ClassEntry {
+                    is_abstract: class.is_abstract(),
+                    fields_by_name: fields
+                        .iter()
+                        .enumerate()
+                        .map(|(index, field)| (field.name.clone(), index))
+                        .collect(),
+                    fields,
+                    supertypes: chain.iter().map(|(owner, _)| owner.clone()).collect(),
+                    identity,
+                    fqn,
+                })
+            }
+            Declaration::Enum(declaration) => Entry::Enum(EnumEntry {
+                members: declaration
+                    .properties
+                    .iter()
+                    .map(|member| Symbol::intern(&member.name))
+                    .collect(),
+                fqn,
+            }),
+            Declaration::Scalar(scalar) => Entry::Scalar(ScalarEntry {
+                primitive: scalar_primitive(scalar)?,
+                fqn,
+            }),
+            Declaration::Map(map) => self.map(fqn, map)?,
+        })
+    }
+
+    fn field(&mut self, fqn: &str, owner: &str, property: &Property) -> Result<Field> {
+        let namespace = namespace_of(owner);
+        let expected_type = property.type_name().map(|name| {
+            property
+                .type_identifier()
+                .and_then(|type_identifier| {
+                    resolve(
+                        self.manager,
+                        namespace,
+                        name,
+                        type_identifier.namespace.as_deref(),
+                    )
+                })
+                .unwrap_or_else(|| Symbol::intern(name))
+        });
+        let check = match property {
+            Property::Boolean(_) => Check::Primitive(Primitive::Boolean),
+            Property::String(p) => Check::Primitive(string_primitive(
+                p.validator.as_ref(),
+                p.length_validator.as_ref(),
+            )?),
+            Property::Integer(p) => Check::Primitive(Primitive::Integer(
+                p.validator.as_ref().map(|v| (v.lower, v.upper)),
+            )),
+            Property::Long(p) => Check::Primitive(Primitive::Long(
+                p.validator.as_ref().map(|v| (v.lower, v.upper)),
+            )),
+            Property::Double(p) => Check::Primitive(Primitive::Double(
+                p.validator.as_ref().map(|v| (v.lower, v.upper)),
+            )),
+            Property::DateTime(_) => Check::Primitive(Primitive::DateTime),
+            Property::Object(p) => self.declared(namespace, &p.type_),
+            Property::Relationship(p) => self.relationship(namespace, &p.type_),
+            Property::Enum(_) => Check::Any,
+        };
+        Ok(Field {
+            name: Symbol::intern(property.name()),
+            description: format!("field {} of {fqn}", property.name()).into(),
+            is_optional: property.is_optional(),
+            is_array: property.is_array(),
+            element_type: expected_type.clone(),
+            expected_type: expected_type.map(|name| match property.is_array() {
+                true => Symbol::intern(&format!("{name}[]")),
+                false => name,
+            }),
+            check,
+        })
+    }
+
+    /// The check for a value of a declared type named in `namespace`.
+    fn declared(&mut self, namespace: &str, type_identifier: &mm::TypeIdentifier) -> Check {
+        match self.resolve(namespace, type_identifier) {
+            Ok(fqn) => match self.by_name.get(&*fqn) {
+                Some(index) => Check::Declared(*index),
+                None => Check::Unresolved(fqn),
+            },
+            Err(check) => check,
+        }
+    }
+
+    /// The check for a relationship, declared in `namespace`, to a type.
+    fn relationship(&mut self, namespace: &str, type_identifier: &mm::TypeIdentifier) -> Check {
+        match self.resolve(namespace, type_identifier) {
+            Ok(target) => Check::Relationship(Box::new(RelationshipCheck {
+                target,
+                scope: self.scope(namespace),
+            })),
+            Err(check) => check,
+        }
+    }
+
+    fn resolve(
+        &self,
+        namespace: &str,
+        type_identifier: &mm::TypeIdentifier,
+    ) -> std::result::Result<Symbol, Check> {
+        match resolve(
+            self.manager,
+            namespace,
+            &type_identifier.name,
+            type_identifier.namespace.as_deref(),
+        ) {
+            Some(fqn) => Ok(Symbol::intern(&fqn)),
+            None => Err(Check::Unresolved(Symbol::intern(&type_identifier.name))),
+        }
+    }
+
+    /// The index of the scope of `namespace`, built the first time it is
+    /// needed.
+    fn scope(&mut self, namespace: &str) -> usize {
+        if let Some(index) = self.scope_index.get(namespace) {
+            return *index;
+        }
+        let model_file = self.manager.model_file(namespace);
+        let locals: Vec<&str> = model_file
+            .map(|model_file| {
+                model_file
+                    .declarations()
+                    .iter()
+                    .map(Declaration::name)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let scope = Scope {
+            namespace: Symbol::intern(namespace),
+            locals: locals.into_iter().map(Symbol::intern).collect(),
+            imports: model_file
+                .map(|model_file| model_file.imports().to_vec())
+                .unwrap_or_default(),
+        };
+        self.scopes.push(scope);
+        self.scope_index
+            .insert(namespace.to_string(), self.scopes.len() - 1);
+        self.scopes.len() - 1
+    }
+
+    fn map(&mut self, fqn: Symbol, map: &MapDeclaration) -> Result<Entry> {
+        let namespace = namespace_of(&fqn).to_string();
+        let type_name = |kind: &str, declared: Option<&mm::TypeIdentifier>| match declared {
+            Some(declared) => Some(
+                self.resolve(&namespace, declared)
+                    .unwrap_or_else(|_| Symbol::intern(&declared.name)),
+            ),
+            None => primitive_of(kind).map(Symbol::intern),
+        };
+        let key_type = type_name(map.key_kind(), map.key_type());
+        let value_type = type_name(map.value_kind(), map.value_type());
+        let key = match (map.key_kind(), map.key_type()) {
+            ("DateTimeMapKeyType", _) => Check::Primitive(Primitive::DateTime),
+            ("ObjectMapKeyType", Some(key_type)) => match self.resolve(&namespace, key_type) {
+                Ok(key_fqn) => match self.manager.get_declaration(&key_fqn) {
+                    Ok(Declaration::Scalar(scalar)) => Check::Primitive(scalar_primitive(scalar)?),
+                    Ok(_) => Check::Unsupported(
+                        format!(
+                            "cannot be checked, because the key type {key_fqn} is not a scalar"
+                        )
+                        .into(),
+                    ),
+                    Err(_) => Check::Unresolved(key_fqn),
+                },
+                Err(check) => check,
+            },
+            _ => Check::Any,
+        };
+        let value = match (map.value_kind(), map.value_type()) {
+            ("BooleanMapValueType", _) => Check::Primitive(Primitive::Boolean),
+            ("StringMapValueType", _) => Check::Primitive(Primitive::String {
+                pattern: None,
+                length: None,
+            }),
+            ("IntegerMapValueType", _) => Check::Primitive(Primitive::Integer(None)),
+            ("LongMapValueType", _) => Check::Primitive(Primitive::Long(None)),
+            ("DoubleMapValueType", _) => Check::Primitive(Primitive::Double(None)),
+            ("DateTimeMapValueType", _) => Check::Primitive(Primitive::DateTime),
+            ("ObjectMapValueType", Some(value_type)) => self.declared(&namespace, value_type),
+            ("RelationshipMapValueType", Some(value_type)) => {
+                self.relationship(&namespace, value_type)
+            }
+            (kind, _) => Check::Unsupported(
+                format!("cannot be checked against the map value type {kind}").into(),
+            ),
+        };
+        Ok(Entry::Map(MapEntry {
+            key_type,
+            value_type,
+            key,
+            value,
+            fqn,
+        }))
+    }
+}
+
+/// The primitive a scalar wraps, with its validators.
+fn scalar_primitive(scalar: &ScalarDeclaration) -> Result<Primitive> {
+    Ok(match scalar {
+        ScalarDeclaration::Boolean(_) => Primitive::Boolean,
+        ScalarDeclaration::String(s) => {
+            string_primitive(s.validator.as_ref(), s.length_validator.as_ref())?
+        }
+        ScalarDeclaration::Integer(s) => {
+            Primitive::Integer(s.validator.as_ref().map(|v| (v.lower, v.upper)))
+        }
+        ScalarDeclaration::Long(s) => {
+            Primitive::Long(s.validator.as_ref().map(|v| (v.lower, v.upper)))
+        }
+        ScalarDeclaration::Double(s) => {
+            Primitive::Double(s.validator.as_ref().map(|v| (v.lower, v.upper)))
+        }
+        ScalarDeclaration::DateTime(_) => Primitive::DateTime,
+    })
+}
+
+/// A `String` with its validators, the pattern compiled.
+fn string_primitive(
+    pattern: Option<&mm::StringRegexValidator>,
+    length: Option<&mm::StringLengthValidator>,
+) -> Result<Primitive> {
+    let pattern = match pattern {
+        None => None,
+        Some(validator) => {
+            let regex =
+                compile_pattern(validator).map_err(|error| ConcertoError::IllegalModel {
+                    message: format!(
+                        "invalid regular expression /{}/: {error}",
+                        validator.pattern
+                    ),
+                    file_name: None,
+                    location: None,
+                })?;
+            Some(Box::new((regex, validator.clone())))
+        }
+    };
+    Ok(Primitive::String {
+        pattern,
+        length: length.cloned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use serde_json::{Value, json};
+
+    use super::CompiledSchema;
+    use crate::model_manager::ModelManager;
+    use crate::test_fixtures::{optional_property, property, typed};
+
+    /// `org.acme@1.0.0`: an abstract `Vehicle` identified by `vin` with a
+    /// concrete `Car`, a system-identified `Driver`, a validated `Plate`
+    /// scalar and `Sku` string, a `Prices` map keyed by `Sku`, and a `Fleet`
+    /// that holds all of them.
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.acme@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Vehicle",
+                          "isAbstract": true,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "vin" },
+                          "properties": [
+                            property("StringProperty", "vin", json!({}))
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Car",
+                          "isAbstract": false, "superType": typed("Vehicle")["type"],
+                          "properties": [
+                            optional_property("ObjectProperty", "plate", typed("Plate")),
+                            optional_property("ObjectProperty", "colour", typed("Colour"))
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.ParticipantDeclaration", "name": "Driver",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.Identified" },
+                          "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.EnumDeclaration", "name": "Colour",
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "RED" },
+                            { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "BLUE" }
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.StringScalar", "name": "Plate",
+                          "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator",
+                                         "pattern": "^[A-Z0-9]{2,7}$", "flags": "" } },
+                        { "$class": "concerto.metamodel@1.0.0.StringScalar", "name": "Sku",
+                          "lengthValidator": { "$class": "concerto.metamodel@1.0.0.StringLengthValidator",
+                                               "minLength": 3, "maxLength": 3 } },
+                        { "$class": "concerto.metamodel@1.0.0.MapDeclaration", "name": "Prices",
+                          "key": { "$class": "concerto.metamodel@1.0.0.ObjectMapKeyType", "type": typed("Sku")["type"] },
+                          "value": { "$class": "concerto.metamodel@1.0.0.DoubleMapValueType" } },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Fleet",
+                          "isAbstract": false,
+                          "properties": [
+                            property("ObjectProperty", "vehicles",
+                                     json!({ "isArray": true, "type": typed("Vehicle")["type"] })),
+                            optional_property("RelationshipProperty", "drivers",
+                                     json!({ "isArray": true, "type": typed("Driver")["type"] })),
+                            optional_property("ObjectProperty", "prices", typed("Prices")),
+                            optional_property("IntegerProperty", "size",
+                                     json!({ "validator": { "$class": "concerto.metamodel@1.0.0.IntegerDomainValidator",
+                                                            "lower": 1 } })),
+                            optional_property("DateTimeProperty", "since", json!({}))
+                          ] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn car(vin: &str) -> Value {
+        json!({ "$class": "org.acme@1.0.0.Car", "vin": vin })
+    }
+
+    /// Valid and invalid instances touching every kind of check.
+    fn instances() -> Vec<Value> {
+        let fleet = |field: &str, value: Value| {
+            let mut fleet = json!({ "$class": "org.acme@1.0.0.Fleet", "vehicles": [car("V1")] });
+            fleet[field] = value;
+            fleet
+        };
+        vec![
+            fleet("size", json!(3)),
+            fleet("size", json!(0)),
+            fleet("size", json!("three")),
+            fleet("since", json!("2024-01-01T00:00:00Z")),
+            fleet("since", json!("yesterday")),
+            fleet("vehicles", json!([car("V1"), car("")])),
+            fleet("vehicles", json!([{ "vin": "V1" }])),
+            fleet("vehicles", json!([{ "$class": "org.acme@1.0.0.Driver" }])),
+            fleet("vehicles", json!([{ "$class": "org.acme@1.0.0.Missing" }])),
+            fleet("vehicles", json!({})),
+            fleet("vehicles", Value::Null),
+            fleet(
+                "vehicles",
+                json!([{ "$class": "org.acme@1.0.0.Car", "vin": "V1", "plate": "AB 12" }]),
+            ),
+            fleet(
+                "vehicles",
+                json!([{ "$class": "org.acme@1.0.0.Car", "vin": "V1", "colour": "GREEN" }]),
+            ),
+            fleet(
+                "vehicles",
+                json!([{ "$class": "org.acme@1.0.0.Car", "vin": "V1", "$identifier": "V2" }]),
+            ),
+            fleet(
+                "drivers",
+                json!(["Driver#d1", "resource:org.acme@1.0.0.Driver#d%202"]),
+            ),
+            fleet("drivers", json!(["Car#V1"])),
+            fleet("drivers", json!(["Nobody#x"])),
+            fleet("drivers", json!(["resource:org.acme@1.0.0.Nobody#x"])),
+            fleet("drivers", json!([7])),
+            fleet(
+                "prices",
+                json!({ "$class": "org.acme@1.0.0.Prices", "ABC": 1.5 }),
+            ),
+            fleet(
+                "prices",
+                json!({ "$class": "org.acme@1.0.0.Prices", "ABCD": 1.5 }),
+            ),
+            fleet(
+                "prices",
+                json!({ "$class": "org.acme@1.0.0.Prices", "a/b": "free" }),
+            ),
+            fleet("prices", json!({ "ABC": 1.5 })),
+            fleet("extra", json!(true)),
+            json!({ "$class": "org.acme@1.0.0.Vehicle", "vin": "V1" }),
+            json!({ "$class": "org.acme@1.0.0.Colour" }),
+            json!({ "$class": "org.acme@1.0.0.Ghost" }),
+            json!({ "$class": "org.acme@1.0.0.Driver" }),
+            json!({ "$class": "org.acme@1.0.0.Driver", "$identifier": "d1" }),
+            json!({ "vin": "V1" }),
+            json!([]),
+        ]
+    }
+
+    #[test]
+    fn compiled_validation_agrees_with_the_manager() {
+        let manager = manager();
+        let schema = CompiledSchema::new(&manager).unwrap();
+        for instance in instances() {
+            assert_eq!(
+                format!("{:?}", schema.validate(&instance)),
+                format!("{:?}", manager.validate_instance(&instance)),
+                "{instance}"
+            );
+        }
+        for (type_name, instance) in [
+            ("org.acme@1.0.0.Vehicle", car("V1")),
+            ("org.acme@1.0.0.Vehicle", json!({ "vin": "V1" })),
+            ("org.acme@1.0.0.Car", json!({ "vin": "V1" })),
+            ("org.acme@1.0.0.Driver", car("V1")),
+            ("org.acme@1.0.0.Nothing", car("V1")),
+            (
+                "org.acme@1.0.0.Vehicle",
+                json!({ "$class": "org.acme@1.0.0.Ghost", "vin": "V1" }),
+            ),
+        ] {
+            assert_eq!(
+                format!("{:?}", schema.validate_as(type_name, &instance)),
+                format!("{:?}", manager.validate_instance_as(type_name, &instance)),
+                "{type_name} {instance}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_schema_is_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CompiledSchema>();
+
+        let schema = CompiledSchema::new(&manager()).unwrap();
+        let instances = instances();
+        let expected: Vec<_> = instances
+            .iter()
+            .map(|instance| schema.validate(instance).is_ok())
+            .collect();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let results: Vec<_> = instances
+                        .iter()
+                        .map(|instance| schema.validate(instance).is_ok())
+                        .collect();
+                    assert_eq!(results, expected);
+                });
+            }
+        });
+    }
+}
@@ -2,9 +2,22 @@
 //!
 //! [`ConcertoError`] covers the hard failures that stop a model from being
 //! used: a type or namespace that cannot be resolved, or model JSON that does
-//! not satisfy the metamodel. Each variant carries enough context to report
-//! what went wrong and, where known, where.
+//! not satisfy the metamodel, and instance data that does not conform to its
+//! type. Each variant carries enough context to report what went wrong and,
+//! where known, where.
+//!
+//! The bindings hand errors on as structured values rather than text. They
+//! all build on [`ConcertoError::kind`] and [`ConcertoError::fields`], and
+//! serializing an error gives the JSON object the WebAssembly and C bindings
+//! pass on:
+//!
+//! ```json
+//! { "kind": "InvalidInstance",
+//!   "message": "invalid instance at /email: The field email of ...",
+//!   "path": "/email", "expectedType": "String", "class": "org.acme@1.0.0.Person" }
+//! ```
 
+use serde::ser::{Serialize, SerializeMap, Serializer};
 use thiserror::Error;
 
 /// Shorthand `Result` used all over `concerto-core`.
@@ -46,6 +59,101 @@ pub enum ConcertoError {
         /// A description of what did not validate.
         message: String,
     },
+
+    /// An instance does not conform to the model: a value of the wrong type,
+    /// a missing or undeclared field, a validator that does not pass.
+    #[error("invalid instance{}: {message}", at(.path))]
+    InvalidInstance {
+        /// A JSON Pointer to the offending value within the instance, such as
+        /// `/orders/3/lines/0/quantity`. Empty for the instance itself.
+        path: String,
+        /// The type the model declares for the value, if it has one. A
+        /// declared type is fully qualified, and an array is written with a
+        /// trailing `[]`.
+        expected_type: Option<String>,
+        /// The `$class` of the innermost instance holding the value, if it
+        /// names one.
+        class: Option<String>,
+        /// A description of what did not validate.
+        message: String,
+    },
+}
+
+impl ConcertoError {
+    /// The name of the variant, such as `TypeNotFound` or `InvalidInstance`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::TypeNotFound { .. } => "TypeNotFound",
+            Self::NamespaceNotFound { .. } => "NamespaceNotFound",
+            Self::IllegalModel { .. } => "IllegalModel",
+            Self::ValidationFailed { .. } => "ValidationFailed",
+            Self::InvalidInstance { .. } => "InvalidInstance",
+        }
+    }
+
+    /// The context the variant carries besides its message, as pairs of the
+    /// field's name and its value, `None` when it is unknown. The names are
+    /// those of the variant's fields, in the order it declares them.
+    pub fn fields(&self) -> Vec<(&'static str, Option<&str>)> {
+        match self {
+            Self::TypeNotFound { type_name } => vec![("type_name", Some(type_name.as_str()))],
+            Self::NamespaceNotFound { namespace } => vec![("namespace", Some(namespace.as_str()))],
+            Self::IllegalModel {
+                file_name,
+                location,
+                ..
+            } => vec![
+                ("file_name", file_name.as_deref()),
+                ("location", location.as_deref()),
+            ],
+            Self::ValidationFailed { .. } => Vec::new(),
+            Self::InvalidInstance {
+                path,
+                expected_type,
+                class,
+                ..
+            } => vec![
+                ("path", Some(path.as_str())),
+                ("expected_type", expected_type.as_deref()),
+                ("class", class.as_deref()),
+            ],
+        }
+    }
+}
+
+/// An object with the error's `kind`, its full text as `message`, and the
+/// [`fields`](ConcertoError::fields) it knows, in camelCase.
+impl Serialize for ConcertoError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        for (name, value) in self.fields() {
+            if let Some(value) = value {
+                map.serialize_entry(camel_case(name), value)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// The JSON name of a field.
+fn camel_case(name: &'static str) -> &'static str {
+    match name {
+        "type_name" => "typeName",
+        "file_name" => "fileName",
+        "expected_type" => "expectedType",
+        name => name,
+    }
+}
+
+/// Where an instance error is, for its message.
+fn at(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" at {path}")
+    }
 }
 
 #[cfg(test)]
@@ -69,4 +177,38 @@ mod tests {
         };
         assert!(err.to_string().contains("missing 'namespace'"));
     }
+
+    #[test]
+    fn invalid_instance_displays_path() {
+        let err = ConcertoError::InvalidInstance {
+            path: "/orders/3/quantity".into(),
+            expected_type: Some("Integer".into()),
+            class: Some("org.acme@1.0.0.Line".into()),
+            message: "expects an Integer".into(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "invalid instance at /orders/3/quantity: expects an Integer"
+        );
+    }
+
+    #[test]
+    fn serializes_with_its_known_fields() {
+        let err = ConcertoError::InvalidInstance {
+            path: "/email".into(),
+            expected_type: Some("String".into()),
+            class: None,
+            message: "expects a String".into(),
+        };
+        assert_eq!(err.kind(), "InvalidInstance");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "InvalidInstance",
+                "message": "invalid instance at /email: expects a String",
+                "path": "/email",
+                "expectedType": "String"
+            })
+        );
+    }
 }
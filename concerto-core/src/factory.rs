@@ -0,0 +1,853 @@
+//! Creation of instance data from the loaded models.
+//!
+//! A [`Factory`] builds a JSON instance of a class that passes
+//! [`ModelManager::validate_instance`]. Every required property along the
+//! class's inheritance chain gets a value, and optional properties can be
+//! included on request. A property or scalar with a `defaultValue` takes that
+//! value; otherwise the value depends on the [`Generation`] mode.
+//!
+//! [`Generation::Empty`] gives the plainest value that is still valid: an
+//! empty string, zero moved into the property's domain, `false`, the Unix
+//! epoch, the first member of an enum, and empty arrays and maps.
+//! [`Generation::Sample`] gives random values from a seeded generator, so the
+//! same seed always produces the same instance: words of text, numbers within
+//! the domain validator, one element per array and one entry per map.
+//!
+//! A string with a regular expression or length validator gets a value that
+//! satisfies it in either mode. Candidates are drawn from the pattern and kept
+//! only once the validator's own pattern accepts them. Anchors and word
+//! boundaries cannot be drawn from, so they are dropped for drawing and left
+//! to that check. A pattern that relies on lookaround or backreferences cannot
+//! be drawn from at all, and neither can one that no candidate satisfies
+//! within a fixed number of attempts; either is reported as an error.
+//!
+//! Identified instances get a generated identifier unless one is passed in.
+//! It is written to `$identifier` and to the identifying field, if the class
+//! has one. A relationship points at a generated identifier of its target
+//! type. A property typed by an abstract class holds an instance of the first
+//! concrete subtype, by fully-qualified name. A class that needs an instance
+//! of itself gets an empty array or an omitted optional property at that
+//! point. If the property is required and single, no finite instance exists,
+//! which is an error.
+
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use concerto_metamodel::datetime;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use regex_syntax::hir::{self, Hir, HirKind};
+use serde_json::{Map, Value, json};
+
+use crate::error::{ConcertoError, Result};
+use crate::introspect::compile_pattern;
+use crate::introspect::declaration::{Declaration, MapDeclaration, ScalarDeclaration};
+use crate::introspect::property::Property;
+use crate::model_manager::ModelManager;
+use crate::model_util::{namespace_of, short_name};
+use crate::runtime::Relationship;
+use crate::symbol::Symbol;
+use crate::validation::resolve;
+
+/// How property values are chosen when no default is declared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Generation {
+    /// The plainest valid value of each type.
+    #[default]
+    Empty,
+    /// Random values drawn from a generator seeded with `seed`.
+    Sample {
+        /// The seed; equal seeds give equal instances.
+        seed: u64,
+    },
+}
+
+/// Options for [`Factory::instance`].
+#[derive(Debug, Clone, Default)]
+pub struct InstanceOptions {
+    /// The identifier of the top-level instance. One is generated if it is
+    /// needed and not given.
+    pub identifier: Option<String>,
+    /// Whether optional properties get values too.
+    pub include_optional_fields: bool,
+    /// How values are chosen.
+    pub generation: Generation,
+}
+
+/// Creates instances of the types in a [`ModelManager`].
+#[derive(Debug, Clone, Copy)]
+pub struct Factory<'a> {
+    manager: &'a ModelManager,
+}
+
+impl<'a> Factory<'a> {
+    /// A factory for the types `manager` has loaded.
+    pub fn new(manager: &'a ModelManager) -> Self {
+        Self { manager }
+    }
+
+    /// Creates an instance of the class `fqn`.
+    ///
+    /// Fails if `fqn` is not a concrete class, or if no valid instance can be
+    /// generated for it.
+    pub fn instance(&self, fqn: &str, options: &InstanceOptions) -> Result<Value> {
+        let seed = match options.generation {
+            Generation::Empty => 0,
+            Generation::Sample { seed } => seed,
+        };
+        let mut generator = Generator {
+            manager: self.manager,
+            options,
+            rng: StdRng::seed_from_u64(seed),
+            identifiers: 0,
+            building: Vec::new(),
+            concrete: HashMap::new(),
+        };
+        generator.class_instance(fqn, options.identifier.clone())
+    }
+}
+
+/// The regular expression and length validators of a string.
+#[derive(Clone, Copy, Default)]
+struct StringRules<'a> {
+    pattern: Option<&'a mm::StringRegexValidator>,
+    length: Option<&'a mm::StringLengthValidator>,
+}
+
+/// How many candidates are drawn for a validated string before giving up.
+const ATTEMPTS: usize = 64;
+
+/// The most extra repetitions drawn for an open-ended repetition such as `+`.
+const MAX_REPEAT: u32 = 8;
+
+/// Words that sample text is made from.
+const WORDS: &[&str] = &[
+    "alpha", "bravo", "cargo", "delta", "ember", "fable", "grove", "harbor", "ivory", "jasper",
+    "kestrel", "lumen", "meadow", "nectar", "orbit", "pillar", "quartz", "river", "summit",
+    "timber",
+];
+
+/// The state of one [`Factory::instance`] call.
+struct Generator<'a> {
+    manager: &'a ModelManager,
+    options: &'a InstanceOptions,
+    rng: StdRng,
+    identifiers: u32,
+    /// The classes whose instances are under construction, outermost first.
+    building: Vec<String>,
+    /// The concrete class chosen for each class instantiated so far.
+    concrete: HashMap<String, String>,
+}
+
+impl Generator<'_> {
+    fn sample(&self) -> bool {
+        matches!(self.options.generation, Generation::Sample { .. })
+    }
+
+    fn class_instance(&mut self, fqn: &str, identifier: Option<String>) -> Result<Value> {
+        let class = self
+            .manager
+            .get_declaration(fqn)?
+            .as_class()
+            .ok_or_else(|| failed(format!("{fqn} is not a class and cannot be instantiated")))?;
+        if class.is_abstract() {
+            return Err(failed(format!(
+                "Cannot instantiate the abstract type {fqn}"
+            )));
+        }
+        if self.building.iter().any(|outer| outer == fqn) {
+            return Err(failed(format!(
+                "Cannot create an instance of {fqn}, because it requires an instance of itself"
+            )));
+        }
+
+        let chain = self.manager.super_chain(fqn)?;
+        let identified = chain.iter().any(|(_, class)| class.is_identified());
+        let identifying_field = chain
+            .iter()
+            .find_map(|(_, class)| class.identifier_field_name());
+        let identifier = match (identified, identifier) {
+            (false, _) => None,
+            (true, Some(identifier)) => Some(identifier),
+            (true, None) => Some(self.identifier(fqn)?),
+        };
+
+        self.building.push(fqn.to_string());
+        let mut object = Map::new();
+        object.insert("$class".into(), json!(fqn));
+        if let Some(identifier) = &identifier {
+            object.insert("$identifier".into(), json!(identifier));
+        }
+        for (owner, class) in &chain {
+            for property in class.own_properties() {
+                if Some(property.name()) == identifying_field {
+                    object.insert(property.name().into(), json!(identifier));
+                    continue;
+                }
+                if property.is_optional() && !self.options.include_optional_fields {
+                    continue;
+                }
+                if let Some(value) = self.field(namespace_of(owner), fqn, property)? {
+                    object.insert(property.name().into(), value);
+                }
+            }
+        }
+        self.building.pop();
+        Ok(Value::Object(object))
+    }
+
+    /// The value of one property, or `None` to leave it out.
+    fn field(
+        &mut self,
+        namespace: &str,
+        class_name: &str,
+        property: &Property,
+    ) -> Result<Option<Value>> {
+        if let Some(nested) = self.nested_class(namespace, property)?
+            && self.building.contains(&nested)
+        {
+            return if property.is_array() {
+                Ok(Some(json!([])))
+            } else if property.is_optional() {
+                Ok(None)
+            } else {
+                Err(failed(format!(
+                    "Cannot create an instance of {class_name}, because its field {} requires an instance of {nested}",
+                    property.name()
+                )))
+            };
+        }
+        if !property.is_array() {
+            return self.value(namespace, property).map(Some);
+        }
+        let mut elements = Vec::new();
+        if self.sample() {
+            elements.push(self.value(namespace, property)?);
+        }
+        Ok(Some(Value::Array(elements)))
+    }
+
+    /// The class a property's value would be an instance of, if it holds a
+    /// nested class.
+    fn nested_class(&mut self, namespace: &str, property: &Property) -> Result<Option<String>> {
+        let Property::Object(object) = property else {
+            return Ok(None);
+        };
+        let fqn = self.resolve(namespace, &object.type_)?;
+        match self.manager.get_declaration(&fqn)? {
+            Declaration::Class(_) => self.concrete(&fqn).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn value(&mut self, namespace: &str, property: &Property) -> Result<Value> {
+        let what = format!("field {}", property.name());
+        match property {
+            Property::Boolean(p) => Ok(match p.default_value {
+                Some(default) => json!(default),
+                None => json!(self.boolean()),
+            }),
+            Property::String(p) => match &p.default_value {
+                Some(default) => Ok(json!(default)),
+                None => {
+                    let rules = StringRules {
+                        pattern: p.validator.as_ref(),
+                        length: p.length_validator.as_ref(),
+                    };
+                    self.string(&what, rules).map(Value::from)
+                }
+            },
+            Property::Integer(p) => Ok(json!(p.default_value.unwrap_or_else(|| {
+                let domain = p.validator.as_ref();
+                self.integer(domain.and_then(|d| d.lower), domain.and_then(|d| d.upper))
+            }))),
+            Property::Long(p) => Ok(json!(p.default_value.unwrap_or_else(|| {
+                let domain = p.validator.as_ref();
+                self.long(domain.and_then(|d| d.lower), domain.and_then(|d| d.upper))
+            }))),
+            Property::Double(p) => Ok(json!(p.default_value.unwrap_or_else(|| {
+                let domain = p.validator.as_ref();
+                self.double(domain.and_then(|d| d.lower), domain.and_then(|d| d.upper))
+            }))),
+            Property::DateTime(_) => Ok(json!(self.datetime())),
+            Property::Object(p) => {
+                let fqn = self.resolve(namespace, &p.type_)?;
+                self.declared_value(&fqn)
+            }
+            Property::Relationship(p) => {
+                let fqn = self.resolve(namespace, &p.type_)?;
+                self.relationship(&fqn).map(Value::from)
+            }
+            // Enum members only ever belong to enum declarations, never to a
+            // class, so no instance holds one.
+            Property::Enum(_) => Ok(Value::Null),
+        }
+    }
+
+    /// A value of a declared type: a nested class instance, an enum member, a
+    /// scalar or a map.
+    fn declared_value(&mut self, fqn: &str) -> Result<Value> {
+        match self.manager.get_declaration(fqn)? {
+            Declaration::Class(_) => {
+                let concrete = self.concrete(fqn)?;
+                self.class_instance(&concrete, None)
+            }
+            Declaration::Enum(declaration) => {
+                let member = if self.sample() {
+                    declaration.properties.choose(&mut self.rng)
+                } else {
+                    declaration.properties.first()
+                };
+                member
+                    .map(|member| json!(member.name))
+                    .ok_or_else(|| failed(format!("The enum {fqn} has no members")))
+            }
+            Declaration::Scalar(scalar) => self.scalar(fqn, scalar),
+            Declaration::Map(map) => self.map(fqn, map),
+        }
+    }
+
+    fn scalar(&mut self, fqn: &str, scalar: &ScalarDeclaration) -> Result<Value> {
+        let what = format!("scalar {fqn}");
+        Ok(match scalar {
+            ScalarDeclaration::Boolean(s) => {
+                json!(s.default_value.unwrap_or_else(|| self.boolean()))
+            }
+            ScalarDeclaration::Integer(s) => json!(s.default_value.unwrap_or_else(|| {
+                let domain = s.validator.as_ref();
+                self.integer(domain.and_then(|d| d.lower), domain.and_then(|d| d.upper))
+            })),
+            ScalarDeclaration::Long(s) => json!(s.default_value.unwrap_or_else(|| {
+                let domain = s.validator.as_ref();
+                self.long(domain.and_then(|d| d.lower), domain.and_then(|d| d.upper))
+            })),
+            ScalarDeclaration::Double(s) => json!(s.default_value.unwrap_or_else(|| {
+                let domain = s.validator.as_ref();
+                self.double(domain.and_then(|d| d.lower), domain.and_then(|d| d.upper))
+            })),
+            ScalarDeclaration::String(s) => match &s.default_value {
+                Some(default) => json!(default),
+                None => {
+                    let rules = StringRules {
+                        pattern: s.validator.as_ref(),
+                        length: s.length_validator.as_ref(),
+                    };
+                    json!(self.string(&what, rules)?)
+                }
+            },
+            ScalarDeclaration::DateTime(s) => match &s.default_value {
+                Some(default) => json!(default),
+                None => json!(self.datetime()),
+            },
+        })
+    }
+
+    /// A map instance: no entries when empty, one entry as a sample. Key and
+    /// value types resolve in the map's own namespace.
+    fn map(&mut self, fqn: &str, map: &MapDeclaration) -> Result<Value> {
+        let mut object = Map::new();
+        object.insert("$class".into(), json!(fqn));
+        if !self.sample() {
+            return Ok(Value::Object(object));
+        }
+        let namespace = namespace_of(fqn);
+        let key = match (map.key_kind(), map.key_type()) {
+            ("DateTimeMapKeyType", _) => self.datetime(),
+            ("ObjectMapKeyType", Some(key_type)) => {
+                let key_fqn = self.resolve(namespace, key_type)?;
+                match self.declared_value(&key_fqn)? {
+                    Value::String(key) => key,
+                    _ => {
+                        return Err(failed(format!(
+                            "Cannot create a key of the map {fqn}, because {key_fqn} is not a string scalar"
+                        )));
+                    }
+                }
+            }
+            _ => self.string(&format!("key of map {fqn}"), StringRules::default())?,
+        };
+        let value = match (map.value_kind(), map.value_type()) {
+            ("BooleanMapValueType", _) => json!(self.boolean()),
+            ("StringMapValueType", _) => {
+                json!(self.string(&format!("value of map {fqn}"), StringRules::default())?)
+            }
+            ("IntegerMapValueType", _) => json!(self.integer(None, None)),
+            ("LongMapValueType", _) => json!(self.long(None, None)),
+            ("DoubleMapValueType", _) => json!(self.double(None, None)),
+            ("DateTimeMapValueType", _) => json!(self.datetime()),
+            ("ObjectMapValueType", Some(value_type)) => {
+                let value_fqn = self.resolve(namespace, value_type)?;
+                self.declared_value(&value_fqn)?
+            }
+            ("RelationshipMapValueType", Some(value_type)) => {
+                let value_fqn = self.resolve(namespace, value_type)?;
+                json!(self.relationship(&value_fqn)?)
+            }
+            (kind, _) => {
+                return Err(failed(format!(
+                    "Cannot create a value of the map {fqn} for the value type {kind}"
+                )));
+            }
+        };
+        object.insert(key, value);
+        Ok(Value::Object(object))
+    }
+
+    /// A relationship to a generated instance of `target`, or of its first
+    /// concrete subtype.
+    fn relationship(&mut self, target: &str) -> Result<String> {
+        let concrete = self.concrete(target)?;
+        let identifier = self.identifier(&concrete)?;
+        Ok(Relationship::new(concrete, identifier).to_string())
+    }
+
+    /// A fresh identifier for an instance of `fqn`. It satisfies the
+    /// validators of the identifying field, if the class has one.
+    fn identifier(&mut self, fqn: &str) -> Result<String> {
+        self.identifiers += 1;
+        let chain = self.manager.super_chain(fqn)?;
+        let field = chain.iter().find_map(|(owner, class)| {
+            let name = class.identifier_field_name()?;
+            let property = class.own_properties().iter().find(|p| p.name() == name);
+            Some((namespace_of(owner), property?))
+        });
+        let rules = match field {
+            Some((_, Property::String(p))) => StringRules {
+                pattern: p.validator.as_ref(),
+                length: p.length_validator.as_ref(),
+            },
+            Some((namespace, Property::Object(p))) => {
+                let scalar_fqn = self.resolve(namespace, &p.type_)?;
+                match self.manager.get_declaration(&scalar_fqn)? {
+                    Declaration::Scalar(ScalarDeclaration::String(s)) => StringRules {
+                        pattern: s.validator.as_ref(),
+                        length: s.length_validator.as_ref(),
+                    },
+                    _ => StringRules::default(),
+                }
+            }
+            _ => StringRules::default(),
+        };
+        let plain = format!("{}{}", short_name(fqn), self.identifiers);
+        self.validated_string(&format!("identifier of {fqn}"), rules, plain)
+    }
+
+    /// The class itself if it is concrete, otherwise its first concrete
+    /// subtype by fully-qualified name.
+    fn concrete(&mut self, fqn: &str) -> Result<String> {
+        if let Some(concrete) = self.concrete.get(fqn) {
+            return Ok(concrete.clone());
+        }
+        let concrete = self.find_concrete(fqn)?;
+        self.concrete.insert(fqn.to_string(), concrete.clone());
+        Ok(concrete)
+    }
+
+    fn find_concrete(&self, fqn: &str) -> Result<String> {
+        let declaration = self.manager.get_declaration(fqn)?;
+        if declaration
+            .as_class()
+            .is_some_and(|class| !class.is_abstract())
+        {
+            return Ok(fqn.to_string());
+        }
+        let mut subtypes = Vec::new();
+        for model_file in self.manager.model_files() {
+            for declaration in model_file.declarations() {
+                let Some(class) = declaration.as_class() else {
+                    continue;
+                };
+                let candidate = format!("{}.{}", model_file.namespace(), class.name());
+                if !class.is_abstract() && self.manager.is_assignable_to(&candidate, fqn)? {
+                    subtypes.push(candidate);
+                }
+            }
+        }
+        subtypes.sort();
+        subtypes.into_iter().next().ok_or_else(|| {
+            failed(format!(
+                "Cannot create an instance of the abstract type {fqn}, because it has no concrete subtype"
+            ))
+        })
+    }
+
+    fn resolve(&self, namespace: &str, type_identifier: &mm::TypeIdentifier) -> Result<Symbol> {
+        resolve(
+            self.manager,
+            namespace,
+            &type_identifier.name,
+            type_identifier.namespace.as_deref(),
+        )
+        .ok_or_else(|| ConcertoError::TypeNotFound {
+            type_name: type_identifier.name.clone(),
+        })
+    }
+
+    fn boolean(&mut self) -> bool {
+        self.sample() && self.rng.r#gen()
+    }
+
+    fn integer(&mut self, lower: Option<i32>, upper: Option<i32>) -> i32 {
+        let value = self.whole(lower.map(i64::from), upper.map(i64::from));
+        value.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+    }
+
+    fn long(&mut self, lower: Option<i64>, upper: Option<i64>) -> i64 {
+        self.whole(lower, upper)
+    }
+
+    /// A whole number within an inclusive domain: zero, moved into the domain
+    /// when empty, or drawn from it as a sample. An open side of the domain
+    /// is closed a thousand away from the other.
+    fn whole(&mut self, lower: Option<i64>, upper: Option<i64>) -> i64 {
+        let (low, high) = match (lower, upper) {
+            (Some(low), Some(high)) => (low, high),
+            (Some(low), None) => (low, low.saturating_add(1000)),
+            (None, Some(high)) => (high.saturating_sub(1000), high),
+            (None, None) => (0, 1000),
+        };
+        if !self.sample() || low > high {
+            return 0.max(low).min(high);
+        }
+        self.rng.gen_range(low..=high)
+    }
+
+    /// As [`Self::whole`], for a `Double`. Samples are rounded to two decimal
+    /// places.
+    fn double(&mut self, lower: Option<f64>, upper: Option<f64>) -> f64 {
+        let (low, high) = match (lower, upper) {
+            (Some(low), Some(high)) => (low, high),
+            (Some(low), None) => (low, low + 1000.0),
+            (None, Some(high)) => (high - 1000.0, high),
+            (None, None) => (0.0, 1000.0),
+        };
+        if !self.sample() || low >= high {
+            return 0f64.max(low).min(high);
+        }
+        let value = self.rng.gen_range(low..=high);
+        ((value * 100.0).round() / 100.0).clamp(low, high)
+    }
+
+    /// The Unix epoch when empty; a sample falls between 2000 and 2030.
+    fn datetime(&mut self) -> String {
+        let millis = if self.sample() {
+            self.rng.gen_range(946_684_800_000..1_893_456_000_000)
+        } else {
+            0
+        };
+        let instant = DateTime::from_timestamp_millis(millis).expect("the range is representable");
+        datetime::format(&instant)
+    }
+
+    /// An empty string, or some words as a sample, made to satisfy `rules`.
+    fn string(&mut self, what: &str, rules: StringRules<'_>) -> Result<String> {
+        let plain = if self.sample() {
+            let count = self.rng.gen_range(1..=3);
+            (0..count)
+                .map(|_| *WORDS.choose(&mut self.rng).expect("there are words"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        } else {
+            String::new()
+        };
+        self.validated_string(what, rules, plain)
+    }
+
+    /// `plain`, if there is no pattern, padded or cut to the length
+    /// validator; otherwise a string drawn from the pattern.
+    fn validated_string(
+        &mut self,
+        what: &str,
+        rules: StringRules<'_>,
+        plain: String,
+    ) -> Result<String> {
+        let (min, max) = rules.length.map_or((None, None), |length| {
+            (
+                length.min_length.map(|min| min.max(0) as usize),
+                length.max_length.map(|max| max.max(0) as usize),
+            )
+        });
+        let Some(validator) = rules.pattern else {
+            let mut text: String = plain.chars().take(max.unwrap_or(usize::MAX)).collect();
+            let short = min.unwrap_or(0).saturating_sub(text.chars().count());
+            text.extend(std::iter::repeat_n('x', short));
+            return Ok(text);
+        };
+
+        let unable = |reason: &str| {
+            failed(format!(
+                "Cannot create a value for the {what} that matches /{}/{}: {reason}",
+                validator.pattern, validator.flags
+            ))
+        };
+        let check = compile_pattern(validator).map_err(|error| unable(&error.to_string()))?;
+        let drawn = drawable(validator).ok_or_else(|| unable("the pattern is not supported"))?;
+        for _ in 0..ATTEMPTS {
+            let candidate: String = self.rng.sample(&drawn);
+            let length = candidate.encode_utf16().count();
+            if min.is_some_and(|min| length < min) || max.is_some_and(|max| length > max) {
+                continue;
+            }
+            if check.is_match(&candidate).unwrap_or(false) {
+                return Ok(candidate);
+            }
+        }
+        Err(unable("no candidate satisfied its validators"))
+    }
+}
+
+/// A generator of strings from a validator's pattern, with anchors and word
+/// boundaries taken out. ASCII classes are preferred, so `\w` and `\d` give
+/// readable text; a pattern that only parses with Unicode classes, such as
+/// one using `.`, falls back to those.
+fn drawable(validator: &mm::StringRegexValidator) -> Option<rand_regex::Regex> {
+    let flags: String = validator
+        .flags
+        .chars()
+        .filter(|flag| matches!(flag, 'i' | 'm' | 's'))
+        .collect();
+    let pattern = if flags.is_empty() {
+        validator.pattern.clone()
+    } else {
+        format!("(?{flags}){}", validator.pattern)
+    };
+    let hir = regex_syntax::ParserBuilder::new()
+        .unicode(false)
+        .build()
+        .parse(&pattern)
+        .ok()
+        .or_else(|| regex_syntax::Parser::new().parse(&pattern).ok())?;
+    rand_regex::Regex::with_hir(without_assertions(hir), MAX_REPEAT).ok()
+}
+
+/// Replaces every anchor and word boundary with the empty pattern.
+fn without_assertions(hir: Hir) -> Hir {
+    match hir.into_kind() {
+        HirKind::Anchor(_) | HirKind::WordBoundary(_) => Hir::empty(),
+        HirKind::Repetition(repetition) => Hir::repetition(hir::Repetition {
+            hir: Box::new(without_assertions(*repetition.hir)),
+            ..repetition
+        }),
+        HirKind::Group(group) => Hir::group(hir::Group {
+            hir: Box::new(without_assertions(*group.hir)),
+            ..group
+        }),
+        HirKind::Concat(hirs) => Hir::concat(hirs.into_iter().map(without_assertions).collect()),
+        HirKind::Alternation(hirs) => {
+            Hir::alternation(hirs.into_iter().map(without_assertions).collect())
+        }
+        HirKind::Empty => Hir::empty(),
+        HirKind::Literal(literal) => Hir::literal(literal),
+        HirKind::Class(class) => Hir::class(class),
+    }
+}
+
+fn failed(message: String) -> ConcertoError {
+    ConcertoError::ValidationFailed { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Factory, Generation, InstanceOptions};
+    use crate::model_manager::ModelManager;
+    use crate::test_fixtures::{property, typed};
+
+    /// `org.store@1.0.0`: a `Product` asset identified by a patterned `sku`,
+    /// with defaults, validators, an enum, an abstract `Shape`, a map and a
+    /// relationship to a system-identified `Supplier`; and the recursive
+    /// `Node` and `Loop`.
+    fn manager() -> ModelManager {
+        let sku = json!({ "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator",
+                                         "pattern": "^[A-Z]{3}-\\d{4}$", "flags": "" } });
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.store@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.EnumDeclaration", "name": "Color",
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "RED" },
+                            { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "GREEN" }
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.IntegerScalar", "name": "Percent",
+                          "validator": { "$class": "concerto.metamodel@1.0.0.IntegerDomainValidator",
+                                         "lower": 1, "upper": 100 } },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Shape",
+                          "isAbstract": true, "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Square",
+                          "isAbstract": false,
+                          "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Shape" },
+                          "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Circle",
+                          "isAbstract": false,
+                          "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Shape" },
+                          "properties": [
+                            property("DoubleProperty", "radius",
+                                     json!({ "validator": { "$class": "concerto.metamodel@1.0.0.DoubleDomainValidator",
+                                                            "lower": 0.5, "upper": 10.0 } }))
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.MapDeclaration", "name": "Prices",
+                          "key": { "$class": "concerto.metamodel@1.0.0.StringMapKeyType" },
+                          "value": { "$class": "concerto.metamodel@1.0.0.DoubleMapValueType" } },
+                        { "$class": "concerto.metamodel@1.0.0.ParticipantDeclaration", "name": "Supplier",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.Identified" },
+                          "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Product",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "sku" },
+                          "properties": [
+                            property("StringProperty", "sku", sku),
+                            property("StringProperty", "name",
+                                     json!({ "lengthValidator": { "$class": "concerto.metamodel@1.0.0.StringLengthValidator",
+                                                                  "minLength": 3, "maxLength": 12 } })),
+                            property("IntegerProperty", "quantity", json!({ "defaultValue": 5 })),
+                            property("LongProperty", "stock",
+                                     json!({ "validator": { "$class": "concerto.metamodel@1.0.0.LongDomainValidator",
+                                                            "lower": 10, "upper": 20 } })),
+                            property("BooleanProperty", "active", json!({})),
+                            property("DateTimeProperty", "added", json!({})),
+                            property("StringProperty", "tags", json!({ "isArray": true })),
+                            property("StringProperty", "note", json!({ "isOptional": true })),
+                            property("ObjectProperty", "color", typed("Color")),
+                            property("ObjectProperty", "discount", typed("Percent")),
+                            property("ObjectProperty", "shape", typed("Shape")),
+                            property("ObjectProperty", "prices", typed("Prices")),
+                            property("RelationshipProperty", "supplier", typed("Supplier"))
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Node",
+                          "isAbstract": false,
+                          "properties": [
+                            property("ObjectProperty", "children",
+                                     json!({ "isArray": true, "type": typed("Node")["type"] })),
+                            property("ObjectProperty", "parent",
+                                     json!({ "isOptional": true, "type": typed("Node")["type"] }))
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Loop",
+                          "isAbstract": false,
+                          "properties": [property("ObjectProperty", "next", typed("Loop"))] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn sample(seed: u64) -> InstanceOptions {
+        InstanceOptions {
+            include_optional_fields: true,
+            generation: Generation::Sample { seed },
+            ..InstanceOptions::default()
+        }
+    }
+
+    #[test]
+    fn an_empty_instance_is_valid_and_takes_defaults() {
+        let manager = manager();
+        let product = Factory::new(&manager)
+            .instance("org.store@1.0.0.Product", &InstanceOptions::default())
+            .unwrap();
+        manager.validate_instance(&product).unwrap();
+
+        assert_eq!(product["quantity"], json!(5));
+        assert_eq!(product["stock"], json!(10));
+        assert_eq!(product["discount"], json!(1));
+        assert_eq!(product["color"], json!("RED"));
+        assert_eq!(product["tags"], json!([]));
+        assert_eq!(product["name"].as_str().unwrap().len(), 3);
+        assert_eq!(product["shape"]["$class"], json!("org.store@1.0.0.Circle"));
+        assert_eq!(
+            product["prices"],
+            json!({ "$class": "org.store@1.0.0.Prices" })
+        );
+        assert_eq!(product["$identifier"], product["sku"]);
+        assert!(product.get("note").is_none());
+        assert!(
+            product["supplier"]
+                .as_str()
+                .unwrap()
+                .starts_with("resource:org.store@1.0.0.Supplier#")
+        );
+    }
+
+    #[test]
+    fn samples_are_valid_and_reproducible() {
+        let manager = manager();
+        let factory = Factory::new(&manager);
+        let mut distinct = std::collections::HashSet::new();
+        for seed in 0..25 {
+            let product = factory
+                .instance("org.store@1.0.0.Product", &sample(seed))
+                .unwrap();
+            manager.validate_instance(&product).unwrap();
+            assert_eq!(
+                product,
+                factory
+                    .instance("org.store@1.0.0.Product", &sample(seed))
+                    .unwrap()
+            );
+            assert_eq!(product["tags"].as_array().unwrap().len(), 1);
+            assert!(product.get("note").is_some());
+            distinct.insert(product.to_string());
+        }
+        assert_eq!(distinct.len(), 25);
+    }
+
+    #[test]
+    fn a_given_identifier_is_used() {
+        let manager = manager();
+        let options = InstanceOptions {
+            identifier: Some("ABC-0001".into()),
+            ..InstanceOptions::default()
+        };
+        let product = Factory::new(&manager)
+            .instance("org.store@1.0.0.Product", &options)
+            .unwrap();
+        assert_eq!(product["sku"], json!("ABC-0001"));
+        assert_eq!(product["$identifier"], json!("ABC-0001"));
+    }
+
+    #[test]
+    fn recursion_stops_where_the_model_allows_it() {
+        let manager = manager();
+        let factory = Factory::new(&manager);
+        let node = factory
+            .instance("org.store@1.0.0.Node", &sample(7))
+            .unwrap();
+        assert_eq!(
+            node,
+            json!({ "$class": "org.store@1.0.0.Node", "children": [] })
+        );
+
+        let looped = factory
+            .instance("org.store@1.0.0.Loop", &InstanceOptions::default())
+            .unwrap_err();
+        assert!(
+            looped
+                .to_string()
+                .contains("requires an instance of org.store@1.0.0.Loop")
+        );
+    }
+
+    #[test]
+    fn abstract_types_and_non_classes_cannot_be_created() {
+        let manager = manager();
+        let factory = Factory::new(&manager);
+        let options = InstanceOptions::default();
+        let shape = factory
+            .instance("org.store@1.0.0.Shape", &options)
+            .unwrap_err();
+        assert!(shape.to_string().contains("abstract type"));
+        let color = factory
+            .instance("org.store@1.0.0.Color", &options)
+            .unwrap_err();
+        assert!(color.to_string().contains("is not a class"));
+    }
+}
@@ -0,0 +1,521 @@
+//! Imports a model file can do without.
+//!
+//! Validation checks that every import resolves and that none clashes with a
+//! local declaration, but an import can be perfectly legal and still be dead
+//! weight: a type that nothing in the file refers to, or one that an earlier
+//! import already brings in under the same name. This module finds both and,
+//! when asked, works out the import list with them taken out.
+//!
+//! A type counts as used when a super type, a property, a map key or value, a
+//! decorator or a decorator argument names it. An import only ever resolves
+//! names inside the file that declares it, so the check needs nothing beyond
+//! the [`ModelFile`] itself.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+
+use crate::introspect::declaration::Declaration;
+use crate::introspect::import::Import;
+use crate::introspect::model_file::ModelFile;
+use crate::model_manager::ModelManager;
+use crate::model_util::qualify;
+
+/// An import that a model file does not need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportIssue {
+    /// Nothing in the file refers to the imported type.
+    Unused {
+        /// The namespace of the file holding the import.
+        namespace: String,
+        /// The name the type is visible under in the file: its alias, if it
+        /// has one.
+        name: String,
+        /// The fully-qualified name of the imported type.
+        type_name: String,
+    },
+    /// Another import already brings the same type in, under this name or
+    /// under one the file uses instead.
+    Redundant {
+        /// The namespace of the file holding the import.
+        namespace: String,
+        /// The name the type is visible under in the file.
+        name: String,
+        /// The fully-qualified name of the imported type.
+        type_name: String,
+    },
+}
+
+impl ImportIssue {
+    /// The namespace of the file holding the import.
+    pub fn namespace(&self) -> &str {
+        match self {
+            Self::Unused { namespace, .. } | Self::Redundant { namespace, .. } => namespace,
+        }
+    }
+
+    /// The name the imported type is visible under in the file.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Unused { name, .. } | Self::Redundant { name, .. } => name,
+        }
+    }
+
+    /// The fully-qualified name of the imported type.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Self::Unused { type_name, .. } | Self::Redundant { type_name, .. } => type_name,
+        }
+    }
+}
+
+impl fmt::Display for ImportIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unused {
+                namespace,
+                type_name,
+                ..
+            } => write!(
+                f,
+                "Type {type_name} is imported by {namespace} but never used"
+            ),
+            Self::Redundant {
+                namespace,
+                type_name,
+                ..
+            } => write!(
+                f,
+                "Type {type_name} is imported more than once by {namespace}"
+            ),
+        }
+    }
+}
+
+impl ModelManager {
+    /// Finds the unused and redundant imports of every loaded user model,
+    /// leaving the built-in system model aside. Namespaces are visited in
+    /// order, and the issues of one file follow the order of its imports.
+    pub fn import_issues(&self) -> Vec<ImportIssue> {
+        let mut model_files: Vec<_> = self
+            .model_files()
+            .filter(|model_file| !model_file.is_system_namespace())
+            .collect();
+        model_files.sort_by_key(|model_file| model_file.namespace());
+        model_files
+            .into_iter()
+            .flat_map(ModelFile::import_issues)
+            .collect()
+    }
+}
+
+impl ModelFile {
+    /// Finds the imports this file does not need, in the order they are
+    /// written.
+    pub fn import_issues(&self) -> Vec<ImportIssue> {
+        self.classify_imports()
+            .into_iter()
+            .filter_map(|entry| match entry.state {
+                EntryState::Used => None,
+                EntryState::Unused => Some(ImportIssue::Unused {
+                    namespace: self.namespace().to_string(),
+                    name: entry.local,
+                    type_name: entry.fqn,
+                }),
+                EntryState::Redundant => Some(ImportIssue::Redundant {
+                    namespace: self.namespace().to_string(),
+                    name: entry.local,
+                    type_name: entry.fqn,
+                }),
+            })
+            .collect()
+    }
+
+    /// The file's imports with every unused and redundant name taken out. An
+    /// import statement left with no names is dropped altogether, and an
+    /// alias goes with the name it renames.
+    pub fn pruned_imports(&self) -> Vec<Import> {
+        let entries = self.classify_imports();
+        let keep = |statement: usize, original: &str| {
+            entries.iter().any(|entry| {
+                entry.statement == statement
+                    && entry.original == original
+                    && entry.state == EntryState::Used
+            })
+        };
+
+        self.imports()
+            .iter()
+            .enumerate()
+            .filter_map(|(statement, import)| match import {
+                Import::Type { namespace, name } => keep(statement, name).then(|| Import::Type {
+                    namespace: namespace.clone(),
+                    name: name.clone(),
+                }),
+                Import::Types {
+                    namespace,
+                    names,
+                    aliases,
+                } => {
+                    let names: Vec<String> = names
+                        .iter()
+                        .filter(|name| keep(statement, name))
+                        .cloned()
+                        .collect();
+                    let aliases = aliases
+                        .iter()
+                        .filter(|(_, original)| names.contains(original))
+                        .cloned()
+                        .collect();
+                    (!names.is_empty()).then(|| Import::Types {
+                        namespace: namespace.clone(),
+                        names,
+                        aliases,
+                    })
+                }
+            })
+            .collect()
+    }
+
+    /// Sorts every name the file imports into used, unused or redundant.
+    ///
+    /// A type brought in more than once, under the same name or under an
+    /// alias, needs only the names the file uses; each other name for it is
+    /// redundant, and so is every name after the first if none is used.
+    fn classify_imports(&self) -> Vec<ImportEntry> {
+        let usage = Usage::of(self);
+        let imported: Vec<(usize, &String, &str, String)> = self
+            .imports()
+            .iter()
+            .enumerate()
+            .flat_map(|(statement, import)| {
+                import
+                    .imported_names()
+                    .iter()
+                    .zip(import.local_names())
+                    .map(move |(original, local)| {
+                        let fqn = qualify(import.namespace(), original);
+                        (statement, original, local, fqn)
+                    })
+            })
+            .collect();
+        let used_by_name: HashSet<&str> = imported
+            .iter()
+            .filter(|(_, _, local, _)| usage.names.contains(local))
+            .map(|(_, _, _, fqn)| fqn.as_str())
+            .collect();
+
+        let mut seen_names = HashSet::new();
+        let mut seen_types = HashSet::new();
+        let mut entries = Vec::new();
+        for (statement, original, local, fqn) in &imported {
+            let first_name = seen_names.insert((*local, fqn.as_str()));
+            let first_type = seen_types.insert(fqn.as_str());
+            let state = if !first_name {
+                EntryState::Redundant
+            } else if usage.names.contains(local) {
+                EntryState::Used
+            } else if !first_type || used_by_name.contains(fqn.as_str()) {
+                EntryState::Redundant
+            } else if usage.qualified.contains(fqn) {
+                EntryState::Used
+            } else {
+                EntryState::Unused
+            };
+            entries.push(ImportEntry {
+                statement: *statement,
+                original: original.to_string(),
+                local: local.to_string(),
+                fqn: fqn.clone(),
+                state,
+            });
+        }
+        entries
+    }
+}
+
+/// One name pulled in by one import statement.
+struct ImportEntry {
+    /// The index of the import statement in the file.
+    statement: usize,
+    /// The name as declared in the source namespace.
+    original: String,
+    /// The name as visible in the importing file.
+    local: String,
+    /// The fully-qualified name of the imported type.
+    fqn: String,
+    state: EntryState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryState {
+    Used,
+    Unused,
+    Redundant,
+}
+
+/// The type names a file refers to: short names as written, and the full
+/// names of references that carry their own namespace.
+#[derive(Default)]
+struct Usage<'a> {
+    names: HashSet<&'a str>,
+    qualified: HashSet<String>,
+}
+
+impl<'a> Usage<'a> {
+    fn of(model_file: &'a ModelFile) -> Self {
+        let mut usage = Self::default();
+        usage.decorators(model_file.decorators());
+        for reference in model_file.decorator_type_references() {
+            usage.reference(reference);
+        }
+        for declaration in model_file.declarations() {
+            usage.decorators(declaration.decorators());
+            match declaration {
+                Declaration::Class(class) => {
+                    if let Some(super_type) = class.super_type() {
+                        usage.reference(super_type);
+                    }
+                    for property in class.own_properties() {
+                        usage.decorators(property.decorators());
+                        if let Some(reference) = property.type_identifier() {
+                            usage.reference(reference);
+                        }
+                    }
+                }
+                Declaration::Enum(declaration) => {
+                    for member in &declaration.properties {
+                        usage.decorators(member.decorators.as_deref().unwrap_or(&[]));
+                    }
+                }
+                Declaration::Map(map) => {
+                    for reference in map.key_type().into_iter().chain(map.value_type()) {
+                        usage.reference(reference);
+                    }
+                }
+                Declaration::Scalar(_) => {}
+            }
+        }
+        usage
+    }
+
+    /// A decorator is a use of the type it is named after.
+    fn decorators(&mut self, decorators: &'a [mm::Decorator]) {
+        self.names
+            .extend(decorators.iter().map(|decorator| decorator.name.as_str()));
+    }
+
+    fn reference(&mut self, reference: &'a mm::TypeIdentifier) {
+        if let Some(namespace) = &reference.namespace {
+            self.qualified.insert(qualify(namespace, &reference.name));
+        } else if let Some(resolved) = &reference.resolved_name {
+            self.qualified.insert(resolved.clone());
+        } else {
+            self.names.insert(&reference.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `org.common@1.0.0` declaring `Address`, `Phone` and the `Term`
+    /// decorator, beside `org.example@1.0.0` with the given imports and
+    /// declarations.
+    fn manager(imports: serde_json::Value, declarations: serde_json::Value) -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &serde_json::json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.common@1.0.0",
+                    "declarations": declarations_named(&["Address", "Phone", "Term"])
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+            .add_model(
+                &serde_json::json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.example@1.0.0",
+                    "imports": imports,
+                    "declarations": declarations
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn declarations_named(names: &[&str]) -> serde_json::Value {
+        names
+            .iter()
+            .map(|name| {
+                serde_json::json!({
+                    "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+                    "name": name, "isAbstract": false, "properties": []
+                })
+            })
+            .collect()
+    }
+
+    fn import_of(name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "$class": "concerto.metamodel@1.0.0.ImportType",
+            "namespace": "org.common@1.0.0", "name": name
+        })
+    }
+
+    fn person(properties: serde_json::Value) -> serde_json::Value {
+        serde_json::json!([{
+            "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+            "name": "Person", "isAbstract": false, "properties": properties
+        }])
+    }
+
+    fn address_field() -> serde_json::Value {
+        serde_json::json!([
+            { "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "home",
+              "isArray": false, "isOptional": false,
+              "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Address" } }
+        ])
+    }
+
+    #[test]
+    fn an_import_nothing_refers_to_is_unused() {
+        let manager = manager(
+            serde_json::json!([import_of("Address"), import_of("Phone")]),
+            person(address_field()),
+        );
+        let issues = manager.import_issues();
+        assert_eq!(
+            issues,
+            [ImportIssue::Unused {
+                namespace: "org.example@1.0.0".into(),
+                name: "Phone".into(),
+                type_name: "org.common@1.0.0.Phone".into(),
+            }]
+        );
+        assert!(issues[0].to_string().contains("never used"));
+    }
+
+    #[test]
+    fn a_type_imported_twice_is_redundant() {
+        let manager = manager(
+            serde_json::json!([
+                import_of("Address"),
+                { "$class": "concerto.metamodel@1.0.0.ImportTypes",
+                  "namespace": "org.common@1.0.0", "types": ["Address"] }
+            ]),
+            person(address_field()),
+        );
+        let issues = manager.import_issues();
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(&issues[0], ImportIssue::Redundant { name, .. } if name == "Address"));
+    }
+
+    #[test]
+    fn a_type_imported_under_two_names_needs_only_the_one_used() {
+        let imports = serde_json::json!([
+            import_of("Address"),
+            { "$class": "concerto.metamodel@1.0.0.ImportTypes",
+              "namespace": "org.common@1.0.0", "types": ["Address"],
+              "aliasedTypes": [
+                { "$class": "concerto.metamodel@1.0.0.AliasedType",
+                  "name": "Address", "aliasedName": "Location" }
+              ] }
+        ]);
+        let by_alias = manager(
+            imports.clone(),
+            person(serde_json::json!([
+                { "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "home",
+                  "isArray": false, "isOptional": false,
+                  "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Location" } }
+            ])),
+        );
+        let issues = by_alias.import_issues();
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(&issues[0], ImportIssue::Redundant { name, .. } if name == "Address"));
+
+        let by_name = manager(imports, person(address_field()));
+        let issues = by_name.import_issues();
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(&issues[0], ImportIssue::Redundant { name, .. } if name == "Location"));
+    }
+
+    #[test]
+    fn decorators_and_decorator_arguments_count_as_uses() {
+        let manager = manager(
+            serde_json::json!([import_of("Term"), import_of("Address")]),
+            serde_json::json!([{
+                "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+                "name": "Person", "isAbstract": false, "properties": [],
+                "decorators": [
+                    { "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Term",
+                      "arguments": [
+                        { "$class": "concerto.metamodel@1.0.0.DecoratorTypeReference",
+                          "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Address" },
+                          "isArray": false }
+                      ] }
+                ]
+            }]),
+        );
+        assert!(manager.import_issues().is_empty());
+    }
+
+    #[test]
+    fn an_aliased_import_is_used_through_its_alias() {
+        let imports = serde_json::json!([
+            { "$class": "concerto.metamodel@1.0.0.ImportTypes",
+              "namespace": "org.common@1.0.0", "types": ["Address"],
+              "aliasedTypes": [
+                { "$class": "concerto.metamodel@1.0.0.AliasedType",
+                  "name": "Address", "aliasedName": "Location" }
+              ] }
+        ]);
+        let unused = manager(imports.clone(), person(address_field()));
+        assert_eq!(unused.import_issues()[0].name(), "Location");
+
+        let used = manager(
+            imports,
+            person(serde_json::json!([
+                { "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "home",
+                  "isArray": false, "isOptional": false,
+                  "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Location" } }
+            ])),
+        );
+        assert!(used.import_issues().is_empty());
+    }
+
+    #[test]
+    fn pruning_drops_unused_names_and_empty_statements() {
+        let manager = manager(
+            serde_json::json!([
+                import_of("Phone"),
+                { "$class": "concerto.metamodel@1.0.0.ImportTypes",
+                  "namespace": "org.common@1.0.0", "types": ["Address", "Term"],
+                  "aliasedTypes": [
+                    { "$class": "concerto.metamodel@1.0.0.AliasedType",
+                      "name": "Term", "aliasedName": "Label" }
+                  ] },
+                import_of("Address")
+            ]),
+            person(address_field()),
+        );
+        let pruned = manager
+            .model_file("org.example@1.0.0")
+            .unwrap()
+            .pruned_imports();
+        assert_eq!(pruned.len(), 1);
+        match &pruned[0] {
+            Import::Types { names, aliases, .. } => {
+                assert_eq!(names, &["Address"]);
+                assert!(aliases.is_empty());
+            }
+            other => panic!("expected the multi-type import, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,1440 @@
+//! Validation of instance data against the loaded models.
+//!
+//! Model validation checks that the models themselves hang together; this
+//! module checks a JSON document that claims to be an instance of one of their
+//! types. The document names its type in `$class`. Every property along that
+//! type's inheritance chain is checked for presence, shape and validators, and
+//! a property the type does not declare is refused.
+//!
+//! Identified types carry extra rules. An identified instance must have a
+//! non-empty identifier, and a `$identifier`, where given, must agree with the
+//! field the type is `identified by`. A relationship holds a [`Relationship`]
+//! URI such as `resource:org.acme@1.0.0.Car#ABC123`, or the short
+//! `Car#ABC123`, and the type it names must be assignable to the
+//! relationship's declared target.
+//!
+//! A value declared as a class may be an instance of any concrete subtype of
+//! that class, named by the nested object's `$class`. This is how a property
+//! typed by an abstract concept holds its data: the named subtype has to be
+//! assignable to the declared type, and the value is then checked against
+//! every property the subtype declares or inherits.
+//!
+//! A map instance is an object whose `$class` names the map declaration and
+//! whose other entries are the map's contents. Each key has to be a `String`
+//! or `DateTime`, or satisfy the scalar the map's key is typed as, validator
+//! included, and each value has to be of the map's value type: a primitive,
+//! a scalar, a concept or a relationship.
+//!
+//! A `DateTime`, wherever it appears, is a string that
+//! [`concerto_metamodel::datetime`] parses: an ISO-8601 date, optionally with
+//! a time, fractional seconds and a `Z` or numeric offset.
+//!
+//! As with model validation, checking stops at the first problem. It is
+//! reported as [`ConcertoError::InvalidInstance`], which gives a JSON Pointer
+//! to the offending value, the type declared for it and the `$class` of the
+//! instance that holds it.
+
+use std::fmt::Display;
+
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use concerto_metamodel::datetime;
+use fancy_regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::error::{ConcertoError, Result};
+use crate::introspect::compile_pattern;
+use crate::introspect::declaration::{
+    ClassDeclaration, Declaration, MapDeclaration, ScalarDeclaration,
+};
+use crate::introspect::property::Property;
+use crate::model_manager::ModelManager;
+use crate::model_util::namespace_of;
+use crate::runtime::Relationship;
+use crate::symbol::Symbol;
+use crate::validation::resolve;
+
+/// The system properties an instance may carry beside its declared fields.
+pub(crate) const INSTANCE_SYSTEM_PROPERTIES: &[&str] = &["$class", "$identifier", "$timestamp"];
+
+impl ModelManager {
+    /// Validates a JSON instance against the type its `$class` names. Returns
+    /// `Ok(())` if the instance is valid, otherwise the first problem found.
+    pub fn validate_instance(&self, instance: &Value) -> Result<()> {
+        let object = instance.as_object().ok_or_else(|| {
+            invalid_instance(
+                "",
+                None,
+                None,
+                format!("An instance must be an object, not {instance}"),
+            )
+        })?;
+        let class_name = object
+            .get("$class")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                invalid_instance(
+                    "/$class",
+                    None,
+                    None,
+                    "The instance does not name its type in $class".into(),
+                )
+            })?;
+        validate_class_instance(self, class_name, object, "")
+    }
+
+    /// Validates a JSON instance that is declared to be a `type_name`, as a
+    /// payload typed by an abstract base would be. The instance's `$class` may
+    /// name any concrete subtype, and it is validated as that subtype. Without
+    /// a `$class`, the instance is taken to be a `type_name` itself.
+    pub fn validate_instance_as(&self, type_name: &str, instance: &Value) -> Result<()> {
+        let object = instance.as_object().ok_or_else(|| {
+            invalid_instance(
+                "",
+                Some(type_name),
+                None,
+                format!("An instance of {type_name} must be an object, not {instance}"),
+            )
+        })?;
+        let class = object.get("$class").and_then(Value::as_str);
+        let concrete = concrete_type(self, type_name, object, |problem| {
+            invalid_instance(
+                "",
+                Some(type_name),
+                class,
+                format!("The instance {problem}"),
+            )
+        })?;
+        validate_class_instance(self, &concrete, object, "")
+    }
+}
+
+/// Works out which class an object declared as `declared` really is. A
+/// `$class` names the type, which has to be `declared` or one of its subtypes;
+/// without one, the object is `declared` itself, which must then be concrete
+/// because nothing says which subtype was meant.
+pub(crate) fn concrete_type(
+    manager: &ModelManager,
+    declared: &str,
+    object: &Map<String, Value>,
+    invalid: impl Fn(String) -> ConcertoError,
+) -> Result<String> {
+    match object.get("$class") {
+        None => {
+            let is_abstract = manager
+                .get_declaration(declared)?
+                .as_class()
+                .is_some_and(ClassDeclaration::is_abstract);
+            if is_abstract {
+                return Err(invalid(format!(
+                    "is declared as the abstract type {declared}, so it must name a concrete subtype in $class"
+                )));
+            }
+            Ok(declared.to_string())
+        }
+        Some(Value::String(class_name)) => {
+            let is_class = named_type(manager, class_name, || {
+                invalid(format!(
+                    "has $class {class_name}, which is not a declared type"
+                ))
+            })?
+            .is_class_declaration();
+            if !is_class || !manager.is_assignable_to(class_name, declared)? {
+                return Err(invalid(format!(
+                    "has $class {class_name}, which is not a subtype of {declared}"
+                )));
+            }
+            Ok(class_name.clone())
+        }
+        Some(other) => Err(invalid(format!(
+            "has the $class {other}, which is not a type name"
+        ))),
+    }
+}
+
+/// Validates an object, found at `pointer`, as an instance of the class
+/// `fqn`.
+fn validate_class_instance(
+    manager: &ModelManager,
+    fqn: &str,
+    object: &Map<String, Value>,
+    pointer: &str,
+) -> Result<()> {
+    let chain = instance_chain(manager, fqn, pointer)?;
+    for key in object.keys() {
+        if INSTANCE_SYSTEM_PROPERTIES.contains(&key.as_str()) {
+            continue;
+        }
+        if declared_property(&chain, key).is_none() {
+            return Err(undeclared(fqn, pointer, key));
+        }
+    }
+
+    for (owner, class) in &chain {
+        for property in class.own_properties() {
+            let value = object.get(property.name());
+            validate_property(manager, fqn, pointer, owner, property, value)?;
+        }
+    }
+    check_identity(fqn, &chain, object, pointer)
+}
+
+/// The inheritance chain of `fqn`, for an instance of it at `pointer`. Fails
+/// unless `fqn` is a concrete class.
+pub(crate) fn instance_chain<'m>(
+    manager: &'m ModelManager,
+    fqn: &str,
+    pointer: &str,
+) -> Result<Vec<(Symbol, &'m ClassDeclaration)>> {
+    let invalid = |message| invalid_instance(pointer, Some(fqn), Some(fqn), message);
+    let class = named_type(manager, fqn, || {
+        invalid(format!("{fqn} is not a declared type"))
+    })?
+    .as_class()
+    .ok_or_else(|| invalid(format!("{fqn} is not a class and cannot be instantiated")))?;
+    if class.is_abstract() {
+        return Err(invalid(format!(
+            "Cannot instantiate the abstract type {fqn}"
+        )));
+    }
+    manager.super_chain(fqn)
+}
+
+/// Looks up a type that an instance names, in its `$class` or in a
+/// relationship. An instance can name anything, so a type that is not loaded
+/// is a fault of the instance, reported by `unknown`, not of the model.
+pub(crate) fn named_type<'m>(
+    manager: &'m ModelManager,
+    fqn: &str,
+    unknown: impl FnOnce() -> ConcertoError,
+) -> Result<&'m Declaration> {
+    manager.get_declaration(fqn).map_err(|error| match error {
+        ConcertoError::TypeNotFound { .. } => unknown(),
+        other => other,
+    })
+}
+
+/// The property named `key` along an inheritance chain, with the class that
+/// declares it.
+pub(crate) fn declared_property<'c>(
+    chain: &'c [(Symbol, &ClassDeclaration)],
+    key: &str,
+) -> Option<(&'c str, &'c Property)> {
+    chain.iter().find_map(|(owner, class)| {
+        class
+            .own_properties()
+            .iter()
+            .find(|property| property.name() == key)
+            .map(|property| (&**owner, property))
+    })
+}
+
+/// The error for a property that the instance of `fqn` at `pointer` has but
+/// its type does not declare.
+pub(crate) fn undeclared(fqn: &str, pointer: &str, key: &str) -> ConcertoError {
+    invalid_instance(
+        &child(pointer, key),
+        None,
+        Some(fqn),
+        format!("Instance of {fqn} has a property named {key}, which is not declared"),
+    )
+}
+
+/// Checks the value of one field of the instance of `fqn` at `pointer`, where
+/// `owner` is the class along the chain that declares the property.
+pub(crate) fn validate_property(
+    manager: &ModelManager,
+    fqn: &str,
+    pointer: &str,
+    owner: &str,
+    property: &Property,
+    value: Option<&Value>,
+) -> Result<()> {
+    let site = property_site(manager, fqn, pointer, owner, property);
+    validate_field(manager, &site, property, value)
+}
+
+/// The site of one field of the instance of `fqn` at `pointer`.
+pub(crate) fn property_site<'a>(
+    manager: &ModelManager,
+    fqn: &'a str,
+    pointer: &str,
+    owner: &'a str,
+    property: &Property,
+) -> Site<'a> {
+    let namespace = namespace_of(owner);
+    Site {
+        description: format!("field {} of {fqn}", property.name()),
+        namespace,
+        pointer: child(pointer, property.name()),
+        class: fqn,
+        expected_type: declared_type(manager, namespace, property),
+    }
+}
+
+/// The type declared for a property, fully qualified where it names a
+/// declaration, with `[]` after it for an array.
+fn declared_type(manager: &ModelManager, namespace: &str, property: &Property) -> Option<String> {
+    let name = property.type_name()?;
+    let fqn = property
+        .type_identifier()
+        .and_then(|type_identifier| {
+            resolve(
+                manager,
+                namespace,
+                name,
+                type_identifier.namespace.as_deref(),
+            )
+        })
+        .map_or_else(|| name.to_string(), |fqn| fqn.to_string());
+    Some(if property.is_array() {
+        format!("{fqn}[]")
+    } else {
+        fqn
+    })
+}
+
+/// Where a value sits, for reporting a problem with it, and the namespace the
+/// type names written there resolve in. For a field, that is the namespace of
+/// the class along the chain that declares it; for a map key or value, the
+/// namespace of the map. `class` is the `$class` of the instance that holds
+/// the value.
+pub(crate) struct Site<'a> {
+    description: String,
+    pub(crate) namespace: &'a str,
+    pub(crate) pointer: String,
+    class: &'a str,
+    expected_type: Option<String>,
+}
+
+impl<'a> Site<'a> {
+    /// The same site, narrowed to one element of an array.
+    pub(crate) fn element(&self, index: usize) -> Site<'a> {
+        Site {
+            description: self.description.clone(),
+            namespace: self.namespace,
+            pointer: child(&self.pointer, &index.to_string()),
+            class: self.class,
+            expected_type: self
+                .expected_type
+                .as_ref()
+                .map(|name| name.trim_end_matches("[]").to_string()),
+        }
+    }
+
+    fn error(&self, message: String) -> ConcertoError {
+        invalid_instance(
+            &self.pointer,
+            self.expected_type.as_deref(),
+            Some(self.class),
+            message,
+        )
+    }
+
+    pub(crate) fn invalid(&self, problem: impl Display) -> ConcertoError {
+        self.error(format!("The {} {problem}", self.description))
+    }
+
+    /// The error for a value of the wrong shape.
+    fn expected(&self, expectation: &str, value: &Value) -> ConcertoError {
+        self.invalid(expected(expectation, value))
+    }
+
+    /// Turns a problem found by one of the checks below into an error here.
+    fn check(&self, checked: Checked) -> Result<()> {
+        checked.map_err(|problem| self.invalid(problem))
+    }
+}
+
+/// What is wrong with a value, if anything, worded to follow "The field x of
+/// y". The checks of single values return one, so that they can be run
+/// without knowing where the value is; the caller, which does, makes the
+/// error.
+pub(crate) type Checked = std::result::Result<(), String>;
+
+/// The problem with a value of the wrong shape.
+pub(crate) fn expected(expectation: &str, value: &Value) -> String {
+    format!("expects {expectation}, not {value}")
+}
+
+/// Checks one property of an instance: present unless optional, an array if
+/// declared as one, and each value of the declared type.
+pub(crate) fn validate_field(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    property: &Property,
+    value: Option<&Value>,
+) -> Result<()> {
+    let value = match value {
+        None | Some(Value::Null) if property.is_optional() => return Ok(()),
+        None | Some(Value::Null) => {
+            return Err(site.error(format!(
+                "Instance of {} is missing the required field {}",
+                site.class,
+                property.name()
+            )));
+        }
+        Some(value) => value,
+    };
+    if property.is_array() {
+        let items = value
+            .as_array()
+            .ok_or_else(|| site.expected("an array", value))?;
+        items.iter().enumerate().try_for_each(|(index, item)| {
+            validate_value(manager, &site.element(index), property, item)
+        })
+    } else {
+        validate_value(manager, site, property, value)
+    }
+}
+
+/// Checks a single value (an array element, or the whole of a scalar field)
+/// against the property's type.
+pub(crate) fn validate_value(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    property: &Property,
+    value: &Value,
+) -> Result<()> {
+    match property {
+        Property::Boolean(_) => site.check(check_boolean(value)),
+        Property::String(p) => site.check(check_declared_string(
+            value,
+            p.validator.as_ref(),
+            p.length_validator.as_ref(),
+        )),
+        Property::Integer(p) => site.check(check_integer(
+            value,
+            p.validator.as_ref().map(|v| (v.lower, v.upper)),
+        )),
+        Property::Long(p) => site.check(check_long(
+            value,
+            p.validator.as_ref().map(|v| (v.lower, v.upper)),
+        )),
+        Property::Double(p) => site.check(check_double(
+            value,
+            p.validator.as_ref().map(|v| (v.lower, v.upper)),
+        )),
+        Property::DateTime(_) => site.check(check_datetime(value)),
+        Property::Object(p) => validate_object_value(manager, site, &p.type_, value),
+        Property::Relationship(p) => validate_relationship(manager, site, &p.type_, value),
+        // Enum members only ever belong to enum declarations, never to a
+        // class, so an instance cannot reach one.
+        Property::Enum(_) => Ok(()),
+    }
+}
+
+/// Checks a value whose type is a declared type: a nested concept, an enum
+/// member, a scalar, or a map.
+fn validate_object_value(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    type_identifier: &mm::TypeIdentifier,
+    value: &Value,
+) -> Result<()> {
+    let fqn = resolve_site_type(manager, site, type_identifier)?;
+    match manager.get_declaration(&fqn)? {
+        Declaration::Class(_) => {
+            let object = value
+                .as_object()
+                .ok_or_else(|| site.expected(&format!("an instance of {fqn}"), value))?;
+            // The nested instance may be any concrete subtype of the declared
+            // type, and is checked against everything that subtype declares.
+            let concrete = concrete_type(manager, &fqn, object, |problem| site.invalid(problem))?;
+            validate_class_instance(manager, &concrete, object, &site.pointer)
+        }
+        Declaration::Enum(declaration) => {
+            let member = value.as_str().filter(|member| {
+                declaration
+                    .properties
+                    .iter()
+                    .any(|property| property.name == *member)
+            });
+            match member {
+                Some(_) => Ok(()),
+                None => Err(site.expected(&format!("a value of the enum {fqn}"), value)),
+            }
+        }
+        Declaration::Scalar(scalar) => site.check(check_scalar(scalar, value)),
+        Declaration::Map(map) => validate_map(manager, site, &fqn, map, value),
+    }
+}
+
+/// Checks a map instance: an object whose `$class` names the map, and whose
+/// every other entry has a key and a value of the types the map declares.
+/// Key and value types resolve in the map's own namespace.
+fn validate_map(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    fqn: &str,
+    map: &MapDeclaration,
+    value: &Value,
+) -> Result<()> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| site.expected(&format!("an instance of the map {fqn}"), value))?;
+    check_map_class(site, fqn, object.get("$class"))?;
+    for (key, entry) in object {
+        if key == "$class" {
+            continue;
+        }
+        let (key_site, value_site) = entry_sites(manager, site, fqn, map, key);
+        validate_map_key(manager, &key_site, map, key)?;
+        validate_map_value(manager, &value_site, map, entry)?;
+    }
+    Ok(())
+}
+
+/// Checks that a map instance names its map, `fqn`, in `$class`.
+pub(crate) fn check_map_class(site: &Site<'_>, fqn: &str, class: Option<&Value>) -> Result<()> {
+    match class {
+        Some(Value::String(class_name)) if class_name == fqn => Ok(()),
+        Some(other) => Err(site.invalid(format_args!(
+            "has the $class {other}, but is declared as the map {fqn}"
+        ))),
+        None => Err(site.invalid(format_args!(
+            "is a map, so it must name the map {fqn} in $class"
+        ))),
+    }
+}
+
+/// The sites of the key and the value of one entry of the `fqn` map at
+/// `site`. A key has nowhere of its own to point at, so its problems are
+/// reported at the entry, like its value's.
+pub(crate) fn entry_sites<'a>(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    fqn: &'a str,
+    map: &MapDeclaration,
+    key: &str,
+) -> (Site<'a>, Site<'a>) {
+    let namespace = namespace_of(fqn);
+    let type_name = |kind: &str, declared: Option<&mm::TypeIdentifier>| match declared {
+        Some(declared) => resolve(
+            manager,
+            namespace,
+            &declared.name,
+            declared.namespace.as_deref(),
+        )
+        .map_or_else(|| declared.name.clone(), |fqn| fqn.to_string())
+        .into(),
+        None => primitive_of(kind).map(str::to_string),
+    };
+    let pointer = child(&site.pointer, key);
+    let key_site = Site {
+        description: format!("key {key:?} of {}", site.description),
+        namespace,
+        pointer: pointer.clone(),
+        class: fqn,
+        expected_type: type_name(map.key_kind(), map.key_type()),
+    };
+    let value_site = Site {
+        description: format!("value at key {key:?} of {}", site.description),
+        namespace,
+        pointer,
+        class: fqn,
+        expected_type: type_name(map.value_kind(), map.value_type()),
+    };
+    (key_site, value_site)
+}
+
+/// The primitive a map key or value kind such as `DateTimeMapKeyType` names,
+/// if it names one.
+pub(crate) fn primitive_of(kind: &str) -> Option<&'static str> {
+    ["Boolean", "String", "Integer", "Long", "Double", "DateTime"]
+        .into_iter()
+        .find(|primitive| {
+            kind.strip_prefix(primitive)
+                .is_some_and(|rest| rest == "MapKeyType" || rest == "MapValueType")
+        })
+}
+
+/// A key is a `String`, a `DateTime`, or a scalar over one of those whose
+/// validator it has to satisfy.
+pub(crate) fn validate_map_key(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    map: &MapDeclaration,
+    key: &str,
+) -> Result<()> {
+    let key = Value::String(key.to_string());
+    match (map.key_kind(), map.key_type()) {
+        ("DateTimeMapKeyType", _) => site.check(check_datetime(&key)),
+        ("ObjectMapKeyType", Some(key_type)) => {
+            let fqn = resolve_site_type(manager, site, key_type)?;
+            match manager.get_declaration(&fqn)? {
+                Declaration::Scalar(scalar) => site.check(check_scalar(scalar, &key)),
+                _ => Err(site.invalid(format_args!(
+                    "cannot be checked, because the key type {fqn} is not a scalar"
+                ))),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A value is one of the primitives, or an object or relationship value
+/// naming a declared type.
+pub(crate) fn validate_map_value(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    map: &MapDeclaration,
+    value: &Value,
+) -> Result<()> {
+    match (map.value_kind(), map.value_type()) {
+        ("BooleanMapValueType", _) => site.check(check_boolean(value)),
+        ("StringMapValueType", _) => site.check(check_string(value, None, None)),
+        ("IntegerMapValueType", _) => site.check(check_integer(value, None)),
+        ("LongMapValueType", _) => site.check(check_long(value, None)),
+        ("DoubleMapValueType", _) => site.check(check_double(value, None)),
+        ("DateTimeMapValueType", _) => site.check(check_datetime(value)),
+        ("ObjectMapValueType", Some(value_type)) => {
+            validate_object_value(manager, site, value_type, value)
+        }
+        ("RelationshipMapValueType", Some(value_type)) => {
+            validate_relationship(manager, site, value_type, value)
+        }
+        (kind, _) => Err(site.invalid(format_args!(
+            "cannot be checked against the map value type {kind}"
+        ))),
+    }
+}
+
+/// Checks a relationship value: a reference to an instance of a type that is
+/// assignable to the declared target, written as a [`Relationship`] URI. A
+/// short type name resolves in the declaring namespace.
+fn validate_relationship(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    target: &mm::TypeIdentifier,
+    value: &Value,
+) -> Result<()> {
+    let target_fqn = resolve_site_type(manager, site, target)?;
+    let expectation = format!("a relationship such as resource:{target_fqn}#ID");
+    let reference = value
+        .as_str()
+        .ok_or_else(|| site.expected(&expectation, value))?;
+    let relationship =
+        Relationship::resolve(manager, reference, site.namespace).map_err(|error| match error {
+            ConcertoError::ValidationFailed { .. } => site.expected(&expectation, value),
+            ConcertoError::TypeNotFound { type_name } => site.invalid(format_args!(
+                "refers to {type_name}, which is not a declared type"
+            )),
+            other => other,
+        })?;
+
+    let referenced = relationship.fully_qualified_type();
+    let declaration = named_type(manager, referenced, || {
+        site.invalid(format_args!(
+            "refers to {referenced}, which is not a declared type"
+        ))
+    })?;
+    if !declaration.is_class_declaration() || !manager.is_assignable_to(referenced, &target_fqn)? {
+        return Err(site.invalid(format_args!(
+            "expects a relationship to {target_fqn}, not to {referenced}"
+        )));
+    }
+    Ok(())
+}
+
+/// Resolves a type name written at a site, in the namespace of that site.
+pub(crate) fn resolve_site_type(
+    manager: &ModelManager,
+    site: &Site<'_>,
+    type_identifier: &mm::TypeIdentifier,
+) -> Result<Symbol> {
+    resolve(
+        manager,
+        site.namespace,
+        &type_identifier.name,
+        type_identifier.namespace.as_deref(),
+    )
+    .ok_or_else(|| {
+        site.invalid(format_args!(
+            "is declared as {}, which is not a declared type",
+            type_identifier.name
+        ))
+    })
+}
+
+/// Checks a value against a scalar: the primitive it wraps, then its
+/// validator.
+fn check_scalar(scalar: &ScalarDeclaration, value: &Value) -> Checked {
+    match scalar {
+        ScalarDeclaration::Boolean(_) => check_boolean(value),
+        ScalarDeclaration::String(s) => {
+            check_declared_string(value, s.validator.as_ref(), s.length_validator.as_ref())
+        }
+        ScalarDeclaration::Integer(s) => {
+            check_integer(value, s.validator.as_ref().map(|v| (v.lower, v.upper)))
+        }
+        ScalarDeclaration::Long(s) => {
+            check_long(value, s.validator.as_ref().map(|v| (v.lower, v.upper)))
+        }
+        ScalarDeclaration::Double(s) => {
+            check_double(value, s.validator.as_ref().map(|v| (v.lower, v.upper)))
+        }
+        ScalarDeclaration::DateTime(_) => check_datetime(value),
+    }
+}
+
+pub(crate) fn check_boolean(value: &Value) -> Checked {
+    match value {
+        Value::Bool(_) => Ok(()),
+        other => Err(expected("a Boolean", other)),
+    }
+}
+
+pub(crate) fn check_datetime(value: &Value) -> Checked {
+    match value {
+        Value::String(text) if datetime::parse(text).is_ok() => Ok(()),
+        other => Err(expected("an ISO-8601 DateTime", other)),
+    }
+}
+
+/// Checks a `String` against its validators as they are declared, compiling
+/// the pattern first.
+fn check_declared_string(
+    value: &Value,
+    pattern: Option<&mm::StringRegexValidator>,
+    length: Option<&mm::StringLengthValidator>,
+) -> Checked {
+    if !value.is_string() {
+        return Err(expected("a String", value));
+    }
+    let regex = pattern
+        .map(compile_pattern)
+        .transpose()
+        .map_err(|error| format!("has an invalid regular expression: {error}"))?;
+    check_string(value, regex.as_ref().zip(pattern), length)
+}
+
+/// Checks a `String`, then its regular expression, given compiled, and its
+/// length validator. The length is counted in UTF-16 code units, as the
+/// JavaScript runtime counts it.
+pub(crate) fn check_string(
+    value: &Value,
+    pattern: Option<(&Regex, &mm::StringRegexValidator)>,
+    length: Option<&mm::StringLengthValidator>,
+) -> Checked {
+    let Some(text) = value.as_str() else {
+        return Err(expected("a String", value));
+    };
+    if let Some((regex, validator)) = pattern {
+        let matched = regex
+            .is_match(text)
+            .map_err(|error| format!("could not be matched against its pattern: {error}"))?;
+        if !matched {
+            return Err(format!(
+                "has the value {value}, which does not match /{}/{}",
+                validator.pattern, validator.flags
+            ));
+        }
+    }
+    if let Some(validator) = length {
+        let count = text.encode_utf16().count();
+        let too_short = validator.min_length.is_some_and(|min| count < min as usize);
+        let too_long = validator.max_length.is_some_and(|max| count > max as usize);
+        if too_short || too_long {
+            return Err(format!(
+                "has the value {value}, whose length is outside {}..{}",
+                bound(validator.min_length),
+                bound(validator.max_length)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// An `Integer` is a whole number that fits in 32 bits.
+pub(crate) fn check_integer(value: &Value, domain: Option<(Option<i32>, Option<i32>)>) -> Checked {
+    let integer = whole_number(value)
+        .and_then(|number| i32::try_from(number).ok())
+        .ok_or_else(|| expected("an Integer", value))?;
+    check_domain(integer, domain)
+}
+
+/// A `Long` is a whole number that fits in 64 bits.
+pub(crate) fn check_long(value: &Value, domain: Option<(Option<i64>, Option<i64>)>) -> Checked {
+    let long = whole_number(value).ok_or_else(|| expected("a Long", value))?;
+    check_domain(long, domain)
+}
+
+pub(crate) fn check_double(value: &Value, domain: Option<(Option<f64>, Option<f64>)>) -> Checked {
+    let double = value.as_f64().ok_or_else(|| expected("a Double", value))?;
+    check_domain(double, domain)
+}
+
+/// A JSON number with no fractional part. JavaScript has a single number
+/// type, so `1.0` is as whole as `1`.
+pub(crate) fn whole_number(value: &Value) -> Option<i64> {
+    value.as_i64().or_else(|| {
+        value
+            .as_f64()
+            .filter(|number| number.fract() == 0.0 && number.abs() < i64::MAX as f64)
+            .map(|number| number as i64)
+    })
+}
+
+/// Checks a number against an inclusive domain validator.
+fn check_domain<T: PartialOrd + Display + Copy>(
+    value: T,
+    domain: Option<(Option<T>, Option<T>)>,
+) -> Checked {
+    let Some((lower, upper)) = domain else {
+        return Ok(());
+    };
+    let below = lower.is_some_and(|lower| value < lower);
+    let above = upper.is_some_and(|upper| value > upper);
+    if below || above {
+        return Err(format!(
+            "has the value {value}, which is outside {}..{}",
+            bound(lower),
+            bound(upper)
+        ));
+    }
+    Ok(())
+}
+
+/// An open bound is left empty, as in `..10`.
+fn bound<T: Display>(bound: Option<T>) -> String {
+    bound.map(|bound| bound.to_string()).unwrap_or_default()
+}
+
+/// How a class is identified: not at all, by the `$identifier` the system
+/// gives its instances, or by one of its fields, held as `S`. Identity is
+/// inherited, so it is read from the whole chain.
+#[derive(Debug, Clone)]
+pub(crate) enum Identity<S> {
+    None,
+    System,
+    Field(S),
+}
+
+impl<'c> Identity<&'c str> {
+    /// The identity of the class whose inheritance chain is `chain`.
+    pub(crate) fn of(chain: &[(Symbol, &'c ClassDeclaration)]) -> Self {
+        if !chain.iter().any(|(_, class)| class.is_identified()) {
+            return Self::None;
+        }
+        match chain
+            .iter()
+            .find_map(|(_, class)| class.identifier_field_name())
+        {
+            Some(field_name) => Self::Field(field_name),
+            None => Self::System,
+        }
+    }
+}
+
+impl<S: AsRef<str>> Identity<S> {
+    /// Enforces identity on an instance of `fqn`, whose member `key` is at
+    /// the pointer `at(key)`. A type identified by one of its fields needs
+    /// that field non-empty, and any `$identifier` to match it; a
+    /// system-identified type needs a non-empty `$identifier`.
+    pub(crate) fn check(
+        &self,
+        fqn: &str,
+        object: &Map<String, Value>,
+        at: impl Fn(&str) -> String,
+    ) -> Result<()> {
+        let invalid =
+            |key: &str, message| invalid_instance(&at(key), Some("String"), Some(fqn), message);
+        let declared = object.get("$identifier");
+        match self {
+            Self::None => Ok(()),
+            Self::Field(field_name) => {
+                let field_name = field_name.as_ref();
+                let identifier = object
+                    .get(field_name)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                if identifier.is_empty() {
+                    return Err(invalid(
+                        field_name,
+                        format!("Instance of {fqn} has an empty identifying field {field_name}"),
+                    ));
+                }
+                match declared {
+                    Some(declared) if declared.as_str() != Some(identifier) => Err(invalid(
+                        "$identifier",
+                        format!(
+                            "Instance of {fqn} has $identifier {declared}, but its identifying field {field_name} is \"{identifier}\""
+                        ),
+                    )),
+                    _ => Ok(()),
+                }
+            }
+            Self::System => match declared.and_then(Value::as_str) {
+                Some(identifier) if !identifier.is_empty() => Ok(()),
+                _ => Err(invalid(
+                    "$identifier",
+                    format!("Instance of {fqn} must carry a non-empty $identifier"),
+                )),
+            },
+        }
+    }
+}
+
+/// Enforces identity on the instance of `fqn` at `pointer`; see
+/// [`Identity::check`].
+pub(crate) fn check_identity(
+    fqn: &str,
+    chain: &[(Symbol, &ClassDeclaration)],
+    object: &Map<String, Value>,
+    pointer: &str,
+) -> Result<()> {
+    Identity::of(chain).check(fqn, object, |key| child(pointer, key))
+}
+
+/// The JSON Pointer to the `token` member or element of the value at
+/// `pointer`, with `~` and `/` escaped as RFC 6901 requires.
+fn child(pointer: &str, token: &str) -> String {
+    format!("{pointer}/{}", token.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Builds a [`ConcertoError::InvalidInstance`].
+pub(crate) fn invalid_instance(
+    path: &str,
+    expected_type: Option<&str>,
+    class: Option<&str>,
+    message: String,
+) -> ConcertoError {
+    ConcertoError::InvalidInstance {
+        path: path.to_string(),
+        expected_type: expected_type.map(str::to_string),
+        class: class.map(str::to_string),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::error::ConcertoError;
+    use crate::model_manager::ModelManager;
+
+    /// `org.acme@1.0.0`: an abstract `Vehicle` asset identified by `vin`, a
+    /// concrete `Car` extending it, a system-identified `Driver`, a `Garage`
+    /// that points at vehicles, and a `Person` with validated primitives.
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.acme@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Vehicle",
+                          "isAbstract": true,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "vin" },
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "vin",
+                              "isArray": false, "isOptional": false }
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Car",
+                          "isAbstract": false,
+                          "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Vehicle" },
+                          "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.ParticipantDeclaration", "name": "Driver",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.Identified" },
+                          "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Garage",
+                          "isAbstract": false,
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.RelationshipProperty", "name": "vehicles",
+                              "isArray": true, "isOptional": false,
+                              "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Vehicle" } }
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Person",
+                          "isAbstract": false,
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "email",
+                              "isArray": false, "isOptional": false,
+                              "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator",
+                                             "pattern": "^[a-z]+@acme\\.org$", "flags": "i" } },
+                            { "$class": "concerto.metamodel@1.0.0.IntegerProperty", "name": "age",
+                              "isArray": false, "isOptional": true,
+                              "validator": { "$class": "concerto.metamodel@1.0.0.IntegerDomainValidator",
+                                             "lower": 0, "upper": 150 } }
+                          ] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn message(result: crate::error::Result<()>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn a_valid_instance_passes() {
+        let manager = manager();
+        assert!(
+            manager
+                .validate_instance(&json!({
+                    "$class": "org.acme@1.0.0.Person", "email": "Ada@ACME.org", "age": 36
+                }))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn missing_unknown_and_mistyped_fields_are_rejected() {
+        let manager = manager();
+        let missing = manager.validate_instance(&json!({ "$class": "org.acme@1.0.0.Person" }));
+        assert!(message(missing).contains("missing the required field email"));
+
+        let unknown = manager.validate_instance(&json!({
+            "$class": "org.acme@1.0.0.Person", "email": "ada@acme.org", "nickname": "A"
+        }));
+        assert!(message(unknown).contains("not declared"));
+
+        let mistyped = manager.validate_instance(&json!({
+            "$class": "org.acme@1.0.0.Person", "email": "ada@acme.org", "age": "old"
+        }));
+        assert!(message(mistyped).contains("expects an Integer"));
+    }
+
+    #[test]
+    fn validators_are_enforced() {
+        let manager = manager();
+        let pattern = manager.validate_instance(&json!({
+            "$class": "org.acme@1.0.0.Person", "email": "ada@example.com"
+        }));
+        assert!(message(pattern).contains("does not match"));
+
+        let range = manager.validate_instance(&json!({
+            "$class": "org.acme@1.0.0.Person", "email": "ada@acme.org", "age": 200
+        }));
+        assert!(message(range).contains("outside 0..150"));
+    }
+
+    #[test]
+    fn an_abstract_type_cannot_be_instantiated() {
+        let err = manager().validate_instance(&json!({
+            "$class": "org.acme@1.0.0.Vehicle", "vin": "V1"
+        }));
+        assert!(message(err).contains("abstract"));
+    }
+
+    #[test]
+    fn an_identifying_field_must_be_non_empty_and_match_identifier() {
+        let manager = manager();
+        assert!(
+            manager
+                .validate_instance(&json!({
+                    "$class": "org.acme@1.0.0.Car", "vin": "V1", "$identifier": "V1"
+                }))
+                .is_ok()
+        );
+        let empty =
+            manager.validate_instance(&json!({ "$class": "org.acme@1.0.0.Car", "vin": "" }));
+        assert!(message(empty).contains("empty identifying field vin"));
+
+        let disagreeing = manager.validate_instance(&json!({
+            "$class": "org.acme@1.0.0.Car", "vin": "V1", "$identifier": "V2"
+        }));
+        assert!(message(disagreeing).contains("$identifier"));
+    }
+
+    #[test]
+    fn a_system_identified_instance_needs_an_identifier() {
+        let manager = manager();
+        let err = manager.validate_instance(&json!({ "$class": "org.acme@1.0.0.Driver" }));
+        assert!(message(err).contains("non-empty $identifier"));
+        assert!(
+            manager
+                .validate_instance(
+                    &json!({ "$class": "org.acme@1.0.0.Driver", "$identifier": "d1" })
+                )
+                .is_ok()
+        );
+    }
+
+    /// `org.events@1.0.0`: an abstract `Payload` with a concrete `Created`, an
+    /// abstract `Change` beneath it with a concrete `Renamed`, an unrelated
+    /// `Other`, and an `Envelope` that carries payloads.
+    fn events() -> ModelManager {
+        let concept = |name: &str, is_abstract: bool, parent: Option<&str>, properties| {
+            let mut declaration = json!({
+                "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+                "name": name, "isAbstract": is_abstract, "properties": properties
+            });
+            if let Some(parent) = parent {
+                declaration["superType"] =
+                    json!({ "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": parent });
+            }
+            declaration
+        };
+        let string = |name: &str| {
+            json!({ "$class": "concerto.metamodel@1.0.0.StringProperty", "name": name,
+                    "isArray": false, "isOptional": false })
+        };
+        let payload = |name: &str, is_array: bool| {
+            json!({ "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": name,
+                    "isArray": is_array, "isOptional": false,
+                    "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Payload" } })
+        };
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.events@1.0.0",
+                    "declarations": [
+                        concept("Payload", true, None, json!([string("source")])),
+                        concept("Created", false, Some("Payload"), json!([string("id")])),
+                        concept("Change", true, Some("Payload"), json!([])),
+                        concept("Renamed", false, Some("Change"), json!([string("name")])),
+                        concept("Other", false, None, json!([])),
+                        concept("Envelope", false, None, json!([payload("payload", false), payload("history", true)]))
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn envelope(payload: serde_json::Value) -> serde_json::Value {
+        json!({ "$class": "org.events@1.0.0.Envelope", "payload": payload, "history": [] })
+    }
+
+    #[test]
+    fn an_abstract_property_holds_any_concrete_subtype() {
+        let manager = events();
+        for payload in [
+            json!({ "$class": "org.events@1.0.0.Created", "source": "api", "id": "1" }),
+            json!({ "$class": "org.events@1.0.0.Renamed", "source": "api", "name": "new" }),
+        ] {
+            assert!(manager.validate_instance(&envelope(payload)).is_ok());
+        }
+        let history = json!({
+            "$class": "org.events@1.0.0.Envelope",
+            "payload": { "$class": "org.events@1.0.0.Created", "source": "api", "id": "1" },
+            "history": [
+                { "$class": "org.events@1.0.0.Renamed", "source": "api", "name": "a" },
+                { "$class": "org.events@1.0.0.Created", "source": "api", "id": "2" }
+            ]
+        });
+        assert!(manager.validate_instance(&history).is_ok());
+    }
+
+    #[test]
+    fn a_subtype_is_validated_against_all_of_its_properties() {
+        let err = events().validate_instance(&envelope(json!({
+            "$class": "org.events@1.0.0.Renamed", "source": "api"
+        })));
+        assert!(message(err).contains("missing the required field name"));
+    }
+
+    #[test]
+    fn a_polymorphic_value_must_name_a_concrete_subtype() {
+        let manager = events();
+        let unrelated = manager.validate_instance(&envelope(json!({
+            "$class": "org.events@1.0.0.Other"
+        })));
+        assert!(message(unrelated).contains("not a subtype of org.events@1.0.0.Payload"));
+
+        let implicit = manager.validate_instance(&envelope(json!({ "source": "api" })));
+        assert!(message(implicit).contains("must name a concrete subtype"));
+
+        let abstract_subtype = manager.validate_instance(&envelope(json!({
+            "$class": "org.events@1.0.0.Change", "source": "api"
+        })));
+        assert!(message(abstract_subtype).contains("abstract"));
+    }
+
+    #[test]
+    fn an_instance_can_be_validated_as_its_declared_base() {
+        let manager = events();
+        let created = json!({ "$class": "org.events@1.0.0.Created", "source": "api", "id": "1" });
+        assert!(
+            manager
+                .validate_instance_as("org.events@1.0.0.Payload", &created)
+                .is_ok()
+        );
+        let err = manager.validate_instance_as("org.events@1.0.0.Change", &created);
+        assert!(message(err).contains("not a subtype"));
+    }
+
+    /// `org.shop@1.0.0`: maps keyed by a validated String scalar, by
+    /// DateTime and by String, holding a Double, a String and a concept, all
+    /// carried on a `Catalog`.
+    fn shop() -> ModelManager {
+        let map = |name: &str, key: serde_json::Value, value: serde_json::Value| {
+            json!({ "$class": "concerto.metamodel@1.0.0.MapDeclaration", "name": name,
+                    "key": key, "value": value })
+        };
+        let field = |name: &str, type_name: &str| {
+            json!({ "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": name,
+                    "isArray": false, "isOptional": true,
+                    "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": type_name } })
+        };
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.shop@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.StringScalar", "name": "Sku",
+                          "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator",
+                                         "pattern": "^[A-Z]{3}$", "flags": "" } },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Item",
+                          "isAbstract": false,
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "label",
+                              "isArray": false, "isOptional": false }
+                          ] },
+                        map("Prices",
+                            json!({ "$class": "concerto.metamodel@1.0.0.ObjectMapKeyType",
+                                    "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Sku" } }),
+                            json!({ "$class": "concerto.metamodel@1.0.0.DoubleMapValueType" })),
+                        map("Schedule",
+                            json!({ "$class": "concerto.metamodel@1.0.0.DateTimeMapKeyType" }),
+                            json!({ "$class": "concerto.metamodel@1.0.0.StringMapValueType" })),
+                        map("Items",
+                            json!({ "$class": "concerto.metamodel@1.0.0.StringMapKeyType" }),
+                            json!({ "$class": "concerto.metamodel@1.0.0.ObjectMapValueType",
+                                    "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Item" } })),
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Catalog",
+                          "isAbstract": false,
+                          "properties": [field("prices", "Prices"), field("schedule", "Schedule"), field("items", "Items")] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn catalog(field: &str, map: serde_json::Value) -> serde_json::Value {
+        let mut catalog = json!({ "$class": "org.shop@1.0.0.Catalog" });
+        catalog[field] = map;
+        catalog
+    }
+
+    #[test]
+    fn a_valid_map_passes() {
+        let manager = shop();
+        let prices = json!({ "$class": "org.shop@1.0.0.Prices", "ABC": 1.5, "XYZ": 2 });
+        assert!(
+            manager
+                .validate_instance(&catalog("prices", prices))
+                .is_ok()
+        );
+        let items = json!({
+            "$class": "org.shop@1.0.0.Items",
+            "first": { "$class": "org.shop@1.0.0.Item", "label": "One" }
+        });
+        assert!(manager.validate_instance(&catalog("items", items)).is_ok());
+    }
+
+    #[test]
+    fn a_map_key_must_satisfy_its_scalar() {
+        let err = shop().validate_instance(&catalog(
+            "prices",
+            json!({ "$class": "org.shop@1.0.0.Prices", "abcd": 1.0 }),
+        ));
+        let message = message(err);
+        assert!(message.contains("key \"abcd\" of field prices"));
+        assert!(message.contains("does not match"));
+    }
+
+    #[test]
+    fn datetime_keys_must_be_iso_8601() {
+        let manager = shop();
+        let schedule = json!({
+            "$class": "org.shop@1.0.0.Schedule",
+            "2024-03-01T09:30:00Z": "open",
+            "2024-03-01T18:00:00.250+01:00": "close",
+            "2024-03-02": "closed"
+        });
+        assert!(
+            manager
+                .validate_instance(&catalog("schedule", schedule))
+                .is_ok()
+        );
+
+        let err = manager.validate_instance(&catalog(
+            "schedule",
+            json!({ "$class": "org.shop@1.0.0.Schedule", "next tuesday": "open" }),
+        ));
+        assert!(message(err).contains("expects an ISO-8601 DateTime"));
+    }
+
+    #[test]
+    fn a_map_value_must_be_of_the_value_type() {
+        let manager = shop();
+        let price = manager.validate_instance(&catalog(
+            "prices",
+            json!({ "$class": "org.shop@1.0.0.Prices", "ABC": "cheap" }),
+        ));
+        assert!(message(price).contains("expects a Double"));
+
+        let item = manager.validate_instance(&catalog(
+            "items",
+            json!({ "$class": "org.shop@1.0.0.Items", "first": { "$class": "org.shop@1.0.0.Item" } }),
+        ));
+        assert!(message(item).contains("missing the required field label"));
+    }
+
+    #[test]
+    fn a_map_must_name_its_declaration() {
+        let manager = shop();
+        let wrong = manager.validate_instance(&catalog(
+            "schedule",
+            json!({ "$class": "org.shop@1.0.0.Prices" }),
+        ));
+        assert!(message(wrong).contains("declared as the map org.shop@1.0.0.Schedule"));
+
+        let missing = manager.validate_instance(&catalog("schedule", json!({})));
+        assert!(message(missing).contains("must name the map"));
+    }
+
+    #[test]
+    fn relationships_must_reference_an_assignable_type() {
+        let manager = manager();
+        let garage = |vehicles: serde_json::Value| {
+            manager.validate_instance(&json!({
+                "$class": "org.acme@1.0.0.Garage", "vehicles": vehicles
+            }))
+        };
+        assert!(garage(json!(["resource:org.acme@1.0.0.Car#V1", "Car#V2"])).is_ok());
+
+        let not_a_vehicle = garage(json!(["resource:org.acme@1.0.0.Driver#D1"]));
+        assert!(message(not_a_vehicle).contains("expects a relationship to"));
+
+        let malformed = garage(json!(["V1"]));
+        assert!(message(malformed).contains("resource:org.acme@1.0.0.Vehicle#ID"));
+
+        let empty_identifier = garage(json!(["Car#"]));
+        assert!(empty_identifier.is_err());
+    }
+
+    #[test]
+    fn an_undeclared_class_is_an_invalid_instance() {
+        let manager = manager();
+        let ghost = manager.validate_instance(&json!({ "$class": "org.acme@1.0.0.Ghost" }));
+        let ghost_class = Some("org.acme@1.0.0.Ghost".to_string());
+        assert_eq!(
+            location(ghost),
+            (String::new(), ghost_class.clone(), ghost_class)
+        );
+
+        let nested = manager.validate_instance_as(
+            "org.acme@1.0.0.Vehicle",
+            &json!({ "$class": "org.acme@1.0.0.Ghost", "vin": "V1" }),
+        );
+        assert!(message(nested).contains("org.acme@1.0.0.Ghost, which is not a declared type"));
+    }
+
+    #[test]
+    fn a_relationship_to_an_undeclared_type_is_an_invalid_instance() {
+        let manager = manager();
+        for reference in ["resource:org.acme@1.0.0.Nobody#x", "Nobody#x"] {
+            let result = manager.validate_instance(&json!({
+                "$class": "org.acme@1.0.0.Garage", "vehicles": [reference]
+            }));
+            let (path, _, class) = location(result);
+            assert_eq!(path, "/vehicles/0", "{reference}");
+            assert_eq!(class.as_deref(), Some("org.acme@1.0.0.Garage"));
+        }
+    }
+
+    /// The path, expected type and `$class` of an instance error.
+    fn location(result: crate::error::Result<()>) -> (String, Option<String>, Option<String>) {
+        match result.unwrap_err() {
+            ConcertoError::InvalidInstance {
+                path,
+                expected_type,
+                class,
+                ..
+            } => (path, expected_type, class),
+            other => panic!("expected an instance error, not {other}"),
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_value() {
+        let some = |text: &str| Some(text.to_string());
+
+        let events = events();
+        let renamed = events.validate_instance(&json!({
+            "$class": "org.events@1.0.0.Envelope",
+            "payload": { "$class": "org.events@1.0.0.Created", "source": "a", "id": "1" },
+            "history": [
+                { "$class": "org.events@1.0.0.Created", "source": "a", "id": "1" },
+                { "$class": "org.events@1.0.0.Renamed", "source": "a", "name": 3 }
+            ]
+        }));
+        assert_eq!(
+            location(renamed),
+            (
+                "/history/1/name".into(),
+                some("String"),
+                some("org.events@1.0.0.Renamed")
+            )
+        );
+        let not_an_array = events.validate_instance(&json!({
+            "$class": "org.events@1.0.0.Envelope",
+            "payload": { "$class": "org.events@1.0.0.Created", "source": "a", "id": "1" },
+            "history": {}
+        }));
+        assert_eq!(
+            location(not_an_array),
+            (
+                "/history".into(),
+                some("org.events@1.0.0.Payload[]"),
+                some("org.events@1.0.0.Envelope")
+            )
+        );
+
+        let shop = shop();
+        let price = shop.validate_instance(&catalog(
+            "prices",
+            json!({ "$class": "org.shop@1.0.0.Prices", "ABC": "free" }),
+        ));
+        assert_eq!(
+            location(price),
+            (
+                "/prices/ABC".into(),
+                some("Double"),
+                some("org.shop@1.0.0.Prices")
+            )
+        );
+        let label = shop.validate_instance(&catalog(
+            "items",
+            json!({ "$class": "org.shop@1.0.0.Items", "a/b~c": { "$class": "org.shop@1.0.0.Item" } }),
+        ));
+        assert_eq!(
+            location(label),
+            (
+                "/items/a~1b~0c/label".into(),
+                some("String"),
+                some("org.shop@1.0.0.Item")
+            )
+        );
+
+        let manager = manager();
+        let untyped = manager.validate_instance(&json!({ "email": "ada@acme.org" }));
+        assert_eq!(location(untyped), ("/$class".into(), None, None));
+        let identifier = manager.validate_instance(&json!({
+            "$class": "org.acme@1.0.0.Car", "vin": "V1", "$identifier": "V2"
+        }));
+        assert_eq!(
+            location(identifier),
+            (
+                "/$identifier".into(),
+                some("String"),
+                some("org.acme@1.0.0.Car")
+            )
+        );
+    }
+}
@@ -9,11 +9,13 @@
 //! selected by matching on the node's `$class`.
 
 use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde::Deserialize;
 
 use crate::error::{ConcertoError, Result};
 use crate::introspect::property::Property;
 use crate::introspect::{check_domain, check_length, check_pattern, declared_class};
 use crate::model_util::{is_valid_identifier, short_name};
+use crate::symbol::Symbol;
 
 /// Which class-like declaration a [`ClassDeclaration`] represents.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -57,8 +59,8 @@ impl ClassKind {
 /// The header fields every class-like declaration shares. Properties are
 /// handled on their own (into [`Property`]), so we don't deserialize them here.
 #[derive(serde::Deserialize)]
-struct ClassHeader {
-    name: String,
+struct ClassHeader<'a> {
+    name: &'a str,
     #[serde(rename = "isAbstract", default)]
     is_abstract: bool,
     #[serde(rename = "superType")]
@@ -73,11 +75,11 @@ struct ClassHeader {
 #[derive(Debug, Clone)]
 pub struct ClassDeclaration {
     kind: ClassKind,
-    name: String,
+    name: Symbol,
     is_abstract: bool,
     super_type: Option<mm::TypeIdentifier>,
     identified: Option<mm::Identified>,
-    identifier_field: Option<String>,
+    identifier_field: Option<Symbol>,
     properties: Vec<Property>,
     decorators: Vec<mm::Decorator>,
     location: Option<mm::Range>,
@@ -136,14 +138,14 @@ impl ClassDeclaration {
 
     fn from_json(kind: ClassKind, value: &serde_json::Value) -> Result<Self> {
         let header: ClassHeader =
-            serde_json::from_value(value.clone()).map_err(|e| ConcertoError::IllegalModel {
+            Deserialize::deserialize(value).map_err(|e| ConcertoError::IllegalModel {
                 message: format!("invalid {}: {e}", kind.declaration_kind()),
                 file_name: None,
                 location: None,
             })?;
         Ok(Self {
             kind,
-            name: header.name,
+            name: Symbol::intern(header.name),
             is_abstract: header.is_abstract,
             identifier_field: identifier_field(value),
             super_type: header.super_type,
@@ -157,7 +159,7 @@ impl ClassDeclaration {
 
 /// The identifying field name from an `identified by field` declaration, read
 /// from the raw AST because the base `Identified` type does not carry it.
-fn identifier_field(value: &serde_json::Value) -> Option<String> {
+fn identifier_field(value: &serde_json::Value) -> Option<Symbol> {
     let identified = value.get("identified")?;
     if short_name(declared_class(identified)) != "IdentifiedBy" {
         return None;
@@ -165,7 +167,7 @@ fn identifier_field(value: &serde_json::Value) -> Option<String> {
     identified
         .get("name")
         .and_then(|n| n.as_str())
-        .map(str::to_string)
+        .map(Symbol::intern)
 }
 
 /// A scalar: a named alias for a primitive, sometimes with a validator
@@ -199,6 +201,19 @@ impl ScalarDeclaration {
         }
     }
 
+    /// The decorators attached to this scalar.
+    pub fn decorators(&self) -> &[mm::Decorator] {
+        let decorators = match self {
+            Self::Boolean(s) => &s.decorators,
+            Self::Integer(s) => &s.decorators,
+            Self::Long(s) => &s.decorators,
+            Self::Double(s) => &s.decorators,
+            Self::String(s) => &s.decorators,
+            Self::DateTime(s) => &s.decorators,
+        };
+        decorators.as_deref().unwrap_or(&[])
+    }
+
     /// The primitive type this scalar aliases.
     pub fn scalar_type(&self) -> &'static str {
         match self {
@@ -229,14 +244,13 @@ impl ScalarDeclaration {
             file_name: None,
             location: None,
         };
-        let v = value.clone();
         let scalar = match short {
-            "BooleanScalar" => Self::Boolean(serde_json::from_value(v).map_err(bad)?),
-            "IntegerScalar" => Self::Integer(serde_json::from_value(v).map_err(bad)?),
-            "LongScalar" => Self::Long(serde_json::from_value(v).map_err(bad)?),
-            "DoubleScalar" => Self::Double(serde_json::from_value(v).map_err(bad)?),
-            "StringScalar" => Self::String(serde_json::from_value(v).map_err(bad)?),
-            "DateTimeScalar" => Self::DateTime(serde_json::from_value(v).map_err(bad)?),
+            "BooleanScalar" => Self::Boolean(Deserialize::deserialize(value).map_err(bad)?),
+            "IntegerScalar" => Self::Integer(Deserialize::deserialize(value).map_err(bad)?),
+            "LongScalar" => Self::Long(Deserialize::deserialize(value).map_err(bad)?),
+            "DoubleScalar" => Self::Double(Deserialize::deserialize(value).map_err(bad)?),
+            "StringScalar" => Self::String(Deserialize::deserialize(value).map_err(bad)?),
+            "DateTimeScalar" => Self::DateTime(Deserialize::deserialize(value).map_err(bad)?),
             other => {
                 return Err(ConcertoError::IllegalModel {
                     message: format!("unknown scalar type: {other}"),
@@ -304,11 +318,12 @@ pub enum Declaration {
 /// non-primitive key or value points at, so those are re-read from the raw AST.
 #[derive(Debug, Clone)]
 pub struct MapDeclaration {
-    name: String,
+    name: Symbol,
     key_kind: String,
     key_type: Option<mm::TypeIdentifier>,
     value_kind: String,
     value_type: Option<mm::TypeIdentifier>,
+    decorators: Vec<mm::Decorator>,
 }
 
 impl MapDeclaration {
@@ -339,19 +354,25 @@ impl MapDeclaration {
         self.value_type.as_ref()
     }
 
+    /// The decorators attached to this map.
+    pub fn decorators(&self) -> &[mm::Decorator] {
+        &self.decorators
+    }
+
     fn from_json(value: &serde_json::Value) -> Result<Self> {
         let declaration: mm::MapDeclaration =
-            serde_json::from_value(value.clone()).map_err(|e| ConcertoError::IllegalModel {
+            Deserialize::deserialize(value).map_err(|e| ConcertoError::IllegalModel {
                 message: format!("invalid MapDeclaration: {e}"),
                 file_name: None,
                 location: None,
             })?;
         Ok(Self {
-            name: declaration.name,
+            name: Symbol::intern(&declaration.name),
             key_kind: node_kind(value.get("key")),
             key_type: type_reference(value.get("key")),
             value_kind: node_kind(value.get("value")),
             value_type: type_reference(value.get("value")),
+            decorators: declaration.decorators.unwrap_or_default(),
         })
     }
 }
@@ -365,7 +386,7 @@ fn node_kind(node: Option<&serde_json::Value>) -> String {
 /// The type a map key or value node points at. Primitive keys and values carry
 /// no reference, so they give `None`.
 fn type_reference(node: Option<&serde_json::Value>) -> Option<mm::TypeIdentifier> {
-    serde_json::from_value(node?.get("type")?.clone()).ok()
+    Deserialize::deserialize(node?.get("type")?).ok()
 }
 
 impl Declaration {
@@ -389,6 +410,17 @@ impl Declaration {
         }
     }
 
+    /// The decorators attached to the declaration itself. Decorators on its
+    /// properties are reached through the properties.
+    pub fn decorators(&self) -> &[mm::Decorator] {
+        match self {
+            Self::Class(c) => c.decorators(),
+            Self::Enum(e) => e.decorators.as_deref().unwrap_or(&[]),
+            Self::Scalar(s) => s.decorators(),
+            Self::Map(m) => m.decorators(),
+        }
+    }
+
     /// Borrow this as a [`ClassDeclaration`], if it is one.
     pub fn as_class(&self) -> Option<&ClassDeclaration> {
         match self {
@@ -458,15 +490,13 @@ impl TryFrom<&serde_json::Value> for Declaration {
         }
 
         let declaration = match kind {
-            "EnumDeclaration" => {
-                Self::Enum(serde_json::from_value(value.clone()).map_err(|e| {
-                    ConcertoError::IllegalModel {
-                        message: format!("invalid EnumDeclaration: {e}"),
-                        file_name: None,
-                        location: None,
-                    }
-                })?)
-            }
+            "EnumDeclaration" => Self::Enum(Deserialize::deserialize(value).map_err(|e| {
+                ConcertoError::IllegalModel {
+                    message: format!("invalid EnumDeclaration: {e}"),
+                    file_name: None,
+                    location: None,
+                }
+            })?),
             "MapDeclaration" => Self::Map(MapDeclaration::from_json(value)?),
             s if s.ends_with("Scalar") => Self::Scalar(ScalarDeclaration::from_json(s, value)?),
             other => {
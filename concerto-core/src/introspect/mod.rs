@@ -74,11 +74,30 @@ pub(crate) fn check_domain<T: PartialOrd>(
 /// Patterns come from the JavaScript runtime, so the engine here is one that
 /// takes the same constructs, lookahead and backreferences among them.
 pub(crate) fn check_pattern(owner: &str, validator: &mm::StringRegexValidator) -> Result<()> {
-    fancy_regex::Regex::new(&validator.pattern)
+    compile_pattern(validator)
         .map_err(|error| illegal(format!("Invalid regular expression on {owner}: {error}")))?;
     Ok(())
 }
 
+/// Compiles a string regex validator, carrying its JavaScript flags over as
+/// inline flags. Only `i`, `m` and `s` change what a pattern matches; `u` is
+/// how the engine here always behaves, and `g` and `y` only matter to
+/// repeated matching, so those are dropped.
+pub(crate) fn compile_pattern(
+    validator: &mm::StringRegexValidator,
+) -> std::result::Result<fancy_regex::Regex, fancy_regex::Error> {
+    let flags: String = validator
+        .flags
+        .chars()
+        .filter(|flag| matches!(flag, 'i' | 'm' | 's'))
+        .collect();
+    if flags.is_empty() {
+        fancy_regex::Regex::new(&validator.pattern)
+    } else {
+        fancy_regex::Regex::new(&format!("(?{flags}){}", validator.pattern))
+    }
+}
+
 /// Checks a string length validator. At least one bound must be given, neither
 /// bound may be negative, and a minimum may not exceed the maximum.
 pub(crate) fn check_length(owner: &str, validator: &mm::StringLengthValidator) -> Result<()> {
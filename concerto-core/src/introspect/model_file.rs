@@ -4,23 +4,34 @@
 //! indexes its declarations by short name. It resolves a short name to a
 //! fully-qualified one from what it declares or imports: the primitives, its
 //! own declarations, and its named imports. (Wildcard imports are rejected
-//! while parsing, per strict mode in Concerto v4.)
+//! while parsing, per strict mode in Concerto v4.) Every name a file can see
+//! is resolved, and the result interned, when it is loaded, so resolving one
+//! later is a lookup.
 
 use std::collections::HashMap;
 
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde::Deserialize;
+
 use crate::error::{ConcertoError, Result};
 use crate::introspect::declaration::Declaration;
+use crate::introspect::declared_class;
 use crate::introspect::import::Import;
-use crate::model_util::{is_primitive_type, parse_namespace, qualify};
+use crate::model_util::{PRIMITIVE_TYPES, parse_namespace, qualify, short_name};
+use crate::symbol::Symbol;
 
 /// A parsed model file for one namespace.
 #[derive(Debug, Clone)]
 pub struct ModelFile {
-    namespace: String,
+    namespace: Symbol,
     version: String,
     imports: Vec<Import>,
     declarations: Vec<Declaration>,
-    local_types: HashMap<String, usize>,
+    local_types: HashMap<Symbol, usize>,
+    /// Every short name the file can see, with what it resolves to.
+    resolved: HashMap<Symbol, Symbol>,
+    decorators: Vec<mm::Decorator>,
+    decorator_type_references: Vec<mm::TypeIdentifier>,
     file_name: Option<String>,
 }
 
@@ -34,10 +45,9 @@ impl ModelFile {
                 message: "model missing 'namespace'".into(),
                 file_name: file_name.clone(),
                 location: None,
-            })?
-            .to_string();
+            })?;
 
-        let version = parse_namespace(&namespace)?.version;
+        let version = parse_namespace(namespace)?.version;
 
         let imports = match value.get("imports") {
             None => Vec::new(),
@@ -62,7 +72,7 @@ impl ModelFile {
                 for raw in arr {
                     let decl = Declaration::try_from(raw).map_err(|e| annotate(e, &file_name))?;
                     if local_types
-                        .insert(decl.name().to_string(), declarations.len())
+                        .insert(Symbol::intern(decl.name()), declarations.len())
                         .is_some()
                     {
                         return Err(ConcertoError::IllegalModel {
@@ -86,12 +96,29 @@ impl ModelFile {
             }
         }
 
+        let decorators = match value.get("decorators") {
+            None => Vec::new(),
+            Some(raw) => {
+                Deserialize::deserialize(raw).map_err(|e| ConcertoError::IllegalModel {
+                    message: format!("invalid model decorators: {e}"),
+                    file_name: file_name.clone(),
+                    location: None,
+                })?
+            }
+        };
+        let mut decorator_type_references = Vec::new();
+        collect_decorator_type_references(value, &mut decorator_type_references);
+
+        let resolved = resolution_table(namespace, &imports, &local_types);
         Ok(Self {
-            namespace,
+            namespace: Symbol::intern(namespace),
             version,
             imports,
             declarations,
             local_types,
+            resolved,
+            decorators,
+            decorator_type_references,
             file_name,
         })
     }
@@ -121,11 +148,30 @@ impl ModelFile {
         &self.imports
     }
 
+    /// The decorators applied to the model as a whole.
+    pub fn decorators(&self) -> &[mm::Decorator] {
+        &self.decorators
+    }
+
+    /// Every type a decorator argument refers to, anywhere in the file, such
+    /// as `Person` in `@Term(Person)`. The generated `Decorator` type keeps
+    /// its arguments only as bare literals, so these are read from the raw AST
+    /// when the file is loaded.
+    pub fn decorator_type_references(&self) -> &[mm::TypeIdentifier] {
+        &self.decorator_type_references
+    }
+
     /// Finds a declaration by its short name.
     pub fn local_declaration(&self, short: &str) -> Option<&Declaration> {
         self.local_types.get(short).map(|&i| &self.declarations[i])
     }
 
+    /// The position among [`declarations`](Self::declarations) of the
+    /// declaration with this short name.
+    pub(crate) fn local_index(&self, short: &str) -> Option<usize> {
+        self.local_types.get(short).copied()
+    }
+
     /// True if this is the built-in `concerto` system namespace.
     pub fn is_system_namespace(&self) -> bool {
         self.namespace.starts_with("concerto@")
@@ -135,13 +181,73 @@ impl ModelFile {
     /// primitives, its own declarations, and its named imports. Returns `None`
     /// if the name is none of those.
     pub fn resolve_local_type(&self, short: &str) -> Option<String> {
-        if is_primitive_type(short) {
-            return Some(short.to_string());
+        self.resolve_local_symbol(short).map(Symbol::to_string)
+    }
+
+    /// Like [`resolve_local_type`](Self::resolve_local_type), without
+    /// allocating.
+    pub(crate) fn resolve_local_symbol(&self, short: &str) -> Option<&Symbol> {
+        self.resolved.get(short)
+    }
+}
+
+/// What each short name a file can see resolves to. A primitive shadows a
+/// declaration, a declaration an import, and an import those after it.
+fn resolution_table(
+    namespace: &str,
+    imports: &[Import],
+    local_types: &HashMap<Symbol, usize>,
+) -> HashMap<Symbol, Symbol> {
+    let mut resolved = HashMap::new();
+    for import in imports {
+        let aliases = match import {
+            Import::Types { aliases, .. } => aliases.as_slice(),
+            Import::Type { .. } => &[],
+        };
+        let visible = import
+            .imported_names()
+            .iter()
+            .chain(aliases.iter().map(|(alias, _)| alias));
+        for short in visible {
+            if let Some(fqn) = import.resolve(short) {
+                resolved
+                    .entry(Symbol::intern(short))
+                    .or_insert_with(|| Symbol::intern(&fqn));
+            }
+        }
+    }
+    for short in local_types.keys() {
+        resolved.insert(short.clone(), Symbol::intern(&qualify(namespace, short)));
+    }
+    for primitive in PRIMITIVE_TYPES {
+        let primitive = Symbol::intern(primitive);
+        resolved.insert(primitive.clone(), primitive);
+    }
+    resolved
+}
+
+/// Collects the type of every `DecoratorTypeReference` argument beneath a node.
+fn collect_decorator_type_references(
+    value: &serde_json::Value,
+    references: &mut Vec<mm::TypeIdentifier>,
+) {
+    match value {
+        serde_json::Value::Object(node) => {
+            if short_name(declared_class(value)) == "DecoratorTypeReference"
+                && let Some(Ok(reference)) = node.get("type").map(Deserialize::deserialize)
+            {
+                references.push(reference);
+            }
+            for child in node.values() {
+                collect_decorator_type_references(child, references);
+            }
         }
-        if self.local_types.contains_key(short) {
-            return Some(qualify(&self.namespace, short));
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_decorator_type_references(item, references);
+            }
         }
-        self.imports.iter().find_map(|imp| imp.resolve(short))
+        _ => {}
     }
 }
 
@@ -209,6 +315,72 @@ mod tests {
         assert_eq!(mf.resolve_local_type("Missing"), None);
     }
 
+    #[test]
+    fn resolves_aliases_and_prefers_local_declarations() {
+        let mf = ModelFile::from_json(
+            &serde_json::json!({
+                "$class": "concerto.metamodel@1.0.0.Model",
+                "namespace": "org.example@1.0.0",
+                "imports": [
+                    { "$class": "concerto.metamodel@1.0.0.ImportTypes",
+                      "namespace": "org.common@1.0.0", "types": ["Person", "Address"],
+                      "aliasedTypes": [
+                        { "$class": "concerto.metamodel@1.0.0.AliasedType",
+                          "name": "Address", "aliasedName": "Location" }
+                      ] }
+                ],
+                "declarations": [
+                    { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+                      "name": "Person", "isAbstract": false, "properties": [] }
+                ]
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            mf.resolve_local_type("Location").as_deref(),
+            Some("org.common@1.0.0.Address")
+        );
+        assert_eq!(
+            mf.resolve_local_type("Person").as_deref(),
+            Some("org.example@1.0.0.Person")
+        );
+    }
+
+    #[test]
+    fn keeps_model_decorators_and_decorator_type_arguments() {
+        let mf = ModelFile::from_json(
+            &serde_json::json!({
+                "$class": "concerto.metamodel@1.0.0.Model",
+                "namespace": "org.example@1.0.0",
+                "decorators": [
+                    { "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Info", "arguments": [] }
+                ],
+                "declarations": [
+                    { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration",
+                      "name": "Person", "isAbstract": false, "properties": [],
+                      "decorators": [
+                        { "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Term",
+                          "arguments": [
+                            { "$class": "concerto.metamodel@1.0.0.DecoratorTypeReference",
+                              "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Address" },
+                              "isArray": false }
+                          ] }
+                      ] }
+                ]
+            }),
+            None,
+        )
+        .unwrap();
+        assert_eq!(mf.decorators()[0].name, "Info");
+        let references: Vec<&str> = mf
+            .decorator_type_references()
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(references, ["Address"]);
+    }
+
     #[test]
     fn duplicate_declaration_is_rejected() {
         let err = ModelFile::from_json(
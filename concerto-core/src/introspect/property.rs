@@ -8,6 +8,7 @@
 //! off the enum directly. No trait hierarchy to chase.
 
 use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde::Deserialize;
 
 use crate::error::{ConcertoError, Result};
 use crate::introspect::{check_domain, check_length, check_pattern, declared_class};
@@ -181,19 +182,17 @@ impl TryFrom<&serde_json::Value> for Property {
         };
 
         let property = match kind {
-            "BooleanProperty" => Self::Boolean(serde_json::from_value(value.clone()).map_err(bad)?),
-            "StringProperty" => Self::String(serde_json::from_value(value.clone()).map_err(bad)?),
-            "IntegerProperty" => Self::Integer(serde_json::from_value(value.clone()).map_err(bad)?),
-            "LongProperty" => Self::Long(serde_json::from_value(value.clone()).map_err(bad)?),
-            "DoubleProperty" => Self::Double(serde_json::from_value(value.clone()).map_err(bad)?),
-            "DateTimeProperty" => {
-                Self::DateTime(serde_json::from_value(value.clone()).map_err(bad)?)
-            }
-            "ObjectProperty" => Self::Object(serde_json::from_value(value.clone()).map_err(bad)?),
+            "BooleanProperty" => Self::Boolean(Deserialize::deserialize(value).map_err(bad)?),
+            "StringProperty" => Self::String(Deserialize::deserialize(value).map_err(bad)?),
+            "IntegerProperty" => Self::Integer(Deserialize::deserialize(value).map_err(bad)?),
+            "LongProperty" => Self::Long(Deserialize::deserialize(value).map_err(bad)?),
+            "DoubleProperty" => Self::Double(Deserialize::deserialize(value).map_err(bad)?),
+            "DateTimeProperty" => Self::DateTime(Deserialize::deserialize(value).map_err(bad)?),
+            "ObjectProperty" => Self::Object(Deserialize::deserialize(value).map_err(bad)?),
             "RelationshipProperty" => {
-                Self::Relationship(serde_json::from_value(value.clone()).map_err(bad)?)
+                Self::Relationship(Deserialize::deserialize(value).map_err(bad)?)
             }
-            "EnumProperty" => Self::Enum(serde_json::from_value(value.clone()).map_err(bad)?),
+            "EnumProperty" => Self::Enum(Deserialize::deserialize(value).map_err(bad)?),
             other => {
                 return Err(ConcertoError::IllegalModel {
                     message: format!("unknown property type: {other}"),
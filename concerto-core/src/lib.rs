@@ -2,20 +2,40 @@
 //!
 //! The heart of the Rust Concerto implementation. This crate holds the
 //! in-memory picture of a Concerto schema, the type lookups built on top of
-//! it, and the semantic validation that checks a loaded model is consistent.
+//! it, the semantic validation that checks a loaded model is consistent, and
+//! the validation, creation and serialization of instance data for those
+//! models.
 //!
 //! Everything sits on top of the generated [`concerto_metamodel`] types. We
 //! wrap those in our own enums rather than redefining the schema by hand.
 
+pub mod compare;
+pub mod compiled_schema;
 pub mod error;
+pub mod factory;
+pub mod import_usage;
+mod instance_validation;
 pub mod introspect;
 pub mod model_manager;
 pub mod model_util;
 pub mod rootmodel;
+pub mod runtime;
+pub mod serializer;
+pub mod stream_validation;
+pub mod symbol;
+#[cfg(test)]
+mod test_fixtures;
 mod validation;
 
+pub use compiled_schema::CompiledSchema;
 pub use error::{ConcertoError, Result};
+pub use factory::{Factory, Generation, InstanceOptions};
+pub use import_usage::ImportIssue;
 pub use introspect::{
     ClassDeclaration, ClassKind, Declaration, Import, ModelFile, Property, ScalarDeclaration,
 };
 pub use model_manager::ModelManager;
+pub use runtime::{Concept, Instance, Relationship, Resource, Typed};
+pub use serializer::{Serializer, SerializerOptions};
+pub use stream_validation::{RecordFailure, StreamOptions, StreamReport, StreamValidator};
+pub use symbol::Symbol;
@@ -6,8 +6,24 @@
 //! collecting every property along an inheritance chain, and checking whether
 //! one type is assignable to another. Keeping that state here lets the
 //! validation layer remain a function over already-resolved model state.
+//!
+//! Inheritance is looked up far more often than models change: validation,
+//! the factory and the serializer all walk a class's super types, often for
+//! the same class many times over. The manager therefore remembers each
+//! class's lineage, the names along its chain and the set of its ancestors,
+//! the first time it is asked for it, and where each of the class's
+//! properties is declared the first time they are listed. Adding or removing
+//! a model forgets every lineage, since either can change what a name
+//! resolves to.
+//!
+//! Loaded model files are immutable and shared: the manager holds each one
+//! behind an [`Arc`], keyed by its interned namespace. Cloning a manager
+//! therefore copies a table of pointers rather than the models, which makes a
+//! clone a cheap snapshot to hand to another thread; adding a model to one
+//! snapshot leaves the others as they were.
 
 use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
 
 use crate::error::{ConcertoError, Result};
 use crate::introspect::declaration::{ClassDeclaration, Declaration};
@@ -15,11 +31,51 @@ use crate::introspect::model_file::ModelFile;
 use crate::introspect::property::Property;
 use crate::model_util::{namespace_of, qualify, short_name};
 use crate::rootmodel::root_model_ast;
+use crate::symbol::Symbol;
 
 /// Owns a set of model files and resolves types across them.
 #[derive(Debug, Default)]
 pub struct ModelManager {
-    model_files: HashMap<String, ModelFile>,
+    model_files: HashMap<Symbol, Arc<ModelFile>>,
+    lineages: RwLock<HashMap<Symbol, Arc<Lineage>>>,
+}
+
+impl Clone for ModelManager {
+    /// A snapshot sharing this manager's model files and the lineages it has
+    /// worked out so far.
+    fn clone(&self) -> Self {
+        Self {
+            model_files: self.model_files.clone(),
+            lineages: RwLock::new(
+                self.lineages
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone(),
+            ),
+        }
+    }
+}
+
+/// A class's inheritance, as resolved for the models loaded at the time.
+#[derive(Debug)]
+struct Lineage {
+    /// The class and its super types, from the class up to the root.
+    chain: Vec<Symbol>,
+    /// The same names, for answering assignability without a walk.
+    ancestors: HashSet<Symbol>,
+    /// Every property along the chain, worked out the first time they are
+    /// listed.
+    properties: OnceLock<Flattened>,
+}
+
+/// Where the properties along a chain are declared, in the order
+/// [`ModelManager::get_all_properties`] lists them: runs of properties from
+/// the same namespace, each property given by the position of its
+/// declaration in that namespace's model file and its own position there.
+#[derive(Debug)]
+struct Flattened {
+    runs: Vec<(Symbol, Vec<(usize, usize)>)>,
+    count: usize,
 }
 
 impl ModelManager {
@@ -27,7 +83,8 @@ impl ModelManager {
     pub fn new() -> Result<Self> {
         let mut mgr = Self::default();
         let root = ModelFile::from_json(&root_model_ast(), Some("concerto@1.0.0".into()))?;
-        mgr.model_files.insert(root.namespace().to_string(), root);
+        mgr.model_files
+            .insert(Symbol::intern(root.namespace()), Arc::new(root));
         Ok(mgr)
     }
 
@@ -38,8 +95,15 @@ impl ModelManager {
         value: &serde_json::Value,
         file_name: Option<String>,
     ) -> Result<()> {
-        let mf = ModelFile::from_json(value, file_name)?;
-        let ns = mf.namespace().to_string();
+        self.add_model_file(ModelFile::from_json(value, file_name)?)
+    }
+
+    /// Loads a model file that has already been parsed. The file may be
+    /// shared, so that one parse serves several managers. Loading two models
+    /// with the same namespace is an error.
+    pub fn add_model_file(&mut self, model_file: impl Into<Arc<ModelFile>>) -> Result<()> {
+        let mf = model_file.into();
+        let ns = Symbol::intern(mf.namespace());
         if self.model_files.contains_key(&ns) {
             return Err(ConcertoError::IllegalModel {
                 message: format!("duplicate namespace: {ns}"),
@@ -48,18 +112,31 @@ impl ModelManager {
             });
         }
         self.model_files.insert(ns, mf);
+        self.forget_lineages();
         Ok(())
     }
 
+    /// Unloads the model for a namespace and hands it back. The built-in
+    /// system model stays loaded, so removing it returns `None`, as does a
+    /// namespace that is not loaded.
+    pub fn remove_model(&mut self, namespace: &str) -> Option<Arc<ModelFile>> {
+        if self.model_files.get(namespace)?.is_system_namespace() {
+            return None;
+        }
+        let removed = self.model_files.remove(namespace);
+        self.forget_lineages();
+        removed
+    }
+
     /// The loaded model file for a namespace, if there is one.
     pub fn model_file(&self, namespace: &str) -> Option<&ModelFile> {
-        self.model_files.get(namespace)
+        self.model_files.get(namespace).map(Arc::as_ref)
     }
 
     /// Every loaded model file, including the built-in system model. The order
     /// is unspecified.
     pub fn model_files(&self) -> impl Iterator<Item = &ModelFile> {
-        self.model_files.values()
+        self.model_files.values().map(Arc::as_ref)
     }
 
     /// Looks up a declaration by its fully-qualified name.
@@ -80,6 +157,14 @@ impl ModelManager {
     /// fully-qualified name, using the primitives, local declarations and named
     /// imports the model file can see.
     pub fn resolve_type_name(&self, in_namespace: &str, short: &str) -> Result<String> {
+        self.resolve_symbol(in_namespace, short)
+            .map(Symbol::to_string)
+    }
+
+    /// Like [`resolve_type_name`](Self::resolve_type_name), without
+    /// allocating: the name resolves to a symbol interned when its model file
+    /// was loaded.
+    pub(crate) fn resolve_symbol(&self, in_namespace: &str, short: &str) -> Result<&Symbol> {
         let mf =
             self.model_files
                 .get(in_namespace)
@@ -87,7 +172,7 @@ impl ModelManager {
                     namespace: in_namespace.to_string(),
                 })?;
 
-        mf.resolve_local_type(short)
+        mf.resolve_local_symbol(short)
             .ok_or_else(|| ConcertoError::TypeNotFound {
                 type_name: qualify(in_namespace, short),
             })
@@ -98,11 +183,28 @@ impl ModelManager {
     /// concept-like type, a super type cannot be resolved, or the inheritance
     /// chain is circular.
     pub fn get_all_properties(&self, fqn: &str) -> Result<Vec<&Property>> {
-        Ok(self
-            .super_chain(fqn)?
-            .into_iter()
-            .flat_map(|(_, class)| class.own_properties())
-            .collect())
+        let lineage = self.lineage(fqn)?;
+        let flattened = match lineage.properties.get() {
+            Some(flattened) => flattened,
+            None => {
+                let flattened = self.flatten(&lineage.chain)?;
+                lineage.properties.get_or_init(|| flattened)
+            }
+        };
+        let mut properties = Vec::with_capacity(flattened.count);
+        for (namespace, run) in &flattened.runs {
+            let Some(model_file) = self.model_files.get(namespace) else {
+                continue;
+            };
+            let declarations = model_file.declarations();
+            properties.extend(run.iter().filter_map(|&(declaration, property)| {
+                declarations[declaration]
+                    .as_class()?
+                    .own_properties()
+                    .get(property)
+            }));
+        }
+        Ok(properties)
     }
 
     /// Returns `true` if a value of `sub_fqn` is also a valid `super_fqn`: the
@@ -113,16 +215,45 @@ impl ModelManager {
         }
         match self.get_declaration(sub_fqn)?.as_class() {
             None => Ok(false),
-            Some(_) => Ok(self
-                .super_chain(sub_fqn)?
-                .iter()
-                .any(|(fqn, _)| fqn == super_fqn)),
+            Some(_) => Ok(self.lineage(sub_fqn)?.ancestors.contains(super_fqn)),
         }
     }
 
     /// Walks a class's inheritance chain, handing back each
-    /// `(full-name, declaration)` pair from the type up to its root.
-    fn super_chain(&self, fqn: &str) -> Result<Vec<(String, &ClassDeclaration)>> {
+    /// `(full-name, declaration)` pair from the type up to its root. Returns
+    /// an error if the name is not a concept-like type, a super type cannot
+    /// be resolved, or the chain is circular.
+    pub fn super_chain(&self, fqn: &str) -> Result<Vec<(Symbol, &ClassDeclaration)>> {
+        self.lineage(fqn)?
+            .chain
+            .iter()
+            .map(|name| Ok((name.clone(), self.class_declaration(name)?)))
+            .collect()
+    }
+
+    /// The lineage of a class, worked out on first use and remembered until
+    /// the loaded models change.
+    fn lineage(&self, fqn: &str) -> Result<Arc<Lineage>> {
+        let cached = self
+            .lineages
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(fqn)
+            .cloned();
+        if let Some(lineage) = cached {
+            return Ok(lineage);
+        }
+        let lineage = Arc::new(self.resolve_lineage(fqn)?);
+        self.lineages
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(Symbol::intern(fqn), lineage.clone());
+        Ok(lineage)
+    }
+
+    /// Resolves a class's lineage by walking up its super types, until the
+    /// root or a super type whose lineage is already known.
+    fn resolve_lineage(&self, fqn: &str) -> Result<Lineage> {
         let mut chain = Vec::new();
         let mut visited = HashSet::new();
         let mut current = fqn.to_string();
@@ -136,23 +267,80 @@ impl ModelManager {
                 });
             }
 
-            let class = self.get_declaration(&current)?.as_class().ok_or_else(|| {
-                ConcertoError::IllegalModel {
-                    message: format!("{current} is not a concept-like declaration"),
-                    file_name: None,
-                    location: None,
+            let class = self.class_declaration(&current)?;
+            let next = self.super_type_fqn(class, namespace_of(&current))?;
+            chain.push(Symbol::intern(&current));
+            let Some(parent) = next else {
+                break;
+            };
+            let known = self
+                .lineages
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(parent.as_str())
+                .cloned();
+            match known {
+                Some(lineage) => {
+                    chain.extend(lineage.chain.iter().cloned());
+                    break;
                 }
-            })?;
+                None => current = parent,
+            }
+        }
 
-            let next = self.super_type_fqn(class, namespace_of(&current))?;
-            chain.push((current, class));
-            match next {
-                Some(parent) => current = parent,
-                None => break,
+        Ok(Lineage {
+            ancestors: chain.iter().cloned().collect(),
+            chain,
+            properties: OnceLock::new(),
+        })
+    }
+
+    /// Works out where each property along a chain is declared.
+    fn flatten(&self, chain: &[Symbol]) -> Result<Flattened> {
+        let mut flattened = Flattened {
+            runs: Vec::new(),
+            count: 0,
+        };
+        for name in chain {
+            let namespace = namespace_of(name);
+            let class = self.class_declaration(name)?;
+            let declaration = self
+                .model_files
+                .get(namespace)
+                .and_then(|model_file| model_file.local_index(short_name(name)))
+                .ok_or_else(|| ConcertoError::TypeNotFound {
+                    type_name: name.to_string(),
+                })?;
+            let properties = (0..class.own_properties().len()).map(|index| (declaration, index));
+            flattened.count += properties.len();
+            match flattened.runs.last_mut() {
+                Some((last, run)) if **last == *namespace => run.extend(properties),
+                _ => flattened
+                    .runs
+                    .push((Symbol::intern(namespace), properties.collect())),
             }
         }
+        Ok(flattened)
+    }
+
+    /// Looks up a declaration that has to be a class.
+    fn class_declaration(&self, fqn: &str) -> Result<&ClassDeclaration> {
+        self.get_declaration(fqn)?
+            .as_class()
+            .ok_or_else(|| ConcertoError::IllegalModel {
+                message: format!("{fqn} is not a concept-like declaration"),
+                file_name: None,
+                location: None,
+            })
+    }
 
-        Ok(chain)
+    /// Drops every remembered lineage, and with it the properties listed for
+    /// it, for when the loaded models change.
+    fn forget_lineages(&mut self) {
+        self.lineages
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
     }
 
     /// Works out the full name of a class's direct super type, resolved in the
@@ -288,4 +476,90 @@ mod tests {
         let mgr = manager();
         assert!(mgr.get_all_properties("org.example@1.0.0.Color").is_err());
     }
+
+    #[test]
+    fn lineages_are_forgotten_when_models_change() {
+        let base = serde_json::json!({
+            "$class": "concerto.metamodel@1.0.0.Model",
+            "namespace": "org.base@1.0.0",
+            "declarations": [
+                { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Base", "isAbstract": false,
+                  "properties": [
+                    { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "id", "isArray": false, "isOptional": false }
+                  ] }
+            ]
+        });
+        let mut mgr = ModelManager::new().unwrap();
+        mgr.add_model(
+            &serde_json::json!({
+                "$class": "concerto.metamodel@1.0.0.Model",
+                "namespace": "org.derived@1.0.0",
+                "imports": [
+                    { "$class": "concerto.metamodel@1.0.0.ImportType",
+                      "namespace": "org.base@1.0.0", "name": "Base" }
+                ],
+                "declarations": [
+                    { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Derived", "isAbstract": false,
+                      "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Base" },
+                      "properties": [] }
+                ]
+            }),
+            None,
+        )
+        .unwrap();
+        let derived = "org.derived@1.0.0.Derived";
+        assert!(mgr.get_all_properties(derived).is_err());
+
+        mgr.add_model(&base, None).unwrap();
+        assert_eq!(mgr.get_all_properties(derived).unwrap().len(), 1);
+        assert!(
+            mgr.is_assignable_to(derived, "org.base@1.0.0.Base")
+                .unwrap()
+        );
+
+        assert!(mgr.remove_model("org.base@1.0.0").is_some());
+        assert!(mgr.get_all_properties(derived).is_err());
+        assert!(
+            mgr.is_assignable_to(derived, "org.base@1.0.0.Base")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn system_model_cannot_be_removed() {
+        let mut mgr = manager();
+        assert!(mgr.remove_model("concerto@1.0.0").is_none());
+        assert!(mgr.remove_model("org.nowhere@1.0.0").is_none());
+        assert!(mgr.remove_model("org.example@1.0.0").is_some());
+        assert!(mgr.get_declaration("concerto@1.0.0.Concept").is_ok());
+    }
+
+    #[test]
+    fn a_clone_is_an_independent_snapshot_sharing_model_files() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ModelManager>();
+
+        let mgr = manager();
+        let mut snapshot = mgr.clone();
+        assert!(std::ptr::eq(
+            mgr.model_file("org.example@1.0.0").unwrap(),
+            snapshot.model_file("org.example@1.0.0").unwrap()
+        ));
+
+        let shared = snapshot.remove_model("org.example@1.0.0").unwrap();
+        assert!(
+            snapshot
+                .get_declaration("org.example@1.0.0.Person")
+                .is_err()
+        );
+        assert!(mgr.get_all_properties("org.example@1.0.0.Manager").is_ok());
+
+        let mut other = ModelManager::new().unwrap();
+        other.add_model_file(shared.clone()).unwrap();
+        assert!(std::ptr::eq(
+            other.model_file("org.example@1.0.0").unwrap(),
+            shared.as_ref()
+        ));
+        assert!(other.add_model_file(shared).is_err());
+    }
 }
@@ -11,7 +11,8 @@
 use crate::error::{ConcertoError, Result};
 
 /// Concerto's six primitives. Everything else is a declared type.
-const PRIMITIVE_TYPES: &[&str] = &["Boolean", "String", "DateTime", "Double", "Integer", "Long"];
+pub const PRIMITIVE_TYPES: &[&str] =
+    &["Boolean", "String", "DateTime", "Double", "Integer", "Long"];
 
 /// The property names Concerto reserves for itself. A model may not declare a
 /// field with any of these names. Identifiers are otherwise allowed to start
@@ -0,0 +1,47 @@
+//! Instances of classes without identity.
+
+use std::ops::{Deref, DerefMut};
+
+use crate::error::{ConcertoError, Result};
+use crate::model_manager::ModelManager;
+use crate::runtime::typed::Typed;
+
+/// An instance of a class that is not identified: a concept, transaction or
+/// event without an identifier. Concepts are values, held by the fields of
+/// other instances rather than referred to.
+#[derive(Debug, Clone)]
+pub struct Concept<'m>(Typed<'m>);
+
+impl<'m> Concept<'m> {
+    /// A concept of the concrete class `fqn` with no fields set.
+    pub fn new(manager: &'m ModelManager, fqn: &str) -> Result<Self> {
+        Self::from_typed(Typed::new(manager, fqn)?)
+    }
+
+    /// Wraps an instance of a class without identity.
+    pub(crate) fn from_typed(typed: Typed<'m>) -> Result<Self> {
+        if typed.is_identified() {
+            return Err(ConcertoError::ValidationFailed {
+                message: format!(
+                    "{} is identified, so it is created as a Resource",
+                    typed.fully_qualified_type()
+                ),
+            });
+        }
+        Ok(Self(typed))
+    }
+}
+
+impl<'m> Deref for Concept<'m> {
+    type Target = Typed<'m>;
+
+    fn deref(&self) -> &Typed<'m> {
+        &self.0
+    }
+}
+
+impl DerefMut for Concept<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
@@ -0,0 +1,72 @@
+//! An instance that is either a resource or a concept.
+
+use std::ops::Deref;
+
+use crate::error::Result;
+use crate::runtime::concept::Concept;
+use crate::runtime::resource::Resource;
+use crate::runtime::typed::Typed;
+
+/// An instance read from JSON, which is a resource or a concept depending on
+/// whether its class is identified.
+#[derive(Debug, Clone)]
+pub enum Instance<'m> {
+    /// An instance of an identified class.
+    Resource(Resource<'m>),
+    /// An instance of a class without identity.
+    Concept(Concept<'m>),
+}
+
+impl<'m> Instance<'m> {
+    /// Wraps a typed instance according to its identity.
+    pub(crate) fn from_typed(typed: Typed<'m>) -> Result<Self> {
+        if typed.is_identified() {
+            Resource::from_typed(typed).map(Self::Resource)
+        } else {
+            Concept::from_typed(typed).map(Self::Concept)
+        }
+    }
+
+    /// The resource, if this is one.
+    pub fn as_resource(&self) -> Option<&Resource<'m>> {
+        match self {
+            Self::Resource(resource) => Some(resource),
+            Self::Concept(_) => None,
+        }
+    }
+
+    /// The concept, if this is one.
+    pub fn as_concept(&self) -> Option<&Concept<'m>> {
+        match self {
+            Self::Concept(concept) => Some(concept),
+            Self::Resource(_) => None,
+        }
+    }
+
+    /// The resource, if this is one.
+    pub fn into_resource(self) -> Option<Resource<'m>> {
+        match self {
+            Self::Resource(resource) => Some(resource),
+            Self::Concept(_) => None,
+        }
+    }
+
+    /// The concept, if this is one.
+    pub fn into_concept(self) -> Option<Concept<'m>> {
+        match self {
+            Self::Concept(concept) => Some(concept),
+            Self::Resource(_) => None,
+        }
+    }
+}
+
+impl<'m> Deref for Instance<'m> {
+    type Target = Typed<'m>;
+
+    fn deref(&self) -> &Typed<'m> {
+        match self {
+            Self::Resource(resource) => resource,
+            Self::Concept(concept) => concept,
+        }
+    }
+}
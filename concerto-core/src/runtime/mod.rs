@@ -0,0 +1,28 @@
+//! A dynamic object model for instance data.
+//!
+//! Instances are JSON, but changing one as a raw [`serde_json::Value`] gives
+//! no help with types: nothing stops a number being written to a `String`
+//! field or a field being misspelt. The objects here know their
+//! [`ClassDeclaration`](crate::ClassDeclaration) and check each change against
+//! it.
+//!
+//! The JavaScript runtime builds these as a class hierarchy: `Resource` and
+//! `Concept` both extend `Typed`. Here the shared part is the [`Typed`]
+//! struct, which holds the type and the field values and has the typed
+//! getters and setters. [`Resource`], for identified classes, and
+//! [`Concept`], for the rest, wrap it and dereference to it, so its methods
+//! are available on both. An [`Instance`] is one or the other, as read from
+//! JSON. A [`Relationship`] is a reference to a resource by type and
+//! identifier.
+
+pub mod concept;
+pub mod instance;
+pub mod relationship;
+pub mod resource;
+pub mod typed;
+
+pub use concept::Concept;
+pub use instance::Instance;
+pub use relationship::Relationship;
+pub use resource::Resource;
+pub use typed::Typed;
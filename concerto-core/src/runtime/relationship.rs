@@ -0,0 +1,280 @@
+//! References from one instance to a resource.
+//!
+//! A relationship is written as a URI: `resource:`, the fully-qualified type
+//! of the resource, `#` and its identifier. The identifier is the URI's
+//! fragment, so any character a fragment cannot hold, `#` and `%` among them,
+//! is percent-encoded as UTF-8. Older data may leave out the `resource:`
+//! scheme or write the type by its short name, as in `Car#ABC123`; a short
+//! name is read in a default namespace.
+
+use std::fmt::{self, Write};
+
+use crate::error::{ConcertoError, Result};
+use crate::model_manager::ModelManager;
+use crate::model_util::{namespace_of, qualify, short_name};
+
+const SCHEME: &str = "resource:";
+
+/// A reference to a resource: its fully-qualified type and its identifier.
+/// It is written as a URI such as `resource:org.acme@1.0.0.Car#ABC123`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Relationship {
+    fqn: String,
+    identifier: String,
+}
+
+impl Relationship {
+    /// A reference to the `fqn` resource identified by `identifier`.
+    pub fn new(fqn: impl Into<String>, identifier: impl Into<String>) -> Self {
+        Self {
+            fqn: fqn.into(),
+            identifier: identifier.into(),
+        }
+    }
+
+    /// Reads a relationship URI. The `resource:` scheme may be left out, and
+    /// a type written by its short name is placed in `default_namespace`;
+    /// without one, a short name is an error. The identifier is
+    /// percent-decoded and must not be empty.
+    pub fn parse(uri: &str, default_namespace: Option<&str>) -> Result<Self> {
+        let (type_name, identifier) = split(uri)?;
+        let fqn = if type_name.contains('.') {
+            type_name.to_string()
+        } else {
+            let namespace = default_namespace.ok_or_else(|| {
+                failed(format!(
+                    "The relationship {uri} names the type {type_name} without a namespace"
+                ))
+            })?;
+            qualify(namespace, type_name)
+        };
+        Ok(Self::new(fqn, identifier))
+    }
+
+    /// Reads a relationship URI written in the model file for `namespace`,
+    /// where a short type name resolves as it would in that file, through
+    /// its imports. The type is not checked; see [`Relationship::validate`].
+    pub fn resolve(manager: &ModelManager, uri: &str, namespace: &str) -> Result<Self> {
+        Self::resolve_with(uri, |short| manager.resolve_type_name(namespace, short))
+    }
+
+    /// Reads a relationship URI, resolving a short type name with `resolve`.
+    pub(crate) fn resolve_with(
+        uri: &str,
+        resolve: impl FnOnce(&str) -> Result<String>,
+    ) -> Result<Self> {
+        let (type_name, identifier) = split(uri)?;
+        let fqn = if type_name.contains('.') {
+            type_name.to_string()
+        } else {
+            resolve(type_name)?
+        };
+        Ok(Self::new(fqn, identifier))
+    }
+
+    /// Checks that the referenced type is a class that is identified, itself
+    /// or through a super type, so that its instances can be referred to.
+    pub fn validate(&self, manager: &ModelManager) -> Result<()> {
+        let identified = match manager.get_declaration(&self.fqn)?.as_class() {
+            Some(_) => manager
+                .super_chain(&self.fqn)?
+                .iter()
+                .any(|(_, class)| class.is_identified()),
+            None => false,
+        };
+        if !identified {
+            return Err(failed(format!(
+                "The relationship {self} refers to {}, which is not an identified class",
+                self.fqn
+            )));
+        }
+        Ok(())
+    }
+
+    /// The fully-qualified type of the referenced resource.
+    pub fn fully_qualified_type(&self) -> &str {
+        &self.fqn
+    }
+
+    /// The namespace of the referenced resource's type.
+    pub fn namespace(&self) -> &str {
+        namespace_of(&self.fqn)
+    }
+
+    /// The short name of the referenced resource's type.
+    pub fn type_name(&self) -> &str {
+        short_name(&self.fqn)
+    }
+
+    /// The identifier of the referenced resource, decoded.
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// The type and identifier together, as in `org.acme@1.0.0.Car#ABC123`.
+    pub fn fully_qualified_identifier(&self) -> String {
+        format!("{}#{}", self.fqn, self.identifier)
+    }
+}
+
+impl fmt::Display for Relationship {
+    /// Writes the URI, with the identifier percent-encoded.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{SCHEME}{}#", self.fqn)?;
+        for c in self.identifier.chars() {
+            if is_fragment_char(c) {
+                f.write_char(c)?;
+            } else {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    write!(f, "%{byte:02X}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits a URI into its type name and decoded identifier.
+fn split(uri: &str) -> Result<(&str, String)> {
+    let malformed = || {
+        failed(format!(
+            "{uri} is not a relationship such as {SCHEME}org.acme@1.0.0.Car#ABC123"
+        ))
+    };
+    let (type_name, fragment) = uri
+        .strip_prefix(SCHEME)
+        .unwrap_or(uri)
+        .split_once('#')
+        .ok_or_else(malformed)?;
+    if type_name.is_empty() || fragment.is_empty() {
+        return Err(malformed());
+    }
+    let identifier = decode(fragment).ok_or_else(|| {
+        failed(format!(
+            "The relationship {uri} has an identifier that is not correctly percent-encoded"
+        ))
+    })?;
+    Ok((type_name, identifier))
+}
+
+/// Decodes `%XX` escapes. `None` if an escape is cut short, is not
+/// hexadecimal, or the bytes are not UTF-8.
+fn decode(fragment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(fragment.len());
+    let mut rest = fragment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The characters RFC 3986 allows unescaped in a fragment.
+fn is_fragment_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~!$&'()*+,;=:@/?".contains(c)
+}
+
+fn failed(message: String) -> ConcertoError {
+    ConcertoError::ValidationFailed { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn formats_as_a_resource_uri() {
+        let relationship = Relationship::new("org.acme@1.0.0.Car", "ABC123");
+        assert_eq!(relationship.namespace(), "org.acme@1.0.0");
+        assert_eq!(relationship.type_name(), "Car");
+        assert_eq!(
+            relationship.fully_qualified_identifier(),
+            "org.acme@1.0.0.Car#ABC123"
+        );
+        assert_eq!(
+            relationship.to_string(),
+            "resource:org.acme@1.0.0.Car#ABC123"
+        );
+    }
+
+    #[test]
+    fn percent_encodes_the_identifier() {
+        let relationship = Relationship::new("org.acme@1.0.0.Person", "ada lovelace#1 100%é");
+        let uri = relationship.to_string();
+        assert_eq!(
+            uri,
+            "resource:org.acme@1.0.0.Person#ada%20lovelace%231%20100%25%C3%A9"
+        );
+        assert_eq!(Relationship::parse(&uri, None).unwrap(), relationship);
+        assert_eq!(
+            Relationship::new("org.acme@1.0.0.Person", "ada@acme.org").to_string(),
+            "resource:org.acme@1.0.0.Person#ada@acme.org"
+        );
+    }
+
+    #[test]
+    fn parses_short_forms_in_the_default_namespace() {
+        let car = Relationship::new("org.acme@1.0.0.Car", "ABC123");
+        let parse = |uri| Relationship::parse(uri, Some("org.acme@1.0.0"));
+        assert_eq!(parse("resource:org.acme@1.0.0.Car#ABC123").unwrap(), car);
+        assert_eq!(parse("org.acme@1.0.0.Car#ABC123").unwrap(), car);
+        assert_eq!(parse("resource:Car#ABC123").unwrap(), car);
+        assert_eq!(parse("Car#ABC123").unwrap(), car);
+        assert!(Relationship::parse("Car#ABC123", None).is_err());
+
+        for malformed in ["ABC123", "resource:Car#", "#ABC123", "Car#AB%2", "Car#%ZZ"] {
+            assert!(parse(malformed).is_err(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn resolves_against_identified_classes() {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.acme@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Vehicle",
+                          "isAbstract": true,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "vin" },
+                          "properties": [{ "$class": "concerto.metamodel@1.0.0.StringProperty",
+                                           "name": "vin", "isArray": false, "isOptional": false }] },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Car",
+                          "isAbstract": false,
+                          "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Vehicle" },
+                          "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Address",
+                          "isAbstract": false, "properties": [] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+
+        let resolve = |uri| Relationship::resolve(&manager, uri, "org.acme@1.0.0");
+        let car = resolve("Car#ABC123").unwrap();
+        assert_eq!(car.fully_qualified_type(), "org.acme@1.0.0.Car");
+        assert!(car.validate(&manager).is_ok());
+
+        let address = resolve("Address#home").unwrap().validate(&manager);
+        assert!(
+            address
+                .unwrap_err()
+                .to_string()
+                .contains("not an identified class")
+        );
+        assert!(resolve("Boat#B1").is_err());
+        let string = Relationship::parse("String#x", Some("")).unwrap();
+        assert!(string.validate(&manager).is_err());
+    }
+}
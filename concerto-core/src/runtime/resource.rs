@@ -0,0 +1,83 @@
+//! Instances of identified classes.
+
+use std::ops::{Deref, DerefMut};
+
+use crate::error::{ConcertoError, Result};
+use crate::model_manager::ModelManager;
+use crate::runtime::relationship::Relationship;
+use crate::runtime::typed::Typed;
+
+/// An instance of an identified class: an asset, a participant, or any class
+/// declared `identified` or `identified by` a field.
+#[derive(Debug, Clone)]
+pub struct Resource<'m>(Typed<'m>);
+
+impl<'m> Resource<'m> {
+    /// A resource of the concrete, identified class `fqn`, with the given
+    /// identifier and no other fields set. For a class identified by a field,
+    /// the identifier is checked against that field and written to it.
+    pub fn new(
+        manager: &'m ModelManager,
+        fqn: &str,
+        identifier: impl Into<String>,
+    ) -> Result<Self> {
+        let mut typed = Typed::new(manager, fqn)?;
+        if !typed.is_identified() {
+            return Err(failed(format!(
+                "{fqn} is not identified, so it is created as a Concept"
+            )));
+        }
+        typed.set_identifier(identifier.into())?;
+        Ok(Self(typed))
+    }
+
+    /// Wraps an instance of an identified class.
+    pub(crate) fn from_typed(typed: Typed<'m>) -> Result<Self> {
+        if !typed.is_identified() {
+            return Err(failed(format!(
+                "{} is not identified, so it is created as a Concept",
+                typed.fully_qualified_type()
+            )));
+        }
+        Ok(Self(typed))
+    }
+
+    /// The identifier. Empty only if the identifying field has been cleared.
+    pub fn identifier(&self) -> &str {
+        self.0.identifier().unwrap_or_default()
+    }
+
+    /// Changes the identifier, along with the identifying field if the class
+    /// has one.
+    pub fn set_identifier(&mut self, identifier: impl Into<String>) -> Result<()> {
+        self.0.set_identifier(identifier.into())
+    }
+
+    /// The type and identifier together, as in `org.acme@1.0.0.Car#ABC123`.
+    pub fn fully_qualified_identifier(&self) -> String {
+        format!("{}#{}", self.fully_qualified_type(), self.identifier())
+    }
+
+    /// A relationship that refers to this resource.
+    pub fn to_relationship(&self) -> Relationship {
+        Relationship::new(self.fully_qualified_type(), self.identifier())
+    }
+}
+
+impl<'m> Deref for Resource<'m> {
+    type Target = Typed<'m>;
+
+    fn deref(&self) -> &Typed<'m> {
+        &self.0
+    }
+}
+
+impl DerefMut for Resource<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+fn failed(message: String) -> ConcertoError {
+    ConcertoError::ValidationFailed { message }
+}
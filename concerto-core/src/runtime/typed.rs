@@ -0,0 +1,693 @@
+//! The state shared by resources and concepts: a type and its field values.
+
+use chrono::{DateTime, Utc};
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use concerto_metamodel::datetime;
+use serde_json::{Map, Value, json};
+
+use crate::error::{ConcertoError, Result};
+use crate::instance_validation::{validate_property, whole_number};
+use crate::introspect::declaration::{ClassDeclaration, Declaration};
+use crate::introspect::property::Property;
+use crate::model_manager::ModelManager;
+use crate::model_util::{is_system_property, namespace_of, short_name};
+use crate::runtime::concept::Concept;
+use crate::runtime::relationship::Relationship;
+use crate::symbol::Symbol;
+use crate::validation::resolve;
+
+/// An instance of a class: its type, identifier and field values.
+///
+/// Every setter checks the value against the property it is written to,
+/// validators included, so a field never holds a value of the wrong shape.
+/// Required fields may still be missing until they are set; [`Typed::validate`]
+/// checks the instance as a whole.
+#[derive(Debug, Clone)]
+pub struct Typed<'m> {
+    manager: &'m ModelManager,
+    fqn: String,
+    chain: Vec<(Symbol, &'m ClassDeclaration)>,
+    identifier: Option<String>,
+    fields: Map<String, Value>,
+}
+
+impl<'m> Typed<'m> {
+    /// An instance of the concrete class `fqn` with no fields set.
+    pub(crate) fn new(manager: &'m ModelManager, fqn: &str) -> Result<Self> {
+        let class = manager
+            .get_declaration(fqn)?
+            .as_class()
+            .ok_or_else(|| failed(format!("{fqn} is not a class and cannot be instantiated")))?;
+        if class.is_abstract() {
+            return Err(failed(format!(
+                "Cannot instantiate the abstract type {fqn}"
+            )));
+        }
+        Ok(Self {
+            manager,
+            fqn: fqn.to_string(),
+            chain: manager.super_chain(fqn)?,
+            identifier: None,
+            fields: Map::new(),
+        })
+    }
+
+    /// An instance of `fqn` holding the fields of a JSON object as they are.
+    /// System properties other than `$identifier` are dropped. The object is
+    /// not checked; see [`Typed::validate`].
+    pub(crate) fn from_object(
+        manager: &'m ModelManager,
+        fqn: &str,
+        object: &Map<String, Value>,
+    ) -> Result<Self> {
+        let mut typed = Self::new(manager, fqn)?;
+        for (key, value) in object {
+            if !is_system_property(key) {
+                typed.fields.insert(key.clone(), value.clone());
+            }
+        }
+        typed.identifier = match typed.identifying_field() {
+            Some(field) => typed.fields.get(field).and_then(Value::as_str),
+            None => object.get("$identifier").and_then(Value::as_str),
+        }
+        .map(str::to_string);
+        Ok(typed)
+    }
+
+    /// The manager the type was found in.
+    pub fn model_manager(&self) -> &'m ModelManager {
+        self.manager
+    }
+
+    /// The fully-qualified name of the type.
+    pub fn fully_qualified_type(&self) -> &str {
+        &self.fqn
+    }
+
+    /// The namespace of the type.
+    pub fn namespace(&self) -> &str {
+        namespace_of(&self.fqn)
+    }
+
+    /// The short name of the type.
+    pub fn type_name(&self) -> &str {
+        short_name(&self.fqn)
+    }
+
+    /// The declaration of the type.
+    pub fn class_declaration(&self) -> &'m ClassDeclaration {
+        self.chain[0].1
+    }
+
+    /// The property called `name`, declared by the type or inherited.
+    pub fn property(&self, name: &str) -> Result<&'m Property> {
+        self.owned_property(name).map(|(_, property)| property)
+    }
+
+    /// The raw value of a field, if it is set.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.fields.get(name)
+    }
+
+    /// Sets a field to a JSON value, after checking the value against the
+    /// property. Setting an optional field to `null` clears it.
+    pub fn set(&mut self, name: &str, value: Value) -> Result<()> {
+        let (owner, property) = self.owned_property(name)?;
+        validate_property(self.manager, &self.fqn, "", owner, property, Some(&value))?;
+        if self.identifying_field() == Some(name) {
+            self.identifier = value.as_str().map(str::to_string);
+        }
+        if value.is_null() {
+            self.fields.remove(name);
+        } else {
+            self.fields.insert(name.to_string(), value);
+        }
+        Ok(())
+    }
+
+    /// The value of a `Boolean` field, or of a field typed by a `Boolean`
+    /// scalar.
+    pub fn get_boolean(&self, name: &str) -> Result<Option<bool>> {
+        self.typed_get(name, Kind::Boolean, Value::as_bool)
+    }
+
+    /// Sets a `Boolean` field.
+    pub fn set_boolean(&mut self, name: &str, value: bool) -> Result<()> {
+        self.typed_set(name, Kind::Boolean, json!(value))
+    }
+
+    /// The value of a `String` field, an enum field, or a field typed by a
+    /// `String` scalar.
+    pub fn get_string(&self, name: &str) -> Result<Option<&str>> {
+        self.typed_get(name, Kind::String, Value::as_str)
+    }
+
+    /// Sets a `String` field, an enum field to one of its members, or a field
+    /// typed by a `String` scalar.
+    pub fn set_string(&mut self, name: &str, value: impl Into<String>) -> Result<()> {
+        self.typed_set(name, Kind::String, Value::String(value.into()))
+    }
+
+    /// The value of an `Integer` field.
+    pub fn get_integer(&self, name: &str) -> Result<Option<i32>> {
+        self.typed_get(name, Kind::Integer, |value| {
+            whole_number(value).and_then(|number| i32::try_from(number).ok())
+        })
+    }
+
+    /// Sets an `Integer` field.
+    pub fn set_integer(&mut self, name: &str, value: i32) -> Result<()> {
+        self.typed_set(name, Kind::Integer, json!(value))
+    }
+
+    /// The value of a `Long` field.
+    pub fn get_long(&self, name: &str) -> Result<Option<i64>> {
+        self.typed_get(name, Kind::Long, whole_number)
+    }
+
+    /// Sets a `Long` field.
+    pub fn set_long(&mut self, name: &str, value: i64) -> Result<()> {
+        self.typed_set(name, Kind::Long, json!(value))
+    }
+
+    /// The value of a `Double` field.
+    pub fn get_double(&self, name: &str) -> Result<Option<f64>> {
+        self.typed_get(name, Kind::Double, Value::as_f64)
+    }
+
+    /// Sets a `Double` field.
+    pub fn set_double(&mut self, name: &str, value: f64) -> Result<()> {
+        self.typed_set(name, Kind::Double, json!(value))
+    }
+
+    /// The value of a `DateTime` field, in UTC.
+    pub fn get_datetime(&self, name: &str) -> Result<Option<DateTime<Utc>>> {
+        self.typed_get(name, Kind::DateTime, |value| {
+            value.as_str().and_then(|text| datetime::parse(text).ok())
+        })
+    }
+
+    /// Sets a `DateTime` field.
+    pub fn set_datetime(&mut self, name: &str, value: &DateTime<Utc>) -> Result<()> {
+        self.typed_set(name, Kind::DateTime, json!(datetime::format(value)))
+    }
+
+    /// The concept held by a field typed by a concept.
+    pub fn get_concept(&self, name: &str) -> Result<Option<Concept<'m>>> {
+        self.check_kind(name, Kind::Concept)?;
+        let Some(value) = self.fields.get(name) else {
+            return Ok(None);
+        };
+        let object = value
+            .as_object()
+            .ok_or_else(|| self.mismatch(name, "an object", value))?;
+        let fqn = object
+            .get("$class")
+            .and_then(Value::as_str)
+            .ok_or_else(|| self.mismatch(name, "an object with a $class", value))?;
+        Concept::from_typed(Self::from_object(self.manager, fqn, object)?).map(Some)
+    }
+
+    /// Sets a field typed by a concept. The concept may be of any subtype of
+    /// the field's type.
+    pub fn set_concept(&mut self, name: &str, value: &Concept<'_>) -> Result<()> {
+        self.typed_set(name, Kind::Concept, value.to_value())
+    }
+
+    /// The resource a relationship field points at. A type written by its
+    /// short name resolves as it would in the model file that declares the
+    /// field.
+    pub fn get_relationship(&self, name: &str) -> Result<Option<Relationship>> {
+        self.check_kind(name, Kind::Relationship)?;
+        let Some(value) = self.fields.get(name) else {
+            return Ok(None);
+        };
+        let uri = value
+            .as_str()
+            .ok_or_else(|| self.mismatch(name, "a relationship URI", value))?;
+        let (owner, _) = self.owned_property(name)?;
+        Relationship::resolve(self.manager, uri, namespace_of(owner)).map(Some)
+    }
+
+    /// Points a relationship field at a resource.
+    pub fn set_relationship(&mut self, name: &str, value: &Relationship) -> Result<()> {
+        self.typed_set(name, Kind::Relationship, json!(value.to_string()))
+    }
+
+    /// The entries of a field typed by a map, keyed as they are in JSON,
+    /// without the map's `$class`.
+    pub fn get_map(&self, name: &str) -> Result<Option<Map<String, Value>>> {
+        self.typed_get(name, Kind::Map, |value| {
+            let entries = value.as_object()?;
+            Some(
+                entries
+                    .iter()
+                    .filter(|(key, _)| !is_system_property(key))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            )
+        })
+    }
+
+    /// Sets a field typed by a map to `entries`, under the map's `$class`.
+    /// Every key and value is checked against the map's declaration.
+    pub fn set_map(&mut self, name: &str, entries: Map<String, Value>) -> Result<()> {
+        self.check_kind(name, Kind::Map)?;
+        let (owner, property) = self.owned_property(name)?;
+        let type_identifier = property
+            .type_identifier()
+            .ok_or_else(|| failed(format!("The field {name} of {} is not a map", self.fqn)))?;
+        let fqn = object_type(self.manager, namespace_of(owner), type_identifier)?;
+        let mut map = Map::new();
+        map.insert("$class".into(), json!(&*fqn));
+        map.extend(entries);
+        self.set(name, Value::Object(map))
+    }
+
+    /// The instance as JSON: `$class`, `$identifier` for an identified type,
+    /// and the fields that are set, including any the type does not declare,
+    /// which only an instance read leniently can hold. The members are
+    /// inserted in that order, with the declared fields in the order
+    /// [`ModelManager::get_all_properties`] lists them, but only a
+    /// [`Map`] built with serde_json's `preserve_order` feature keeps it; the
+    /// [`Serializer`](crate::Serializer) writes it regardless.
+    pub fn to_value(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("$class".into(), json!(self.fqn));
+        if let Some(identifier) = &self.identifier {
+            object.insert("$identifier".into(), json!(identifier));
+        }
+        for (_, class) in &self.chain {
+            for property in class.own_properties() {
+                if let Some(value) = self.fields.get(property.name()) {
+                    object.insert(property.name().to_string(), value.clone());
+                }
+            }
+        }
+        for (name, value) in &self.fields {
+            if !object.contains_key(name) {
+                object.insert(name.clone(), value.clone());
+            }
+        }
+        Value::Object(object)
+    }
+
+    /// Checks the whole instance, including that every required field is set.
+    pub fn validate(&self) -> Result<()> {
+        self.manager.validate_instance(&self.to_value())
+    }
+
+    pub(crate) fn is_identified(&self) -> bool {
+        self.chain.iter().any(|(_, class)| class.is_identified())
+    }
+
+    pub(crate) fn identifier(&self) -> Option<&str> {
+        self.identifier.as_deref()
+    }
+
+    /// Sets the identifier, and the identifying field if the type has one.
+    pub(crate) fn set_identifier(&mut self, identifier: String) -> Result<()> {
+        match self.identifying_field() {
+            Some(field) => self.set(field, Value::String(identifier)),
+            None => {
+                self.identifier = Some(identifier);
+                Ok(())
+            }
+        }
+    }
+
+    fn identifying_field(&self) -> Option<&'m str> {
+        self.chain
+            .iter()
+            .find_map(|(_, class)| class.identifier_field_name())
+    }
+
+    /// The property called `name` and the class along the chain that
+    /// declares it.
+    fn owned_property(&self, name: &str) -> Result<(&str, &'m Property)> {
+        self.chain
+            .iter()
+            .find_map(|(owner, class)| {
+                let property = class.own_properties().iter().find(|p| p.name() == name)?;
+                Some((&**owner, property))
+            })
+            .ok_or_else(|| failed(format!("{} has no field named {name}", self.fqn)))
+    }
+
+    fn typed_get<'a, T>(
+        &'a self,
+        name: &str,
+        kind: Kind,
+        read: impl FnOnce(&'a Value) -> Option<T>,
+    ) -> Result<Option<T>> {
+        self.check_kind(name, kind)?;
+        match self.fields.get(name) {
+            None => Ok(None),
+            Some(value) => read(value)
+                .map(Some)
+                .ok_or_else(|| self.mismatch(name, kind.describe(), value)),
+        }
+    }
+
+    fn typed_set(&mut self, name: &str, kind: Kind, value: Value) -> Result<()> {
+        self.check_kind(name, kind)?;
+        self.set(name, value)
+    }
+
+    /// Checks that `name` is a single field of the given kind.
+    fn check_kind(&self, name: &str, kind: Kind) -> Result<()> {
+        let (owner, property) = self.owned_property(name)?;
+        if property.is_array() {
+            return Err(failed(format!(
+                "The field {name} of {} is an array; use get and set for arrays",
+                self.fqn
+            )));
+        }
+        let actual = Kind::of(self.manager, namespace_of(owner), property)?;
+        if actual != kind {
+            return Err(failed(format!(
+                "The field {name} of {} holds {}, not {}",
+                self.fqn,
+                actual.describe(),
+                kind.describe()
+            )));
+        }
+        Ok(())
+    }
+
+    fn mismatch(&self, name: &str, expected: &str, value: &Value) -> ConcertoError {
+        failed(format!(
+            "The field {name} of {} should hold {expected}, but holds {value}",
+            self.fqn
+        ))
+    }
+}
+
+/// What a single field holds, seen through scalars: a field typed by a
+/// scalar holds the scalar's primitive, and an enum field holds a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Boolean,
+    String,
+    Integer,
+    Long,
+    Double,
+    DateTime,
+    Concept,
+    Map,
+    Relationship,
+}
+
+impl Kind {
+    fn of(manager: &ModelManager, namespace: &str, property: &Property) -> Result<Self> {
+        Ok(match property {
+            Property::Boolean(_) => Self::Boolean,
+            Property::String(_) | Property::Enum(_) => Self::String,
+            Property::Integer(_) => Self::Integer,
+            Property::Long(_) => Self::Long,
+            Property::Double(_) => Self::Double,
+            Property::DateTime(_) => Self::DateTime,
+            Property::Relationship(_) => Self::Relationship,
+            Property::Object(p) => {
+                let fqn = object_type(manager, namespace, &p.type_)?;
+                match manager.get_declaration(&fqn)? {
+                    Declaration::Class(_) => Self::Concept,
+                    Declaration::Enum(_) => Self::String,
+                    Declaration::Map(_) => Self::Map,
+                    Declaration::Scalar(scalar) => match scalar.scalar_type() {
+                        "Boolean" => Self::Boolean,
+                        "Integer" => Self::Integer,
+                        "Long" => Self::Long,
+                        "Double" => Self::Double,
+                        "DateTime" => Self::DateTime,
+                        _ => Self::String,
+                    },
+                }
+            }
+        })
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Boolean => "a Boolean",
+            Self::String => "a String",
+            Self::Integer => "an Integer",
+            Self::Long => "a Long",
+            Self::Double => "a Double",
+            Self::DateTime => "a DateTime",
+            Self::Concept => "a concept",
+            Self::Map => "a map",
+            Self::Relationship => "a relationship",
+        }
+    }
+}
+
+/// The fully-qualified name of the type an object property declared in
+/// `namespace` refers to.
+fn object_type(
+    manager: &ModelManager,
+    namespace: &str,
+    type_identifier: &mm::TypeIdentifier,
+) -> Result<Symbol> {
+    resolve(
+        manager,
+        namespace,
+        &type_identifier.name,
+        type_identifier.namespace.as_deref(),
+    )
+    .ok_or_else(|| ConcertoError::TypeNotFound {
+        type_name: type_identifier.name.clone(),
+    })
+}
+
+fn failed(message: String) -> ConcertoError {
+    ConcertoError::ValidationFailed { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use crate::model_manager::ModelManager;
+    use crate::runtime::{Concept, Resource};
+    use crate::test_fixtures::{optional_property, property, typed};
+
+    /// `org.fleet@1.0.0`: a `Car` asset identified by `vin`, holding fields of
+    /// each kind, an `Address` concept, a `Colour` enum, a `Mileage` scalar, a
+    /// `Services` map from dates to mileages and a system-identified `Driver`.
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.fleet@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.EnumDeclaration", "name": "Colour",
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "RED" },
+                            { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "BLUE" }
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.LongScalar", "name": "Mileage",
+                          "validator": { "$class": "concerto.metamodel@1.0.0.LongDomainValidator",
+                                         "lower": 0 } },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Address",
+                          "isAbstract": false,
+                          "properties": [property("StringProperty", "city", json!({}))] },
+                        { "$class": "concerto.metamodel@1.0.0.MapDeclaration", "name": "Services",
+                          "key": { "$class": "concerto.metamodel@1.0.0.DateTimeMapKeyType" },
+                          "value": { "$class": "concerto.metamodel@1.0.0.LongMapValueType" } },
+                        { "$class": "concerto.metamodel@1.0.0.ParticipantDeclaration", "name": "Driver",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.Identified" },
+                          "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Car",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "vin" },
+                          "properties": [
+                            property("StringProperty", "vin",
+                                     json!({ "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator",
+                                                            "pattern": "^[A-Z0-9]{6}$", "flags": "" } })),
+                            property("BooleanProperty", "electric", json!({})),
+                            optional_property("IntegerProperty", "seats",
+                                     json!({ "validator": { "$class": "concerto.metamodel@1.0.0.IntegerDomainValidator",
+                                                            "lower": 1, "upper": 9 } })),
+                            optional_property("DoubleProperty", "price", json!({})),
+                            optional_property("DateTimeProperty", "registered", json!({})),
+                            optional_property("StringProperty", "tags", json!({ "isArray": true })),
+                            optional_property("ObjectProperty", "colour", typed("Colour")),
+                            optional_property("ObjectProperty", "mileage", typed("Mileage")),
+                            optional_property("ObjectProperty", "garage", typed("Address")),
+                            optional_property("RelationshipProperty", "driver", typed("Driver")),
+                            optional_property("ObjectProperty", "services", typed("Services"))
+                          ] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    #[test]
+    fn typed_setters_and_getters_round_trip() {
+        let manager = manager();
+        let mut car = Resource::new(&manager, "org.fleet@1.0.0.Car", "ABC123").unwrap();
+        car.set_boolean("electric", true).unwrap();
+        car.set_integer("seats", 5).unwrap();
+        car.set_double("price", 19999.5).unwrap();
+        car.set_string("colour", "BLUE").unwrap();
+        car.set_long("mileage", 12000).unwrap();
+        let registered = concerto_metamodel::datetime::parse("2024-03-01T10:00:00+01:00").unwrap();
+        car.set_datetime("registered", &registered).unwrap();
+        car.set("tags", json!(["fleet", "city"])).unwrap();
+
+        assert_eq!(car.get_boolean("electric").unwrap(), Some(true));
+        assert_eq!(car.get_integer("seats").unwrap(), Some(5));
+        assert_eq!(car.get_double("price").unwrap(), Some(19999.5));
+        assert_eq!(car.get_string("colour").unwrap(), Some("BLUE"));
+        assert_eq!(car.get_long("mileage").unwrap(), Some(12000));
+        assert_eq!(car.get_datetime("registered").unwrap(), Some(registered));
+        assert_eq!(car.get_string("vin").unwrap(), Some("ABC123"));
+        assert!(car.get_string("price").is_err());
+        car.validate().unwrap();
+
+        let value = car.to_value();
+        assert_eq!(value["$class"], json!("org.fleet@1.0.0.Car"));
+        assert_eq!(value["$identifier"], json!("ABC123"));
+        assert_eq!(value["registered"], json!("2024-03-01T09:00:00.000Z"));
+    }
+
+    #[test]
+    fn whole_floats_read_back_as_integers() {
+        let manager = manager();
+        let mut car = Resource::new(&manager, "org.fleet@1.0.0.Car", "ABC123").unwrap();
+        car.set("seats", json!(5.0)).unwrap();
+        car.set("mileage", json!(12000.0)).unwrap();
+        assert_eq!(car.get_integer("seats").unwrap(), Some(5));
+        assert_eq!(car.get_long("mileage").unwrap(), Some(12000));
+    }
+
+    #[test]
+    fn setters_check_kinds_and_validators() {
+        let manager = manager();
+        let mut car = Resource::new(&manager, "org.fleet@1.0.0.Car", "ABC123").unwrap();
+
+        let kind = car.set_string("seats", "five").unwrap_err();
+        assert!(kind.to_string().contains("holds an Integer, not a String"));
+        let domain = car.set_integer("seats", 12).unwrap_err();
+        assert!(domain.to_string().contains("outside 1..9"));
+        let member = car.set_string("colour", "GREEN").unwrap_err();
+        assert!(member.to_string().contains("GREEN"));
+        let scalar = car.set_long("mileage", -1).unwrap_err();
+        assert!(scalar.to_string().contains("outside 0.."));
+        let array = car.set_string("tags", "fleet").unwrap_err();
+        assert!(array.to_string().contains("is an array"));
+        let unknown = car.set_boolean("wings", true).unwrap_err();
+        assert!(unknown.to_string().contains("has no field named wings"));
+        assert!(car.get("seats").is_none());
+
+        car.set_integer("seats", 4).unwrap();
+        car.set("seats", Value::Null).unwrap();
+        assert_eq!(car.get_integer("seats").unwrap(), None);
+    }
+
+    #[test]
+    fn concepts_nest_inside_other_instances() {
+        let manager = manager();
+        let mut address = Concept::new(&manager, "org.fleet@1.0.0.Address").unwrap();
+        assert!(address.validate().is_err());
+        address.set_string("city", "Dublin").unwrap();
+
+        let mut car = Resource::new(&manager, "org.fleet@1.0.0.Car", "ABC123").unwrap();
+        car.set_concept("garage", &address).unwrap();
+        let garage = car.get_concept("garage").unwrap().unwrap();
+        assert_eq!(garage.type_name(), "Address");
+        assert_eq!(garage.get_string("city").unwrap(), Some("Dublin"));
+
+        let wrong = car.set("garage", json!({ "$class": "org.fleet@1.0.0.Address" }));
+        assert!(
+            wrong
+                .unwrap_err()
+                .to_string()
+                .contains("missing the required field city")
+        );
+    }
+
+    #[test]
+    fn maps_round_trip() {
+        let manager = manager();
+        let mut car = Resource::new(&manager, "org.fleet@1.0.0.Car", "ABC123").unwrap();
+        assert_eq!(car.get_map("services").unwrap(), None);
+
+        let services =
+            json!({ "2024-03-01T10:00:00.000Z": 12000, "2025-03-01T10:00:00.000Z": 24000 });
+        let Value::Object(services) = services else {
+            unreachable!()
+        };
+        car.set_map("services", services.clone()).unwrap();
+        car.set_boolean("electric", false).unwrap();
+        car.validate().unwrap();
+        assert_eq!(car.get_map("services").unwrap(), Some(services));
+        assert_eq!(
+            car.get("services").unwrap()["$class"],
+            json!("org.fleet@1.0.0.Services")
+        );
+
+        let far = json!({ "2024-03-01T10:00:00.000Z": "far" });
+        let Value::Object(far) = far else {
+            unreachable!()
+        };
+        assert!(car.set_map("services", far).is_err());
+        assert!(car.get_map("garage").is_err());
+    }
+
+    #[test]
+    fn resources_are_identified_and_referenced() {
+        let manager = manager();
+        let driver = Resource::new(&manager, "org.fleet@1.0.0.Driver", "d-42").unwrap();
+        assert_eq!(driver.identifier(), "d-42");
+        assert_eq!(
+            driver.fully_qualified_identifier(),
+            "org.fleet@1.0.0.Driver#d-42"
+        );
+        assert_eq!(driver.to_value()["$identifier"], json!("d-42"));
+
+        let mut car = Resource::new(&manager, "org.fleet@1.0.0.Car", "ABC123").unwrap();
+        car.set_relationship("driver", &driver.to_relationship())
+            .unwrap();
+        assert_eq!(
+            car.get("driver"),
+            Some(&json!("resource:org.fleet@1.0.0.Driver#d-42"))
+        );
+        assert_eq!(
+            car.get_relationship("driver").unwrap(),
+            Some(driver.to_relationship())
+        );
+        // A short type name resolves in the namespace that declares the field.
+        car.set("driver", json!("Driver#d-7")).unwrap();
+        let short = car.get_relationship("driver").unwrap().unwrap();
+        assert_eq!(
+            short.fully_qualified_identifier(),
+            "org.fleet@1.0.0.Driver#d-7"
+        );
+        assert!(car.get_relationship("vin").is_err());
+
+        car.set_identifier("XYZ789").unwrap();
+        assert_eq!(car.get_string("vin").unwrap(), Some("XYZ789"));
+        car.set_string("vin", "QQQ111").unwrap();
+        assert_eq!(car.identifier(), "QQQ111");
+        assert!(car.set_identifier("not valid").is_err());
+        assert!(Resource::new(&manager, "org.fleet@1.0.0.Car", "lower").is_err());
+    }
+
+    #[test]
+    fn identity_decides_between_resource_and_concept() {
+        let manager = manager();
+        let concept = Concept::new(&manager, "org.fleet@1.0.0.Driver").unwrap_err();
+        assert!(concept.to_string().contains("created as a Resource"));
+        let resource = Resource::new(&manager, "org.fleet@1.0.0.Address", "a").unwrap_err();
+        assert!(resource.to_string().contains("created as a Concept"));
+        assert!(Concept::new(&manager, "org.fleet@1.0.0.Colour").is_err());
+    }
+}
@@ -0,0 +1,733 @@
+//! Conversion between JSON and the runtime object model.
+//!
+//! A [`Serializer`] reads JSON into an [`Instance`] and writes a [`Typed`]
+//! instance back out, with the options the JavaScript runtime's serializer
+//! takes. Both directions walk the instance along its declared properties,
+//! so the output is deterministic: `$class` first, then `$identifier` and
+//! `$timestamp`, then each field in the order
+//! [`ModelManager::get_all_properties`] lists them.
+//!
+//! [`Serializer::to_json_string`] writes the members of each object in that
+//! order. [`Serializer::to_json`] returns the same members as a [`Value`],
+//! whose [`Map`] sorts them by key unless the application builds serde_json
+//! with its `preserve_order` feature.
+//!
+//! A property the type does not declare is an error in strict mode, the
+//! default. In lenient mode it is kept, written after the declared fields and
+//! left out when the instance is validated.
+//!
+//! `DateTime` values are normalized to UTC when read. When written they stay
+//! in UTC, or are shifted to [`SerializerOptions::utc_offset`] if one is set.
+//!
+//! A relationship normally holds a reference such as
+//! `resource:org.acme@1.0.0.Person#ada`. A relationship that holds the
+//! resource itself is refused unless
+//! [`permit_resources_for_relationships`](SerializerOptions::permit_resources_for_relationships)
+//! is set. When writing,
+//! [`convert_resources_to_relationships`](SerializerOptions::convert_resources_to_relationships)
+//! replaces such a resource with a reference to it, and
+//! [`deduplicate_resources`](SerializerOptions::deduplicate_resources) writes
+//! a resource out in full only the first time it appears and as a reference
+//! after that.
+
+use std::collections::HashSet;
+
+use chrono::FixedOffset;
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use concerto_metamodel::datetime;
+use serde::ser::{Serialize, SerializeMap};
+use serde_json::{Map, Value, json};
+
+use crate::error::{ConcertoError, Result};
+use crate::introspect::declaration::{Declaration, MapDeclaration, ScalarDeclaration};
+use crate::introspect::property::Property;
+use crate::model_manager::ModelManager;
+use crate::model_util::{is_system_property, namespace_of};
+use crate::runtime::{Instance, Relationship, Typed};
+use crate::symbol::Symbol;
+use crate::validation::resolve;
+
+/// The options of a [`Serializer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializerOptions {
+    /// Validate instances as they are read and before they are written.
+    /// On by default.
+    pub validate: bool,
+    /// Refuse properties the type does not declare, rather than keeping them.
+    /// On by default.
+    pub strict: bool,
+    /// When writing, replace a resource held by a relationship with a
+    /// reference to it.
+    pub convert_resources_to_relationships: bool,
+    /// Allow a relationship to hold the resource itself rather than a
+    /// reference.
+    pub permit_resources_for_relationships: bool,
+    /// When writing, write each resource in full only once and as a
+    /// reference wherever it appears again.
+    pub deduplicate_resources: bool,
+    /// The offset from UTC, in minutes, to write `DateTime` values at.
+    pub utc_offset: Option<i32>,
+}
+
+impl Default for SerializerOptions {
+    fn default() -> Self {
+        Self {
+            validate: true,
+            strict: true,
+            convert_resources_to_relationships: false,
+            permit_resources_for_relationships: false,
+            deduplicate_resources: false,
+            utc_offset: None,
+        }
+    }
+}
+
+/// Reads and writes instances of the types in a [`ModelManager`].
+#[derive(Debug, Clone)]
+pub struct Serializer<'m> {
+    manager: &'m ModelManager,
+    options: SerializerOptions,
+}
+
+impl<'m> Serializer<'m> {
+    /// A serializer with the default options.
+    pub fn new(manager: &'m ModelManager) -> Self {
+        Self::with_options(manager, SerializerOptions::default())
+    }
+
+    /// A serializer with the given options.
+    pub fn with_options(manager: &'m ModelManager, options: SerializerOptions) -> Self {
+        Self { manager, options }
+    }
+
+    /// The options this serializer uses.
+    pub fn options(&self) -> &SerializerOptions {
+        &self.options
+    }
+
+    /// Writes an instance as JSON.
+    pub fn to_json(&self, instance: &Typed<'_>) -> Result<Value> {
+        self.write(instance).map(Written::into_value)
+    }
+
+    /// Writes an instance as JSON text, with the members of each object in
+    /// declaration order.
+    pub fn to_json_string(&self, instance: &Typed<'_>) -> Result<String> {
+        let written = self.write(instance)?;
+        serde_json::to_string(&written).map_err(|error| failed(error.to_string()))
+    }
+
+    fn write(&self, instance: &Typed<'_>) -> Result<Written> {
+        let value = instance.to_value();
+        let object = value.as_object().expect("an instance is an object");
+        if self.options.validate {
+            self.validate(instance.fully_qualified_type(), object)?;
+        }
+        let dates = match self.options.utc_offset {
+            None => Dates::Keep,
+            Some(minutes) => minutes
+                .checked_mul(60)
+                .and_then(FixedOffset::east_opt)
+                .map(Dates::Offset)
+                .ok_or_else(|| failed(format!("{minutes} minutes is not a UTC offset")))?,
+        };
+        let resources = if self.options.convert_resources_to_relationships {
+            Resources::Reference
+        } else if self.options.permit_resources_for_relationships {
+            Resources::Keep
+        } else {
+            Resources::Reject
+        };
+        let mut walk = self.walk(dates, resources, self.unknown());
+        if self.options.deduplicate_resources {
+            walk.deduplicate = true;
+            if let Some(reference) = walk.reference(|key| object.get(key).and_then(Value::as_str)) {
+                walk.seen.insert(reference);
+            }
+        }
+        walk.object(instance.fully_qualified_type(), object)
+    }
+
+    /// Reads an instance from JSON. The JSON must name its type in `$class`.
+    pub fn from_json(&self, json: &Value) -> Result<Instance<'m>> {
+        let object = json
+            .as_object()
+            .ok_or_else(|| failed(format!("An instance must be an object, not {json}")))?;
+        let fqn = object
+            .get("$class")
+            .and_then(Value::as_str)
+            .ok_or_else(|| failed("The instance does not name its type in $class".into()))?;
+        let resources = if self.options.permit_resources_for_relationships {
+            Resources::Keep
+        } else {
+            Resources::Reject
+        };
+        let read = self
+            .walk(Dates::Normalize, resources, self.unknown())
+            .object(fqn, object)?
+            .into_object();
+        if self.options.validate {
+            self.validate(fqn, &read)?;
+        }
+        Instance::from_typed(Typed::from_object(self.manager, fqn, &read)?)
+    }
+
+    /// Validates an instance with any resources held by relationships
+    /// replaced by references, and each of those resources on its own.
+    /// Undeclared properties are left out.
+    fn validate(&self, fqn: &str, object: &Map<String, Value>) -> Result<()> {
+        let mut walk = self.walk(Dates::Keep, Resources::Reference, Unknown::Drop);
+        let view = walk.object(fqn, object)?;
+        self.manager.validate_instance(&view.into_value())?;
+        walk.referenced
+            .iter()
+            .try_for_each(|(target, resource)| self.manager.validate_instance_as(target, resource))
+    }
+
+    fn unknown(&self) -> Unknown {
+        if self.options.strict {
+            Unknown::Reject
+        } else {
+            Unknown::Keep
+        }
+    }
+
+    fn walk(&self, dates: Dates, resources: Resources, unknown: Unknown) -> Walk<'m> {
+        Walk {
+            manager: self.manager,
+            dates,
+            resources,
+            unknown,
+            deduplicate: false,
+            seen: HashSet::new(),
+            referenced: Vec::new(),
+        }
+    }
+}
+
+/// What happens to `DateTime` values.
+#[derive(Clone, Copy)]
+enum Dates {
+    Keep,
+    Normalize,
+    Offset(FixedOffset),
+}
+
+/// What happens to a resource held by a relationship.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Resources {
+    Keep,
+    Reference,
+    Reject,
+}
+
+/// What happens to a property the type does not declare.
+#[derive(Clone, Copy)]
+enum Unknown {
+    Keep,
+    Drop,
+    Reject,
+}
+
+/// One pass over an instance, rebuilding it in declaration order.
+struct Walk<'m> {
+    manager: &'m ModelManager,
+    dates: Dates,
+    resources: Resources,
+    unknown: Unknown,
+    deduplicate: bool,
+    /// References to the resources already written in full.
+    seen: HashSet<String>,
+    /// Resources replaced by references, with the type each was declared as.
+    referenced: Vec<(String, Value)>,
+}
+
+/// JSON as a walk writes it, with the members of each object in the order
+/// they were written.
+enum Written {
+    Object(Vec<(String, Written)>),
+    Array(Vec<Written>),
+    Value(Value),
+}
+
+impl Written {
+    fn into_value(self) -> Value {
+        match self {
+            Written::Object(_) => Value::Object(self.into_object()),
+            Written::Array(items) => {
+                Value::Array(items.into_iter().map(Written::into_value).collect())
+            }
+            Written::Value(value) => value,
+        }
+    }
+
+    fn into_object(self) -> Map<String, Value> {
+        match self {
+            Written::Object(members) => members
+                .into_iter()
+                .map(|(key, value)| (key, value.into_value()))
+                .collect(),
+            _ => Map::new(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Written> {
+        match self {
+            Written::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Written::Value(value) => value.as_str(),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for Written {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Written::Object(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (key, value) in members {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Written::Array(items) => serializer.collect_seq(items),
+            Written::Value(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl Walk<'_> {
+    /// Rebuilds an object declared as `declared`, or as the subtype its
+    /// `$class` names.
+    fn object(&mut self, declared: &str, object: &Map<String, Value>) -> Result<Written> {
+        let fqn = object
+            .get("$class")
+            .and_then(Value::as_str)
+            .unwrap_or(declared)
+            .to_string();
+        let chain = self.manager.super_chain(&fqn)?;
+
+        let mut out = vec![("$class".to_string(), Written::Value(json!(fqn)))];
+        for key in ["$identifier", "$timestamp"] {
+            if let Some(value) = object.get(key) {
+                out.push((key.into(), Written::Value(value.clone())));
+            }
+        }
+        let mut declared = HashSet::new();
+        for (owner, class) in &chain {
+            for property in class.own_properties() {
+                if let Some(value) = object.get(property.name()) {
+                    let value = self.field(namespace_of(owner), &fqn, property, value)?;
+                    out.push((property.name().to_string(), value));
+                    declared.insert(property.name());
+                }
+            }
+        }
+        for (key, value) in object {
+            if declared.contains(key.as_str()) || is_system_property(key) {
+                continue;
+            }
+            match self.unknown {
+                Unknown::Keep => out.push((key.clone(), Written::Value(value.clone()))),
+                Unknown::Drop => {}
+                Unknown::Reject => {
+                    return Err(failed(format!(
+                        "Instance of {fqn} has a property named {key}, which is not declared"
+                    )));
+                }
+            }
+        }
+        Ok(Written::Object(out))
+    }
+
+    fn field(
+        &mut self,
+        namespace: &str,
+        class_name: &str,
+        property: &Property,
+        value: &Value,
+    ) -> Result<Written> {
+        let what = format!("field {} of {class_name}", property.name());
+        match value {
+            Value::Array(items) if property.is_array() => items
+                .iter()
+                .map(|item| self.value(namespace, &what, property, item))
+                .collect::<Result<_>>()
+                .map(Written::Array),
+            _ => self.value(namespace, &what, property, value),
+        }
+    }
+
+    fn value(
+        &mut self,
+        namespace: &str,
+        what: &str,
+        property: &Property,
+        value: &Value,
+    ) -> Result<Written> {
+        match property {
+            Property::DateTime(_) => Ok(Written::Value(self.datetime(value))),
+            Property::Object(p) => {
+                let fqn = self.resolve(namespace, &p.type_)?;
+                self.declared(&fqn, value)
+            }
+            Property::Relationship(p) => {
+                let target = self.resolve(namespace, &p.type_)?;
+                self.relationship(&target, what, value)
+            }
+            _ => Ok(Written::Value(value.clone())),
+        }
+    }
+
+    /// A value of a declared type: a nested instance, a map, or a scalar.
+    fn declared(&mut self, fqn: &str, value: &Value) -> Result<Written> {
+        match (self.manager.get_declaration(fqn)?, value) {
+            (Declaration::Class(_), Value::Object(object)) => self.object(fqn, object),
+            (Declaration::Map(map), Value::Object(object)) => self.map(fqn, map, object),
+            (Declaration::Scalar(ScalarDeclaration::DateTime(_)), _) => {
+                Ok(Written::Value(self.datetime(value)))
+            }
+            _ => Ok(Written::Value(value.clone())),
+        }
+    }
+
+    /// A map, with `$class` first and its entries in their own order. Key and
+    /// value types resolve in the map's namespace.
+    fn map(
+        &mut self,
+        fqn: &str,
+        map: &MapDeclaration,
+        object: &Map<String, Value>,
+    ) -> Result<Written> {
+        let namespace = namespace_of(fqn);
+        let datetime_keys = match (map.key_kind(), map.key_type()) {
+            ("DateTimeMapKeyType", _) => true,
+            ("ObjectMapKeyType", Some(key_type)) => {
+                let key_fqn = self.resolve(namespace, key_type)?;
+                matches!(
+                    self.manager.get_declaration(&key_fqn)?,
+                    Declaration::Scalar(ScalarDeclaration::DateTime(_))
+                )
+            }
+            _ => false,
+        };
+        let value_type = match map.value_type() {
+            Some(value_type) => Some(self.resolve(namespace, value_type)?),
+            None => None,
+        };
+
+        let mut out = Vec::new();
+        if let Some(class) = object.get("$class") {
+            out.push(("$class".to_string(), Written::Value(class.clone())));
+        }
+        for (key, value) in object {
+            if key == "$class" {
+                continue;
+            }
+            let key = if datetime_keys {
+                match self.datetime(&json!(key)) {
+                    Value::String(key) => key,
+                    _ => key.clone(),
+                }
+            } else {
+                key.clone()
+            };
+            let what = format!("value at key {key:?} of {fqn}");
+            let value = match (map.value_kind(), &value_type) {
+                ("DateTimeMapValueType", _) => Written::Value(self.datetime(value)),
+                ("ObjectMapValueType", Some(value_type)) => self.declared(value_type, value)?,
+                ("RelationshipMapValueType", Some(value_type)) => {
+                    self.relationship(value_type, &what, value)?
+                }
+                _ => Written::Value(value.clone()),
+            };
+            out.push((key, value));
+        }
+        Ok(Written::Object(out))
+    }
+
+    /// A relationship, which holds a reference or, where permitted, the
+    /// resource itself.
+    fn relationship(&mut self, target: &str, what: &str, value: &Value) -> Result<Written> {
+        let Value::Object(object) = value else {
+            return Ok(Written::Value(value.clone()));
+        };
+        if self.resources == Resources::Reject {
+            return Err(failed(format!(
+                "The {what} holds a resource rather than a reference to one"
+            )));
+        }
+        let resource = self.object(target, object)?;
+        let reference = self
+            .reference(|key| resource.get(key).and_then(Written::as_str))
+            .ok_or_else(|| {
+                failed(format!(
+                    "The {what} holds a resource without an identifier, which cannot be referenced"
+                ))
+            })?;
+        match self.resources {
+            Resources::Reference => {
+                self.referenced
+                    .push((target.to_string(), resource.into_value()));
+                Ok(Written::Value(json!(reference)))
+            }
+            _ if self.deduplicate && !self.seen.insert(reference.clone()) => {
+                Ok(Written::Value(json!(reference)))
+            }
+            _ => Ok(resource),
+        }
+    }
+
+    /// The reference to a resource, from its `$class` and its identifying
+    /// field or `$identifier`.
+    fn reference<'v>(&self, member: impl Fn(&str) -> Option<&'v str>) -> Option<String> {
+        let fqn = member("$class")?;
+        let chain = self.manager.super_chain(fqn).ok()?;
+        let identifier = match chain
+            .iter()
+            .find_map(|(_, class)| class.identifier_field_name())
+        {
+            Some(field) => member(field),
+            None => member("$identifier"),
+        }?;
+        Some(Relationship::new(fqn, identifier).to_string())
+    }
+
+    /// A `DateTime`, rewritten as the walk requires. A value that does not
+    /// parse is left for validation to report.
+    fn datetime(&self, value: &Value) -> Value {
+        let Some(parsed) = value.as_str().and_then(|text| datetime::parse(text).ok()) else {
+            return value.clone();
+        };
+        match self.dates {
+            Dates::Keep => value.clone(),
+            Dates::Normalize => json!(datetime::format(&parsed)),
+            Dates::Offset(offset) => json!(datetime::format_with_offset(&parsed, offset)),
+        }
+    }
+
+    fn resolve(&self, namespace: &str, type_identifier: &mm::TypeIdentifier) -> Result<Symbol> {
+        resolve(
+            self.manager,
+            namespace,
+            &type_identifier.name,
+            type_identifier.namespace.as_deref(),
+        )
+        .ok_or_else(|| ConcertoError::TypeNotFound {
+            type_name: type_identifier.name.clone(),
+        })
+    }
+}
+
+fn failed(message: String) -> ConcertoError {
+    ConcertoError::ValidationFailed { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::{Serializer, SerializerOptions};
+    use crate::model_manager::ModelManager;
+    use crate::test_fixtures::{optional_property, property, typed};
+
+    /// `org.acme@1.0.0`: a `Person` participant identified by `email`, an
+    /// abstract `Vehicle` asset identified by `vin`, and a `Car` that extends
+    /// it with an address, a registration date and relationships to people.
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.acme@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Address",
+                          "isAbstract": false,
+                          "properties": [property("StringProperty", "city", json!({}))] },
+                        { "$class": "concerto.metamodel@1.0.0.ParticipantDeclaration", "name": "Person",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "email" },
+                          "properties": [
+                            property("StringProperty", "email", json!({})),
+                            optional_property("StringProperty", "name", json!({}))
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Vehicle",
+                          "isAbstract": true,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "vin" },
+                          "properties": [property("StringProperty", "vin", json!({}))] },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Car",
+                          "isAbstract": false,
+                          "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Vehicle" },
+                          "properties": [
+                            property("StringProperty", "model", json!({})),
+                            optional_property("DateTimeProperty", "registered", json!({})),
+                            optional_property("ObjectProperty", "garage", typed("Address")),
+                            optional_property("RelationshipProperty", "owner", typed("Person")),
+                            optional_property("RelationshipProperty", "previousOwners",
+                                     json!({ "isArray": true, "type": typed("Person")["type"] }))
+                          ] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn ada() -> Value {
+        json!({ "$class": "org.acme@1.0.0.Person", "email": "ada@acme.org", "name": "Ada" })
+    }
+
+    fn options(change: impl FnOnce(&mut SerializerOptions)) -> SerializerOptions {
+        let mut options = SerializerOptions::default();
+        change(&mut options);
+        options
+    }
+
+    #[test]
+    fn round_trips_in_declaration_order() {
+        let manager = manager();
+        let serializer = Serializer::new(&manager);
+        let car = serializer
+            .from_json(&json!({
+                "owner": "resource:org.acme@1.0.0.Person#ada@acme.org",
+                "garage": { "city": "Dublin", "$class": "org.acme@1.0.0.Address" },
+                "model": "Zoe",
+                "vin": "ABC123",
+                "$class": "org.acme@1.0.0.Car"
+            }))
+            .unwrap();
+        assert_eq!(car.as_resource().unwrap().identifier(), "ABC123");
+
+        assert_eq!(
+            serializer.to_json_string(&car).unwrap(),
+            concat!(
+                r#"{"$class":"org.acme@1.0.0.Car","$identifier":"ABC123","model":"Zoe","#,
+                r#""garage":{"$class":"org.acme@1.0.0.Address","city":"Dublin"},"#,
+                r#""owner":"resource:org.acme@1.0.0.Person#ada@acme.org","vin":"ABC123"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn strict_mode_rejects_unknown_properties_and_lenient_mode_keeps_them() {
+        let manager = manager();
+        let json = json!({ "$class": "org.acme@1.0.0.Address", "zip": "D02", "city": "Dublin" });
+
+        let strict = Serializer::new(&manager).from_json(&json).unwrap_err();
+        assert!(
+            strict
+                .to_string()
+                .contains("a property named zip, which is not declared")
+        );
+
+        let lenient = Serializer::with_options(&manager, options(|o| o.strict = false));
+        let address = lenient.from_json(&json).unwrap();
+        assert!(address.as_concept().is_some());
+        let written = lenient.to_json_string(&address).unwrap();
+        assert_eq!(
+            written,
+            r#"{"$class":"org.acme@1.0.0.Address","city":"Dublin","zip":"D02"}"#
+        );
+        assert!(Serializer::new(&manager).to_json(&address).is_err());
+    }
+
+    #[test]
+    fn datetimes_are_read_as_utc_and_written_at_an_offset() {
+        let manager = manager();
+        let json = json!({ "$class": "org.acme@1.0.0.Car", "vin": "ABC123", "model": "Zoe",
+                           "registered": "2024-03-01T10:00:00+01:00" });
+        let car = Serializer::new(&manager).from_json(&json).unwrap();
+        assert_eq!(
+            car.get("registered"),
+            Some(&json!("2024-03-01T09:00:00.000Z"))
+        );
+
+        let shifted = Serializer::with_options(&manager, options(|o| o.utc_offset = Some(120)))
+            .to_json(&car)
+            .unwrap();
+        assert_eq!(
+            shifted["registered"],
+            json!("2024-03-01T11:00:00.000+02:00")
+        );
+    }
+
+    #[test]
+    fn resources_for_relationships_follow_the_options() {
+        let manager = manager();
+        let json = json!({ "$class": "org.acme@1.0.0.Car", "vin": "ABC123", "model": "Zoe",
+                           "owner": ada(), "previousOwners": [ada(), ada()] });
+
+        let refused = Serializer::new(&manager).from_json(&json).unwrap_err();
+        assert!(
+            refused
+                .to_string()
+                .contains("holds a resource rather than a reference")
+        );
+
+        let permit = options(|o| o.permit_resources_for_relationships = true);
+        let car = Serializer::with_options(&manager, permit.clone())
+            .from_json(&json)
+            .unwrap();
+        assert!(Serializer::new(&manager).to_json(&car).is_err());
+
+        let kept = Serializer::with_options(&manager, permit.clone())
+            .to_json(&car)
+            .unwrap();
+        assert_eq!(kept["owner"], ada());
+
+        let reference = json!("resource:org.acme@1.0.0.Person#ada@acme.org");
+        let converted = Serializer::with_options(
+            &manager,
+            options(|o| o.convert_resources_to_relationships = true),
+        )
+        .to_json(&car)
+        .unwrap();
+        assert_eq!(converted["owner"], reference);
+        assert_eq!(converted["previousOwners"], json!([reference, reference]));
+
+        let deduplicated = Serializer::with_options(
+            &manager,
+            SerializerOptions {
+                deduplicate_resources: true,
+                ..permit
+            },
+        )
+        .to_json(&car)
+        .unwrap();
+        assert_eq!(deduplicated["owner"], ada());
+        assert_eq!(
+            deduplicated["previousOwners"],
+            json!([reference, reference])
+        );
+    }
+
+    #[test]
+    fn validation_can_be_turned_off() {
+        let manager = manager();
+        let json = json!({ "$class": "org.acme@1.0.0.Car", "vin": "ABC123" });
+        let invalid = Serializer::new(&manager).from_json(&json).unwrap_err();
+        assert!(
+            invalid
+                .to_string()
+                .contains("missing the required field model")
+        );
+
+        let unchecked = Serializer::with_options(&manager, options(|o| o.validate = false));
+        let car = unchecked.from_json(&json).unwrap();
+        assert_eq!(unchecked.to_json(&car).unwrap()["vin"], json!("ABC123"));
+    }
+}
@@ -0,0 +1,1179 @@
+//! Validation of large instance documents as they are read.
+//!
+//! [`ModelManager::validate_instance`] checks an instance that is already
+//! held as a [`Value`]. A [`StreamValidator`] reads a stream of instances,
+//! either as JSON lines or as one JSON array, and checks each instance while
+//! it is parsed. The parse is guided by the declared type of each field:
+//! nested instances, maps and arrays are walked as they are read, and only
+//! their leaves are read whole, so an instance is never held as a tree.
+//!
+//! The fields of an object can only be checked once its class is known; see
+//! [`StreamOptions`] for when that is. Each class is looked at once per
+//! stream, and what is learned about it, such as the type each of its fields
+//! holds, is reused for every instance of it.
+//!
+//! Each record is checked as [`ModelManager::validate_instance`] checks it,
+//! and fails with the same [`ConcertoError::InvalidInstance`], except that
+//! the problem reported is the first one in document order. Failures are
+//! collected per record in a [`StreamReport`], and reading can stop after
+//! [`StreamOptions::max_errors`] of them.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::rc::Rc;
+
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde::Deserialize;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+
+use crate::error::{ConcertoError, Result};
+use crate::instance_validation::{
+    INSTANCE_SYSTEM_PROPERTIES, Site, check_identity, check_map_class, concrete_type, entry_sites,
+    instance_chain, invalid_instance, property_site, undeclared, validate_field, validate_map_key,
+    validate_map_value, validate_property, validate_value,
+};
+use crate::introspect::declaration::{ClassDeclaration, Declaration, MapDeclaration};
+use crate::introspect::property::Property;
+use crate::model_manager::ModelManager;
+use crate::model_util::namespace_of;
+use crate::symbol::Symbol;
+use crate::validation::resolve;
+
+/// The options of a [`StreamValidator`].
+///
+/// An instance is only read field by field once its class is known. That is
+/// from the start when it is declared as a type that only one concrete class
+/// can be: a record, if [`record_type`](Self::record_type) is such a type, or
+/// a nested instance whose property is. Otherwise the class is known once
+/// `$class` is read, and each field that comes before it is held whole until
+/// then. A stream is read in the least memory when every instance has its
+/// `$class` first, as the [`Serializer`](crate::Serializer) writes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// Stop reading once this many records have failed. Unbounded by
+    /// default.
+    pub max_errors: Option<usize>,
+    /// Validate each record as this type, as
+    /// [`ModelManager::validate_instance_as`] does: its `$class` may name any
+    /// concrete subtype, and without one it is taken to be this type. By
+    /// default each record names its type in `$class`.
+    pub record_type: Option<String>,
+}
+
+/// A record that did not validate.
+#[derive(Debug)]
+pub struct RecordFailure {
+    /// The position of the record in the stream, counting from zero. Blank
+    /// lines between JSON lines are not records.
+    pub record: usize,
+    /// The line the record is on, counting from 1, for JSON lines.
+    pub line: Option<usize>,
+    /// Why it failed.
+    pub error: ConcertoError,
+}
+
+/// The outcome of validating a stream.
+#[derive(Debug, Default)]
+pub struct StreamReport {
+    /// The number of records read, valid or not.
+    pub records: usize,
+    /// The records that failed, in stream order.
+    pub failures: Vec<RecordFailure>,
+    /// Whether reading stopped early, because
+    /// [`max_errors`](StreamOptions::max_errors) records had failed or
+    /// because the rest of the stream could not be parsed. Any records after
+    /// that point were not read.
+    pub stopped: bool,
+}
+
+impl StreamReport {
+    /// `true` if the whole stream was read and every record is valid.
+    pub fn is_valid(&self) -> bool {
+        !self.stopped && self.failures.is_empty()
+    }
+
+    /// Counts a record, and says whether reading should stop.
+    fn add(&mut self, result: Result<()>, line: Option<usize>, options: &StreamOptions) -> bool {
+        if let Err(error) = result {
+            self.failures.push(RecordFailure {
+                record: self.records,
+                line,
+                error,
+            });
+        }
+        self.records += 1;
+        self.stopped = options
+            .max_errors
+            .is_some_and(|max| self.failures.len() >= max);
+        self.stopped
+    }
+}
+
+/// Validates streams of instances of the types in a [`ModelManager`].
+#[derive(Debug, Clone)]
+pub struct StreamValidator<'m> {
+    manager: &'m ModelManager,
+    options: StreamOptions,
+}
+
+impl<'m> StreamValidator<'m> {
+    /// A validator with the default options.
+    pub fn new(manager: &'m ModelManager) -> Self {
+        Self::with_options(manager, StreamOptions::default())
+    }
+
+    /// A validator with the given options.
+    pub fn with_options(manager: &'m ModelManager, options: StreamOptions) -> Self {
+        Self { manager, options }
+    }
+
+    /// The options this validator uses.
+    pub fn options(&self) -> &StreamOptions {
+        &self.options
+    }
+
+    /// Validates JSON lines: one instance per line. A line that is not JSON
+    /// fails as a record, and reading carries on with the next line. Fails
+    /// only if the reader does.
+    pub fn validate_json_lines<R: BufRead>(&self, reader: R) -> io::Result<StreamReport> {
+        let cx = Context::new(self);
+        let mut report = StreamReport::default();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut deserializer = serde_json::Deserializer::from_str(&line);
+            let parsed = deserializer
+                .deserialize_any(Shape(Record { cx: &cx }))
+                .and_then(|()| deserializer.end());
+            let checked = cx.take();
+            let result = parsed.map_or_else(|error| Err(not_json(&error)), |()| checked);
+            if report.add(result, Some(index + 1), &self.options) {
+                break;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Validates a JSON array of instances. JSON that cannot be parsed fails
+    /// as a record and stops the read, since nothing after it can be found.
+    /// Fails only if the reader does.
+    ///
+    /// The reader is read a byte at a time, so a file is best wrapped in a
+    /// [`BufReader`](std::io::BufReader).
+    pub fn validate_json_array<R: Read>(&self, reader: R) -> io::Result<StreamReport> {
+        let cx = Context::new(self);
+        let mut report = StreamReport::default();
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        let result = Records {
+            cx: &cx,
+            options: &self.options,
+            report: &mut report,
+        }
+        .deserialize(&mut deserializer)
+        .and_then(|()| deserializer.end());
+        match result {
+            // Stopping part way through leaves the rest of the array unread,
+            // which the parser reports.
+            Err(_) if report.stopped => {}
+            Err(error) if error.is_io() => return Err(error.into()),
+            Err(error) => {
+                report.failures.push(RecordFailure {
+                    record: report.records,
+                    line: None,
+                    error: not_json(&error),
+                });
+                report.stopped = true;
+            }
+            Ok(()) => {}
+        }
+        Ok(report)
+    }
+}
+
+/// The error for a record that is not JSON.
+fn not_json(error: &serde_json::Error) -> ConcertoError {
+    invalid_instance(
+        "",
+        None,
+        None,
+        format!("The record is not valid JSON: {error}"),
+    )
+}
+
+/// The elements of a JSON array, each validated as a record.
+struct Records<'a, 'm> {
+    cx: &'a Context<'m>,
+    options: &'a StreamOptions,
+    report: &'a mut StreamReport,
+}
+
+impl<'de> DeserializeSeed<'de> for Records<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Parsed<D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Records<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON array of instances")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Parsed<A::Error> {
+        while seq.next_element_seed(RecordSeed { cx: self.cx })?.is_some() {
+            if self.report.add(self.cx.take(), None, self.options) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+type Parsed<E> = std::result::Result<(), E>;
+
+/// The state of a stream: the model, what has been worked out about its
+/// types, and the first problem found in the record being read. Once a record
+/// has a problem, the rest of it is skipped over.
+struct Context<'m> {
+    manager: &'m ModelManager,
+    record_type: Option<String>,
+    /// The classes instances have been read as, by fully-qualified name.
+    classes: RefCell<HashMap<String, Rc<ClassPlan<'m>>>>,
+    /// What the values of each map hold, by the map's fully-qualified name.
+    maps: RefCell<HashMap<String, Resolved<'m>>>,
+    /// The one concrete class that each declared type can be, if there is
+    /// just one.
+    candidates: RefCell<HashMap<String, Option<Symbol>>>,
+    error: RefCell<Option<ConcertoError>>,
+}
+
+impl<'m> Context<'m> {
+    fn new(validator: &StreamValidator<'m>) -> Self {
+        Self {
+            manager: validator.manager,
+            record_type: validator.options.record_type.clone(),
+            classes: RefCell::default(),
+            maps: RefCell::default(),
+            candidates: RefCell::default(),
+            error: RefCell::default(),
+        }
+    }
+
+    fn failed(&self) -> bool {
+        self.error.borrow().is_some()
+    }
+
+    fn check(&self, result: Result<()>) {
+        if let Err(error) = result {
+            self.error.borrow_mut().get_or_insert(error);
+        }
+    }
+
+    /// The outcome of the record just read, clearing the way for the next.
+    fn take(&self) -> Result<()> {
+        self.error.borrow_mut().take().map_or(Ok(()), Err)
+    }
+
+    /// What is known about the class `fqn`, for an instance of it at
+    /// `pointer`. Fails unless `fqn` is a concrete class.
+    fn class_plan(&self, fqn: &str, pointer: &str) -> Result<Rc<ClassPlan<'m>>> {
+        if let Some(plan) = self.classes.borrow().get(fqn) {
+            return Ok(Rc::clone(plan));
+        }
+        let chain = instance_chain(self.manager, fqn, pointer)?;
+        let mut fields = HashMap::new();
+        for (owner, class) in &chain {
+            let class: &'m ClassDeclaration = class;
+            for property in class.own_properties() {
+                fields.entry(property.name()).or_insert_with(|| FieldPlan {
+                    owner: owner.clone(),
+                    property,
+                    target: match property {
+                        Property::Object(object) => {
+                            self.resolve_target(namespace_of(owner), &object.type_)
+                        }
+                        _ => Ok(Target::Leaf),
+                    },
+                });
+            }
+        }
+        let plan = Rc::new(ClassPlan {
+            fqn: Symbol::intern(fqn),
+            identifying_field: chain
+                .iter()
+                .find_map(|(_, class)| class.identifier_field_name()),
+            chain,
+            fields,
+        });
+        self.classes
+            .borrow_mut()
+            .insert(fqn.to_string(), Rc::clone(&plan));
+        Ok(plan)
+    }
+
+    /// What the values of the map `fqn` hold.
+    fn map_target(&self, fqn: &str, map: &'m MapDeclaration) -> Resolved<'m> {
+        if let Some(target) = self.maps.borrow().get(fqn) {
+            return target.clone();
+        }
+        let target = match (map.value_kind(), map.value_type()) {
+            ("ObjectMapValueType", Some(value_type)) => {
+                self.resolve_target(namespace_of(fqn), value_type)
+            }
+            _ => Ok(Target::Leaf),
+        };
+        self.maps
+            .borrow_mut()
+            .insert(fqn.to_string(), target.clone());
+        target
+    }
+
+    /// What a value of the type named by `type_identifier`, written in
+    /// `namespace`, holds.
+    fn resolve_target(
+        &self,
+        namespace: &str,
+        type_identifier: &mm::TypeIdentifier,
+    ) -> Resolved<'m> {
+        let fqn = resolve(
+            self.manager,
+            namespace,
+            &type_identifier.name,
+            type_identifier.namespace.as_deref(),
+        )
+        .ok_or_else(|| type_identifier.name.clone())?;
+        Ok(match self.manager.get_declaration(&fqn) {
+            Ok(Declaration::Class(_)) => Target::Class(fqn),
+            Ok(Declaration::Map(map)) => Target::Map(fqn, map),
+            Ok(_) => Target::Leaf,
+            Err(_) => return Err(fqn.to_string()),
+        })
+    }
+
+    /// The concrete class an instance declared as `declared` has to be, if
+    /// there is only one.
+    fn single_candidate(&self, declared: &str) -> Option<Symbol> {
+        if let Some(candidate) = self.candidates.borrow().get(declared) {
+            return candidate.clone();
+        }
+        let mut candidates = Vec::new();
+        for model_file in self.manager.model_files() {
+            for declaration in model_file.declarations() {
+                let Some(class) = declaration.as_class() else {
+                    continue;
+                };
+                let fqn = format!("{}.{}", model_file.namespace(), class.name());
+                if !class.is_abstract()
+                    && matches!(self.manager.is_assignable_to(&fqn, declared), Ok(true))
+                {
+                    candidates.push(fqn);
+                }
+            }
+        }
+        let single = match candidates.as_slice() {
+            [only] => Some(Symbol::intern(only)),
+            _ => None,
+        };
+        self.candidates
+            .borrow_mut()
+            .insert(declared.to_string(), single.clone());
+        single
+    }
+}
+
+/// A concrete class, as a stream reads instances of it.
+struct ClassPlan<'m> {
+    fqn: Symbol,
+    chain: Vec<(Symbol, &'m ClassDeclaration)>,
+    identifying_field: Option<&'m str>,
+    /// Each property along the chain, by name.
+    fields: HashMap<&'m str, FieldPlan<'m>>,
+}
+
+/// A property of a [`ClassPlan`].
+struct FieldPlan<'m> {
+    /// The class along the chain that declares the property.
+    owner: Symbol,
+    property: &'m Property,
+    /// What one value of the property holds; for an array, each element.
+    target: Resolved<'m>,
+}
+
+/// How a value is checked, depending on its JSON shape. Objects and arrays
+/// are walked as they are read; a value of any other shape is read whole.
+trait Expectation<'de>: Sized {
+    fn object<A: MapAccess<'de>>(self, map: A) -> Parsed<A::Error> {
+        let value = Value::deserialize(MapAccessDeserializer::new(map))?;
+        self.value(value);
+        Ok(())
+    }
+
+    fn array<A: SeqAccess<'de>>(self, seq: A) -> Parsed<A::Error> {
+        let value = Value::deserialize(SeqAccessDeserializer::new(seq))?;
+        self.value(value);
+        Ok(())
+    }
+
+    fn value(self, value: Value);
+}
+
+/// A visitor that hands each shape of value to an [`Expectation`].
+struct Shape<E>(E);
+
+impl<'de, E: Expectation<'de>> Visitor<'de> for Shape<E> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<Er: de::Error>(self, value: bool) -> Parsed<Er> {
+        self.0.value(Value::Bool(value));
+        Ok(())
+    }
+
+    fn visit_i64<Er: de::Error>(self, value: i64) -> Parsed<Er> {
+        self.0.value(Value::from(value));
+        Ok(())
+    }
+
+    fn visit_u64<Er: de::Error>(self, value: u64) -> Parsed<Er> {
+        self.0.value(Value::from(value));
+        Ok(())
+    }
+
+    fn visit_f64<Er: de::Error>(self, value: f64) -> Parsed<Er> {
+        self.0.value(Value::from(value));
+        Ok(())
+    }
+
+    fn visit_str<Er: de::Error>(self, value: &str) -> Parsed<Er> {
+        self.0.value(Value::String(value.to_string()));
+        Ok(())
+    }
+
+    fn visit_string<Er: de::Error>(self, value: String) -> Parsed<Er> {
+        self.0.value(Value::String(value));
+        Ok(())
+    }
+
+    fn visit_unit<Er: de::Error>(self) -> Parsed<Er> {
+        self.0.value(Value::Null);
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Parsed<A::Error> {
+        self.0.array(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Parsed<A::Error> {
+        self.0.object(map)
+    }
+}
+
+/// A whole record, which must be an instance.
+struct RecordSeed<'a, 'm> {
+    cx: &'a Context<'m>,
+}
+
+impl<'de> DeserializeSeed<'de> for RecordSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Parsed<D::Error> {
+        deserializer.deserialize_any(Shape(Record { cx: self.cx }))
+    }
+}
+
+struct Record<'a, 'm> {
+    cx: &'a Context<'m>,
+}
+
+impl<'de> Expectation<'de> for Record<'_, '_> {
+    fn object<A: MapAccess<'de>>(self, map: A) -> Parsed<A::Error> {
+        let declared = match &self.cx.record_type {
+            Some(record_type) => Declared::Record(record_type),
+            None => Declared::Nothing,
+        };
+        instance(self.cx, map, declared, "")
+    }
+
+    fn array<A: SeqAccess<'de>>(self, mut seq: A) -> Parsed<A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        let record_type = self.cx.record_type.as_deref();
+        let message = match record_type {
+            Some(record_type) => {
+                format!("An instance of {record_type} must be an object, not an array")
+            }
+            None => "An instance must be an object, not an array".into(),
+        };
+        self.cx
+            .check(Err(invalid_instance("", record_type, None, message)));
+        Ok(())
+    }
+
+    fn value(self, value: Value) {
+        let manager = self.cx.manager;
+        self.cx.check(match &self.cx.record_type {
+            Some(record_type) => manager.validate_instance_as(record_type, &value),
+            None => manager.validate_instance(&value),
+        });
+    }
+}
+
+/// A value at a site: a field, one element of an array field, or a value in
+/// a map.
+struct Slot<'a, 'm> {
+    cx: &'a Context<'m>,
+    site: Site<'a>,
+    kind: SlotKind<'a, 'm>,
+}
+
+#[derive(Clone, Copy)]
+enum SlotKind<'a, 'm> {
+    Field(&'a FieldPlan<'m>),
+    Element(&'a FieldPlan<'m>),
+    MapValue(&'m MapDeclaration, &'a Resolved<'m>),
+}
+
+/// What a value holds, as far as walking it goes.
+#[derive(Clone)]
+enum Target<'m> {
+    /// A value that is read whole: a primitive, an enum, a scalar or a
+    /// relationship.
+    Leaf,
+    /// The elements of an array field.
+    Array,
+    /// An instance of a class, or of one of its subtypes.
+    Class(Symbol),
+    /// A map instance.
+    Map(Symbol, &'m MapDeclaration),
+}
+
+/// A [`Target`] worked out from a type name, or the name of the type that
+/// could not be found.
+type Resolved<'m> = std::result::Result<Target<'m>, String>;
+
+impl<'m> Slot<'_, 'm> {
+    fn target(&self) -> Result<Target<'m>> {
+        let resolved = match self.kind {
+            SlotKind::Field(field) if field.property.is_array() => return Ok(Target::Array),
+            SlotKind::Field(field) | SlotKind::Element(field) => &field.target,
+            SlotKind::MapValue(_, resolved) => resolved,
+        };
+        resolved.clone().map_err(|type_name| {
+            self.site.invalid(format_args!(
+                "is declared as {type_name}, which is not a declared type"
+            ))
+        })
+    }
+
+    /// Checks a value read whole.
+    fn check(&self, value: &Value) {
+        let manager = self.cx.manager;
+        self.cx.check(match self.kind {
+            SlotKind::Field(field) => {
+                validate_field(manager, &self.site, field.property, Some(value))
+            }
+            SlotKind::Element(field) => validate_value(manager, &self.site, field.property, value),
+            SlotKind::MapValue(map, _) => validate_map_value(manager, &self.site, map, value),
+        });
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Slot<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Parsed<D::Error> {
+        if self.cx.failed() {
+            IgnoredAny::deserialize(deserializer)?;
+            return Ok(());
+        }
+        match self.target() {
+            Err(error) => {
+                self.cx.check(Err(error));
+                IgnoredAny::deserialize(deserializer)?;
+                Ok(())
+            }
+            Ok(Target::Leaf) => {
+                let value = Value::deserialize(deserializer)?;
+                self.check(&value);
+                Ok(())
+            }
+            Ok(target) => deserializer.deserialize_any(Shape(Walk { slot: self, target })),
+        }
+    }
+}
+
+/// A slot whose value is walked as it is read.
+struct Walk<'a, 'm> {
+    slot: Slot<'a, 'm>,
+    target: Target<'m>,
+}
+
+impl<'de> Expectation<'de> for Walk<'_, '_> {
+    fn object<A: MapAccess<'de>>(self, map: A) -> Parsed<A::Error> {
+        let Slot { cx, site, .. } = &self.slot;
+        match &self.target {
+            Target::Class(fqn) => instance(cx, map, Declared::Site(fqn, site), &site.pointer),
+            Target::Map(fqn, declaration) => map_instance(cx, map, site, fqn, declaration),
+            _ => {
+                let value = Value::deserialize(MapAccessDeserializer::new(map))?;
+                self.slot.check(&value);
+                Ok(())
+            }
+        }
+    }
+
+    fn array<A: SeqAccess<'de>>(self, mut seq: A) -> Parsed<A::Error> {
+        let (Target::Array, SlotKind::Field(field)) = (&self.target, self.slot.kind) else {
+            let value = Value::deserialize(SeqAccessDeserializer::new(seq))?;
+            self.slot.check(&value);
+            return Ok(());
+        };
+        for index in 0.. {
+            let element = Slot {
+                cx: self.slot.cx,
+                site: self.slot.site.element(index),
+                kind: SlotKind::Element(field),
+            };
+            if seq.next_element_seed(element)?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn value(self, value: Value) {
+        self.slot.check(&value);
+    }
+}
+
+/// What an instance is declared to be, which decides how its class is found.
+#[derive(Clone, Copy)]
+enum Declared<'a> {
+    /// Nothing: a record, which names its type in `$class`.
+    Nothing,
+    /// A record read as a type, as [`ModelManager::validate_instance_as`]
+    /// reads one.
+    Record(&'a str),
+    /// A nested instance, declared as a type at a site.
+    Site(&'a str, &'a Site<'a>),
+}
+
+impl Declared<'_> {
+    fn type_name(&self) -> Option<&str> {
+        match *self {
+            Declared::Nothing => None,
+            Declared::Record(declared) | Declared::Site(declared, _) => Some(declared),
+        }
+    }
+
+    /// The class an instance is, given its `$class`, if it has one.
+    fn class(&self, manager: &ModelManager, class: Option<&Value>) -> Result<String> {
+        let object = class
+            .map(|class| Map::from_iter([("$class".to_string(), class.clone())]))
+            .unwrap_or_default();
+        match *self {
+            Declared::Nothing => class
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    invalid_instance(
+                        "/$class",
+                        None,
+                        None,
+                        "The instance does not name its type in $class".into(),
+                    )
+                }),
+            Declared::Record(declared) => concrete_type(manager, declared, &object, |problem| {
+                invalid_instance(
+                    "",
+                    Some(declared),
+                    class.and_then(Value::as_str),
+                    format!("The instance {problem}"),
+                )
+            }),
+            Declared::Site(declared, site) => {
+                concrete_type(manager, declared, &object, |problem| site.invalid(problem))
+            }
+        }
+    }
+}
+
+/// Walks an instance of a class at `pointer`.
+///
+/// When there is only one concrete class the instance can be, its fields are
+/// checked as they are read and its `$class` only has to agree. Otherwise
+/// the fields that come before `$class` are held until it is read.
+fn instance<'de, A: MapAccess<'de>>(
+    cx: &Context<'_>,
+    mut map: A,
+    declared: Declared<'_>,
+    pointer: &str,
+) -> Parsed<A::Error> {
+    let mut state = declared
+        .type_name()
+        .and_then(|declared| cx.single_candidate(declared))
+        .and_then(|fqn| InstanceState::start(cx, &fqn, pointer));
+    let mut pending = Vec::new();
+    let mut named = false;
+    while let Some(key) = map.next_key::<String>()? {
+        if cx.failed() {
+            map.next_value::<IgnoredAny>()?;
+            continue;
+        }
+        match &mut state {
+            None if key == "$class" => {
+                let class = map.next_value::<Value>()?;
+                named = true;
+                state = InstanceState::start_as(cx, declared, Some(&class), pointer);
+                if let Some(state) = &mut state {
+                    state.replay(cx, &mut pending);
+                }
+            }
+            Some(state) if key == "$class" && !named => {
+                let class = map.next_value::<Value>()?;
+                named = true;
+                cx.check(state.agrees(cx, declared, Some(&class)));
+            }
+            Some(state) => state.field(cx, &mut map, key)?,
+            None => pending.push((key, map.next_value::<Value>()?)),
+        }
+    }
+    if !named && !cx.failed() {
+        match &mut state {
+            Some(state) => cx.check(state.agrees(cx, declared, None)),
+            None => {
+                state = InstanceState::start_as(cx, declared, None, pointer);
+                if let Some(state) = &mut state {
+                    state.replay(cx, &mut pending);
+                }
+            }
+        }
+    }
+    if let Some(state) = state
+        && !cx.failed()
+    {
+        state.finish(cx);
+    }
+    Ok(())
+}
+
+/// An instance being walked, once its class is known.
+struct InstanceState<'m> {
+    plan: Rc<ClassPlan<'m>>,
+    pointer: String,
+    /// The declared fields read so far.
+    seen: HashSet<&'m str>,
+    /// The values that identity is checked against: the identifying field
+    /// and the system properties.
+    identity: Map<String, Value>,
+}
+
+impl<'m> InstanceState<'m> {
+    /// Starts walking an instance of the class `fqn`.
+    fn start(cx: &Context<'m>, fqn: &str, pointer: &str) -> Option<Self> {
+        match cx.class_plan(fqn, pointer) {
+            Ok(plan) => Some(Self {
+                plan,
+                pointer: pointer.to_string(),
+                seen: HashSet::new(),
+                identity: Map::new(),
+            }),
+            Err(error) => {
+                cx.check(Err(error));
+                None
+            }
+        }
+    }
+
+    /// Works out the class of the instance from its `$class`, if it has one,
+    /// and starts walking it.
+    fn start_as(
+        cx: &Context<'m>,
+        declared: Declared<'_>,
+        class: Option<&Value>,
+        pointer: &str,
+    ) -> Option<Self> {
+        match declared.class(cx.manager, class) {
+            Ok(fqn) => Self::start(cx, &fqn, pointer),
+            Err(error) => {
+                cx.check(Err(error));
+                None
+            }
+        }
+    }
+
+    /// Checks that the instance's `$class`, or the lack of one, names the
+    /// class it is being walked as.
+    fn agrees(
+        &self,
+        cx: &Context<'_>,
+        declared: Declared<'_>,
+        class: Option<&Value>,
+    ) -> Result<()> {
+        let fqn = declared.class(cx.manager, class)?;
+        if fqn != *self.plan.fqn {
+            // The only other class it can name is an abstract one.
+            instance_chain(cx.manager, &fqn, &self.pointer)?;
+        }
+        Ok(())
+    }
+
+    /// Reads and checks the value of the field `key`.
+    fn field<'de, A: MapAccess<'de>>(
+        &mut self,
+        cx: &Context<'m>,
+        map: &mut A,
+        key: String,
+    ) -> Parsed<A::Error> {
+        let plan = Rc::clone(&self.plan);
+        if INSTANCE_SYSTEM_PROPERTIES.contains(&key.as_str())
+            || plan.identifying_field == Some(key.as_str())
+        {
+            let value = map.next_value::<Value>()?;
+            self.held(cx, key, value);
+            return Ok(());
+        }
+        let Some(field) = plan.fields.get(key.as_str()) else {
+            cx.check(Err(undeclared(&plan.fqn, &self.pointer, &key)));
+            map.next_value::<IgnoredAny>()?;
+            return Ok(());
+        };
+        self.seen.insert(field.property.name());
+        let site = property_site(
+            cx.manager,
+            &plan.fqn,
+            &self.pointer,
+            &field.owner,
+            field.property,
+        );
+        map.next_value_seed(Slot {
+            cx,
+            site,
+            kind: SlotKind::Field(field),
+        })
+    }
+
+    /// Checks the fields that came before `$class`.
+    fn replay(&mut self, cx: &Context<'_>, pending: &mut Vec<(String, Value)>) {
+        for (key, value) in pending.drain(..) {
+            self.held(cx, key, value);
+        }
+    }
+
+    /// Checks the value of the field `key`, already read whole.
+    fn held(&mut self, cx: &Context<'_>, key: String, value: Value) {
+        if INSTANCE_SYSTEM_PROPERTIES.contains(&key.as_str()) {
+            self.identity.insert(key, value);
+            return;
+        }
+        let plan = Rc::clone(&self.plan);
+        match plan.fields.get(key.as_str()) {
+            None => cx.check(Err(undeclared(&plan.fqn, &self.pointer, &key))),
+            Some(field) => {
+                self.seen.insert(field.property.name());
+                cx.check(validate_property(
+                    cx.manager,
+                    &plan.fqn,
+                    &self.pointer,
+                    &field.owner,
+                    field.property,
+                    Some(&value),
+                ));
+            }
+        }
+        if plan.identifying_field == Some(key.as_str()) {
+            self.identity.insert(key, value);
+        }
+    }
+
+    /// Checks for required fields that never appeared, then identity.
+    fn finish(self, cx: &Context<'_>) {
+        let plan = &self.plan;
+        for (owner, class) in &plan.chain {
+            for property in class.own_properties() {
+                if !self.seen.contains(property.name()) {
+                    cx.check(validate_property(
+                        cx.manager,
+                        &plan.fqn,
+                        &self.pointer,
+                        owner,
+                        property,
+                        None,
+                    ));
+                }
+            }
+        }
+        cx.check(check_identity(
+            &plan.fqn,
+            &plan.chain,
+            &self.identity,
+            &self.pointer,
+        ));
+    }
+}
+
+/// Walks an instance of the map `fqn`.
+fn map_instance<'de, 'm, A: MapAccess<'de>>(
+    cx: &Context<'m>,
+    mut map: A,
+    site: &Site<'_>,
+    fqn: &str,
+    declaration: &'m MapDeclaration,
+) -> Parsed<A::Error> {
+    let values = cx.map_target(fqn, declaration);
+    let mut named = false;
+    while let Some(key) = map.next_key::<String>()? {
+        if cx.failed() {
+            map.next_value::<IgnoredAny>()?;
+            continue;
+        }
+        if key == "$class" {
+            let class = map.next_value::<Value>()?;
+            cx.check(check_map_class(site, fqn, Some(&class)));
+            named = true;
+            continue;
+        }
+        let (key_site, value_site) = entry_sites(cx.manager, site, fqn, declaration, &key);
+        cx.check(validate_map_key(cx.manager, &key_site, declaration, &key));
+        map.next_value_seed(Slot {
+            cx,
+            site: value_site,
+            kind: SlotKind::MapValue(declaration, &values),
+        })?;
+    }
+    if !named {
+        cx.check(check_map_class(site, fqn, None));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::{StreamOptions, StreamValidator};
+    use crate::model_manager::ModelManager;
+    use crate::test_fixtures::{optional_property, property, typed};
+
+    /// `org.acme@1.0.0`: an `Order` asset identified by `id`, holding an
+    /// array of `Line` concepts and a `Stock` map from SKUs to counts.
+    fn manager() -> ModelManager {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.acme@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Line",
+                          "isAbstract": false,
+                          "properties": [
+                            property("StringProperty", "sku", json!({
+                                "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator",
+                                               "pattern": "^[A-Z]{3}$", "flags": "" } })),
+                            property("IntegerProperty", "quantity", json!({
+                                "validator": { "$class": "concerto.metamodel@1.0.0.IntegerDomainValidator",
+                                               "lower": 1 } }))
+                          ] },
+                        { "$class": "concerto.metamodel@1.0.0.MapDeclaration", "name": "Stock",
+                          "key": { "$class": "concerto.metamodel@1.0.0.StringMapKeyType" },
+                          "value": { "$class": "concerto.metamodel@1.0.0.IntegerMapValueType" } },
+                        { "$class": "concerto.metamodel@1.0.0.AssetDeclaration", "name": "Order",
+                          "isAbstract": false,
+                          "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "id" },
+                          "properties": [
+                            property("StringProperty", "id", json!({})),
+                            optional_property("DateTimeProperty", "placed", json!({})),
+                            property("ObjectProperty", "lines",
+                                     json!({ "isArray": true, "type": typed("Line")["type"] })),
+                            optional_property("ObjectProperty", "stock", typed("Stock"))
+                          ] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        manager
+    }
+
+    fn order(lines: Value) -> Value {
+        json!({ "$class": "org.acme@1.0.0.Order", "id": "o-1", "lines": lines })
+    }
+
+    fn line(sku: &str, quantity: i64) -> Value {
+        json!({ "$class": "org.acme@1.0.0.Line", "sku": sku, "quantity": quantity })
+    }
+
+    #[test]
+    fn json_lines_are_reported_per_record() {
+        let manager = manager();
+        let text = [
+            order(json!([line("ABC", 1)])).to_string(),
+            String::new(),
+            order(json!([line("ABC", 1), line("ABC", 0)])).to_string(),
+            "{ not json".to_string(),
+            json!({ "$class": "org.acme@1.0.0.Line", "sku": "ABC", "quantity": 2 }).to_string(),
+        ]
+        .join("\n");
+        let report = StreamValidator::new(&manager)
+            .validate_json_lines(text.as_bytes())
+            .unwrap();
+
+        assert_eq!(report.records, 4);
+        assert!(!report.stopped && !report.is_valid());
+        let failed: Vec<_> = report
+            .failures
+            .iter()
+            .map(|failure| (failure.record, failure.error.to_string()))
+            .collect();
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].0, 1);
+        assert!(
+            failed[0]
+                .1
+                .starts_with("invalid instance at /lines/1/quantity:")
+        );
+        assert_eq!(failed[1].0, 2);
+        assert!(failed[1].1.contains("not valid JSON"));
+        let lines: Vec<_> = report.failures.iter().map(|failure| failure.line).collect();
+        assert_eq!(lines, [Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn errors_match_validating_a_value() {
+        let manager = manager();
+        let invalid = [
+            json!({ "id": "o-1" }),
+            json!("an order"),
+            order(json!({})),
+            order(json!([line("abc", 1)])),
+            order(json!([{ "$class": "org.acme@1.0.0.Order", "sku": "ABC", "quantity": 1 }])),
+            order(json!([{ "sku": "ABC" }])),
+            order(json!([{ "sku": "ABC", "quantity": 0, "$class": "org.acme@1.0.0.Line" }])),
+            order(json!([{ "sku": "ABC", "quantity": 1, "$class": "org.acme@1.0.0.Order" }])),
+            json!({ "lines": [], "id": "", "$class": "org.acme@1.0.0.Order" }),
+            json!({ "id": "o-1", "$class": "org.acme@1.0.0.Ghost" }),
+            order(json!([{ "sku": "ABC", "quantity": 1, "$class": "org.acme@1.0.0.Ghost" }])),
+            json!({ "$class": "org.acme@1.0.0.Order", "id": "o-1", "lines": [], "placed": "soon" }),
+            json!({ "$class": "org.acme@1.0.0.Order", "id": "o-1", "lines": [], "note": "" }),
+            json!({ "$class": "org.acme@1.0.0.Order", "id": "o-1", "$identifier": "o-2", "lines": [] }),
+            json!({ "$class": "org.acme@1.0.0.Order", "id": "o-1", "lines": [],
+                    "stock": { "$class": "org.acme@1.0.0.Stock", "ABC": "many" } }),
+            json!({ "$class": "org.acme@1.0.0.Order", "id": "o-1", "lines": [],
+                    "stock": { "ABC": 1 } }),
+        ];
+        let validator = StreamValidator::new(&manager);
+        for instance in invalid {
+            let expected = manager
+                .validate_instance(&instance)
+                .unwrap_err()
+                .to_string();
+            let report = validator
+                .validate_json_lines(instance.to_string().as_bytes())
+                .unwrap();
+            assert_eq!(report.failures.len(), 1, "{instance}");
+            assert_eq!(report.failures[0].error.to_string(), expected);
+        }
+
+        let valid = json!({ "lines": [line("ABC", 3)], "id": "o-1", "$class": "org.acme@1.0.0.Order",
+                            "stock": { "$class": "org.acme@1.0.0.Stock", "ABC": 4 } });
+        assert!(manager.validate_instance(&valid).is_ok());
+        let report = validator
+            .validate_json_lines(valid.to_string().as_bytes())
+            .unwrap();
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn a_json_array_stops_after_max_errors() {
+        let manager = manager();
+        let array = json!([
+            order(json!([])),
+            order(json!([line("AB", 1)])),
+            order(json!([])),
+            order(json!([line("ABC", -1)])),
+            order(json!([line("ABC", -1)]))
+        ])
+        .to_string();
+
+        let all = StreamValidator::new(&manager)
+            .validate_json_array(array.as_bytes())
+            .unwrap();
+        assert_eq!(all.records, 5);
+        let records: Vec<_> = all.failures.iter().map(|failure| failure.record).collect();
+        assert_eq!(records, [1, 3, 4]);
+        assert!(!all.stopped);
+
+        let bounded = StreamValidator::with_options(
+            &manager,
+            StreamOptions {
+                max_errors: Some(2),
+                ..StreamOptions::default()
+            },
+        )
+        .validate_json_array(array.as_bytes())
+        .unwrap();
+        assert_eq!(bounded.records, 4);
+        assert_eq!(bounded.failures.len(), 2);
+        assert!(bounded.stopped);
+    }
+
+    #[test]
+    fn a_broken_json_array_stops_where_it_breaks() {
+        let manager = manager();
+        let text = format!("[{}, {{ \"$class\": ", order(json!([])));
+        let report = StreamValidator::new(&manager)
+            .validate_json_array(text.as_bytes())
+            .unwrap();
+        assert_eq!(report.records, 1);
+        assert_eq!(report.failures[0].record, 1);
+        assert!(report.stopped);
+    }
+
+    #[test]
+    fn records_can_be_read_as_a_declared_type() {
+        let manager = manager();
+        let options = StreamOptions {
+            record_type: Some("org.acme@1.0.0.Order".into()),
+            ..StreamOptions::default()
+        };
+        let validator = StreamValidator::with_options(&manager, options);
+        let records = [
+            json!({ "id": "o-1", "lines": [line("ABC", 1)] }),
+            json!({ "id": "o-1", "lines": [line("ABC", 0)] }),
+            json!({ "id": "o-1", "lines": [], "$class": "org.acme@1.0.0.Line" }),
+            json!({ "lines": [], "$class": "org.acme@1.0.0.Order" }),
+            json!([]),
+        ];
+        let text: Vec<String> = records.iter().map(Value::to_string).collect();
+        let report = validator
+            .validate_json_lines(text.join("\n").as_bytes())
+            .unwrap();
+
+        assert_eq!(report.records, 5);
+        let failed: Vec<_> = report
+            .failures
+            .iter()
+            .map(|failure| failure.record)
+            .collect();
+        assert_eq!(failed, [1, 2, 3, 4]);
+        for failure in &report.failures[..3] {
+            let expected = manager
+                .validate_instance_as("org.acme@1.0.0.Order", &records[failure.record])
+                .unwrap_err()
+                .to_string();
+            assert_eq!(failure.error.to_string(), expected);
+        }
+    }
+}
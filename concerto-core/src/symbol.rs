@@ -0,0 +1,181 @@
+//! Interned names.
+//!
+//! A loaded set of models mentions the same few names over and over: every
+//! property typed `Address` names it, every subtype names its super type, and
+//! each lookup of a class's lineage hands the names along it back out. A
+//! [`Symbol`] is one such name, interned: every symbol for the same text
+//! shares one allocation, so cloning a symbol is a reference count, and two
+//! symbols are compared by pointer rather than by their text.
+//!
+//! The interner is global and shared between threads. It holds on to a name
+//! only while some symbol for it is alive; names that nothing refers to any
+//! more are swept out as the table grows.
+
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+/// The fewest names the interner holds before it first sweeps.
+const FIRST_SWEEP: usize = 1024;
+
+/// An interned, immutable name, such as a namespace or a fully-qualified type
+/// name. It dereferences to `str`, and hashes as the text does, so a map keyed
+/// by symbols can be looked up with a plain `&str`.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    /// The symbol for `name`, shared with every other symbol for the same text.
+    pub fn intern(name: &str) -> Self {
+        static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+        let interner = INTERNER.get_or_init(|| {
+            Mutex::new(Interner {
+                names: HashSet::new(),
+                sweep_at: FIRST_SWEEP,
+            })
+        });
+        Self(
+            interner
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .intern(name),
+        )
+    }
+
+    /// The name, as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The table behind [`Symbol::intern`].
+struct Interner {
+    names: HashSet<Arc<str>>,
+    /// The size at which the table next sweeps out the names that no symbol
+    /// refers to.
+    sweep_at: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(interned) = self.names.get(name) {
+            return interned.clone();
+        }
+        if self.names.len() >= self.sweep_at {
+            // Only the table holds a name whose count is one, and the table
+            // is locked, so no symbol for it can appear while it goes.
+            self.names.retain(|name| Arc::strong_count(name) > 1);
+            self.sweep_at = (self.names.len() * 2).max(FIRST_SWEEP);
+        }
+        let interned: Arc<str> = Arc::from(name);
+        self.names.insert(interned.clone());
+        interned
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        // Interning makes equal text the same allocation.
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hashed as the text, to agree with `Borrow<str>`.
+        self.0.hash(state);
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Symbol;
+
+    #[test]
+    fn equal_names_share_one_symbol() {
+        let name = String::from("org.example@1.0.0.Person");
+        let first = Symbol::intern(&name);
+        let second = Symbol::intern("org.example@1.0.0.Person");
+        assert_eq!(first, second);
+        assert!(std::ptr::eq(first.as_str(), second.as_str()));
+        assert_ne!(first, Symbol::intern("org.example@1.0.0.Employee"));
+        assert_eq!(first, "org.example@1.0.0.Person");
+    }
+
+    #[test]
+    fn a_symbol_map_is_looked_up_by_str() {
+        let mut map = HashMap::new();
+        map.insert(Symbol::intern("org.example@1.0.0"), 1);
+        assert_eq!(map.get("org.example@1.0.0"), Some(&1));
+        assert_eq!(map.get("org.other@1.0.0"), None);
+    }
+}
@@ -0,0 +1,31 @@
+//! Metamodel fragments shared by the unit tests.
+
+use serde_json::{Value, json};
+
+/// A required, single property of the metamodel `class`, with `extra` merged
+/// in; `extra` may override `isArray` and `isOptional`.
+pub(crate) fn property(class: &str, name: &str, extra: Value) -> Value {
+    declared(class, name, false, extra)
+}
+
+/// An optional, single property of the metamodel `class`, with `extra`
+/// merged in.
+pub(crate) fn optional_property(class: &str, name: &str, extra: Value) -> Value {
+    declared(class, name, true, extra)
+}
+
+fn declared(class: &str, name: &str, optional: bool, extra: Value) -> Value {
+    let mut property = json!({ "$class": format!("concerto.metamodel@1.0.0.{class}"),
+                               "name": name, "isArray": false, "isOptional": optional });
+    property
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    property
+}
+
+/// The `type` of an object property, relationship or map value typed by
+/// `name`.
+pub(crate) fn typed(name: &str) -> Value {
+    json!({ "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": name } })
+}
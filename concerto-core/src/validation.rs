@@ -28,6 +28,7 @@ use crate::introspect::model_file::ModelFile;
 use crate::introspect::property::Property;
 use crate::model_manager::ModelManager;
 use crate::model_util::{is_primitive_type, parse_namespace, qualify};
+use crate::symbol::Symbol;
 
 impl ModelManager {
     /// Validates every loaded user model, leaving the built-in system model
@@ -35,22 +36,64 @@ impl ModelManager {
     /// the first problem found. Namespaces are visited in order so that the
     /// same set of models always reports the same problem.
     pub fn validate_models(&self) -> Result<()> {
+        self.user_model_files()
+            .into_iter()
+            .try_for_each(|model_file| validate_model_file(self, model_file))
+    }
+
+    /// Validates the one loaded model of `namespace` against the rest, as
+    /// [`ModelManager::validate_models`] would. An editor uses this to tell
+    /// which file a problem is in.
+    pub fn validate_model(&self, namespace: &str) -> Result<()> {
+        let model_file =
+            self.model_file(namespace)
+                .ok_or_else(|| ConcertoError::NamespaceNotFound {
+                    namespace: namespace.to_string(),
+                })?;
+        if model_file.is_system_namespace() {
+            return Ok(());
+        }
+        validate_model_file(self, model_file)
+    }
+
+    /// Validates every loaded user model as [`ModelManager::validate_models`]
+    /// does, but checks the model files concurrently on the rayon thread pool.
+    /// Each file is checked on its own, so the problem reported is the one the
+    /// serial pass would report: that of the first failing namespace in order.
+    #[cfg(feature = "rayon")]
+    pub fn par_validate_models(&self) -> Result<()> {
+        use rayon::prelude::*;
+
+        match self
+            .user_model_files()
+            .into_par_iter()
+            .find_map_first(|model_file| validate_model_file(self, model_file).err())
+        {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// The loaded user models, in namespace order.
+    fn user_model_files(&self) -> Vec<&ModelFile> {
         let mut model_files: Vec<_> = self
             .model_files()
             .filter(|model_file| !model_file.is_system_namespace())
             .collect();
         model_files.sort_by_key(|model_file| model_file.namespace());
+        model_files
+    }
+}
 
-        for model_file in model_files {
-            check_import_clashes(model_file)?;
-            check_import_namespaces(model_file)?;
-            check_imported_types_exist(self, model_file)?;
-            for declaration in model_file.declarations() {
-                validate_declaration(self, model_file.namespace(), declaration)?;
-            }
-        }
-        Ok(())
+/// Validates one model file against the rest of the loaded models.
+fn validate_model_file(manager: &ModelManager, model_file: &ModelFile) -> Result<()> {
+    check_import_clashes(model_file)?;
+    check_import_namespaces(model_file)?;
+    check_imported_types_exist(manager, model_file)?;
+    for declaration in model_file.declarations() {
+        validate_declaration(manager, model_file.namespace(), declaration)?;
     }
+    Ok(())
 }
 
 /// A declaration may not take the name of a type the file imports. Importing
@@ -271,16 +314,17 @@ fn check_property_type(
 
 /// Resolves a referenced type to a fully-qualified name. A reference that
 /// carries its own namespace is qualified directly; otherwise it is resolved
-/// through the imports and local declarations of `namespace`.
-fn resolve(
+/// through the imports and local declarations of `namespace`, which hands
+/// back a name interned when that file was loaded.
+pub(crate) fn resolve(
     manager: &ModelManager,
     namespace: &str,
     name: &str,
     reference_namespace: Option<&str>,
-) -> Option<String> {
+) -> Option<Symbol> {
     match reference_namespace {
-        Some(ns) => Some(qualify(ns, name)),
-        None => manager.resolve_type_name(namespace, name).ok(),
+        Some(ns) => Some(Symbol::intern(&qualify(ns, name))),
+        None => manager.resolve_symbol(namespace, name).ok().cloned(),
     }
 }
 
@@ -460,6 +504,32 @@ mod tests {
         v
     }
 
+    #[test]
+    fn validate_model_checks_one_namespace() {
+        let mut manager = ModelManager::new().unwrap();
+        for (namespace, super_type) in [("org.good@1.0.0", "Concept"), ("org.bad@1.0.0", "Ghost")] {
+            manager
+                .add_model(
+                    &serde_json::json!({
+                        "$class": "concerto.metamodel@1.0.0.Model",
+                        "namespace": namespace,
+                        "declarations": [concept(serde_json::json!({
+                            "name": "Thing",
+                            "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": super_type, "namespace": "concerto@1.0.0" }
+                        }))]
+                    }),
+                    None,
+                )
+                .unwrap();
+        }
+        assert!(manager.validate_model("org.good@1.0.0").is_ok());
+        assert!(manager.validate_model("org.bad@1.0.0").is_err());
+        assert!(matches!(
+            manager.validate_model("org.missing@1.0.0"),
+            Err(crate::error::ConcertoError::NamespaceNotFound { .. })
+        ));
+    }
+
     #[test]
     fn super_type_that_exists_passes() {
         let err = validate(serde_json::json!([
@@ -1029,4 +1099,37 @@ mod tests {
                 .contains("Invalid field name")
         );
     }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_validation_reports_the_serial_error() {
+        let mut manager = ModelManager::new().unwrap();
+        for index in 0..64 {
+            // Every fifth namespace extends a type that does not exist.
+            let mut person = concept(serde_json::json!({ "name": "Person" }));
+            if index % 5 == 0 {
+                person["superType"] = serde_json::json!({
+                    "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": format!("Ghost{index}")
+                });
+            }
+            manager
+                .add_model(
+                    &serde_json::json!({
+                        "$class": "concerto.metamodel@1.0.0.Model",
+                        "namespace": format!("org.example{index:02}@1.0.0"),
+                        "declarations": [person]
+                    }),
+                    None,
+                )
+                .unwrap();
+        }
+        let serial = manager.validate_models().unwrap_err().to_string();
+        assert!(serial.contains("Ghost0 "), "{serial}");
+        for _ in 0..8 {
+            assert_eq!(
+                manager.par_validate_models().unwrap_err().to_string(),
+                serial
+            );
+        }
+    }
 }
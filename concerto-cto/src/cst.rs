@@ -0,0 +1,538 @@
+//! A concrete syntax tree of CTO source, for the formatter.
+//!
+//! The JSON AST keeps what a model means and drops how it was written, the
+//! comments above all. This tree keeps the tokens of each construct and the
+//! comments around it: those on the lines before a node lead it, and those
+//! after it on its own line, or inside it, trail it. No comment is ever
+//! dropped, though one inside a construct moves to the end of its line.
+//!
+//! The tree is built from source the parser has already accepted, so it
+//! trusts the grammar rather than checking it again.
+
+use std::mem;
+
+use crate::lexer::{Token, TokenKind};
+
+/// A comment, and whether a blank line comes before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Comment<'s> {
+    pub text: &'s str,
+    pub blank_before: bool,
+}
+
+/// A construct with the comments around it.
+#[derive(Debug)]
+pub(crate) struct Node<'s, T> {
+    /// The comments on the lines before it.
+    pub leading: Vec<Comment<'s>>,
+    /// Whether a blank line comes between the leading comments, or whatever
+    /// precedes it, and the construct itself.
+    pub blank_before: bool,
+    pub value: T,
+    /// The comments after it on its line, and any inside it.
+    pub trailing: Vec<&'s str>,
+}
+
+impl<T> Node<'_, T> {
+    /// Whether a blank line comes before the node and its comments.
+    pub fn blank_above(&self) -> bool {
+        self.leading
+            .first()
+            .map_or(self.blank_before, |comment| comment.blank_before)
+    }
+}
+
+/// A whole file.
+#[derive(Debug)]
+pub(crate) struct Model<'s> {
+    /// `concerto version "…"`.
+    pub version: Option<Node<'s, Vec<Token<'s>>>>,
+    /// The decorators on the model, each of them its tokens after the `@`.
+    pub decorators: Vec<Node<'s, Vec<Token<'s>>>>,
+    /// `namespace …`.
+    pub namespace: Node<'s, Vec<Token<'s>>>,
+    pub imports: Vec<Node<'s, Import<'s>>>,
+    pub declarations: Vec<Node<'s, Declaration<'s>>>,
+    /// The comments after the last declaration.
+    pub end: Vec<Comment<'s>>,
+}
+
+/// An import.
+#[derive(Debug)]
+pub(crate) struct Import<'s> {
+    /// The namespace imported from, as written.
+    pub namespace: String,
+    pub target: ImportTarget<'s>,
+    /// The URI after `from`.
+    pub uri: Option<&'s str>,
+}
+
+/// What an import brings in.
+#[derive(Debug)]
+pub(crate) enum ImportTarget<'s> {
+    /// `.Name`.
+    Name(&'s str),
+    /// `.*`.
+    All,
+    /// `.{A, B as C}`: each name and its alias.
+    Types(Vec<(&'s str, Option<&'s str>)>),
+}
+
+/// A declaration.
+#[derive(Debug)]
+pub(crate) struct Declaration<'s> {
+    /// Its decorators, each of them its tokens after the `@`.
+    pub decorators: Vec<Node<'s, Vec<Token<'s>>>>,
+    /// Everything from `abstract` or the keyword up to the body or, for a
+    /// scalar, its modifiers.
+    pub header: Vec<Token<'s>>,
+    /// A scalar's `default=`, `regex=`, `range=` and `length=`.
+    pub modifiers: Vec<Vec<Token<'s>>>,
+    /// The members in braces; a scalar has none.
+    pub body: Option<Body<'s>>,
+    /// The comments after the closing brace.
+    pub closing: Vec<&'s str>,
+}
+
+/// The members of a declaration, in braces.
+#[derive(Debug)]
+pub(crate) struct Body<'s> {
+    pub members: Vec<Node<'s, Member<'s>>>,
+    /// The comments after the last member.
+    pub end: Vec<Comment<'s>>,
+}
+
+/// A property, an enum value, or a map's key or value.
+#[derive(Debug)]
+pub(crate) struct Member<'s> {
+    /// Its decorators, each of them its tokens after the `@`.
+    pub decorators: Vec<Node<'s, Vec<Token<'s>>>>,
+    /// `o` or `-->`.
+    pub marker: &'s str,
+    /// The type, with `[]` for an array; for an enum value, its name.
+    pub type_name: Vec<Token<'s>>,
+    /// The property's name; enum values and map entries have none.
+    pub name: Option<&'s str>,
+    /// `default=`, `regex=`, `range=`, `length=` and `optional`.
+    pub modifiers: Vec<Vec<Token<'s>>>,
+}
+
+/// The keywords that start a declaration, `abstract` among them.
+const DECLARATION_STARTS: [&str; 9] = [
+    "abstract",
+    "concept",
+    "asset",
+    "participant",
+    "transaction",
+    "event",
+    "enum",
+    "scalar",
+    "map",
+];
+
+/// The keywords that start a modifier.
+const MODIFIERS: [&str; 5] = ["default", "regex", "range", "length", "optional"];
+
+/// A token that is not trivia, with the comments around it.
+struct Lexeme<'s> {
+    token: Token<'s>,
+    leading: Vec<Comment<'s>>,
+    blank_before: bool,
+    trailing: Vec<&'s str>,
+}
+
+/// Builds the tree of a file from all of its tokens, trivia included.
+pub(crate) fn build<'s>(tokens: &[Token<'s>]) -> Model<'s> {
+    let (lexemes, end) = lexemes(tokens);
+    let mut builder = Builder { lexemes, index: 0 };
+    builder.model(end)
+}
+
+/// Pairs each token with its comments, and gives back the comments at the
+/// end of the file.
+fn lexemes<'s>(tokens: &[Token<'s>]) -> (Vec<Lexeme<'s>>, Vec<Comment<'s>>) {
+    let mut lexemes: Vec<Lexeme<'s>> = Vec::new();
+    let mut pending = Vec::new();
+    let mut newlines = 0;
+    for token in tokens {
+        match token.kind {
+            TokenKind::Whitespace => newlines += token.text.matches('\n').count(),
+            TokenKind::LineComment | TokenKind::BlockComment => {
+                let text = token.text.trim_end();
+                match lexemes.last_mut() {
+                    Some(previous) if newlines == 0 && pending.is_empty() => {
+                        previous.trailing.push(text);
+                    }
+                    _ => pending.push(Comment {
+                        text,
+                        blank_before: newlines > 1,
+                    }),
+                }
+                newlines = 0;
+            }
+            _ => {
+                lexemes.push(Lexeme {
+                    token: *token,
+                    leading: mem::take(&mut pending),
+                    blank_before: newlines > 1,
+                    trailing: Vec::new(),
+                });
+                newlines = 0;
+            }
+        }
+    }
+    (lexemes, pending)
+}
+
+struct Builder<'s> {
+    lexemes: Vec<Lexeme<'s>>,
+    index: usize,
+}
+
+impl<'s> Builder<'s> {
+    fn peek(&self) -> Option<Token<'s>> {
+        self.lexemes.get(self.index).map(|lexeme| lexeme.token)
+    }
+
+    fn peek_kind(&self) -> Option<TokenKind> {
+        self.peek().map(|token| token.kind)
+    }
+
+    fn peek_text(&self) -> Option<&'s str> {
+        self.peek().map(|token| token.text)
+    }
+
+    fn advance(&mut self) -> Token<'s> {
+        let token = self.lexemes[self.index].token;
+        self.index += 1;
+        token
+    }
+
+    /// Starts a node at the current token, taking the comments before it.
+    fn start(&mut self) -> (usize, Vec<Comment<'s>>, bool) {
+        let start = self.index;
+        match self.lexemes.get_mut(start) {
+            Some(lexeme) => (start, mem::take(&mut lexeme.leading), lexeme.blank_before),
+            None => (start, Vec::new(), false),
+        }
+    }
+
+    /// Takes the comments inside, and after, the tokens from `start` up to
+    /// the current one.
+    fn comments(&mut self, start: usize) -> Vec<&'s str> {
+        let mut comments = Vec::new();
+        for lexeme in &mut self.lexemes[start..self.index] {
+            comments.extend(
+                mem::take(&mut lexeme.leading)
+                    .iter()
+                    .map(|comment| comment.text),
+            );
+            comments.append(&mut lexeme.trailing);
+        }
+        comments
+    }
+
+    /// Finishes a node started with [`Builder::start`].
+    fn finish<T>(
+        &mut self,
+        (start, leading, blank_before): (usize, Vec<Comment<'s>>, bool),
+        value: T,
+    ) -> Node<'s, T> {
+        Node {
+            leading,
+            blank_before,
+            value,
+            trailing: self.comments(start),
+        }
+    }
+
+    /// The tokens up to the next one `stop` accepts, or the end.
+    fn until(&mut self, stop: impl Fn(&Self) -> bool) -> Vec<Token<'s>> {
+        let mut tokens = Vec::new();
+        while self.peek().is_some() && !stop(self) {
+            tokens.push(self.advance());
+        }
+        tokens
+    }
+
+    fn model(&mut self, end: Vec<Comment<'s>>) -> Model<'s> {
+        let version = (self.peek_text() == Some("concerto")).then(|| {
+            let start = self.start();
+            let tokens = self.until(|builder| {
+                builder.peek_kind() == Some(TokenKind::At)
+                    || builder.peek_text() == Some("namespace")
+            });
+            self.finish(start, tokens)
+        });
+        let decorators = self.decorators();
+        let start = self.start();
+        // `namespace` and the first segment.
+        let mut namespace = Vec::new();
+        while namespace.len() < 2 && self.peek().is_some() {
+            namespace.push(self.advance());
+        }
+        namespace.extend(self.until(|builder| {
+            let next = builder
+                .lexemes
+                .get(builder.index + 1)
+                .map(|lexeme| lexeme.token.kind);
+            match builder.peek_kind() {
+                // A segment after a dot may be named like a keyword.
+                Some(TokenKind::Identifier) => {
+                    builder.lexemes[builder.index - 1].token.kind != TokenKind::Dot
+                }
+                Some(TokenKind::At) => next != Some(TokenKind::Version),
+                Some(TokenKind::Dot | TokenKind::Version) => false,
+                _ => true,
+            }
+        }));
+        let namespace = self.finish(start, namespace);
+        let mut imports = Vec::new();
+        while self.peek_text() == Some("import") {
+            imports.push(self.import());
+        }
+        let mut declarations = Vec::new();
+        while self.peek().is_some() {
+            declarations.push(self.declaration());
+        }
+        Model {
+            version,
+            decorators,
+            namespace,
+            imports,
+            declarations,
+            end,
+        }
+    }
+
+    /// Whether a declaration starts here, with its decorators or its keyword.
+    fn at_declaration(&self) -> bool {
+        self.peek_kind() == Some(TokenKind::At)
+            || self.peek().is_some_and(|token| {
+                token.kind == TokenKind::Identifier && DECLARATION_STARTS.contains(&token.text)
+            })
+    }
+
+    /// The decorators here, each of them the tokens after its `@`.
+    fn decorators(&mut self) -> Vec<Node<'s, Vec<Token<'s>>>> {
+        let mut decorators = Vec::new();
+        while self.peek_kind() == Some(TokenKind::At) {
+            let start = self.start();
+            self.advance();
+            let mut tokens = vec![self.advance()];
+            if self.peek_kind() == Some(TokenKind::LeftParen) {
+                tokens.extend(
+                    self.until(|builder| builder.peek_kind() == Some(TokenKind::RightParen)),
+                );
+                if self.peek().is_some() {
+                    tokens.push(self.advance());
+                }
+            }
+            decorators.push(self.finish(start, tokens));
+        }
+        decorators
+    }
+
+    fn import(&mut self) -> Node<'s, Import<'s>> {
+        let start = self.start();
+        self.advance();
+        // `org.acme@1.0.0`, or `org.acme.Name` without a version, in which
+        // case the last segment is the name imported.
+        let mut namespace = String::new();
+        let mut last: Option<&'s str> = None;
+        let push = |namespace: &mut String, segment: &str| {
+            if !namespace.is_empty() {
+                namespace.push('.');
+            }
+            namespace.push_str(segment);
+        };
+        while self.peek_kind() == Some(TokenKind::Identifier) {
+            if let Some(previous) = last.replace(self.advance().text) {
+                push(&mut namespace, previous);
+            }
+            match self.peek_kind() {
+                Some(TokenKind::At) => {
+                    self.advance();
+                    push(&mut namespace, last.take().unwrap_or_default());
+                    namespace.push('@');
+                    namespace.push_str(self.advance().text);
+                    break;
+                }
+                Some(TokenKind::Dot)
+                    if self
+                        .lexemes
+                        .get(self.index + 1)
+                        .map(|lexeme| lexeme.token.kind)
+                        == Some(TokenKind::Identifier) =>
+                {
+                    self.advance();
+                }
+                _ => break,
+            }
+        }
+        let target = if self.peek_kind() == Some(TokenKind::Dot) {
+            self.advance();
+            match self.peek_kind() {
+                Some(TokenKind::Star) => {
+                    self.advance();
+                    ImportTarget::All
+                }
+                Some(TokenKind::LeftBrace) => {
+                    self.advance();
+                    let mut types = Vec::new();
+                    while let Some(token) = self.peek() {
+                        self.advance();
+                        match token.kind {
+                            TokenKind::RightBrace => break,
+                            TokenKind::Identifier if token.text == "as" => {
+                                let alias = self.advance().text;
+                                if let Some((_, slot)) = types.last_mut() {
+                                    *slot = Some(alias);
+                                }
+                            }
+                            TokenKind::Identifier => types.push((token.text, None)),
+                            _ => {}
+                        }
+                    }
+                    ImportTarget::Types(types)
+                }
+                _ => ImportTarget::Name(self.advance().text),
+            }
+        } else {
+            ImportTarget::Name(last.take().unwrap_or_default())
+        };
+        if let Some(last) = last {
+            push(&mut namespace, last);
+        }
+        let uri = (self.peek_text() == Some("from")).then(|| {
+            self.advance();
+            self.advance().text
+        });
+        let import = Import {
+            namespace,
+            target,
+            uri,
+        };
+        self.finish(start, import)
+    }
+
+    fn declaration(&mut self) -> Node<'s, Declaration<'s>> {
+        let start = self.start();
+        let decorators = self.decorators();
+        let mut header = Vec::new();
+        if self.peek_text() == Some("abstract") {
+            header.push(self.advance());
+        }
+        let keyword = self.peek_text().unwrap_or_default();
+        let mut modifiers = Vec::new();
+        if self.peek().is_some() {
+            header.push(self.advance());
+        }
+        let body = if keyword == "scalar" {
+            header.extend(self.until(|builder| {
+                builder.at_declaration()
+                    || builder
+                        .peek_text()
+                        .is_some_and(|text| MODIFIERS.contains(&text))
+            }));
+            modifiers = self.modifiers();
+            None
+        } else {
+            header.extend(self.until(|builder| builder.peek_kind() == Some(TokenKind::LeftBrace)));
+            Some(keyword)
+        };
+        let mut node = self.finish(
+            start,
+            Declaration {
+                decorators,
+                header,
+                modifiers,
+                body: None,
+                closing: Vec::new(),
+            },
+        );
+        if let Some(keyword) = body {
+            // The `{` belongs to the header's line, and takes its comments.
+            let brace = self.index;
+            if self.peek().is_some() {
+                self.advance();
+            }
+            node.trailing.extend(self.comments(brace));
+            node.value.body = Some(self.body(!matches!(keyword, "enum" | "map")));
+            let brace = self.index;
+            if self.peek().is_some() {
+                self.advance();
+            }
+            node.value.closing = self.comments(brace);
+        }
+        node
+    }
+
+    /// The members up to the closing brace, which is left for the caller
+    /// with any comments after it.
+    fn body(&mut self, named: bool) -> Body<'s> {
+        let mut members = Vec::new();
+        while self
+            .peek_kind()
+            .is_some_and(|kind| kind != TokenKind::RightBrace)
+        {
+            members.push(self.member(named));
+        }
+        let end = self
+            .lexemes
+            .get_mut(self.index)
+            .map(|brace| mem::take(&mut brace.leading))
+            .unwrap_or_default();
+        Body { members, end }
+    }
+
+    fn member(&mut self, named: bool) -> Node<'s, Member<'s>> {
+        let start = self.start();
+        let decorators = self.decorators();
+        let marker = self.advance().text;
+        let mut type_name = vec![self.advance()];
+        while self.peek_kind() == Some(TokenKind::Dot) {
+            type_name.push(self.advance());
+            type_name.push(self.advance());
+        }
+        if self.peek_kind() == Some(TokenKind::LeftBracket) {
+            type_name.push(self.advance());
+            type_name.push(self.advance());
+        }
+        let name =
+            (named && self.peek_kind() == Some(TokenKind::Identifier)).then(|| self.advance().text);
+        let modifiers = self.modifiers();
+        let member = Member {
+            decorators,
+            marker,
+            type_name,
+            name,
+            modifiers,
+        };
+        self.finish(start, member)
+    }
+
+    /// The modifiers here, each of them its tokens.
+    fn modifiers(&mut self) -> Vec<Vec<Token<'s>>> {
+        let mut modifiers = Vec::new();
+        while self
+            .peek_text()
+            .is_some_and(|text| MODIFIERS.contains(&text))
+        {
+            let mut modifier = vec![self.advance()];
+            modifier.extend(self.until(|builder| {
+                builder.peek().is_none_or(|token| {
+                    matches!(
+                        token.kind,
+                        TokenKind::RightBrace | TokenKind::At | TokenKind::Arrow
+                    ) || (token.kind == TokenKind::Identifier
+                        && (MODIFIERS.contains(&token.text)
+                            || token.text == "o"
+                            || DECLARATION_STARTS.contains(&token.text)))
+                })
+            }));
+            modifiers.push(modifier);
+        }
+        modifiers
+    }
+}
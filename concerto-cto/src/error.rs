@@ -0,0 +1,90 @@
+//! Error types for `concerto-cto`.
+
+use concerto_core::ConcertoError;
+use thiserror::Error;
+
+use crate::lexer::Position;
+
+/// Shorthand `Result` used all over `concerto-cto`.
+pub type Result<T> = std::result::Result<T, CtoError>;
+
+/// A CTO source that does not parse, or a JSON AST that cannot be printed.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CtoError {
+    /// The source is not valid CTO.
+    #[error("syntax error at line {line}, column {column}: {message}")]
+    Syntax {
+        /// A description of the problem.
+        message: String,
+        /// The line it is on, counting from 1.
+        line: usize,
+        /// The column it is at, in characters, counting from 1.
+        column: usize,
+        /// The byte offset it is at, counting from 0.
+        offset: usize,
+    },
+
+    /// The JSON AST is not a model the printer understands.
+    #[error("cannot print model: {message}")]
+    Unprintable {
+        /// A description of the problem.
+        message: String,
+    },
+}
+
+impl CtoError {
+    pub(crate) fn syntax(message: impl Into<String>, at: Position) -> Self {
+        Self::Syntax {
+            message: message.into(),
+            line: at.line,
+            column: at.column,
+            offset: at.offset,
+        }
+    }
+
+    pub(crate) fn unprintable(message: impl Into<String>) -> Self {
+        Self::Unprintable {
+            message: message.into(),
+        }
+    }
+
+    /// The position of a syntax error.
+    pub fn position(&self) -> Option<Position> {
+        match *self {
+            Self::Syntax {
+                line,
+                column,
+                offset,
+                ..
+            } => Some(Position {
+                line,
+                column,
+                offset,
+            }),
+            Self::Unprintable { .. } => None,
+        }
+    }
+}
+
+/// A syntax error is an illegal model, located by line and column.
+impl From<CtoError> for ConcertoError {
+    fn from(error: CtoError) -> Self {
+        match error {
+            CtoError::Syntax {
+                message,
+                line,
+                column,
+                ..
+            } => ConcertoError::IllegalModel {
+                message,
+                file_name: None,
+                location: Some(format!("line {line}, column {column}")),
+            },
+            CtoError::Unprintable { message } => ConcertoError::IllegalModel {
+                message,
+                file_name: None,
+                location: None,
+            },
+        }
+    }
+}
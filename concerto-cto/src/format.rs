@@ -0,0 +1,421 @@
+//! Formats CTO source in a canonical layout, comments and all.
+//!
+//! Where the [printer](crate::print) writes a model out afresh from its AST,
+//! the formatter rewrites a source file, keeping what the AST cannot hold:
+//! comments, and the blank lines that group properties. It lays the file
+//! out the way the printer would, with two spaces of indentation, each
+//! decorator on a line of its own, and a blank line between declarations,
+//! and in addition:
+//!
+//! - sorts the imports by namespace, grouped into those of the `concerto`
+//!   system namespace, the rest, and those fetched `from` a URI, with a blank
+//!   line between groups, and sorts the names in each `{…}`;
+//! - aligns the types, names and modifiers of consecutive members, a blank
+//!   line starting a new group, and puts each member's modifiers in the
+//!   order `default`, `regex` or `range`, `length`, `optional`;
+//! - collapses runs of blank lines into one, and drops those just inside
+//!   braces.
+//!
+//! Formatting is idempotent, and what the file means does not change: its
+//! AST differs, if at all, only in the order of its imports.
+
+use crate::ParseOptions;
+use crate::cst::{self, Comment, Declaration, Import, ImportTarget, Member, Model, Node};
+use crate::error::Result;
+use crate::lexer::{Token, TokenKind, tokenize};
+
+const INDENT: &str = "  ";
+
+/// Formats CTO source. A source that does not parse is the same
+/// [`CtoError::Syntax`](crate::CtoError::Syntax) that [`parse`](crate::parse)
+/// gives.
+pub fn format(source: &str) -> Result<String> {
+    let options = ParseOptions {
+        locations: false,
+        source_uri: None,
+    };
+    crate::parse_with(source, &options)?;
+    let tokens = tokenize(source)?;
+    let mut writer = Writer::default();
+    writer.model(&cst::build(&tokens));
+    let mut out = writer.out.trim_end().to_string();
+    out.push('\n');
+    Ok(out)
+}
+
+#[derive(Default)]
+struct Writer {
+    out: String,
+}
+
+impl Writer {
+    fn line(&mut self, indent: &str, text: &str) {
+        self.out.push_str(indent);
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Ends the output with a blank line, unless it is empty or already
+    /// does.
+    fn blank(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Comments on lines of their own, then a blank line if `blank_after`.
+    fn comments(&mut self, indent: &str, comments: &[Comment<'_>], blank_after: bool) {
+        for (index, comment) in comments.iter().enumerate() {
+            if index > 0 && comment.blank_before {
+                self.blank();
+            }
+            self.line(indent, comment.text);
+        }
+        if !comments.is_empty() && blank_after {
+            self.blank();
+        }
+    }
+
+    fn model(&mut self, model: &Model<'_>) {
+        if let Some(version) = &model.version {
+            self.comments("", &version.leading, version.blank_before);
+            self.line("", &trailed(join(&version.value), &version.trailing));
+            self.blank();
+        }
+        for decorator in &model.decorators {
+            self.decorator("", decorator);
+        }
+        let namespace = &model.namespace;
+        self.comments("", &namespace.leading, namespace.blank_before);
+        self.line("", &trailed(join(&namespace.value), &namespace.trailing));
+
+        let mut imports: Vec<_> = model.imports.iter().collect();
+        imports.sort_by_cached_key(|import| {
+            (
+                group(&import.value),
+                import.value.namespace.clone(),
+                import_text(&import.value),
+            )
+        });
+        let mut previous = None;
+        for import in imports {
+            if previous != Some(group(&import.value)) {
+                self.blank();
+            }
+            previous = Some(group(&import.value));
+            self.comments("", &import.leading, false);
+            self.line("", &trailed(import_text(&import.value), &import.trailing));
+        }
+
+        for declaration in &model.declarations {
+            self.blank();
+            self.declaration(declaration);
+        }
+        if let Some(first) = model.end.first() {
+            if first.blank_before {
+                self.blank();
+            }
+            self.comments("", &model.end, false);
+        }
+    }
+
+    fn decorator(&mut self, indent: &str, decorator: &Node<'_, Vec<Token<'_>>>) {
+        self.comments(indent, &decorator.leading, false);
+        let text = format!("@{}", join(&decorator.value));
+        self.line(indent, &trailed(text, &decorator.trailing));
+    }
+
+    fn declaration(&mut self, node: &Node<'_, Declaration<'_>>) {
+        let declaration = &node.value;
+        self.comments("", &node.leading, node.blank_before);
+        for decorator in &declaration.decorators {
+            self.decorator("", decorator);
+        }
+        let mut header = join(&declaration.header);
+        for modifier in sorted(&declaration.modifiers) {
+            header.push(' ');
+            header.push_str(&join(modifier));
+        }
+        let Some(body) = &declaration.body else {
+            self.line("", &trailed(header, &node.trailing));
+            return;
+        };
+        self.line("", &trailed(format!("{header} {{"), &node.trailing));
+        self.members(&body.members);
+        if let Some(first) = body.end.first() {
+            if first.blank_before && !body.members.is_empty() {
+                self.blank();
+            }
+            self.comments(INDENT, &body.end, false);
+        }
+        self.line("", &trailed("}".into(), &declaration.closing));
+    }
+
+    /// The members of a body, the members of each group between blank lines
+    /// aligned with each other.
+    fn members(&mut self, members: &[Node<'_, Member<'_>>]) {
+        let mut groups: Vec<&[Node<'_, Member<'_>>]> = Vec::new();
+        let mut start = 0;
+        for index in 1..=members.len() {
+            if index == members.len() || members[index].blank_above() {
+                groups.push(&members[start..index]);
+                start = index;
+            }
+        }
+        for (index, group) in groups.into_iter().enumerate() {
+            if index > 0 {
+                self.blank();
+            }
+            let widths = Widths::of(group);
+            for member in group {
+                self.comments(INDENT, &member.leading, member.blank_before);
+                for decorator in &member.value.decorators {
+                    self.decorator(INDENT, decorator);
+                }
+                let text = widths.member(&member.value);
+                self.line(INDENT, &trailed(text, &member.trailing));
+            }
+        }
+    }
+}
+
+/// The widths members in a group are padded to.
+struct Widths {
+    marker: usize,
+    type_name: usize,
+    name: usize,
+}
+
+impl Widths {
+    fn of(members: &[Node<'_, Member<'_>>]) -> Self {
+        let widest = |width: &dyn Fn(&Member<'_>) -> Option<usize>| {
+            members
+                .iter()
+                .filter_map(|member| width(&member.value))
+                .max()
+                .unwrap_or(0)
+        };
+        Self {
+            marker: widest(&|member| Some(member.marker.chars().count())),
+            type_name: widest(&|member| {
+                member.name.map(|_| join(&member.type_name).chars().count())
+            }),
+            name: widest(&|member| {
+                (!member.modifiers.is_empty())
+                    .then(|| member.name.map(|name| name.chars().count()))
+                    .flatten()
+            }),
+        }
+    }
+
+    /// A member on its line, padded to these widths.
+    fn member(&self, member: &Member<'_>) -> String {
+        let mut text = pad(member.marker, self.marker);
+        text.push(' ');
+        let type_name = join(&member.type_name);
+        let Some(name) = member.name else {
+            text.push_str(&type_name);
+            return text;
+        };
+        text.push_str(&pad(&type_name, self.type_name));
+        text.push(' ');
+        if member.modifiers.is_empty() {
+            text.push_str(name);
+            return text;
+        }
+        text.push_str(&pad(name, self.name));
+        for modifier in sorted(&member.modifiers) {
+            text.push(' ');
+            text.push_str(&join(modifier));
+        }
+        text
+    }
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{text:<width$}")
+}
+
+/// Modifiers in the printer's order.
+fn sorted<'m, 's>(modifiers: &'m [Vec<Token<'s>>]) -> Vec<&'m [Token<'s>]> {
+    let rank = |modifier: &[Token<'_>]| match modifier.first().map(|token| token.text) {
+        Some("default") => 0,
+        Some("regex" | "range") => 1,
+        Some("length") => 2,
+        _ => 3,
+    };
+    let mut sorted: Vec<&[Token<'s>]> = modifiers.iter().map(Vec::as_slice).collect();
+    sorted.sort_by_key(|modifier| rank(modifier));
+    sorted
+}
+
+/// Tokens on one line, spaced as the printer spaces them.
+fn join(tokens: &[Token<'_>]) -> String {
+    let mut text = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if index > 0 && !glued(tokens[index - 1].kind, token.kind) {
+            text.push(' ');
+        }
+        text.push_str(token.text);
+    }
+    text
+}
+
+/// Whether two tokens are written with no space between them.
+fn glued(previous: TokenKind, next: TokenKind) -> bool {
+    use TokenKind::*;
+    matches!(
+        next,
+        Dot | Comma | LeftBracket | RightBracket | LeftParen | RightParen | Equals | At | Version
+    ) || matches!(
+        previous,
+        Dot | At | LeftBracket | LeftParen | Comma | Equals
+    )
+}
+
+/// A line with the comments that trail it.
+fn trailed(mut text: String, trailing: &[&str]) -> String {
+    for comment in trailing {
+        text.push(' ');
+        text.push_str(comment);
+    }
+    text
+}
+
+/// Imports from the system namespace come first, then the rest, then those
+/// fetched from a URI.
+fn group(import: &Import<'_>) -> u8 {
+    if import.namespace.starts_with("concerto@") || import.namespace == "concerto" {
+        0
+    } else if import.uri.is_none() {
+        1
+    } else {
+        2
+    }
+}
+
+fn import_text(import: &Import<'_>) -> String {
+    let target = match &import.target {
+        ImportTarget::Name(name) => name.to_string(),
+        ImportTarget::All => "*".into(),
+        ImportTarget::Types(types) => {
+            let mut types: Vec<String> = types
+                .iter()
+                .map(|(name, alias)| match alias {
+                    Some(alias) => format!("{name} as {alias}"),
+                    None => name.to_string(),
+                })
+                .collect();
+            types.sort();
+            format!("{{{}}}", types.join(", "))
+        }
+    };
+    let mut text = format!("import {}.{target}", import.namespace);
+    if let Some(uri) = import.uri {
+        text.push_str(" from ");
+        text.push_str(uri);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::format;
+    use crate::{CtoError, ParseOptions, parse_with};
+
+    const MESSY: &str = r#"// Licensed under the Apache License.
+
+namespace   org.acme.hr@1.0.0
+import org.acme.people@1.0.0.{Person,Address as Addr} // who
+import concerto@1.0.0.Concept
+import org.acme.money@2.1.0.Money from https://models.acme.org/money.cto
+
+
+/* The people on the payroll. */
+@Term( "Employee" , 1 )   @Hidden
+participant   Employee extends Person{
+  o String employeeId optional regex = /^E[0-9]+$/
+  o Money salary
+  // Whom they report to.
+  --> Person[] managers optional
+
+
+  o Double  ratio range=[0.0, 1.0]    default=0.5
+  @Secret o Addr home
+}
+enum   Level { o JUNIOR o SENIOR }
+scalar   Code extends String   length=[1,10] default="X"
+map Salaries {
+  o String
+  o Money
+  // Nothing else.
+}
+// The end.
+"#;
+
+    const FORMATTED: &str = r#"// Licensed under the Apache License.
+
+namespace org.acme.hr@1.0.0
+
+import concerto@1.0.0.Concept
+
+import org.acme.people@1.0.0.{Address as Addr, Person} // who
+
+import org.acme.money@2.1.0.Money from https://models.acme.org/money.cto
+
+/* The people on the payroll. */
+@Term("Employee",1)
+@Hidden
+participant Employee extends Person {
+  o   String   employeeId regex=/^E[0-9]+$/ optional
+  o   Money    salary
+  // Whom they report to.
+  --> Person[] managers   optional
+
+  o Double ratio default=0.5 range=[0.0,1.0]
+  @Secret
+  o Addr   home
+}
+
+enum Level {
+  o JUNIOR
+  o SENIOR
+}
+
+scalar Code extends String default="X" length=[1,10]
+
+map Salaries {
+  o String
+  o Money
+  // Nothing else.
+}
+// The end.
+"#;
+
+    fn declarations(source: &str) -> Value {
+        let options = ParseOptions {
+            locations: false,
+            source_uri: None,
+        };
+        parse_with(source, &options).unwrap()["declarations"].clone()
+    }
+
+    #[test]
+    fn formats_a_messy_model() {
+        assert_eq!(format(MESSY).unwrap(), FORMATTED);
+    }
+
+    #[test]
+    fn formatting_is_idempotent_and_keeps_the_meaning() {
+        assert_eq!(format(FORMATTED).unwrap(), FORMATTED);
+        assert_eq!(declarations(FORMATTED), declarations(MESSY));
+    }
+
+    #[test]
+    fn a_source_that_does_not_parse_is_a_syntax_error() {
+        let error = format("namespace org.acme@1.0.0\nconcept {").unwrap_err();
+        assert!(matches!(error, CtoError::Syntax { line: 2, .. }));
+    }
+}
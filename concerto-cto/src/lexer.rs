@@ -0,0 +1,425 @@
+//! Splits CTO source into tokens.
+//!
+//! The lexer keeps every byte of the source: whitespace and comments come out
+//! as tokens of their own, so that a tool which needs them, such as an editor
+//! looking for the name under the cursor, reads the same stream the parser
+//! does. The parser skips them.
+//!
+//! Three tokens depend on what precedes them. A `/` after `regex =` opens a
+//! regular expression literal, which runs to the next unescaped `/` and takes
+//! any flags after it; anywhere else it is an error. A digit straight after
+//! an `@` starts a semantic version, as in `org.acme@1.0.0`, which may itself
+//! contain dots and dashes; after a decorator's `@` comes a name instead. And
+//! after an import's `from`, a URI runs up to the next whitespace.
+
+use crate::error::{CtoError, Result};
+
+/// What a token is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Spaces, tabs and line breaks.
+    Whitespace,
+    /// A `//` comment, up to the end of its line.
+    LineComment,
+    /// A `/* … */` comment.
+    BlockComment,
+    /// A name or a keyword, such as `Person` or `concept`.
+    Identifier,
+    /// A number, such as `42`, `-1` or `3.5e2`.
+    Number,
+    /// A double-quoted string, escapes and all.
+    String,
+    /// A regular expression literal, such as `/^[A-Z]+$/i`.
+    Regex,
+    /// The version after a namespace's `@`, such as `1.0.0-rc.1`.
+    Version,
+    /// The location after an import's `from`, such as
+    /// `https://models.acme.org/people.cto`.
+    Uri,
+    /// `-->`, which introduces a relationship.
+    Arrow,
+    /// `{`.
+    LeftBrace,
+    /// `}`.
+    RightBrace,
+    /// `[`.
+    LeftBracket,
+    /// `]`.
+    RightBracket,
+    /// `(`.
+    LeftParen,
+    /// `)`.
+    RightParen,
+    /// `,`.
+    Comma,
+    /// `.`.
+    Dot,
+    /// `@`.
+    At,
+    /// `=`.
+    Equals,
+    /// `*`.
+    Star,
+}
+
+impl TokenKind {
+    /// Whether the parser skips this kind of token.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            Self::Whitespace | Self::LineComment | Self::BlockComment
+        )
+    }
+}
+
+/// A place in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// The line, counting from 1.
+    pub line: usize,
+    /// The column, in characters, counting from 1.
+    pub column: usize,
+    /// The byte offset, counting from 0.
+    pub offset: usize,
+}
+
+/// One token, with where it starts and ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'s> {
+    /// What the token is.
+    pub kind: TokenKind,
+    /// The token's text, exactly as written.
+    pub text: &'s str,
+    /// Where the token starts.
+    pub start: Position,
+    /// Where the token ends: the position just past its last character.
+    pub end: Position,
+}
+
+/// Splits `source` into tokens, trivia included.
+pub fn tokenize(source: &str) -> Result<Vec<Token<'_>>> {
+    let mut lexer = Lexer {
+        source,
+        position: Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+        },
+        tokens: Vec::new(),
+    };
+    while lexer.position.offset < source.len() {
+        lexer.next_token()?;
+    }
+    Ok(lexer.tokens)
+}
+
+struct Lexer<'s> {
+    source: &'s str,
+    position: Position,
+    tokens: Vec<Token<'s>>,
+}
+
+impl<'s> Lexer<'s> {
+    fn rest(&self) -> &'s str {
+        &self.source[self.position.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
+    /// Moves past one character.
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position.offset += c.len_utf8();
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn bump_while(&mut self, keep: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&keep) {
+            self.bump();
+        }
+    }
+
+    /// The last token that is not trivia, counting back `skip` of them.
+    fn previous(&self, skip: usize) -> Option<&Token<'s>> {
+        self.tokens
+            .iter()
+            .rev()
+            .filter(|token| !token.kind.is_trivia())
+            .nth(skip)
+    }
+
+    fn error(&self, message: impl Into<String>) -> CtoError {
+        CtoError::syntax(message, self.position)
+    }
+
+    fn next_token(&mut self) -> Result<()> {
+        let start = self.position;
+        let c = self.peek().expect("not at the end");
+        let kind = match c {
+            c if c.is_whitespace() => {
+                self.bump_while(char::is_whitespace);
+                TokenKind::Whitespace
+            }
+            '/' if self.peek_second() == Some('/') => {
+                self.bump_while(|c| c != '\n');
+                TokenKind::LineComment
+            }
+            '/' if self.peek_second() == Some('*') => {
+                self.bump();
+                self.bump();
+                loop {
+                    match self.bump() {
+                        Some('*') if self.peek() == Some('/') => {
+                            self.bump();
+                            break;
+                        }
+                        Some(_) => {}
+                        None => return Err(CtoError::syntax("unterminated comment", start)),
+                    }
+                }
+                TokenKind::BlockComment
+            }
+            c if c.is_ascii_alphabetic() && self.after_from() => {
+                self.bump_while(|c| !c.is_whitespace());
+                TokenKind::Uri
+            }
+            '/' if self.after_regex_equals() => {
+                self.regex(start)?;
+                TokenKind::Regex
+            }
+            '"' => {
+                self.string(start)?;
+                TokenKind::String
+            }
+            c if c.is_ascii_digit()
+                && self.previous(0).is_some_and(|t| t.kind == TokenKind::At) =>
+            {
+                self.version();
+                TokenKind::Version
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && self.peek_second().is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                self.number();
+                TokenKind::Number
+            }
+            '-' if self.rest().starts_with("-->") => {
+                self.bump();
+                self.bump();
+                self.bump();
+                TokenKind::Arrow
+            }
+            c if is_identifier_start(c) => {
+                self.bump_while(is_identifier_part);
+                TokenKind::Identifier
+            }
+            _ => {
+                let kind = match c {
+                    '{' => TokenKind::LeftBrace,
+                    '}' => TokenKind::RightBrace,
+                    '[' => TokenKind::LeftBracket,
+                    ']' => TokenKind::RightBracket,
+                    '(' => TokenKind::LeftParen,
+                    ')' => TokenKind::RightParen,
+                    ',' => TokenKind::Comma,
+                    '.' => TokenKind::Dot,
+                    '@' => TokenKind::At,
+                    '=' => TokenKind::Equals,
+                    '*' => TokenKind::Star,
+                    other => return Err(self.error(format!("unexpected character '{other}'"))),
+                };
+                self.bump();
+                kind
+            }
+        };
+        self.tokens.push(Token {
+            kind,
+            text: &self.source[start.offset..self.position.offset],
+            start,
+            end: self.position,
+        });
+        Ok(())
+    }
+
+    /// Whether the tokens so far end in `regex =`.
+    fn after_regex_equals(&self) -> bool {
+        self.previous(0)
+            .is_some_and(|t| t.kind == TokenKind::Equals)
+            && self
+                .previous(1)
+                .is_some_and(|t| t.kind == TokenKind::Identifier && t.text == "regex")
+    }
+
+    /// Whether the tokens so far end in `from` and what follows has a URI
+    /// scheme, such as `https:`.
+    fn after_from(&self) -> bool {
+        self.previous(0)
+            .is_some_and(|t| t.kind == TokenKind::Identifier && t.text == "from")
+            && self
+                .rest()
+                .split(|c: char| !(c.is_ascii_alphanumeric() || "+.-".contains(c)))
+                .next()
+                .is_some_and(|scheme| self.rest()[scheme.len()..].starts_with(':'))
+    }
+
+    fn regex(&mut self, start: Position) -> Result<()> {
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    if self.bump().is_none() {
+                        break;
+                    }
+                }
+                Some('/') => {
+                    self.bump_while(|c| c.is_ascii_alphabetic());
+                    return Ok(());
+                }
+                Some('\n') | None => break,
+                Some(_) => {}
+            }
+        }
+        Err(CtoError::syntax("unterminated regular expression", start))
+    }
+
+    fn string(&mut self, start: Position) -> Result<()> {
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    if self.bump().is_none() {
+                        break;
+                    }
+                }
+                Some('"') => return Ok(()),
+                Some('\n') | None => break,
+                Some(_) => {}
+            }
+        }
+        Err(CtoError::syntax("unterminated string", start))
+    }
+
+    fn version(&mut self) {
+        self.bump_while(|c| c.is_ascii_digit() || c == '.');
+        // A pre-release or build suffix, such as `-rc.1` or `+build.5`.
+        while matches!(self.peek(), Some('-' | '+')) {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_alphanumeric() || c == '-');
+            while self.peek() == Some('.') && self.peek_second().is_some_and(|c| c.is_ascii_digit())
+            {
+                self.bump();
+                self.bump_while(|c| c.is_ascii_alphanumeric() || c == '-');
+            }
+        }
+        // The dot before an imported name belongs to the import, not the
+        // version.
+        let version = &self.source[..self.position.offset];
+        if version.ends_with('.') {
+            self.position.offset -= 1;
+            self.position.column -= 1;
+        }
+    }
+
+    fn number(&mut self) {
+        if self.peek() == Some('-') {
+            self.bump();
+        }
+        self.bump_while(|c| c.is_ascii_digit());
+        if self.peek() == Some('.') && self.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_digit());
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let exponent = self.rest()[1..].trim_start_matches(['+', '-']);
+            if exponent.starts_with(|c: char| c.is_ascii_digit()) {
+                self.bump();
+                if matches!(self.peek(), Some('+' | '-')) {
+                    self.bump();
+                }
+                self.bump_while(|c| c.is_ascii_digit());
+            }
+        }
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TokenKind, tokenize};
+
+    fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .filter(|token| !token.kind.is_trivia())
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn a_versioned_import_splits_before_the_type() {
+        assert_eq!(
+            kinds("import org.acme@1.0.0-rc.1.{Person}"),
+            vec![
+                (TokenKind::Identifier, "import"),
+                (TokenKind::Identifier, "org"),
+                (TokenKind::Dot, "."),
+                (TokenKind::Identifier, "acme"),
+                (TokenKind::At, "@"),
+                (TokenKind::Version, "1.0.0-rc.1"),
+                (TokenKind::Dot, "."),
+                (TokenKind::LeftBrace, "{"),
+                (TokenKind::Identifier, "Person"),
+                (TokenKind::RightBrace, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_slash_after_regex_equals_is_a_regex() {
+        assert_eq!(
+            kinds(r#"o String a regex=/^[a-z\/]+$/i length=[-1, 2.5]"#)[3..],
+            [
+                (TokenKind::Identifier, "regex"),
+                (TokenKind::Equals, "="),
+                (TokenKind::Regex, r"/^[a-z\/]+$/i"),
+                (TokenKind::Identifier, "length"),
+                (TokenKind::Equals, "="),
+                (TokenKind::LeftBracket, "["),
+                (TokenKind::Number, "-1"),
+                (TokenKind::Comma, ","),
+                (TokenKind::Number, "2.5"),
+                (TokenKind::RightBracket, "]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_kept_and_positions_counted() {
+        let tokens = tokenize("// note\n/* a\nb */ --> x").unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::LineComment);
+        assert_eq!(tokens[2].kind, TokenKind::BlockComment);
+        let arrow = tokens.iter().find(|t| t.kind == TokenKind::Arrow).unwrap();
+        assert_eq!((arrow.start.line, arrow.start.column), (3, 6));
+        assert!(tokenize("\"open").is_err());
+    }
+}
@@ -0,0 +1,32 @@
+//! # concerto-cto
+//!
+//! Reads and writes models in CTO, the Concerto language's own syntax. The
+//! core crate loads models as their metamodel JSON AST; this crate turns CTO
+//! source into that AST and back:
+//!
+//! ```
+//! let ast = concerto_cto::parse("namespace org.acme@1.0.0\nconcept Person {\n  o String name\n}\n")?;
+//! let mut manager = concerto_core::ModelManager::new()?;
+//! manager.add_model(&ast, Some("person.cto".to_string()))?;
+//! assert!(concerto_cto::print(&ast)?.starts_with("namespace org.acme@1.0.0"));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`format`] rewrites CTO source in a canonical layout, keeping its
+//! comments.
+//!
+//! A source that does not parse is a [`CtoError::Syntax`] with the line and
+//! column at fault; it converts into the core's
+//! [`IllegalModel`](concerto_core::ConcertoError::IllegalModel).
+
+mod cst;
+pub mod error;
+mod format;
+pub mod lexer;
+mod parser;
+mod printer;
+
+pub use error::{CtoError, Result};
+pub use format::format;
+pub use parser::{ParseOptions, parse, parse_with};
+pub use printer::print;
@@ -0,0 +1,952 @@
+//! Parses CTO source into the metamodel's JSON AST.
+//!
+//! The parser is a plain recursive descent over the lexer's tokens, one
+//! function per construct of the grammar. It builds the same JSON the
+//! JavaScript `@accordproject/concerto-cto` parser does, `$class` for
+//! `$class`, so the result loads into a
+//! [`ModelManager`](concerto_core::ModelManager) directly. Declarations,
+//! fields, enum members, map keys and values and decorators carry a
+//! `location` unless [`ParseOptions::locations`] is turned off.
+//!
+//! The parser checks syntax only. A field typed by a name nothing declares,
+//! or a namespace without a version, parses; loading the model is what
+//! rejects it.
+
+use serde_json::{Map, Value};
+
+use crate::error::{CtoError, Result};
+use crate::lexer::{Position, Token, TokenKind, tokenize};
+
+/// The namespace of the metamodel the AST is written in.
+const METAMODEL: &str = "concerto.metamodel@1.0.0";
+
+/// The primitives a field, scalar or map can be declared as.
+const PRIMITIVES: &[&str] = &["String", "Boolean", "DateTime", "Integer", "Long", "Double"];
+
+/// How to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Whether to record where each construct is in the source.
+    pub locations: bool,
+    /// The source file, recorded in each location as its `source`.
+    pub source_uri: Option<String>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            locations: true,
+            source_uri: None,
+        }
+    }
+}
+
+/// Parses a CTO model into its JSON AST, with locations.
+pub fn parse(source: &str) -> Result<Value> {
+    parse_with(source, &ParseOptions::default())
+}
+
+/// Parses a CTO model into its JSON AST.
+pub fn parse_with(source: &str, options: &ParseOptions) -> Result<Value> {
+    let tokens: Vec<Token<'_>> = tokenize(source)?
+        .into_iter()
+        .filter(|token| !token.kind.is_trivia())
+        .collect();
+    let end = tokens.last().map_or(
+        Position {
+            line: 1,
+            column: 1,
+            offset: 0,
+        },
+        |token| token.end,
+    );
+    let mut parser = Parser {
+        tokens,
+        index: 0,
+        end,
+        options,
+    };
+    parser.model()
+}
+
+struct Parser<'s, 'o> {
+    tokens: Vec<Token<'s>>,
+    index: usize,
+    /// Where the input ends, for an error there.
+    end: Position,
+    options: &'o ParseOptions,
+}
+
+/// A field's modifiers, before they are checked against its type.
+#[derive(Default)]
+struct Modifiers<'s> {
+    optional: bool,
+    default: Option<Token<'s>>,
+    regex: Option<Token<'s>>,
+    length: Option<(Option<Token<'s>>, Option<Token<'s>>)>,
+    range: Option<(Option<Token<'s>>, Option<Token<'s>>)>,
+}
+
+impl<'s> Parser<'s, '_> {
+    // Tokens.
+
+    fn peek(&self) -> Option<&Token<'s>> {
+        self.tokens.get(self.index)
+    }
+
+    fn peek_kind(&self) -> Option<TokenKind> {
+        self.peek().map(|token| token.kind)
+    }
+
+    fn peek_is(&self, kind: TokenKind) -> bool {
+        self.peek_kind() == Some(kind)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek()
+            .is_some_and(|token| token.kind == TokenKind::Identifier && token.text == keyword)
+    }
+
+    /// Where the next token starts, or the end of the input.
+    fn here(&self) -> Position {
+        self.peek().map_or(self.end, |token| token.start)
+    }
+
+    /// Where the last token taken ends.
+    fn last_end(&self) -> Position {
+        self.index
+            .checked_sub(1)
+            .map_or(self.end, |index| self.tokens[index].end)
+    }
+
+    fn advance(&mut self) -> Token<'s> {
+        let token = self.tokens[self.index];
+        self.index += 1;
+        token
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> Option<Token<'s>> {
+        self.peek_is(kind).then(|| self.advance())
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<Token<'s>> {
+        match self.eat(kind) {
+            Some(token) => Ok(token),
+            None => Err(self.expected(what)),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("'{keyword}'")))
+        }
+    }
+
+    fn identifier(&mut self, what: &str) -> Result<&'s str> {
+        Ok(self.expect(TokenKind::Identifier, what)?.text)
+    }
+
+    /// An error for finding something other than `what`.
+    fn expected(&self, what: &str) -> CtoError {
+        let found = match self.peek() {
+            Some(token) => format!("'{}'", token.text),
+            None => "the end of the input".to_string(),
+        };
+        CtoError::syntax(format!("expected {what}, found {found}"), self.here())
+    }
+
+    // The model.
+
+    fn model(&mut self) -> Result<Value> {
+        let mut model = node("Model");
+        if self.eat_keyword("concerto") {
+            self.expect_keyword("version")?;
+            let version = self.expect(TokenKind::String, "a version string")?;
+            model.insert("concertoVersion".into(), string_literal(&version)?);
+        }
+        let decorators = self.decorators()?;
+        if !decorators.is_empty() {
+            model.insert("decorators".into(), Value::Array(decorators));
+        }
+        self.expect_keyword("namespace")?;
+        let (namespace, _) = self.namespace(false)?;
+        model.insert("namespace".into(), Value::String(namespace));
+
+        let mut imports = Vec::new();
+        while self.peek_keyword("import") {
+            imports.push(self.import()?);
+        }
+        model.insert("imports".into(), Value::Array(imports));
+
+        let mut declarations = Vec::new();
+        while self.peek().is_some() {
+            declarations.push(self.declaration()?);
+        }
+        model.insert("declarations".into(), Value::Array(declarations));
+        Ok(Value::Object(model))
+    }
+
+    /// A namespace such as `org.acme@1.0.0`. In an import, where the
+    /// namespace is followed by a `.` and what it imports, that `.` is left
+    /// for the caller; an unversioned namespace there gives back its last
+    /// segment as the imported name.
+    fn namespace(&mut self, in_import: bool) -> Result<(String, Option<&'s str>)> {
+        let mut namespace = self.identifier("a namespace")?.to_string();
+        loop {
+            if self.eat(TokenKind::At).is_some() {
+                let version = self.expect(TokenKind::Version, "a version, such as 1.0.0")?;
+                namespace.push('@');
+                namespace.push_str(version.text);
+                return Ok((namespace, None));
+            }
+            if !self.peek_is(TokenKind::Dot) {
+                break;
+            }
+            let after_dot = self.tokens.get(self.index + 1).map(|token| token.kind);
+            if in_import && after_dot != Some(TokenKind::Identifier) {
+                break;
+            }
+            self.advance();
+            let segment = self.identifier("a namespace segment")?;
+            let last = in_import && !self.peek_is(TokenKind::Dot) && !self.peek_is(TokenKind::At);
+            if last {
+                return Ok((namespace, Some(segment)));
+            }
+            namespace.push('.');
+            namespace.push_str(segment);
+        }
+        if in_import {
+            return Ok((namespace, None));
+        }
+        Ok((namespace, None))
+    }
+
+    fn import(&mut self) -> Result<Value> {
+        self.expect_keyword("import")?;
+        let (namespace, unversioned_name) = self.namespace(true)?;
+        let mut import = match unversioned_name {
+            Some(name) => {
+                let mut import = node("ImportType");
+                import.insert("name".into(), name.into());
+                import.insert("namespace".into(), namespace.into());
+                import
+            }
+            None => {
+                self.expect(TokenKind::Dot, "'.' and the imported types")?;
+                if self.eat(TokenKind::Star).is_some() {
+                    let mut import = node("ImportAll");
+                    import.insert("namespace".into(), namespace.into());
+                    import
+                } else if self.eat(TokenKind::LeftBrace).is_some() {
+                    self.import_types(namespace)?
+                } else {
+                    let name = self.identifier("an imported type, '{' or '*'")?;
+                    let mut import = node("ImportType");
+                    import.insert("name".into(), name.into());
+                    import.insert("namespace".into(), namespace.into());
+                    import
+                }
+            }
+        };
+        if self.eat_keyword("from") {
+            let uri = self.expect(TokenKind::Uri, "a URI")?;
+            import.insert("uri".into(), uri.text.into());
+        }
+        Ok(Value::Object(import))
+    }
+
+    /// The `{A, B as C}` of an import, after the brace.
+    fn import_types(&mut self, namespace: String) -> Result<Map<String, Value>> {
+        let mut types = Vec::new();
+        let mut aliases = Vec::new();
+        loop {
+            let name = self.identifier("an imported type")?;
+            types.push(Value::from(name));
+            if self.eat_keyword("as") {
+                let alias = self.identifier("an alias")?;
+                let mut aliased = node("AliasedType");
+                aliased.insert("name".into(), name.into());
+                aliased.insert("aliasedName".into(), alias.into());
+                aliases.push(Value::Object(aliased));
+            }
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(TokenKind::RightBrace, "',' or '}'")?;
+        let mut import = node("ImportTypes");
+        import.insert("namespace".into(), namespace.into());
+        import.insert("types".into(), Value::Array(types));
+        if !aliases.is_empty() {
+            import.insert("aliasedTypes".into(), Value::Array(aliases));
+        }
+        Ok(import)
+    }
+
+    // Declarations.
+
+    fn declaration(&mut self) -> Result<Value> {
+        let start = self.here();
+        let decorators = self.decorators()?;
+        let is_abstract = self.eat_keyword("abstract");
+        let keyword = self
+            .peek()
+            .filter(|token| token.kind == TokenKind::Identifier)
+            .map(|token| token.text);
+        let mut declaration = match keyword {
+            Some(keyword @ ("concept" | "asset" | "participant" | "transaction" | "event")) => {
+                self.advance();
+                self.class_declaration(keyword, is_abstract)?
+            }
+            Some(keyword @ ("enum" | "scalar" | "map")) if is_abstract => {
+                return Err(CtoError::syntax(
+                    format!("{keyword} declarations cannot be abstract"),
+                    self.here(),
+                ));
+            }
+            Some("enum") => {
+                self.advance();
+                self.enum_declaration()?
+            }
+            Some("scalar") => {
+                self.advance();
+                self.scalar_declaration()?
+            }
+            Some("map") => {
+                self.advance();
+                self.map_declaration()?
+            }
+            _ => return Err(self.expected("a declaration")),
+        };
+        self.finish(&mut declaration, decorators, start);
+        Ok(Value::Object(declaration))
+    }
+
+    fn class_declaration(
+        &mut self,
+        keyword: &str,
+        is_abstract: bool,
+    ) -> Result<Map<String, Value>> {
+        let kind = match keyword {
+            "concept" => "ConceptDeclaration",
+            "asset" => "AssetDeclaration",
+            "participant" => "ParticipantDeclaration",
+            "transaction" => "TransactionDeclaration",
+            _ => "EventDeclaration",
+        };
+        let mut declaration = node(kind);
+        declaration.insert("name".into(), self.identifier("a name")?.into());
+        declaration.insert("isAbstract".into(), is_abstract.into());
+        let mut identified = None;
+        let mut super_type = None;
+        loop {
+            if identified.is_none() && self.eat_keyword("identified") {
+                identified = Some(if self.eat_keyword("by") {
+                    let mut by = node("IdentifiedBy");
+                    by.insert("name".into(), self.identifier("a field name")?.into());
+                    by
+                } else {
+                    node("Identified")
+                });
+            } else if super_type.is_none() && self.eat_keyword("extends") {
+                super_type = Some(type_identifier(self.identifier("a type")?));
+            } else {
+                break;
+            }
+        }
+        if let Some(identified) = identified {
+            declaration.insert("identified".into(), Value::Object(identified));
+        }
+        if let Some(super_type) = super_type {
+            declaration.insert("superType".into(), super_type);
+        }
+        self.expect(TokenKind::LeftBrace, "'{'")?;
+        let mut properties = Vec::new();
+        while self.eat(TokenKind::RightBrace).is_none() {
+            properties.push(self.property()?);
+        }
+        declaration.insert("properties".into(), Value::Array(properties));
+        Ok(declaration)
+    }
+
+    fn enum_declaration(&mut self) -> Result<Map<String, Value>> {
+        let mut declaration = node("EnumDeclaration");
+        declaration.insert("name".into(), self.identifier("a name")?.into());
+        self.expect(TokenKind::LeftBrace, "'{'")?;
+        let mut members = Vec::new();
+        while self.eat(TokenKind::RightBrace).is_none() {
+            let start = self.here();
+            let decorators = self.decorators()?;
+            if !self.eat_keyword("o") {
+                return Err(self.expected("an enum member, starting with 'o'"));
+            }
+            let mut member = node("EnumProperty");
+            member.insert("name".into(), self.identifier("a member name")?.into());
+            self.finish(&mut member, decorators, start);
+            members.push(Value::Object(member));
+        }
+        declaration.insert("properties".into(), Value::Array(members));
+        Ok(declaration)
+    }
+
+    fn scalar_declaration(&mut self) -> Result<Map<String, Value>> {
+        let name = self.identifier("a name")?;
+        self.expect_keyword("extends")?;
+        let primitive_at = self.here();
+        let primitive = self.identifier("a primitive type")?;
+        if !PRIMITIVES.contains(&primitive) {
+            return Err(CtoError::syntax(
+                format!("a scalar extends a primitive type, not {primitive}"),
+                primitive_at,
+            ));
+        }
+        let mut scalar = node(&format!("{primitive}Scalar"));
+        scalar.insert("name".into(), name.into());
+        let modifiers = self.modifiers()?;
+        if modifiers.optional {
+            return Err(CtoError::syntax(
+                "a scalar cannot be optional",
+                self.last_end(),
+            ));
+        }
+        self.apply_modifiers(&mut scalar, primitive, modifiers, true)?;
+        Ok(scalar)
+    }
+
+    fn map_declaration(&mut self) -> Result<Map<String, Value>> {
+        let mut declaration = node("MapDeclaration");
+        declaration.insert("name".into(), self.identifier("a name")?.into());
+        self.expect(TokenKind::LeftBrace, "'{'")?;
+
+        let start = self.here();
+        let decorators = self.decorators()?;
+        self.expect_keyword("o")?;
+        let key_at = self.here();
+        let key_type = self.identifier("the key type")?;
+        let mut key = match key_type {
+            "String" | "DateTime" => node(&format!("{key_type}MapKeyType")),
+            primitive if PRIMITIVES.contains(&primitive) => {
+                return Err(CtoError::syntax(
+                    format!("a map key is a String, a DateTime or a scalar, not {primitive}"),
+                    key_at,
+                ));
+            }
+            declared => {
+                let mut key = node("ObjectMapKeyType");
+                key.insert("type".into(), type_identifier(declared));
+                key
+            }
+        };
+        self.finish(&mut key, decorators, start);
+
+        let start = self.here();
+        let decorators = self.decorators()?;
+        let mut value = if self.eat(TokenKind::Arrow).is_some() {
+            let mut value = node("RelationshipMapValueType");
+            value.insert(
+                "type".into(),
+                type_identifier(self.identifier("the value type")?),
+            );
+            value
+        } else {
+            self.expect_keyword("o")?;
+            match self.identifier("the value type")? {
+                primitive if PRIMITIVES.contains(&primitive) => {
+                    node(&format!("{primitive}MapValueType"))
+                }
+                declared => {
+                    let mut value = node("ObjectMapValueType");
+                    value.insert("type".into(), type_identifier(declared));
+                    value
+                }
+            }
+        };
+        self.finish(&mut value, decorators, start);
+        self.expect(TokenKind::RightBrace, "'}'")?;
+
+        declaration.insert("key".into(), Value::Object(key));
+        declaration.insert("value".into(), Value::Object(value));
+        Ok(declaration)
+    }
+
+    // Fields.
+
+    fn property(&mut self) -> Result<Value> {
+        let start = self.here();
+        let decorators = self.decorators()?;
+        let relationship = if self.eat(TokenKind::Arrow).is_some() {
+            true
+        } else if self.eat_keyword("o") {
+            false
+        } else {
+            return Err(self.expected("a field, starting with 'o' or '-->'"));
+        };
+        let type_name = self.identifier("a type")?;
+        let is_array = self.eat(TokenKind::LeftBracket).is_some();
+        if is_array {
+            self.expect(TokenKind::RightBracket, "']'")?;
+        }
+        let name = self.identifier("a field name")?;
+
+        let kind = if relationship {
+            "RelationshipProperty".to_string()
+        } else if PRIMITIVES.contains(&type_name) {
+            format!("{type_name}Property")
+        } else {
+            "ObjectProperty".to_string()
+        };
+        let mut property = node(&kind);
+        property.insert("name".into(), name.into());
+        property.insert("isArray".into(), is_array.into());
+        let modifiers = self.modifiers()?;
+        property.insert("isOptional".into(), modifiers.optional.into());
+        if kind == "ObjectProperty" || relationship {
+            property.insert("type".into(), type_identifier(type_name));
+        }
+        let primitive = match relationship {
+            true => "Relationship",
+            false => type_name,
+        };
+        self.apply_modifiers(&mut property, primitive, modifiers, false)?;
+        self.finish(&mut property, decorators, start);
+        Ok(Value::Object(property))
+    }
+
+    /// The `optional`, `default=`, `regex=`, `length=` and `range=` after a
+    /// field or scalar, in any order.
+    fn modifiers(&mut self) -> Result<Modifiers<'s>> {
+        let mut modifiers = Modifiers::default();
+        loop {
+            let at = self.here();
+            let repeated = || CtoError::syntax("a modifier is given twice", at);
+            if self.eat_keyword("optional") {
+                if modifiers.optional {
+                    return Err(repeated());
+                }
+                modifiers.optional = true;
+            } else if self.eat_keyword("default") {
+                self.expect(TokenKind::Equals, "'='")?;
+                let value = match self.peek_kind() {
+                    Some(TokenKind::String | TokenKind::Number | TokenKind::Identifier) => {
+                        self.advance()
+                    }
+                    _ => return Err(self.expected("a default value")),
+                };
+                if modifiers.default.replace(value).is_some() {
+                    return Err(repeated());
+                }
+            } else if self.eat_keyword("regex") {
+                self.expect(TokenKind::Equals, "'='")?;
+                let regex = self.expect(TokenKind::Regex, "a regular expression")?;
+                if modifiers.regex.replace(regex).is_some() {
+                    return Err(repeated());
+                }
+            } else if self.eat_keyword("length") {
+                self.expect(TokenKind::Equals, "'='")?;
+                if modifiers.length.replace(self.bounds()?).is_some() {
+                    return Err(repeated());
+                }
+            } else if self.eat_keyword("range") {
+                self.expect(TokenKind::Equals, "'='")?;
+                if modifiers.range.replace(self.bounds()?).is_some() {
+                    return Err(repeated());
+                }
+            } else {
+                return Ok(modifiers);
+            }
+        }
+    }
+
+    /// `[lower, upper]`, either of which may be left out.
+    fn bounds(&mut self) -> Result<(Option<Token<'s>>, Option<Token<'s>>)> {
+        self.expect(TokenKind::LeftBracket, "'['")?;
+        let lower = self.eat(TokenKind::Number);
+        self.expect(TokenKind::Comma, "','")?;
+        let upper = self.eat(TokenKind::Number);
+        self.expect(TokenKind::RightBracket, "a number or ']'")?;
+        Ok((lower, upper))
+    }
+
+    /// Checks a field's or scalar's modifiers against its type, `primitive`
+    /// (or `Relationship`, or a declared type's name), and records them.
+    fn apply_modifiers(
+        &self,
+        node: &mut Map<String, Value>,
+        primitive: &str,
+        modifiers: Modifiers<'s>,
+        scalar: bool,
+    ) -> Result<()> {
+        let refuse = |what: &str, token: &Token<'_>| {
+            let subject = match scalar {
+                true => "scalar",
+                false => "field",
+            };
+            Err(CtoError::syntax(
+                format!("a {primitive} {subject} cannot have {what}"),
+                token.start,
+            ))
+        };
+        if let Some(default) = &modifiers.default {
+            let value = match (primitive, default.kind) {
+                ("String", TokenKind::String) => string_literal(default)?,
+                ("DateTime", TokenKind::String) if scalar => string_literal(default)?,
+                ("Boolean", TokenKind::Identifier) if matches!(default.text, "true" | "false") => {
+                    Value::Bool(default.text == "true")
+                }
+                ("Integer" | "Long", TokenKind::Number) => integer(default, primitive)?,
+                ("Double", TokenKind::Number) => double(default)?,
+                ("Relationship" | "DateTime", _) => return refuse("a default", default),
+                (declared, TokenKind::String) if !PRIMITIVES.contains(&declared) => {
+                    string_literal(default)?
+                }
+                (declared, TokenKind::Identifier) if !PRIMITIVES.contains(&declared) => {
+                    Value::String(default.text.to_string())
+                }
+                _ => return refuse(&format!("the default {}", default.text), default),
+            };
+            node.insert("defaultValue".into(), value);
+        }
+        if let Some(regex) = &modifiers.regex {
+            if primitive != "String" {
+                return refuse("a regex", regex);
+            }
+            let (pattern, flags) = split_regex(regex.text);
+            let mut validator = self::node("StringRegexValidator");
+            validator.insert("pattern".into(), pattern.into());
+            validator.insert("flags".into(), flags.into());
+            node.insert("validator".into(), Value::Object(validator));
+        }
+        if let Some((lower, upper)) = &modifiers.length {
+            if primitive != "String" {
+                let at = lower.as_ref().or(upper.as_ref());
+                return match at {
+                    Some(token) => refuse("a length", token),
+                    None => Err(CtoError::syntax(
+                        format!("a {primitive} cannot have a length"),
+                        self.last_end(),
+                    )),
+                };
+            }
+            let mut validator = self::node("StringLengthValidator");
+            if let Some(lower) = lower {
+                validator.insert("minLength".into(), integer(lower, "Integer")?);
+            }
+            if let Some(upper) = upper {
+                validator.insert("maxLength".into(), integer(upper, "Integer")?);
+            }
+            node.insert("lengthValidator".into(), Value::Object(validator));
+        }
+        if let Some((lower, upper)) = &modifiers.range {
+            let bound = |token: &Token<'_>| match primitive {
+                "Double" => double(token),
+                _ => integer(token, primitive),
+            };
+            let kind = match primitive {
+                "Integer" => "IntegerDomainValidator",
+                "Long" => "LongDomainValidator",
+                "Double" => "DoubleDomainValidator",
+                _ => {
+                    return Err(CtoError::syntax(
+                        format!("a {primitive} cannot have a range"),
+                        self.last_end(),
+                    ));
+                }
+            };
+            let mut validator = self::node(kind);
+            if let Some(lower) = lower {
+                validator.insert("lower".into(), bound(lower)?);
+            }
+            if let Some(upper) = upper {
+                validator.insert("upper".into(), bound(upper)?);
+            }
+            node.insert("validator".into(), Value::Object(validator));
+        }
+        Ok(())
+    }
+
+    // Decorators.
+
+    fn decorators(&mut self) -> Result<Vec<Value>> {
+        let mut decorators = Vec::new();
+        while self.peek_is(TokenKind::At) {
+            let start = self.here();
+            self.advance();
+            let mut decorator = node("Decorator");
+            decorator.insert("name".into(), self.identifier("a decorator name")?.into());
+            if self.eat(TokenKind::LeftParen).is_some() {
+                let mut arguments = Vec::new();
+                if self.eat(TokenKind::RightParen).is_none() {
+                    loop {
+                        arguments.push(self.decorator_argument()?);
+                        if self.eat(TokenKind::Comma).is_none() {
+                            break;
+                        }
+                    }
+                    self.expect(TokenKind::RightParen, "',' or ')'")?;
+                }
+                decorator.insert("arguments".into(), Value::Array(arguments));
+            }
+            self.finish(&mut decorator, Vec::new(), start);
+            decorators.push(Value::Object(decorator));
+        }
+        Ok(decorators)
+    }
+
+    fn decorator_argument(&mut self) -> Result<Value> {
+        let start = self.here();
+        let mut argument = match self.peek_kind() {
+            Some(TokenKind::String) => {
+                let token = self.advance();
+                let mut argument = node("DecoratorString");
+                argument.insert("value".into(), string_literal(&token)?);
+                argument
+            }
+            Some(TokenKind::Number) => {
+                let token = self.advance();
+                let mut argument = node("DecoratorNumber");
+                argument.insert("value".into(), double(&token)?);
+                argument
+            }
+            Some(TokenKind::Identifier)
+                if self.peek_keyword("true") || self.peek_keyword("false") =>
+            {
+                let token = self.advance();
+                let mut argument = node("DecoratorBoolean");
+                argument.insert("value".into(), Value::Bool(token.text == "true"));
+                argument
+            }
+            Some(TokenKind::Identifier) => {
+                let name = self.advance().text;
+                let is_array = self.eat(TokenKind::LeftBracket).is_some();
+                if is_array {
+                    self.expect(TokenKind::RightBracket, "']'")?;
+                }
+                let mut argument = node("DecoratorTypeReference");
+                argument.insert("type".into(), type_identifier(name));
+                argument.insert("isArray".into(), is_array.into());
+                argument
+            }
+            _ => return Err(self.expected("a string, number, boolean or type")),
+        };
+        self.finish(&mut argument, Vec::new(), start);
+        Ok(Value::Object(argument))
+    }
+
+    /// Adds a node's decorators and its location, which runs from `start` to
+    /// the last token taken.
+    fn finish(&self, node: &mut Map<String, Value>, decorators: Vec<Value>, start: Position) {
+        if !decorators.is_empty() {
+            node.insert("decorators".into(), Value::Array(decorators));
+        }
+        if self.options.locations {
+            let mut range = self::node("Range");
+            range.insert("start".into(), position(start));
+            range.insert("end".into(), position(self.last_end()));
+            if let Some(source) = &self.options.source_uri {
+                range.insert("source".into(), source.clone().into());
+            }
+            node.insert("location".into(), Value::Object(range));
+        }
+    }
+}
+
+/// A metamodel node, holding only its `$class` so far.
+fn node(class: &str) -> Map<String, Value> {
+    let mut node = Map::new();
+    node.insert("$class".into(), format!("{METAMODEL}.{class}").into());
+    node
+}
+
+fn type_identifier(name: &str) -> Value {
+    let mut identifier = node("TypeIdentifier");
+    identifier.insert("name".into(), name.into());
+    Value::Object(identifier)
+}
+
+fn position(at: Position) -> Value {
+    let mut position = node("Position");
+    position.insert("line".into(), at.line.into());
+    position.insert("column".into(), at.column.into());
+    position.insert("offset".into(), at.offset.into());
+    Value::Object(position)
+}
+
+/// The value of a string literal. CTO strings escape as JSON's do.
+fn string_literal(token: &Token<'_>) -> Result<Value> {
+    serde_json::from_str::<String>(token.text)
+        .map(Value::String)
+        .map_err(|error| CtoError::syntax(format!("invalid string: {error}"), token.start))
+}
+
+fn integer(token: &Token<'_>, primitive: &str) -> Result<Value> {
+    let value: i64 = token
+        .text
+        .parse()
+        .map_err(|_| CtoError::syntax(format!("{} is not an integer", token.text), token.start))?;
+    if primitive != "Long" && i32::try_from(value).is_err() {
+        return Err(CtoError::syntax(
+            format!("{value} is out of range for an Integer"),
+            token.start,
+        ));
+    }
+    Ok(Value::from(value))
+}
+
+fn double(token: &Token<'_>) -> Result<Value> {
+    token
+        .text
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .map(Value::from)
+        .ok_or_else(|| CtoError::syntax(format!("{} is not a number", token.text), token.start))
+}
+
+/// Splits `/pattern/flags` into its pattern and flags.
+fn split_regex(literal: &str) -> (&str, &str) {
+    let close = literal.rfind('/').unwrap_or(0);
+    (&literal[1..close.max(1)], &literal[close + 1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::ModelManager;
+    use serde_json::json;
+
+    use super::{ParseOptions, parse, parse_with};
+    use crate::CtoError;
+
+    fn without_locations(source: &str) -> serde_json::Value {
+        let options = ParseOptions {
+            locations: false,
+            source_uri: None,
+        };
+        parse_with(source, &options).unwrap()
+    }
+
+    #[test]
+    fn a_model_parses_to_the_metamodel_ast() {
+        let ast = without_locations(
+            r#"
+            @Model("people")
+            namespace org.acme@1.0.0
+            import org.base@2.1.0.{Thing, Place as Location}
+
+            /** A person. */
+            @Form
+            abstract participant Person identified by email extends Thing {
+              o String email regex=/^[^@]+@[^@]+$/i length=[3,]
+              o Integer age range=[0, 150] default=18 optional
+              o Double[] scores
+              --> Person[] friends optional
+              @Hidden o Location home
+            }
+            "#,
+        );
+        assert_eq!(
+            ast,
+            json!({
+                "$class": "concerto.metamodel@1.0.0.Model",
+                "decorators": [{
+                    "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Model",
+                    "arguments": [{ "$class": "concerto.metamodel@1.0.0.DecoratorString", "value": "people" }]
+                }],
+                "namespace": "org.acme@1.0.0",
+                "imports": [{
+                    "$class": "concerto.metamodel@1.0.0.ImportTypes", "namespace": "org.base@2.1.0",
+                    "types": ["Thing", "Place"],
+                    "aliasedTypes": [{ "$class": "concerto.metamodel@1.0.0.AliasedType", "name": "Place", "aliasedName": "Location" }]
+                }],
+                "declarations": [{
+                    "$class": "concerto.metamodel@1.0.0.ParticipantDeclaration",
+                    "name": "Person", "isAbstract": true,
+                    "identified": { "$class": "concerto.metamodel@1.0.0.IdentifiedBy", "name": "email" },
+                    "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Thing" },
+                    "properties": [
+                        { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "email",
+                          "isArray": false, "isOptional": false,
+                          "validator": { "$class": "concerto.metamodel@1.0.0.StringRegexValidator", "pattern": "^[^@]+@[^@]+$", "flags": "i" },
+                          "lengthValidator": { "$class": "concerto.metamodel@1.0.0.StringLengthValidator", "minLength": 3 } },
+                        { "$class": "concerto.metamodel@1.0.0.IntegerProperty", "name": "age",
+                          "isArray": false, "isOptional": true, "defaultValue": 18,
+                          "validator": { "$class": "concerto.metamodel@1.0.0.IntegerDomainValidator", "lower": 0, "upper": 150 } },
+                        { "$class": "concerto.metamodel@1.0.0.DoubleProperty", "name": "scores",
+                          "isArray": true, "isOptional": false },
+                        { "$class": "concerto.metamodel@1.0.0.RelationshipProperty", "name": "friends",
+                          "isArray": true, "isOptional": true,
+                          "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Person" } },
+                        { "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "home",
+                          "isArray": false, "isOptional": false,
+                          "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Location" },
+                          "decorators": [{ "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Hidden" }] }
+                    ],
+                    "decorators": [{ "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Form" }]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn enums_scalars_and_maps_load_into_a_model_manager() {
+        let ast = parse(
+            r#"
+            namespace org.acme@1.0.0
+            enum Grade { o JUNIOR @Rare o SENIOR }
+            scalar Sku extends String regex=/^[A-Z]{3}$/ default="AAA"
+            scalar Count extends Long range=[0,]
+            map Prices { o Sku o Double }
+            map Owners { o String o Owner }
+            asset Owner identified { o Grade grade default="JUNIOR" }
+            "#,
+        )
+        .unwrap();
+        let declarations = ast["declarations"].as_array().unwrap();
+        assert_eq!(
+            declarations[3]["key"]["$class"],
+            "concerto.metamodel@1.0.0.ObjectMapKeyType"
+        );
+        assert_eq!(
+            declarations[4]["value"]["$class"],
+            "concerto.metamodel@1.0.0.ObjectMapValueType"
+        );
+        assert_eq!(
+            declarations[0]["location"]["start"],
+            json!({ "$class": "concerto.metamodel@1.0.0.Position", "line": 3, "column": 13, "offset": 50 })
+        );
+        let mut manager = ModelManager::new().unwrap();
+        manager.add_model(&ast, None).unwrap();
+        manager.validate_models().unwrap();
+        manager
+            .validate_instance(&json!({
+                "$class": "org.acme@1.0.0.Owner", "$identifier": "o1", "grade": "SENIOR"
+            }))
+            .unwrap();
+    }
+
+    #[test]
+    fn syntax_errors_say_where_and_what() {
+        let error = parse("namespace org.acme@1.0.0\nconcept Person {\n  o String\n}").unwrap_err();
+        assert_eq!(
+            error,
+            CtoError::Syntax {
+                message: "expected a field name, found '}'".into(),
+                line: 4,
+                column: 1,
+                offset: 53,
+            }
+        );
+        let error = parse("namespace a@1.0.0 concept A { o Boolean b range=[0,1] }").unwrap_err();
+        assert!(error.to_string().contains("a Boolean cannot have a range"));
+        assert!(parse("namespace a@1.0.0 abstract enum E {}").is_err());
+    }
+}
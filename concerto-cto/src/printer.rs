@@ -0,0 +1,423 @@
+//! Prints a metamodel JSON AST as CTO source.
+//!
+//! The output is canonical rather than a copy of any original source: two
+//! spaces of indentation, each decorator on a line of its own above what it
+//! decorates, and a blank line between declarations. Locations are ignored,
+//! and the AST holds no comments, so none are printed. Parsing the output
+//! gives back the AST, locations aside.
+
+use std::fmt::Write;
+
+use serde_json::{Map, Value};
+
+use crate::error::{CtoError, Result};
+
+const INDENT: &str = "  ";
+
+/// Prints a model's JSON AST as CTO.
+pub fn print(model: &Value) -> Result<String> {
+    let model = object(model, "the model")?;
+    let mut out = String::new();
+    if let Some(version) = model.get("concertoVersion").and_then(Value::as_str) {
+        writeln!(out, "concerto version {}", quote(version)).unwrap();
+        out.push('\n');
+    }
+    decorators(&mut out, model, "")?;
+    writeln!(out, "namespace {}", string(model, "namespace")?).unwrap();
+
+    let imports = array(model, "imports")?;
+    if !imports.is_empty() {
+        out.push('\n');
+    }
+    for import in imports {
+        print_import(&mut out, object(import, "an import")?)?;
+    }
+    for declaration in array(model, "declarations")? {
+        out.push('\n');
+        print_declaration(&mut out, object(declaration, "a declaration")?)?;
+    }
+    Ok(out)
+}
+
+fn print_import(out: &mut String, import: &Map<String, Value>) -> Result<()> {
+    let namespace = string(import, "namespace")?;
+    match class(import)? {
+        "ImportType" => write!(out, "import {namespace}.{}", string(import, "name")?).unwrap(),
+        "ImportAll" => write!(out, "import {namespace}.*").unwrap(),
+        "ImportTypes" => {
+            let aliases = array(import, "aliasedTypes")?
+                .iter()
+                .map(|alias| {
+                    let alias = object(alias, "an aliased type")?;
+                    Ok((string(alias, "name")?, string(alias, "aliasedName")?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let types = array(import, "types")?
+                .iter()
+                .map(|name| {
+                    let name = name
+                        .as_str()
+                        .ok_or_else(|| CtoError::unprintable("an imported type is not a string"))?;
+                    Ok(
+                        match aliases.iter().find(|(original, _)| *original == name) {
+                            Some((_, alias)) => format!("{name} as {alias}"),
+                            None => name.to_string(),
+                        },
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+            write!(out, "import {namespace}.{{{}}}", types.join(", ")).unwrap();
+        }
+        other => return Err(unknown("import", other)),
+    }
+    if let Some(uri) = import.get("uri").and_then(Value::as_str) {
+        write!(out, " from {uri}").unwrap();
+    }
+    out.push('\n');
+    Ok(())
+}
+
+fn print_declaration(out: &mut String, declaration: &Map<String, Value>) -> Result<()> {
+    decorators(out, declaration, "")?;
+    let name = string(declaration, "name")?;
+    match class(declaration)? {
+        kind @ ("ConceptDeclaration"
+        | "AssetDeclaration"
+        | "ParticipantDeclaration"
+        | "TransactionDeclaration"
+        | "EventDeclaration") => {
+            if declaration.get("isAbstract").and_then(Value::as_bool) == Some(true) {
+                out.push_str("abstract ");
+            }
+            let keyword = kind.trim_end_matches("Declaration").to_lowercase();
+            write!(out, "{keyword} {name}").unwrap();
+            if let Some(identified) = declaration.get("identified") {
+                let identified = object(identified, "an identifier")?;
+                match class(identified)? {
+                    "Identified" => out.push_str(" identified"),
+                    "IdentifiedBy" => {
+                        write!(out, " identified by {}", string(identified, "name")?).unwrap();
+                    }
+                    other => return Err(unknown("identifier", other)),
+                }
+            }
+            if let Some(super_type) = declaration.get("superType") {
+                write!(out, " extends {}", type_name(super_type)?).unwrap();
+            }
+            out.push_str(" {\n");
+            for property in array(declaration, "properties")? {
+                print_property(out, object(property, "a field")?)?;
+            }
+            out.push_str("}\n");
+        }
+        "EnumDeclaration" => {
+            writeln!(out, "enum {name} {{").unwrap();
+            for member in array(declaration, "properties")? {
+                let member = object(member, "an enum member")?;
+                decorators(out, member, INDENT)?;
+                writeln!(out, "{INDENT}o {}", string(member, "name")?).unwrap();
+            }
+            out.push_str("}\n");
+        }
+        "MapDeclaration" => {
+            writeln!(out, "map {name} {{").unwrap();
+            let key = object(field(declaration, "key")?, "a map key")?;
+            decorators(out, key, INDENT)?;
+            let key_type = match class(key)? {
+                "ObjectMapKeyType" => type_name(field(key, "type")?)?,
+                other => primitive(other, "MapKeyType")?.to_string(),
+            };
+            writeln!(out, "{INDENT}o {key_type}").unwrap();
+            let value = object(field(declaration, "value")?, "a map value")?;
+            decorators(out, value, INDENT)?;
+            match class(value)? {
+                "ObjectMapValueType" => {
+                    writeln!(out, "{INDENT}o {}", type_name(field(value, "type")?)?).unwrap();
+                }
+                "RelationshipMapValueType" => {
+                    writeln!(out, "{INDENT}--> {}", type_name(field(value, "type")?)?).unwrap();
+                }
+                other => writeln!(out, "{INDENT}o {}", primitive(other, "MapValueType")?).unwrap(),
+            }
+            out.push_str("}\n");
+        }
+        scalar if scalar.ends_with("Scalar") => {
+            let primitive = primitive(scalar, "Scalar")?;
+            write!(out, "scalar {name} extends {primitive}").unwrap();
+            modifiers(out, declaration, primitive)?;
+            out.push('\n');
+        }
+        other => return Err(unknown("declaration", other)),
+    }
+    Ok(())
+}
+
+fn print_property(out: &mut String, property: &Map<String, Value>) -> Result<()> {
+    decorators(out, property, INDENT)?;
+    let (arrow, type_name) = match class(property)? {
+        "RelationshipProperty" => ("-->", type_name(field(property, "type")?)?),
+        "ObjectProperty" => ("o", type_name(field(property, "type")?)?),
+        other => ("o", primitive(other, "Property")?.to_string()),
+    };
+    let array = match property.get("isArray").and_then(Value::as_bool) {
+        Some(true) => "[]",
+        _ => "",
+    };
+    write!(
+        out,
+        "{INDENT}{arrow} {type_name}{array} {}",
+        string(property, "name")?
+    )
+    .unwrap();
+    let primitive = match arrow {
+        "-->" => "Relationship",
+        _ => type_name.as_str(),
+    };
+    modifiers(out, property, primitive)?;
+    if property.get("isOptional").and_then(Value::as_bool) == Some(true) {
+        out.push_str(" optional");
+    }
+    out.push('\n');
+    Ok(())
+}
+
+/// The `default=`, `regex=`, `range=` and `length=` of a field or scalar.
+fn modifiers(out: &mut String, node: &Map<String, Value>, primitive: &str) -> Result<()> {
+    if let Some(default) = node.get("defaultValue") {
+        let default = match default {
+            Value::String(text) => quote(text),
+            Value::Bool(_) | Value::Number(_) => default.to_string(),
+            _ => return Err(CtoError::unprintable("a default is not a literal")),
+        };
+        write!(out, " default={default}").unwrap();
+    }
+    if let Some(validator) = node.get("validator") {
+        let validator = object(validator, "a validator")?;
+        match class(validator)? {
+            "StringRegexValidator" => {
+                let flags = validator.get("flags").and_then(Value::as_str).unwrap_or("");
+                write!(out, " regex=/{}/{flags}", string(validator, "pattern")?).unwrap();
+            }
+            "IntegerDomainValidator" | "LongDomainValidator" | "DoubleDomainValidator" => {
+                bounds(out, "range", validator, "lower", "upper");
+            }
+            other => return Err(unknown("validator", other)),
+        }
+    }
+    if let Some(validator) = node.get("lengthValidator") {
+        if primitive != "String" {
+            return Err(CtoError::unprintable(format!(
+                "a {primitive} cannot have a length"
+            )));
+        }
+        bounds(
+            out,
+            "length",
+            object(validator, "a length")?,
+            "minLength",
+            "maxLength",
+        );
+    }
+    Ok(())
+}
+
+fn bounds(out: &mut String, keyword: &str, node: &Map<String, Value>, lower: &str, upper: &str) {
+    let bound = |name| node.get(name).map(Value::to_string).unwrap_or_default();
+    write!(out, " {keyword}=[{},{}]", bound(lower), bound(upper)).unwrap();
+}
+
+/// The decorators on a node, a line each, indented by `indent`.
+fn decorators(out: &mut String, node: &Map<String, Value>, indent: &str) -> Result<()> {
+    for decorator in array(node, "decorators")? {
+        let decorator = object(decorator, "a decorator")?;
+        write!(out, "{indent}@{}", string(decorator, "name")?).unwrap();
+        if let Some(arguments) = decorator.get("arguments") {
+            let arguments = arguments
+                .as_array()
+                .ok_or_else(|| CtoError::unprintable("decorator arguments are not an array"))?
+                .iter()
+                .map(decorator_argument)
+                .collect::<Result<Vec<_>>>()?;
+            write!(out, "({})", arguments.join(",")).unwrap();
+        }
+        out.push('\n');
+    }
+    Ok(())
+}
+
+fn decorator_argument(argument: &Value) -> Result<String> {
+    let argument = object(argument, "a decorator argument")?;
+    let value = field(argument, "value");
+    Ok(match class(argument)? {
+        "DecoratorString" => quote(
+            value?
+                .as_str()
+                .ok_or_else(|| CtoError::unprintable("a DecoratorString is not a string"))?,
+        ),
+        "DecoratorBoolean" => value?.to_string(),
+        "DecoratorNumber" => {
+            let number = value?
+                .as_f64()
+                .ok_or_else(|| CtoError::unprintable("a DecoratorNumber is not a number"))?;
+            // Decorator numbers are all doubles; `1` reads better than `1.0`.
+            if number.fract() == 0.0 && number.abs() < 1e15 {
+                format!("{}", number as i64)
+            } else {
+                number.to_string()
+            }
+        }
+        "DecoratorTypeReference" => {
+            let array = match argument.get("isArray").and_then(Value::as_bool) {
+                Some(true) => "[]",
+                _ => "",
+            };
+            format!("{}{array}", type_name(field(argument, "type")?)?)
+        }
+        other => return Err(unknown("decorator argument", other)),
+    })
+}
+
+// Reading the AST.
+
+fn object<'v>(value: &'v Value, what: &str) -> Result<&'v Map<String, Value>> {
+    value
+        .as_object()
+        .ok_or_else(|| CtoError::unprintable(format!("{what} is not an object")))
+}
+
+fn field<'v>(node: &'v Map<String, Value>, name: &str) -> Result<&'v Value> {
+    node.get(name).ok_or_else(|| {
+        let class = class(node).unwrap_or("a node");
+        CtoError::unprintable(format!("{class} has no {name}"))
+    })
+}
+
+fn string<'v>(node: &'v Map<String, Value>, name: &str) -> Result<&'v str> {
+    field(node, name)?
+        .as_str()
+        .ok_or_else(|| CtoError::unprintable(format!("{name} is not a string")))
+}
+
+/// An optional array field, empty when left out.
+fn array<'v>(node: &'v Map<String, Value>, name: &str) -> Result<&'v [Value]> {
+    match node.get(name) {
+        None => Ok(&[]),
+        Some(value) => value
+            .as_array()
+            .map(Vec::as_slice)
+            .ok_or_else(|| CtoError::unprintable(format!("{name} is not an array"))),
+    }
+}
+
+/// The short name of a node's `$class`, such as `StringProperty`.
+fn class(node: &Map<String, Value>) -> Result<&str> {
+    let class = node
+        .get("$class")
+        .and_then(Value::as_str)
+        .ok_or_else(|| CtoError::unprintable("a node has no $class"))?;
+    Ok(class.rsplit('.').next().unwrap_or(class))
+}
+
+/// The primitive a `$class` such as `StringProperty` names.
+fn primitive<'c>(class: &'c str, suffix: &str) -> Result<&'c str> {
+    class
+        .strip_suffix(suffix)
+        .filter(|primitive| {
+            ["String", "Boolean", "DateTime", "Integer", "Long", "Double"].contains(primitive)
+        })
+        .ok_or_else(|| unknown("type", class))
+}
+
+/// The name a TypeIdentifier gives. CTO names types by their short names
+/// only, so a namespace the AST records is dropped.
+fn type_name(identifier: &Value) -> Result<String> {
+    Ok(string(object(identifier, "a type")?, "name")?.to_string())
+}
+
+fn quote(text: &str) -> String {
+    serde_json::to_string(text).expect("a string serializes")
+}
+
+fn unknown(what: &str, class: &str) -> CtoError {
+    CtoError::unprintable(format!("unknown {what} {class}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::print;
+    use crate::{CtoError, ParseOptions, parse_with};
+
+    const MODEL: &str = r#"concerto version "^3.0.0"
+
+@Model("people",1,true)
+namespace org.acme@1.0.0
+
+import org.base@2.1.0.{Thing, Place as Location}
+import org.misc@1.0.0.* from https://models.acme.org/misc.cto
+
+/**
+ * A person.
+ */
+@Form(Person[])
+abstract participant Person identified by email extends Thing {
+  o String email regex=/^[^@\/]+@[^@]+$/i length=[3,]
+  o Integer age default=18 range=[0,150] optional
+  o Double ratio default=0.5 range=[,1.0]
+  @Hidden
+  --> Person[] friends optional
+  o Grade grade default="JUNIOR"
+}
+
+enum Grade {
+  o JUNIOR
+  @Rare
+  o SENIOR
+}
+
+scalar Sku extends String default="AAA" regex=/^[A-Z]{3}$/
+
+map Prices {
+  o Sku
+  o Double
+}
+
+map Owners {
+  o DateTime
+  --> Person
+}
+
+concept Empty {
+}
+"#;
+
+    #[test]
+    fn printing_a_parsed_model_gives_back_its_source() {
+        let options = ParseOptions {
+            locations: false,
+            source_uri: None,
+        };
+        let ast = parse_with(MODEL, &options).unwrap();
+        let printed = print(&ast).unwrap();
+        // The comment is the only thing lost.
+        assert_eq!(printed, MODEL.replace("/**\n * A person.\n */\n", ""));
+        assert_eq!(parse_with(&printed, &options).unwrap(), ast);
+    }
+
+    #[test]
+    fn an_unknown_class_cannot_be_printed() {
+        let error = print(&json!({
+            "$class": "concerto.metamodel@1.0.0.Model",
+            "namespace": "org.acme@1.0.0",
+            "declarations": [{ "$class": "concerto.metamodel@1.0.0.TableDeclaration", "name": "T" }]
+        }))
+        .unwrap_err();
+        assert_eq!(
+            error,
+            CtoError::Unprintable {
+                message: "unknown declaration TableDeclaration".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,113 @@
+#ifndef CONCERTO_H
+#define CONCERTO_H
+
+/* Generated by cbindgen from concerto-ffi. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of a call.
+typedef enum ConcertoStatus {
+  // The call succeeded.
+  CONCERTO_STATUS_OK = 0,
+  // A type could not be resolved.
+  CONCERTO_STATUS_TYPE_NOT_FOUND = 1,
+  // A namespace is not loaded.
+  CONCERTO_STATUS_NAMESPACE_NOT_FOUND = 2,
+  // A model is malformed, or clashes with one already loaded.
+  CONCERTO_STATUS_ILLEGAL_MODEL = 3,
+  // The loaded models do not pass semantic validation.
+  CONCERTO_STATUS_VALIDATION_FAILED = 4,
+  // An instance does not conform to its type.
+  CONCERTO_STATUS_INVALID_INSTANCE = 5,
+  // An argument is null where a value is required, or is not UTF-8, or
+  // is not the JSON it should be.
+  CONCERTO_STATUS_INVALID_ARGUMENT = 6,
+  // The library panicked. This is a bug; the handle should be freed.
+  CONCERTO_STATUS_PANIC = 7,
+} ConcertoStatus;
+
+// A set of loaded models, behind an opaque handle.
+typedef struct ConcertoModelManager ConcertoModelManager;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a manager with the built-in system model loaded, and stores its
+// handle in `*out`.
+//
+// # Safety
+//
+// `out` must be valid for a write. `error` must be null or valid for a
+// write.
+enum ConcertoStatus concerto_model_manager_new(struct ConcertoModelManager **out, char **error);
+
+// Releases a manager. Null is ignored.
+//
+// # Safety
+//
+// `manager` must be null or a handle from [`concerto_model_manager_new`]
+// that has not been freed, and no other call may be using it.
+void concerto_model_manager_free(struct ConcertoModelManager *manager);
+
+// Loads a model from the JSON text of its AST. `file_name` may be null.
+//
+// # Safety
+//
+// `manager` must be a live handle with no other call in flight. `json`
+// must be a NUL-terminated string, and `file_name` null or one. `error`
+// must be null or valid for a write.
+enum ConcertoStatus concerto_model_manager_add_model(struct ConcertoModelManager *manager,
+                                                     const char *json,
+                                                     const char *file_name,
+                                                     char **error);
+
+// Checks that the loaded models hang together.
+//
+// # Safety
+//
+// `manager` must be a live handle. `error` must be null or valid for a
+// write.
+enum ConcertoStatus concerto_model_manager_validate_models(const struct ConcertoModelManager *manager,
+                                                           char **error);
+
+// Validates the JSON text of an instance against the type its `$class`
+// names.
+//
+// # Safety
+//
+// `manager` must be a live handle, and `json` a NUL-terminated string.
+// `error` must be null or valid for a write.
+enum ConcertoStatus concerto_model_manager_validate_instance(const struct ConcertoModelManager *manager,
+                                                             const char *json,
+                                                             char **error);
+
+// Validates the JSON text of an instance declared to be a `type_name`,
+// which its `$class` may narrow to a subtype.
+//
+// # Safety
+//
+// `manager` must be a live handle, and `type_name` and `json`
+// NUL-terminated strings. `error` must be null or valid for a write.
+enum ConcertoStatus concerto_model_manager_validate_instance_as(const struct ConcertoModelManager *manager,
+                                                                const char *type_name,
+                                                                const char *json,
+                                                                char **error);
+
+// Releases a string the library handed out. Null is ignored.
+//
+// # Safety
+//
+// `string` must be null or a string from this library that has not been
+// freed.
+void concerto_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CONCERTO_H */
@@ -0,0 +1,465 @@
+//! # concerto-ffi
+//!
+//! A C ABI over [`concerto_core`], for embedding model validation in services
+//! written in C, C++, Go or anything else that can call C. The declarations
+//! are in `include/concerto.h`, which cbindgen generates from this crate.
+//!
+//! A [`ConcertoModelManager`] is an opaque handle, created by
+//! [`concerto_model_manager_new`] and released by
+//! [`concerto_model_manager_free`]. Every other call takes the handle and
+//! returns a [`ConcertoStatus`]: `CONCERTO_STATUS_OK`, or a code naming the
+//! kind of failure, one per [`ConcertoError`] variant plus two for problems
+//! with the call itself.
+//!
+//! Strings go in and come out as NUL-terminated UTF-8. A string passed in is
+//! only borrowed for the length of the call. A string handed out belongs to
+//! the caller, who releases it with [`concerto_string_free`]. Each fallible
+//! call takes an optional `char **error`: on failure, unless it is null, it
+//! receives a JSON object describing the problem, with a `kind`, a `message`
+//! and, where known, the `path` to the offending value of an instance, the
+//! `expectedType`, `class`, `typeName`, `namespace`, `fileName` and
+//! `location`. On success it is set to null.
+//!
+//! ```c
+//! ConcertoModelManager *manager = NULL;
+//! char *error = NULL;
+//! if (concerto_model_manager_new(&manager, &error) != CONCERTO_STATUS_OK) { ... }
+//! if (concerto_model_manager_add_model(manager, model_json, "acme.json", &error) != CONCERTO_STATUS_OK) {
+//!     fprintf(stderr, "%s\n", error);
+//!     concerto_string_free(error);
+//! }
+//! ConcertoStatus status = concerto_model_manager_validate_instance(manager, instance_json, &error);
+//! concerto_model_manager_free(manager);
+//! ```
+//!
+//! A handle may be shared between threads for the validation calls, which
+//! only read it; loading a model has to be done with no other call in flight.
+//! A panic inside the library is caught at the boundary and reported as
+//! `CONCERTO_STATUS_PANIC`.
+
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+
+use concerto_core::{ConcertoError, ModelManager};
+use serde_json::{Value, json};
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConcertoStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A type could not be resolved.
+    TypeNotFound = 1,
+    /// A namespace is not loaded.
+    NamespaceNotFound = 2,
+    /// A model is malformed, or clashes with one already loaded.
+    IllegalModel = 3,
+    /// The loaded models do not pass semantic validation.
+    ValidationFailed = 4,
+    /// An instance does not conform to its type.
+    InvalidInstance = 5,
+    /// An argument is null where a value is required, or is not UTF-8, or
+    /// is not the JSON it should be.
+    InvalidArgument = 6,
+    /// The library panicked. This is a bug; the handle should be freed.
+    Panic = 7,
+}
+
+/// A set of loaded models, behind an opaque handle.
+pub struct ConcertoModelManager {
+    manager: ModelManager,
+}
+
+/// Creates a manager with the built-in system model loaded, and stores its
+/// handle in `*out`.
+///
+/// # Safety
+///
+/// `out` must be valid for a write. `error` must be null or valid for a
+/// write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn concerto_model_manager_new(
+    out: *mut *mut ConcertoModelManager,
+    error: *mut *mut c_char,
+) -> ConcertoStatus {
+    // SAFETY: the caller guarantees `error` is null or writable.
+    unsafe {
+        call(error, || {
+            if out.is_null() {
+                return Err(Failure::Argument("out is null".into()));
+            }
+            let manager = ModelManager::new()?;
+            let handle = Box::into_raw(Box::new(ConcertoModelManager { manager }));
+            // SAFETY: the caller guarantees `out` is writable.
+            *out = handle;
+            Ok(())
+        })
+    }
+}
+
+/// Releases a manager. Null is ignored.
+///
+/// # Safety
+///
+/// `manager` must be null or a handle from [`concerto_model_manager_new`]
+/// that has not been freed, and no other call may be using it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn concerto_model_manager_free(manager: *mut ConcertoModelManager) {
+    if !manager.is_null() {
+        // SAFETY: the handle came from `Box::into_raw` and is freed once.
+        drop(unsafe { Box::from_raw(manager) });
+    }
+}
+
+/// Loads a model from the JSON text of its AST. `file_name` may be null.
+///
+/// # Safety
+///
+/// `manager` must be a live handle with no other call in flight. `json`
+/// must be a NUL-terminated string, and `file_name` null or one. `error`
+/// must be null or valid for a write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn concerto_model_manager_add_model(
+    manager: *mut ConcertoModelManager,
+    json: *const c_char,
+    file_name: *const c_char,
+    error: *mut *mut c_char,
+) -> ConcertoStatus {
+    // SAFETY: the caller guarantees the pointers are as documented.
+    unsafe {
+        call(error, || {
+            let manager = manager.as_mut().ok_or_else(|| null("manager"))?;
+            let model = parse(json, "json")?;
+            let file_name = match file_name.is_null() {
+                true => None,
+                false => Some(text(file_name, "file_name")?.to_string()),
+            };
+            Ok(manager.manager.add_model(&model, file_name)?)
+        })
+    }
+}
+
+/// Checks that the loaded models hang together.
+///
+/// # Safety
+///
+/// `manager` must be a live handle. `error` must be null or valid for a
+/// write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn concerto_model_manager_validate_models(
+    manager: *const ConcertoModelManager,
+    error: *mut *mut c_char,
+) -> ConcertoStatus {
+    // SAFETY: the caller guarantees the pointers are as documented.
+    unsafe {
+        call(error, || {
+            let manager = manager.as_ref().ok_or_else(|| null("manager"))?;
+            Ok(manager.manager.validate_models()?)
+        })
+    }
+}
+
+/// Validates the JSON text of an instance against the type its `$class`
+/// names.
+///
+/// # Safety
+///
+/// `manager` must be a live handle, and `json` a NUL-terminated string.
+/// `error` must be null or valid for a write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn concerto_model_manager_validate_instance(
+    manager: *const ConcertoModelManager,
+    json: *const c_char,
+    error: *mut *mut c_char,
+) -> ConcertoStatus {
+    // SAFETY: the caller guarantees the pointers are as documented.
+    unsafe {
+        call(error, || {
+            let manager = manager.as_ref().ok_or_else(|| null("manager"))?;
+            let instance = parse(json, "json")?;
+            Ok(manager.manager.validate_instance(&instance)?)
+        })
+    }
+}
+
+/// Validates the JSON text of an instance declared to be a `type_name`,
+/// which its `$class` may narrow to a subtype.
+///
+/// # Safety
+///
+/// `manager` must be a live handle, and `type_name` and `json`
+/// NUL-terminated strings. `error` must be null or valid for a write.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn concerto_model_manager_validate_instance_as(
+    manager: *const ConcertoModelManager,
+    type_name: *const c_char,
+    json: *const c_char,
+    error: *mut *mut c_char,
+) -> ConcertoStatus {
+    // SAFETY: the caller guarantees the pointers are as documented.
+    unsafe {
+        call(error, || {
+            let manager = manager.as_ref().ok_or_else(|| null("manager"))?;
+            let type_name = text(type_name, "type_name")?;
+            let instance = parse(json, "json")?;
+            Ok(manager.manager.validate_instance_as(type_name, &instance)?)
+        })
+    }
+}
+
+/// Releases a string the library handed out. Null is ignored.
+///
+/// # Safety
+///
+/// `string` must be null or a string from this library that has not been
+/// freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn concerto_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: the string came from `CString::into_raw` and is freed once.
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Why a call failed.
+enum Failure {
+    Concerto(ConcertoError),
+    Argument(String),
+}
+
+impl From<ConcertoError> for Failure {
+    fn from(error: ConcertoError) -> Self {
+        Self::Concerto(error)
+    }
+}
+
+/// Runs the body of a call, catching a panic, and reports how it went
+/// through the status and `*error`.
+///
+/// # Safety
+///
+/// `error` must be null or valid for a write.
+unsafe fn call(
+    error: *mut *mut c_char,
+    body: impl FnOnce() -> Result<(), Failure>,
+) -> ConcertoStatus {
+    let (status, info) = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => (ConcertoStatus::Ok, None),
+        Ok(Err(Failure::Concerto(error))) => (status_of(&error), serde_json::to_value(&error).ok()),
+        Ok(Err(Failure::Argument(message))) => (
+            ConcertoStatus::InvalidArgument,
+            Some(json!({ "kind": "InvalidArgument", "message": message })),
+        ),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "the library panicked".into());
+            (
+                ConcertoStatus::Panic,
+                Some(json!({ "kind": "Panic", "message": message })),
+            )
+        }
+    };
+    if !error.is_null() {
+        // JSON text escapes NUL, so it always makes a C string.
+        let string = info.map_or(ptr::null_mut(), |info| {
+            CString::new(info.to_string()).map_or(ptr::null_mut(), CString::into_raw)
+        });
+        // SAFETY: the caller guarantees `error` is writable.
+        unsafe { *error = string };
+    }
+    status
+}
+
+fn status_of(error: &ConcertoError) -> ConcertoStatus {
+    match error {
+        ConcertoError::TypeNotFound { .. } => ConcertoStatus::TypeNotFound,
+        ConcertoError::NamespaceNotFound { .. } => ConcertoStatus::NamespaceNotFound,
+        ConcertoError::IllegalModel { .. } => ConcertoStatus::IllegalModel,
+        ConcertoError::ValidationFailed { .. } => ConcertoStatus::ValidationFailed,
+        ConcertoError::InvalidInstance { .. } => ConcertoStatus::InvalidInstance,
+    }
+}
+
+fn null(what: &str) -> Failure {
+    Failure::Argument(format!("{what} is null"))
+}
+
+/// Borrows a string argument.
+///
+/// # Safety
+///
+/// `string` must be null or NUL-terminated, and outlive the call.
+unsafe fn text<'a>(string: *const c_char, what: &str) -> Result<&'a str, Failure> {
+    if string.is_null() {
+        return Err(null(what));
+    }
+    // SAFETY: the caller guarantees a NUL-terminated string.
+    unsafe { CStr::from_ptr(string) }
+        .to_str()
+        .map_err(|error| Failure::Argument(format!("{what} is not UTF-8: {error}")))
+}
+
+/// Parses a JSON string argument.
+///
+/// # Safety
+///
+/// As for [`text`].
+unsafe fn parse(string: *const c_char, what: &str) -> Result<Value, Failure> {
+    // SAFETY: passed on from the caller.
+    let string = unsafe { text(string, what) }?;
+    serde_json::from_str(string)
+        .map_err(|error| Failure::Argument(format!("{what} is not JSON: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString, c_char};
+    use std::ptr;
+
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    /// Takes the error a call handed out, as JSON.
+    fn take(error: *mut c_char) -> Value {
+        assert!(!error.is_null());
+        let text = unsafe { CStr::from_ptr(error) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { concerto_string_free(error) };
+        serde_json::from_str(&text).unwrap()
+    }
+
+    fn c(value: &Value) -> CString {
+        CString::new(value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn a_model_is_loaded_and_instances_validated() {
+        let model = c(&json!({
+            "$class": "concerto.metamodel@1.0.0.Model",
+            "namespace": "org.acme@1.0.0",
+            "declarations": [
+                { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Person",
+                  "isAbstract": false,
+                  "properties": [
+                    { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "email",
+                      "isArray": false, "isOptional": false }
+                  ] }
+            ]
+        }));
+        let valid = c(&json!({ "$class": "org.acme@1.0.0.Person", "email": "ada@acme.org" }));
+        let invalid = c(&json!({ "$class": "org.acme@1.0.0.Person", "email": 3 }));
+        let file_name = CString::new("acme.json").unwrap();
+        let mut manager = ptr::null_mut();
+        let mut error = ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                concerto_model_manager_new(&mut manager, &mut error),
+                ConcertoStatus::Ok
+            );
+            assert_eq!(
+                concerto_model_manager_add_model(
+                    manager,
+                    model.as_ptr(),
+                    file_name.as_ptr(),
+                    &mut error
+                ),
+                ConcertoStatus::Ok
+            );
+            assert!(error.is_null());
+            assert_eq!(
+                concerto_model_manager_validate_models(manager, &mut error),
+                ConcertoStatus::Ok
+            );
+            assert_eq!(
+                concerto_model_manager_validate_instance(manager, valid.as_ptr(), ptr::null_mut()),
+                ConcertoStatus::Ok
+            );
+
+            assert_eq!(
+                concerto_model_manager_validate_instance(manager, invalid.as_ptr(), &mut error),
+                ConcertoStatus::InvalidInstance
+            );
+            let info = take(error);
+            assert_eq!(info["kind"], "InvalidInstance");
+            assert_eq!(info["path"], "/email");
+            assert_eq!(info["expectedType"], "String");
+
+            let person = CString::new("org.acme@1.0.0.Person").unwrap();
+            let untyped = c(&json!({ "email": "ada@acme.org" }));
+            assert_eq!(
+                concerto_model_manager_validate_instance_as(
+                    manager,
+                    person.as_ptr(),
+                    untyped.as_ptr(),
+                    &mut error
+                ),
+                ConcertoStatus::Ok
+            );
+
+            assert_eq!(
+                concerto_model_manager_add_model(manager, model.as_ptr(), ptr::null(), &mut error),
+                ConcertoStatus::IllegalModel
+            );
+            assert_eq!(take(error)["kind"], "IllegalModel");
+            concerto_model_manager_free(manager);
+        }
+    }
+
+    #[test]
+    fn bad_arguments_are_reported() {
+        let mut manager = ptr::null_mut();
+        let mut error = ptr::null_mut();
+        let not_json = CString::new("{").unwrap();
+        unsafe {
+            assert_eq!(
+                concerto_model_manager_new(&mut manager, &mut error),
+                ConcertoStatus::Ok
+            );
+            assert_eq!(
+                concerto_model_manager_validate_instance(manager, not_json.as_ptr(), &mut error),
+                ConcertoStatus::InvalidArgument
+            );
+            assert_eq!(take(error)["kind"], "InvalidArgument");
+            assert_eq!(
+                concerto_model_manager_validate_models(ptr::null(), &mut error),
+                ConcertoStatus::InvalidArgument
+            );
+            assert_eq!(take(error)["message"], "manager is null");
+            concerto_model_manager_free(manager);
+            concerto_model_manager_free(ptr::null_mut());
+            concerto_string_free(ptr::null_mut());
+        }
+    }
+
+    /// Regenerates the header and compares it with the one checked in. Run
+    /// with `CONCERTO_FFI_BLESS=1` to write it instead.
+    #[test]
+    fn header_is_up_to_date() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap();
+        let mut generated = Vec::new();
+        cbindgen::Builder::new()
+            .with_crate(crate_dir)
+            .with_config(config)
+            .generate()
+            .unwrap()
+            .write(&mut generated);
+        let path = format!("{crate_dir}/include/concerto.h");
+        if std::env::var_os("CONCERTO_FFI_BLESS").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let checked_in = std::fs::read(&path).unwrap_or_default();
+        assert!(
+            generated == checked_in,
+            "include/concerto.h is stale; rerun this test with CONCERTO_FFI_BLESS=1"
+        );
+    }
+}
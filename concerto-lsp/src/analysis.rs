@@ -0,0 +1,585 @@
+//! What the server answers about a place in a file: the type a name refers
+//! to, where that type is declared, what it looks like, what could be typed
+//! there, and what renaming it would change.
+//!
+//! Everything works from the file's tokens and the loaded models. A name in
+//! a type position, such as after `o`, `-->` or `extends`, or a declaration's
+//! own name, resolves through [`ModelManager::resolve_type_name`] in the
+//! file's namespace; a name in an import resolves in the namespace it is
+//! imported from.
+
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use concerto_core::model_util::{
+    PRIMITIVE_TYPES, is_primitive_type, is_valid_identifier, namespace_of, qualify, short_name,
+};
+use concerto_core::{Declaration, ModelFile, ModelManager, Property};
+use concerto_cto::lexer::{Token, TokenKind};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Hover, HoverContents, Location, MarkupContent, MarkupKind,
+    Position, TextEdit, Uri, WorkspaceEdit,
+};
+use serde_json::Value;
+
+use crate::document::Document;
+use crate::workspace::{DECLARATION_KEYWORDS, Workspace, declaration_names};
+
+/// Where the type named at `position` is declared.
+pub fn definition(workspace: &Workspace, uri: &str, position: Position) -> Option<Location> {
+    let document = workspace.document(uri)?;
+    let tokens = document.tokens();
+    let index = document.identifier_at(&tokens, position)?;
+    let fqn = reference(workspace, document, &tokens, index)?;
+    declaration_location(workspace, &fqn)
+}
+
+/// A description of the type named at `position`: its kind, its super types,
+/// and its properties, inherited ones included.
+pub fn hover(workspace: &Workspace, uri: &str, position: Position) -> Option<Hover> {
+    let document = workspace.document(uri)?;
+    let tokens = document.tokens();
+    let index = document.identifier_at(&tokens, position)?;
+    let fqn = reference(workspace, document, &tokens, index)?;
+    let value = describe(workspace.manager(), &fqn)?;
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(document.token_range(&tokens[index])),
+    })
+}
+
+/// What could be typed at `position`: after an `@`, the decorators used
+/// anywhere in the workspace; anywhere else, the types the file can see.
+pub fn completion(workspace: &Workspace, uri: &str, position: Position) -> Vec<CompletionItem> {
+    let Some(document) = workspace.document(uri) else {
+        return Vec::new();
+    };
+    let offset = document.offset(position);
+    let before =
+        document.text()[..offset].trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+    if before.ends_with('@') {
+        return decorator_completions(workspace);
+    }
+    let Some(model_file) = document
+        .ast()
+        .and_then(|ast| ModelFile::from_json(ast, None).ok())
+    else {
+        return Vec::new();
+    };
+    type_completions(workspace.manager(), &model_file)
+}
+
+/// The edits that rename the declaration named at `position` to `new_name`,
+/// in every file: its declaration, each reference to it, and each import of
+/// it. A file that imports it under an alias keeps the alias. The rename is
+/// refused if any file that would then see it as `new_name` already
+/// declares or imports a type by that name.
+// `WorkspaceEdit` keys its changes by `Uri`, which clippy takes for mutable.
+#[allow(clippy::mutable_key_type)]
+pub fn rename(
+    workspace: &Workspace,
+    uri: &str,
+    position: Position,
+    new_name: &str,
+) -> Result<Option<WorkspaceEdit>, String> {
+    let Some(document) = workspace.document(uri) else {
+        return Ok(None);
+    };
+    let tokens = document.tokens();
+    let Some(fqn) = document
+        .identifier_at(&tokens, position)
+        .and_then(|index| reference(workspace, document, &tokens, index))
+    else {
+        return Ok(None);
+    };
+    let namespace = namespace_of(&fqn);
+    let name = short_name(&fqn);
+    if workspace.owner(namespace).is_none() {
+        return Err(format!("{fqn} is not declared in this workspace"));
+    }
+    if !is_valid_identifier(new_name) {
+        return Err(format!("{new_name} is not a valid name"));
+    }
+
+    let mut changes = HashMap::new();
+    for (uri, document) in workspace.documents() {
+        let sees_it_as_name = document
+            .namespace()
+            .and_then(|namespace| workspace.manager().model_file(namespace))
+            .filter(|model_file| {
+                model_file.namespace() == namespace
+                    || model_file.imports().iter().any(|import| {
+                        import.local_names().contains(&name)
+                            && import.resolve(name).as_deref() == Some(fqn.as_str())
+                    })
+            });
+        if let Some(model_file) = sees_it_as_name
+            && model_file.resolve_local_type(new_name).is_some()
+        {
+            return Err(format!(
+                "{} already declares or imports {new_name}",
+                model_file.namespace()
+            ));
+        }
+        let tokens = document.tokens();
+        let edits: Vec<TextEdit> = (0..tokens.len())
+            .filter(|&index| tokens[index].text == name && !is_alias(&tokens, index))
+            .filter(|&index| {
+                reference(workspace, document, &tokens, index).as_deref() == Some(fqn.as_str())
+            })
+            .map(|index| TextEdit {
+                range: document.token_range(&tokens[index]),
+                new_text: new_name.to_string(),
+            })
+            .collect();
+        if !edits.is_empty()
+            && let Ok(uri) = Uri::from_str(uri)
+        {
+            changes.insert(uri, edits);
+        }
+    }
+    Ok(Some(WorkspaceEdit {
+        changes: Some(changes),
+        ..WorkspaceEdit::default()
+    }))
+}
+
+/// The fully-qualified name of the declared type the identifier at `index`
+/// refers to, if it is in a place that names a type.
+fn reference(
+    workspace: &Workspace,
+    document: &Document,
+    tokens: &[Token<'_>],
+    index: usize,
+) -> Option<String> {
+    let token = tokens.get(index)?;
+    if let Some(import) = imports(tokens)
+        .into_iter()
+        .find(|import| import.names.contains(&index))
+    {
+        let name = if is_alias(tokens, index) {
+            tokens[index - 2].text
+        } else {
+            token.text
+        };
+        return Some(qualify(&import.namespace, name));
+    }
+    let previous = index
+        .checked_sub(1)
+        .and_then(|previous| tokens.get(previous))?;
+    let in_type_position = match previous.kind {
+        TokenKind::Arrow | TokenKind::LeftParen | TokenKind::Comma => true,
+        TokenKind::Identifier => {
+            previous.text == "o"
+                || previous.text == "extends"
+                || DECLARATION_KEYWORDS.contains(&previous.text)
+        }
+        _ => false,
+    };
+    if !in_type_position || is_primitive_type(token.text) {
+        return None;
+    }
+    workspace
+        .manager()
+        .resolve_type_name(document.namespace()?, token.text)
+        .ok()
+        .filter(|fqn| workspace.manager().get_declaration(fqn).is_ok())
+}
+
+/// Whether the identifier at `index` is the alias in an `A as B` import.
+fn is_alias(tokens: &[Token<'_>], index: usize) -> bool {
+    index
+        .checked_sub(1)
+        .is_some_and(|previous| tokens[previous].text == "as")
+}
+
+/// An import: the namespace it imports from, and the indexes of the tokens
+/// that name what it imports, aliases included.
+struct ImportSpan {
+    namespace: String,
+    names: Vec<usize>,
+}
+
+/// The imports among `tokens`.
+fn imports(tokens: &[Token<'_>]) -> Vec<ImportSpan> {
+    let mut spans = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        if tokens[index].kind != TokenKind::Identifier || tokens[index].text != "import" {
+            index += 1;
+            continue;
+        }
+        index += 1;
+        // `org.acme@1.0.0`, or `org.acme.Name` without a version.
+        let start = index;
+        while tokens
+            .get(index)
+            .is_some_and(|token| token.kind == TokenKind::Identifier)
+        {
+            index += 1;
+            let kind = |at: usize| tokens.get(at).map(|token| token.kind);
+            match kind(index) {
+                Some(TokenKind::At) if kind(index + 1) == Some(TokenKind::Version) => {
+                    index += 2;
+                    break;
+                }
+                Some(TokenKind::Dot) if kind(index + 1) == Some(TokenKind::Identifier) => {
+                    index += 1;
+                }
+                _ => break,
+            }
+        }
+        let mut namespace: String = tokens[start..index]
+            .iter()
+            .map(|token| token.text)
+            .collect();
+        let mut names = Vec::new();
+        if namespace.contains('@') {
+            // `.Name`, `.{A, B as C}` or `.*`.
+            index += 1;
+            if tokens.get(index).map(|token| token.kind) == Some(TokenKind::LeftBrace) {
+                while tokens
+                    .get(index)
+                    .is_some_and(|token| token.kind != TokenKind::RightBrace)
+                {
+                    if tokens[index].kind == TokenKind::Identifier && tokens[index].text != "as" {
+                        names.push(index);
+                    }
+                    index += 1;
+                }
+            } else if tokens.get(index).map(|token| token.kind) == Some(TokenKind::Identifier) {
+                names.push(index);
+            }
+        } else if index > start {
+            // An unversioned namespace ends with the imported name.
+            names.push(index - 1);
+            namespace = tokens[start..index - 2]
+                .iter()
+                .map(|token| token.text)
+                .collect();
+        }
+        spans.push(ImportSpan { namespace, names });
+    }
+    spans
+}
+
+/// The name token of the declaration of `fqn`, in the file that declares it.
+fn declaration_location(workspace: &Workspace, fqn: &str) -> Option<Location> {
+    let uri = workspace.owner(namespace_of(fqn))?;
+    let document = workspace.document(uri)?;
+    let tokens = document.tokens();
+    let token = declaration_names(&tokens).find(|token| token.text == short_name(fqn))?;
+    Some(Location {
+        uri: Uri::from_str(uri).ok()?,
+        range: document.token_range(token),
+    })
+}
+
+/// The Markdown shown on hovering over `fqn`.
+fn describe(manager: &ModelManager, fqn: &str) -> Option<String> {
+    let declaration = manager.get_declaration(fqn).ok()?;
+    let mut header = String::new();
+    if declaration
+        .as_class()
+        .is_some_and(|class| class.is_abstract())
+    {
+        header.push_str("abstract ");
+    }
+    header.push_str(&format!("{} {fqn}", keyword(declaration)));
+    let body: Vec<String> = match declaration {
+        Declaration::Class(_) => manager
+            .get_all_properties(fqn)
+            .ok()?
+            .into_iter()
+            .map(property)
+            .collect(),
+        Declaration::Enum(declaration) => declaration
+            .properties
+            .iter()
+            .map(|member| format!("o {}", member.name))
+            .collect(),
+        Declaration::Scalar(scalar) => {
+            header.push_str(&format!(" extends {}", scalar.scalar_type()));
+            Vec::new()
+        }
+        Declaration::Map(map) => {
+            let key = map.key_type().map(|key| key.name.as_str());
+            let value = map.value_type().map(|value| value.name.as_str());
+            vec![
+                map_side(map.key_kind(), key),
+                map_side(map.value_kind(), value),
+            ]
+        }
+    };
+    let mut value = format!("```concerto\n{header}");
+    if !declaration.is_scalar_declaration() {
+        value.push_str(" {\n");
+        for line in body {
+            value.push_str(&format!("  {line}\n"));
+        }
+        value.push('}');
+    }
+    value.push_str("\n```");
+    let ancestors = manager.super_chain(fqn).unwrap_or_default();
+    if ancestors.len() > 1 {
+        let chain: Vec<String> = ancestors[1..]
+            .iter()
+            .map(|(name, _)| format!("`{name}`"))
+            .collect();
+        value.push_str(&format!("\n\nExtends {}", chain.join(" → ")));
+    }
+    Some(value)
+}
+
+/// The keyword that declares a declaration, such as `concept` or `scalar`.
+fn keyword(declaration: &Declaration) -> String {
+    if declaration.is_scalar_declaration() {
+        return "scalar".into();
+    }
+    declaration
+        .declaration_kind()
+        .trim_end_matches("Declaration")
+        .to_lowercase()
+}
+
+/// A property as CTO writes it, such as `o String[] tags optional`.
+fn property(property: &Property) -> String {
+    let arrow = if property.is_relationship() {
+        "-->"
+    } else {
+        "o"
+    };
+    let array = if property.is_array() { "[]" } else { "" };
+    let optional = if property.is_optional() {
+        " optional"
+    } else {
+        ""
+    };
+    format!(
+        "{arrow} {}{array} {}{optional}",
+        property.type_name().unwrap_or_default(),
+        property.name()
+    )
+}
+
+/// A map's key or value as CTO writes it.
+fn map_side(kind: &str, type_name: Option<&str>) -> String {
+    match type_name {
+        Some(name) if kind.starts_with("Relationship") => format!("--> {name}"),
+        Some(name) => format!("o {name}"),
+        None => format!(
+            "o {}",
+            kind.trim_end_matches("MapKeyType")
+                .trim_end_matches("MapValueType")
+        ),
+    }
+}
+
+/// The types `model_file` can see: the primitives, its own declarations,
+/// and what it imports, each under the name the file uses for it.
+fn type_completions(manager: &ModelManager, model_file: &ModelFile) -> Vec<CompletionItem> {
+    let primitives = PRIMITIVE_TYPES.iter().map(|&name| CompletionItem {
+        label: name.to_string(),
+        kind: Some(CompletionItemKind::KEYWORD),
+        detail: Some("primitive".into()),
+        ..CompletionItem::default()
+    });
+    let names = model_file
+        .declarations()
+        .iter()
+        .map(|declaration| declaration.name())
+        .chain(
+            model_file
+                .imports()
+                .iter()
+                .flat_map(|import| import.local_names()),
+        );
+    let types = names.filter_map(|name| {
+        let fqn = model_file.resolve_local_type(name)?;
+        let kind = match manager.get_declaration(&fqn) {
+            Ok(Declaration::Enum(_)) => CompletionItemKind::ENUM,
+            Ok(Declaration::Scalar(_)) => CompletionItemKind::TYPE_PARAMETER,
+            Ok(Declaration::Map(_)) => CompletionItemKind::STRUCT,
+            _ => CompletionItemKind::CLASS,
+        };
+        Some(CompletionItem {
+            label: name.to_string(),
+            kind: Some(kind),
+            detail: Some(fqn),
+            ..CompletionItem::default()
+        })
+    });
+    primitives.chain(types).collect()
+}
+
+/// The name of every decorator used in the workspace.
+fn decorator_completions(workspace: &Workspace) -> Vec<CompletionItem> {
+    let mut names = BTreeSet::new();
+    for (_, document) in workspace.documents() {
+        if let Some(ast) = document.ast() {
+            collect_decorator_names(ast, &mut names);
+        }
+    }
+    names
+        .into_iter()
+        .map(|name| CompletionItem {
+            label: name,
+            kind: Some(CompletionItemKind::PROPERTY),
+            detail: Some("decorator".into()),
+            ..CompletionItem::default()
+        })
+        .collect()
+}
+
+fn collect_decorator_names(value: &Value, names: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(node) => {
+            let is_decorator = node
+                .get("$class")
+                .and_then(Value::as_str)
+                .is_some_and(|class| short_name(class) == "Decorator");
+            if is_decorator && let Some(name) = node.get("name").and_then(Value::as_str) {
+                names.insert(name.to_string());
+            }
+            node.values()
+                .for_each(|child| collect_decorator_names(child, names));
+        }
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_decorator_names(item, names)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::{completion, definition, describe, rename};
+    use crate::workspace::Workspace;
+
+    const PEOPLE: &str = "namespace org.people@1.0.0
+
+abstract participant Person identified by email {
+  o String email
+  o String[] nicknames optional
+}
+
+@Staff
+participant Employee extends Person {
+  --> Person manager optional
+}
+";
+
+    const HR: &str = "namespace org.hr@1.0.0
+
+import org.people@1.0.0.{Employee, Person as Human}
+
+@Audited
+concept Review {
+  o Employee subject
+  o Human author
+}
+";
+
+    fn workspace() -> Workspace {
+        let mut workspace = Workspace::new().unwrap();
+        workspace.open("untitled:people", PEOPLE.into(), 1);
+        workspace.open("untitled:hr", HR.into(), 1);
+        workspace
+    }
+
+    fn at(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    #[test]
+    fn definitions_follow_references_and_imports() {
+        let workspace = workspace();
+
+        let location = definition(&workspace, "untitled:hr", at(6, 8)).unwrap();
+        assert_eq!(location.uri.as_str(), "untitled:people");
+        assert_eq!(location.range.start, at(8, 12));
+
+        // The alias and the name it stands for both lead to Person.
+        for position in [at(7, 5), at(2, 46), at(2, 37)] {
+            let location = definition(&workspace, "untitled:hr", position).unwrap();
+            assert_eq!(location.range.start, at(2, 21), "from {position:?}");
+        }
+        // A property name is not a type.
+        assert!(definition(&workspace, "untitled:hr", at(6, 16)).is_none());
+    }
+
+    #[test]
+    fn hovers_show_the_super_types_and_every_property() {
+        let workspace = workspace();
+        let described = describe(workspace.manager(), "org.people@1.0.0.Employee").unwrap();
+
+        assert_eq!(
+            described,
+            "```concerto
+participant org.people@1.0.0.Employee {
+  --> Person manager optional
+  o String email
+  o String[] nicknames optional
+}
+```
+
+Extends `org.people@1.0.0.Person`"
+        );
+    }
+
+    #[test]
+    fn completions_offer_visible_types_or_decorators() {
+        let workspace = workspace();
+
+        let labels = |line, character| -> Vec<String> {
+            completion(&workspace, "untitled:hr", at(line, character))
+                .into_iter()
+                .map(|item| item.label)
+                .collect()
+        };
+        let types = labels(7, 5);
+        for label in ["String", "Review", "Employee", "Human"] {
+            assert!(types.contains(&label.to_string()), "{label} in {types:?}");
+        }
+        assert!(!types.contains(&"Person".to_string()));
+        assert_eq!(labels(4, 3), ["Audited", "Staff"]);
+    }
+
+    #[test]
+    fn renames_reach_every_file_but_keep_aliases() {
+        let workspace = workspace();
+        let edit = rename(&workspace, "untitled:hr", at(7, 5), "Individual")
+            .unwrap()
+            .unwrap();
+        let changes: Vec<_> = edit.changes.unwrap().into_iter().collect();
+        let lines = |uri: &str| -> Vec<u32> {
+            let (_, edits) = changes.iter().find(|(key, _)| key.as_str() == uri).unwrap();
+            edits.iter().map(|edit| edit.range.start.line).collect()
+        };
+
+        assert_eq!(lines("untitled:people"), [2, 8, 9]);
+        assert_eq!(lines("untitled:hr"), [2]);
+
+        assert!(rename(&workspace, "untitled:hr", at(7, 5), "Employee").is_err());
+        assert!(rename(&workspace, "untitled:hr", at(7, 5), "not valid").is_err());
+    }
+
+    #[test]
+    fn renames_clash_with_names_in_importing_files() {
+        let workspace = workspace();
+
+        // org.hr imports Employee by name and declares a Review of its own.
+        let error = rename(&workspace, "untitled:people", at(8, 12), "Review").unwrap_err();
+        assert_eq!(error, "org.hr@1.0.0 already declares or imports Review");
+        // It imports Person as Human, so renaming Person to Review is fine.
+        assert!(rename(&workspace, "untitled:people", at(2, 21), "Review").is_ok());
+        // Nor may a type take the name of a type its own file imports.
+        let error = rename(&workspace, "untitled:hr", at(5, 8), "Employee").unwrap_err();
+        assert_eq!(error, "org.hr@1.0.0 already declares or imports Employee");
+    }
+}
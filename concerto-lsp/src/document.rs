@@ -0,0 +1,185 @@
+//! One open CTO file: its text, what it last parsed to, and the arithmetic
+//! between byte offsets and the positions an editor speaks in.
+
+use concerto_cto::lexer::{Token, TokenKind};
+use concerto_cto::{CtoError, ParseOptions};
+use lsp_types::{Position, Range};
+use serde_json::Value;
+
+/// A CTO file the server knows about, whether open in the editor or found on
+/// disk.
+#[derive(Debug)]
+pub struct Document {
+    text: String,
+    version: Option<i32>,
+    ast: Option<Value>,
+    error: Option<CtoError>,
+    line_starts: Vec<usize>,
+}
+
+impl Document {
+    /// Parses `text`, at the editor's `version` if it is open.
+    pub fn new(text: String, version: Option<i32>) -> Self {
+        let mut document = Self {
+            text: String::new(),
+            version: None,
+            ast: None,
+            error: None,
+            line_starts: Vec::new(),
+        };
+        document.update(text, version);
+        document
+    }
+
+    /// Replaces the text and parses it again. A text that does not parse
+    /// keeps the AST of the last one that did, so that the rest of the
+    /// workspace, and completion in this file, still have something to go on.
+    pub fn update(&mut self, text: String, version: Option<i32>) {
+        let options = ParseOptions {
+            locations: false,
+            source_uri: None,
+        };
+        match concerto_cto::parse_with(&text, &options) {
+            Ok(ast) => {
+                self.ast = Some(ast);
+                self.error = None;
+            }
+            Err(error) => self.error = Some(error),
+        }
+        self.line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+        self.text = text;
+        self.version = version;
+    }
+
+    /// The text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The editor's version of the text, if the file is open.
+    pub fn version(&self) -> Option<i32> {
+        self.version
+    }
+
+    /// The metamodel AST of the last text that parsed.
+    pub fn ast(&self) -> Option<&Value> {
+        self.ast.as_ref()
+    }
+
+    /// Why the current text does not parse, if it does not.
+    pub fn error(&self) -> Option<&CtoError> {
+        self.error.as_ref()
+    }
+
+    /// The namespace the file last declared.
+    pub fn namespace(&self) -> Option<&str> {
+        self.ast.as_ref()?.get("namespace")?.as_str()
+    }
+
+    /// The tokens of the current text, trivia left out. A text the lexer
+    /// cannot split has none.
+    pub fn tokens(&self) -> Vec<Token<'_>> {
+        concerto_cto::lexer::tokenize(&self.text)
+            .map(|tokens| {
+                tokens
+                    .into_iter()
+                    .filter(|token| !token.kind.is_trivia())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The index of the identifier token touching `position`, which may sit
+    /// just past its end, as the cursor does after typing a name.
+    pub fn identifier_at(&self, tokens: &[Token<'_>], position: Position) -> Option<usize> {
+        let offset = self.offset(position);
+        tokens.iter().position(|token| {
+            token.kind == TokenKind::Identifier
+                && token.start.offset <= offset
+                && offset <= token.end.offset
+        })
+    }
+
+    /// The byte offset of an editor position. Editors count characters in
+    /// UTF-16 code units; a position past the end of its line is the end of
+    /// the line.
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let mut units = 0;
+        for (index, c) in self.text[start..].char_indices() {
+            if units >= position.character as usize || c == '\n' {
+                return start + index;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    /// The editor position of a byte offset.
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        Position {
+            line: line as u32,
+            character: self.text[start..offset].encode_utf16().count() as u32,
+        }
+    }
+
+    /// The editor range of a token.
+    pub fn token_range(&self, token: &Token<'_>) -> Range {
+        Range {
+            start: self.position(token.start.offset),
+            end: self.position(token.end.offset),
+        }
+    }
+
+    /// The editor range of the whole of the line `offset` is on.
+    pub fn line_range(&self, offset: usize) -> Range {
+        let start = self.position(offset);
+        let end_offset = self.text[offset.min(self.text.len())..]
+            .find('\n')
+            .map_or(self.text.len(), |end| offset + end);
+        Range {
+            start: Position {
+                line: start.line,
+                character: 0,
+            },
+            end: self.position(end_offset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::Document;
+
+    #[test]
+    fn offsets_and_positions_count_utf16_units() {
+        let document = Document::new("namespace a@1.0.0\n// é𝄞\nconcept X {}\n".into(), None);
+        let at = |line, character| Position { line, character };
+
+        assert_eq!(document.offset(at(1, 3)), 18 + 3);
+        // 'é' is one unit and two bytes, '𝄞' two units and four bytes.
+        assert_eq!(document.offset(at(1, 6)), 18 + 9);
+        assert_eq!(document.position(18 + 9), at(1, 6));
+        assert_eq!(document.offset(at(1, 99)), 18 + 9);
+        assert_eq!(document.position(document.text().len()), at(3, 0));
+    }
+
+    #[test]
+    fn a_text_that_does_not_parse_keeps_the_last_ast() {
+        let mut document = Document::new("namespace a@1.0.0\nconcept X {}\n".into(), Some(1));
+        document.update("namespace a@1.0.0\nconcept X {".into(), Some(2));
+
+        assert!(document.error().is_some());
+        assert_eq!(document.namespace(), Some("a@1.0.0"));
+        assert_eq!(document.version(), Some(2));
+    }
+}
@@ -0,0 +1,25 @@
+//! # concerto-lsp
+//!
+//! A language server for Concerto models written in CTO. It speaks the
+//! Language Server Protocol over stdio, so any editor with an LSP client can
+//! run it, and offers:
+//!
+//! - **diagnostics**: syntax errors as you type, and the problems loading and
+//!   validating the models turns up, in the file they are in;
+//! - **go to definition** of a type, from a property, a super type, a
+//!   decorator argument or an import;
+//! - **hover** over a type, showing its kind, its super types and all of its
+//!   properties, inherited ones included;
+//! - **completion** of the types a file can see, and after an `@`, of the
+//!   decorators the workspace uses;
+//! - **rename** of a declaration, across every file that refers to it.
+//!
+//! The server loads every `.cto` file under the workspace folders when it
+//! starts, and keeps the files the editor has open at the editor's text.
+
+mod analysis;
+mod document;
+mod server;
+mod workspace;
+
+pub use server::{Result, capabilities, run};
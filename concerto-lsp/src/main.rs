@@ -0,0 +1,9 @@
+use lsp_server::Connection;
+
+fn main() -> concerto_lsp::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    concerto_lsp::run(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,233 @@
+//! The protocol: the capabilities the server announces, and the loop that
+//! answers requests and keeps diagnostics up to date.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::str::FromStr;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, GotoDefinition, HoverRequest, Rename, Request as RequestTrait,
+};
+use lsp_types::{
+    CompletionOptions, CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams,
+    GotoDefinitionResponse, HoverParams, HoverProviderCapability, InitializeParams, OneOf,
+    PublishDiagnosticsParams, RenameParams, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+};
+use serde::de::DeserializeOwned;
+
+use crate::analysis;
+use crate::workspace::{Workspace, uri_to_path};
+
+/// The result of running the server.
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// What the server can do.
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["@".into()]),
+            ..CompletionOptions::default()
+        }),
+        rename_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Serves `connection` until the client shuts the server down: initializes,
+/// loads the `.cto` files under the workspace folders, and then answers.
+pub fn run(connection: &Connection) -> Result<()> {
+    let params = connection.initialize(serde_json::to_value(capabilities())?)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let mut server = Server {
+        connection,
+        workspace: Workspace::new()?,
+        published: HashSet::new(),
+    };
+    for root in roots(&params) {
+        if let Some(path) = uri_to_path(root.as_str()) {
+            server.workspace.scan(&path);
+        }
+    }
+    server.workspace.reload();
+    server.publish()?;
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = server.respond(request);
+                connection.sender.send(response.into())?;
+            }
+            Message::Notification(notification) => server.notice(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// The folders the client opened: its workspace folders, or else the one
+/// root older clients send.
+fn roots(params: &InitializeParams) -> Vec<Uri> {
+    if let Some(folders) = &params.workspace_folders {
+        return folders.iter().map(|folder| folder.uri.clone()).collect();
+    }
+    #[allow(deprecated)]
+    params.root_uri.iter().cloned().collect()
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    workspace: Workspace,
+    published: HashSet<String>,
+}
+
+impl Server<'_> {
+    fn respond(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        match request.method.as_str() {
+            GotoDefinition::METHOD => {
+                self.answer(id, request, |workspace, params: GotoDefinitionParams| {
+                    let at = params.text_document_position_params;
+                    let location =
+                        analysis::definition(workspace, at.text_document.uri.as_str(), at.position);
+                    Ok(location.map(GotoDefinitionResponse::Scalar))
+                })
+            }
+            HoverRequest::METHOD => self.answer(id, request, |workspace, params: HoverParams| {
+                let at = params.text_document_position_params;
+                Ok(analysis::hover(
+                    workspace,
+                    at.text_document.uri.as_str(),
+                    at.position,
+                ))
+            }),
+            Completion::METHOD => {
+                self.answer(id, request, |workspace, params: CompletionParams| {
+                    let at = params.text_document_position;
+                    let items =
+                        analysis::completion(workspace, at.text_document.uri.as_str(), at.position);
+                    Ok(Some(CompletionResponse::Array(items)))
+                })
+            }
+            Rename::METHOD => self.answer(id, request, |workspace, params: RenameParams| {
+                let at = params.text_document_position;
+                analysis::rename(
+                    workspace,
+                    at.text_document.uri.as_str(),
+                    at.position,
+                    &params.new_name,
+                )
+            }),
+            method => Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request: {method}"),
+            ),
+        }
+    }
+
+    /// Answers a request with `handle`, or with the error it fails with.
+    fn answer<P, R>(
+        &self,
+        id: RequestId,
+        request: Request,
+        handle: impl FnOnce(&Workspace, P) -> std::result::Result<R, String>,
+    ) -> Response
+    where
+        P: DeserializeOwned,
+        R: serde::Serialize,
+    {
+        let params = match serde_json::from_value(request.params) {
+            Ok(params) => params,
+            Err(error) => {
+                return Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string());
+            }
+        };
+        match handle(&self.workspace, params) {
+            Ok(result) => Response::new_ok(id, result),
+            Err(message) => Response::new_err(id, ErrorCode::RequestFailed as i32, message),
+        }
+    }
+
+    fn notice(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.workspace
+                    .open(document.uri.as_str(), document.text, document.version);
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // Synchronization is full, so the last change is the whole text.
+                let Some(change) = params.content_changes.into_iter().last() else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+                self.workspace
+                    .open(document.uri.as_str(), change.text, document.version);
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.workspace.close(params.text_document.uri.as_str());
+            }
+            _ => return Ok(()),
+        }
+        self.publish()
+    }
+
+    /// Sends the diagnostics of every file, and clears those of any file the
+    /// server has since forgotten.
+    fn publish(&mut self) -> Result<()> {
+        let mut published = HashSet::new();
+        let mut batches: Vec<(String, Vec<_>, Option<i32>)> = self
+            .workspace
+            .diagnostics()
+            .into_iter()
+            .map(|(uri, diagnostics)| {
+                let version = self
+                    .workspace
+                    .document(uri)
+                    .and_then(|document| document.version());
+                (uri.to_string(), diagnostics, version)
+            })
+            .collect();
+        for (uri, _, _) in &batches {
+            published.insert(uri.clone());
+        }
+        batches.extend(
+            self.published
+                .difference(&published)
+                .map(|uri| (uri.clone(), Vec::new(), None)),
+        );
+        for (uri, diagnostics, version) in batches {
+            let Ok(uri) = Uri::from_str(&uri) else {
+                continue;
+            };
+            let params = PublishDiagnosticsParams {
+                uri,
+                diagnostics,
+                version,
+            };
+            let notification = Notification::new(PublishDiagnostics::METHOD.into(), params);
+            self.connection.sender.send(notification.into())?;
+        }
+        self.published = published;
+        Ok(())
+    }
+}
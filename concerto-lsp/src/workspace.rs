@@ -0,0 +1,353 @@
+//! Every CTO file the server knows about, and the models they load into.
+//!
+//! The workspace keeps a [`Document`] per file, keyed by URI: those the
+//! editor has open, at the text it sent, and the rest of the `.cto` files
+//! under the workspace folders, as they are on disk. After any change the
+//! models are loaded again from scratch, from the last AST of each file that
+//! parsed, so that one file with a typo in it does not take the types it
+//! declares away from the others.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use concerto_core::ModelManager;
+use concerto_cto::lexer::{Token, TokenKind};
+use lsp_types::{Diagnostic, DiagnosticSeverity, Range};
+
+use crate::document::Document;
+
+/// The files, and the models they load into.
+pub struct Workspace {
+    documents: BTreeMap<String, Document>,
+    manager: ModelManager,
+    owners: HashMap<String, String>,
+    problems: HashMap<String, Vec<Diagnostic>>,
+}
+
+impl Workspace {
+    /// An empty workspace.
+    pub fn new() -> concerto_core::Result<Self> {
+        Ok(Self {
+            documents: BTreeMap::new(),
+            manager: ModelManager::new()?,
+            owners: HashMap::new(),
+            problems: HashMap::new(),
+        })
+    }
+
+    /// Adds every `.cto` file under `root`, as it is on disk. Files the
+    /// editor has open keep the editor's text. Call [`Workspace::reload`]
+    /// afterwards.
+    pub fn scan(&mut self, root: &Path) {
+        let mut paths = Vec::new();
+        walk(root, &mut paths);
+        for path in paths {
+            let uri = path_to_uri(&path);
+            if self.documents.contains_key(&uri) {
+                continue;
+            }
+            if let Ok(text) = fs::read_to_string(&path) {
+                self.documents.insert(uri, Document::new(text, None));
+            }
+        }
+    }
+
+    /// The editor opened a file, or sent a new text for one.
+    pub fn open(&mut self, uri: &str, text: String, version: i32) {
+        match self.documents.get_mut(uri) {
+            Some(document) => document.update(text, Some(version)),
+            None => {
+                self.documents
+                    .insert(uri.to_string(), Document::new(text, Some(version)));
+            }
+        }
+        self.reload();
+    }
+
+    /// The editor closed a file. A file that is on disk goes back to what
+    /// the disk says; one that is not is forgotten.
+    pub fn close(&mut self, uri: &str) {
+        match uri_to_path(uri).and_then(|path| fs::read_to_string(path).ok()) {
+            Some(text) => {
+                if let Some(document) = self.documents.get_mut(uri) {
+                    document.update(text, None);
+                }
+            }
+            None => {
+                self.documents.remove(uri);
+            }
+        }
+        self.reload();
+    }
+
+    /// A file, by URI.
+    pub fn document(&self, uri: &str) -> Option<&Document> {
+        self.documents.get(uri)
+    }
+
+    /// Every file, in URI order.
+    pub fn documents(&self) -> impl Iterator<Item = (&str, &Document)> {
+        self.documents
+            .iter()
+            .map(|(uri, document)| (uri.as_str(), document))
+    }
+
+    /// The models every file loads into.
+    pub fn manager(&self) -> &ModelManager {
+        &self.manager
+    }
+
+    /// The URI of the file that declares `namespace`.
+    pub fn owner(&self, namespace: &str) -> Option<&str> {
+        self.owners.get(namespace).map(String::as_str)
+    }
+
+    /// The problems in each file, in URI order: syntax errors in the current
+    /// text, and for a file that parses, whatever loading or validating its
+    /// model turned up.
+    pub fn diagnostics(&self) -> Vec<(&str, Vec<Diagnostic>)> {
+        self.documents()
+            .map(|(uri, document)| {
+                let diagnostics = match document.error() {
+                    Some(error) => {
+                        let offset = error.position().map_or(0, |position| position.offset);
+                        let start = document.position(offset);
+                        let range = Range { start, end: start };
+                        vec![diagnostic(range, error.to_string())]
+                    }
+                    None => self.problems.get(uri).cloned().unwrap_or_default(),
+                };
+                (uri, diagnostics)
+            })
+            .collect()
+    }
+
+    /// Loads the models again, from the last AST of each file that parsed,
+    /// and validates each namespace on its own so that a problem lands in
+    /// the file it is in.
+    pub fn reload(&mut self) {
+        self.owners.clear();
+        self.problems.clear();
+        self.manager = match ModelManager::new() {
+            Ok(manager) => manager,
+            Err(_) => return,
+        };
+        for (uri, document) in &self.documents {
+            let (Some(ast), Some(namespace)) = (document.ast(), document.namespace()) else {
+                continue;
+            };
+            if let Some(owner) = self.owners.get(namespace) {
+                let message = format!("namespace {namespace} is already declared in {owner}");
+                self.problems
+                    .entry(uri.clone())
+                    .or_default()
+                    .push(diagnostic(namespace_range(document), message));
+                continue;
+            }
+            match self.manager.add_model(ast, Some(uri.clone())) {
+                Ok(()) => {
+                    self.owners.insert(namespace.to_string(), uri.clone());
+                }
+                Err(error) => self
+                    .problems
+                    .entry(uri.clone())
+                    .or_default()
+                    .push(diagnostic(
+                        locate(document, &error.to_string()),
+                        error.to_string(),
+                    )),
+            }
+        }
+        for (namespace, uri) in &self.owners {
+            if let Err(error) = self.manager.validate_model(namespace) {
+                let document = &self.documents[uri];
+                let message = error.to_string();
+                self.problems
+                    .entry(uri.clone())
+                    .or_default()
+                    .push(diagnostic(locate(document, &message), message));
+            }
+        }
+    }
+}
+
+fn diagnostic(range: Range, message: String) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("concerto".into()),
+        message,
+        ..Diagnostic::default()
+    }
+}
+
+/// Where in a file a problem described by `message` is: the name of the
+/// first declaration the message mentions, fully qualified or not, or else
+/// the namespace line.
+fn locate(document: &Document, message: &str) -> Range {
+    let tokens = document.tokens();
+    let namespace = document.namespace().unwrap_or_default();
+    let names: Vec<&Token<'_>> = declaration_names(&tokens).collect();
+    names
+        .iter()
+        .find(|token| mentions(message, &format!("{namespace}.{}", token.text)))
+        .or_else(|| names.iter().find(|token| mentions(message, token.text)))
+        .map_or_else(
+            || namespace_range(document),
+            |token| document.token_range(token),
+        )
+}
+
+fn namespace_range(document: &Document) -> Range {
+    let offset = document.text().find("namespace").unwrap_or(0);
+    document.line_range(offset)
+}
+
+/// Whether `name` appears in `message` as a whole word.
+fn mentions(message: &str, name: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    message.match_indices(name).any(|(start, _)| {
+        let before = message[..start].chars().next_back();
+        let after = message[start + name.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+/// The keywords that start a declaration and come just before its name.
+pub const DECLARATION_KEYWORDS: [&str; 8] = [
+    "concept",
+    "asset",
+    "participant",
+    "transaction",
+    "event",
+    "enum",
+    "scalar",
+    "map",
+];
+
+/// The tokens that name a declaration: each identifier just after a
+/// declaration keyword.
+pub fn declaration_names<'t, 's>(
+    tokens: &'t [Token<'s>],
+) -> impl Iterator<Item = &'t Token<'s>> + 't {
+    tokens.windows(2).filter_map(|pair| {
+        let [keyword, name] = pair else { return None };
+        (keyword.kind == TokenKind::Identifier
+            && DECLARATION_KEYWORDS.contains(&keyword.text)
+            && name.kind == TokenKind::Identifier)
+            .then_some(name)
+    })
+}
+
+fn walk(directory: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if entry.is_dir() {
+            walk(&entry, found);
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "cto")
+        {
+            found.push(entry);
+        }
+    }
+}
+
+/// The local path a `file:` URI names.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let path = path.find('/').map(|start| &path[start..])?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// The `file:` URI of a local path.
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.to_string_lossy().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Workspace, mentions, path_to_uri, uri_to_path};
+
+    #[test]
+    fn file_uris_round_trip() {
+        let path = Path::new("/home/me/my models/naïve.cto");
+        let uri = path_to_uri(path);
+
+        assert_eq!(uri, "file:///home/me/my%20models/na%C3%AFve.cto");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    }
+
+    #[test]
+    fn names_are_mentioned_as_whole_words() {
+        assert!(mentions("type Person is not declared", "Person"));
+        assert!(!mentions("type PersonName is not declared", "Person"));
+    }
+
+    #[test]
+    fn problems_land_in_the_file_they_are_in() {
+        let mut workspace = Workspace::new().unwrap();
+        workspace.open(
+            "untitled:a",
+            "namespace org.a@1.0.0\nconcept A {}\n".into(),
+            1,
+        );
+        workspace.open(
+            "untitled:b",
+            "namespace org.b@1.0.0\nconcept B extends Missing {}\n".into(),
+            1,
+        );
+        workspace.open("untitled:c", "namespace org.c@1.0.0\nconcept".into(), 1);
+
+        let diagnostics = workspace.diagnostics();
+        let [(_, a), (_, b), (_, c)] = diagnostics.as_slice() else {
+            panic!("expected three files, found {diagnostics:?}");
+        };
+        assert!(a.is_empty());
+        assert_eq!(b.len(), 1);
+        assert_eq!(b[0].range.start.line, 1);
+        assert_eq!(c.len(), 1);
+        assert!(c[0].message.starts_with("syntax error"));
+    }
+}
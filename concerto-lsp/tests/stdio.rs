@@ -0,0 +1,236 @@
+//! Drives the `concerto-lsp` binary over stdio the way an editor would: a
+//! scripted client opens a workspace of two models, edits one, and asks for
+//! definitions, hovers, completions and a rename.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{Value, json};
+
+const PEOPLE: &str = "namespace org.people@1.0.0
+
+abstract participant Person identified by email {
+  o String email
+}
+
+@Staff
+participant Employee extends Person {
+  --> Person manager optional
+}
+";
+
+const HR: &str = "namespace org.hr@1.0.0
+
+import org.people@1.0.0.{Employee, Person}
+
+concept Review {
+  o Employee subject
+  o Person author
+}
+";
+
+struct Client {
+    server: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    next_id: i64,
+    notifications: VecDeque<Value>,
+}
+
+impl Client {
+    fn start(root: &Path) -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_concerto-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .unwrap();
+        let input = server.stdin.take().unwrap();
+        let output = BufReader::new(server.stdout.take().unwrap());
+        let mut client = Self {
+            server,
+            input,
+            output,
+            next_id: 0,
+            notifications: VecDeque::new(),
+        };
+        let root = uri(root);
+        let result = client.request(
+            "initialize",
+            json!({
+                "processId": null,
+                "capabilities": {},
+                "workspaceFolders": [{ "uri": root, "name": "models" }],
+            }),
+        );
+        assert_eq!(result["capabilities"]["renameProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.input, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.input.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.output.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.output.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                assert!(message.get("error").is_none(), "{message}");
+                return message["result"].clone();
+            }
+            self.notifications.push_back(message);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// The next diagnostics published for `uri`.
+    fn diagnostics(&mut self, uri: &str) -> Vec<Value> {
+        loop {
+            let message = match self.notifications.pop_front() {
+                Some(message) => message,
+                None => self.receive(),
+            };
+            if message["method"] == "textDocument/publishDiagnostics"
+                && message["params"]["uri"] == uri
+            {
+                return message["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+    }
+
+    fn shut_down(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.server.wait().unwrap().success());
+    }
+}
+
+fn workspace(files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("concerto-lsp-{}-stdio", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    for (name, contents) in files {
+        fs::write(directory.join(name), contents).unwrap();
+    }
+    directory
+}
+
+fn uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+fn at(uri: &str, line: u32, character: u32) -> Value {
+    json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": line, "character": character },
+    })
+}
+
+#[test]
+fn a_scripted_editor_session() {
+    let root = workspace(&[("people.cto", PEOPLE), ("hr.cto", HR)]);
+    let people = uri(&root.join("people.cto"));
+    let hr = uri(&root.join("hr.cto"));
+    let mut client = Client::start(&root);
+
+    // Both files load from disk and are clean.
+    assert!(client.diagnostics(&hr).is_empty());
+    assert!(client.diagnostics(&people).is_empty());
+
+    // Opening hr.cto with a reference to a type that does not exist reports
+    // it on the line it is on; fixing it clears it.
+    let broken = HR.replace("o Person author", "o Persn author");
+    client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": hr, "languageId": "concerto", "version": 1, "text": broken } }),
+    );
+    let diagnostics = client.diagnostics(&hr);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+    assert!(
+        diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Persn")
+    );
+    client.notify(
+        "textDocument/didChange",
+        json!({ "textDocument": { "uri": hr, "version": 2 }, "contentChanges": [{ "text": HR }] }),
+    );
+    assert!(client.diagnostics(&hr).is_empty());
+
+    // Definition, from a property in one file to the declaration in the
+    // other.
+    let location = client.request("textDocument/definition", at(&hr, 5, 6));
+    assert_eq!(location["uri"], people.as_str());
+    assert_eq!(
+        location["range"]["start"],
+        json!({ "line": 7, "character": 12 })
+    );
+
+    // Hover shows the super type and the inherited property.
+    let hover = client.request("textDocument/hover", at(&hr, 5, 6));
+    let markdown = hover["contents"]["value"].as_str().unwrap();
+    assert!(markdown.contains("participant org.people@1.0.0.Employee"));
+    assert!(markdown.contains("o String email"));
+    assert!(markdown.contains("Extends `org.people@1.0.0.Person`"));
+
+    // Completion of the types hr.cto can see.
+    let items = client.request("textDocument/completion", at(&hr, 6, 5));
+    let labels: Vec<&str> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    for label in ["DateTime", "Review", "Employee", "Person"] {
+        assert!(labels.contains(&label), "{label} in {labels:?}");
+    }
+
+    // Renaming Person from its use in hr.cto edits both files.
+    let mut params = at(&hr, 6, 5);
+    params["newName"] = json!("Individual");
+    let edit = client.request("textDocument/rename", params);
+    let lines = |uri: &str| -> Vec<u64> {
+        edit["changes"][uri]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|edit| edit["range"]["start"]["line"].as_u64().unwrap())
+            .collect()
+    };
+    assert_eq!(lines(&people), [2, 7, 8]);
+    assert_eq!(lines(&hr), [2, 6]);
+
+    client.shut_down();
+    fs::remove_dir_all(root).unwrap();
+}
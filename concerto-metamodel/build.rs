@@ -17,6 +17,11 @@ const CLI_VERSION: &str = "4.0.2";
 /// Records the CLI version the current `src/metamodel` sources came from.
 const RECORD: &str = "codegen.version";
 
+/// Sources in `src/metamodel` that are maintained by hand and survive a
+/// regeneration: the serde helpers route `DateTime` values through
+/// `crate::datetime` rather than the generator's fixed format string.
+const KEPT: &[&str] = &["utils.rs"];
+
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed={RECORD}");
@@ -61,15 +66,22 @@ fn generate(staging: &Path) {
 /// The generator assumes the files sit at the crate root; they live in the
 /// metamodel module instead, so crate paths become parent module paths.
 fn install(staging: &Path, target: &Path) {
+    let kept = |path: &Path| {
+        path.file_name()
+            .is_some_and(|name| KEPT.iter().any(|k| name == *k))
+    };
     for entry in fs::read_dir(target).expect("failed to list the generated sources") {
         let path = entry.expect("failed to read a directory entry").path();
-        if path.extension().is_some_and(|extension| extension == "rs") {
+        if path.extension().is_some_and(|extension| extension == "rs") && !kept(&path) {
             fs::remove_file(&path).expect("failed to remove a stale generated source");
         }
     }
     let mut installed = Vec::new();
     for entry in fs::read_dir(staging).expect("failed to list the staging directory") {
         let path = entry.expect("failed to read a directory entry").path();
+        if kept(&path) {
+            continue;
+        }
         let source = fs::read_to_string(&path).expect("failed to read a generated source");
         let destination = target.join(path.file_name().expect("generated files have names"));
         fs::write(&destination, source.replace("use crate::", "use super::"))
@@ -0,0 +1,239 @@
+//! Parsing and formatting of Concerto `DateTime` values.
+//!
+//! Concerto writes a `DateTime` as an ISO-8601 timestamp and stores it in
+//! UTC. This module accepts the forms the JavaScript runtime accepts: a date
+//! on its own, or a date and a time with optional seconds and fraction,
+//! followed by `Z`, a `±HH:MM` or `±HHMM` offset, or nothing at all, which
+//! means UTC. The JavaScript runtime keeps millisecond precision, so any
+//! further fractional digits are dropped.
+//!
+//! Values are written back the way the JavaScript runtime writes them: in
+//! UTC, with milliseconds and a trailing `Z`, as in
+//! `2024-03-01T09:30:00.000Z`. The generated serde helpers in
+//! [`utils`](crate::utils) go through this module too, so the metamodel types
+//! and instance validation agree on what a `DateTime` is.
+
+use std::fmt;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// A string that is not a Concerto `DateTime`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDateTime {
+    /// The text that failed to parse.
+    pub text: String,
+}
+
+impl fmt::Display for InvalidDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid DateTime: {:?}", self.text)
+    }
+}
+
+impl std::error::Error for InvalidDateTime {}
+
+/// Parses a Concerto `DateTime` into UTC.
+///
+/// ```
+/// # use concerto_metamodel::datetime::{format, parse};
+/// let parsed = parse("2024-03-01T10:30:00.5+01:00").unwrap();
+/// assert_eq!(format(&parsed), "2024-03-01T09:30:00.500Z");
+/// assert!(parse("01/03/2024").is_err());
+/// ```
+pub fn parse(text: &str) -> Result<DateTime<Utc>, InvalidDateTime> {
+    let invalid = || InvalidDateTime {
+        text: text.to_string(),
+    };
+    let mut cursor = Cursor(text.as_bytes());
+
+    let year = cursor.digits(4).ok_or_else(invalid)?;
+    cursor.expect(b'-').ok_or_else(invalid)?;
+    let month = cursor.digits(2).ok_or_else(invalid)?;
+    cursor.expect(b'-').ok_or_else(invalid)?;
+    let day = cursor.digits(2).ok_or_else(invalid)?;
+    let date = NaiveDate::from_ymd_opt(year as i32, month, day).ok_or_else(invalid)?;
+    if cursor.is_empty() {
+        return Ok(Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)));
+    }
+
+    cursor.expect(b'T').ok_or_else(invalid)?;
+    let hour = cursor.digits(2).ok_or_else(invalid)?;
+    cursor.expect(b':').ok_or_else(invalid)?;
+    let minute = cursor.digits(2).ok_or_else(invalid)?;
+    let mut second = 0;
+    let mut millis = 0;
+    if cursor.expect(b':').is_some() {
+        second = cursor.digits(2).ok_or_else(invalid)?;
+        if cursor.expect(b'.').is_some() {
+            millis = cursor.fraction_millis().ok_or_else(invalid)?;
+        }
+    }
+    let time = NaiveTime::from_hms_milli_opt(hour, minute, second, millis).ok_or_else(invalid)?;
+
+    let offset = cursor.offset().ok_or_else(invalid)?;
+    if !cursor.is_empty() {
+        return Err(invalid());
+    }
+    let local = NaiveDateTime::new(date, time);
+    let utc = local
+        .checked_sub_signed(Duration::seconds(i64::from(offset.local_minus_utc())))
+        .ok_or_else(invalid)?;
+    Ok(Utc.from_utc_datetime(&utc))
+}
+
+/// Formats a `DateTime` the way Concerto serializes one: UTC, milliseconds,
+/// and a trailing `Z`.
+pub fn format(datetime: &DateTime<Utc>) -> String {
+    datetime.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// Formats a `DateTime` at a fixed offset from UTC, for callers that ask for
+/// local time, as in `2024-03-01T10:30:00.000+01:00`. An offset of zero is
+/// still written as `Z`.
+pub fn format_with_offset(datetime: &DateTime<Utc>, offset: FixedOffset) -> String {
+    if offset.local_minus_utc() == 0 {
+        return format(datetime);
+    }
+    datetime
+        .with_timezone(&offset)
+        .format("%Y-%m-%dT%H:%M:%S%.3f%:z")
+        .to_string()
+}
+
+/// Parses a `DateTime` and writes it back in the canonical UTC form.
+///
+/// ```
+/// # use concerto_metamodel::datetime::normalize;
+/// assert_eq!(normalize("2024-03-01").unwrap(), "2024-03-01T00:00:00.000Z");
+/// ```
+pub fn normalize(text: &str) -> Result<String, InvalidDateTime> {
+    parse(text).map(|datetime| format(&datetime))
+}
+
+/// Reads a timestamp from left to right.
+struct Cursor<'a>(&'a [u8]);
+
+impl Cursor<'_> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        let (&first, rest) = self.0.split_first()?;
+        if first == byte {
+            self.0 = rest;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Exactly `count` ASCII digits.
+    fn digits(&mut self, count: usize) -> Option<u32> {
+        if self.0.len() < count || !self.0[..count].iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let value = self.0[..count]
+            .iter()
+            .fold(0, |value, digit| value * 10 + u32::from(digit - b'0'));
+        self.0 = &self.0[count..];
+        Some(value)
+    }
+
+    /// One or more fractional digits, truncated to milliseconds.
+    fn fraction_millis(&mut self) -> Option<u32> {
+        let count = self.0.iter().take_while(|b| b.is_ascii_digit()).count();
+        if count == 0 {
+            return None;
+        }
+        let millis = self.0[..count]
+            .iter()
+            .chain(b"00")
+            .take(3)
+            .fold(0, |value, digit| value * 10 + u32::from(digit - b'0'));
+        self.0 = &self.0[count..];
+        Some(millis)
+    }
+
+    /// `Z`, `±HH:MM`, `±HHMM`, or nothing, which means UTC.
+    fn offset(&mut self) -> Option<FixedOffset> {
+        let sign = match self.0.first() {
+            None => return FixedOffset::east_opt(0),
+            Some(b'Z') => {
+                self.0 = &self.0[1..];
+                return FixedOffset::east_opt(0);
+            }
+            Some(b'+') => 1,
+            Some(b'-') => -1,
+            Some(_) => return None,
+        };
+        self.0 = &self.0[1..];
+        let hours = self.digits(2)?;
+        self.expect(b':');
+        let minutes = self.digits(2)?;
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60) as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_offsets_zulu_and_fractions() {
+        for (text, expected) in [
+            ("2024-03-01T09:30:00Z", "2024-03-01T09:30:00.000Z"),
+            ("2024-03-01T09:30:00.123Z", "2024-03-01T09:30:00.123Z"),
+            ("2024-03-01T09:30:00.1Z", "2024-03-01T09:30:00.100Z"),
+            ("2024-03-01T09:30:00.123456789Z", "2024-03-01T09:30:00.123Z"),
+            ("2024-03-01T10:30:00+01:00", "2024-03-01T09:30:00.000Z"),
+            ("2024-03-01T04:00:00-0530", "2024-03-01T09:30:00.000Z"),
+            ("2024-03-01T09:30:00", "2024-03-01T09:30:00.000Z"),
+            ("2024-03-01T09:30Z", "2024-03-01T09:30:00.000Z"),
+            ("2024-03-01", "2024-03-01T00:00:00.000Z"),
+        ] {
+            assert_eq!(normalize(text).as_deref(), Ok(expected), "{text}");
+        }
+    }
+
+    #[test]
+    fn an_offset_can_cross_midnight() {
+        assert_eq!(
+            normalize("2024-03-01T00:30:00+01:00").unwrap(),
+            "2024-02-29T23:30:00.000Z"
+        );
+    }
+
+    #[test]
+    fn rejects_what_is_not_iso_8601() {
+        for text in [
+            "",
+            "2024-3-1",
+            "2024-02-30",
+            "2024-03-01T25:00:00Z",
+            "2024-03-01T09:30:00.Z",
+            "2024-03-01T09:30:00+1",
+            "2024-03-01T09:30:00+24:00",
+            "2024-03-01T09:30:00Z trailing",
+            "2024-03-01 09:30:00",
+            "01/03/2024",
+        ] {
+            assert!(parse(text).is_err(), "{text:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn formats_at_an_offset() {
+        let parsed = parse("2024-03-01T09:30:00Z").unwrap();
+        let cet = FixedOffset::east_opt(3600).unwrap();
+        assert_eq!(
+            format_with_offset(&parsed, cet),
+            "2024-03-01T10:30:00.000+01:00"
+        );
+        let utc = FixedOffset::east_opt(0).unwrap();
+        assert_eq!(format_with_offset(&parsed, utc), "2024-03-01T09:30:00.000Z");
+    }
+}
@@ -1,3 +1,4 @@
+pub mod datetime;
 mod metamodel;
 
 pub use metamodel::concerto_1_0_0;
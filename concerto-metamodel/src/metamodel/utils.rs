@@ -1,216 +1,192 @@
+//! Serde helpers for the `DateTime` fields of the generated types.
+//!
+//! The generator emits a copy of this file, but the build keeps this one: it
+//! parses and formats through [`crate::datetime`], so the metamodel types
+//! accept every timestamp Concerto does and write them back in the same form.
+
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::datetime;
+
+/// A map keyed by and holding `DateTime` values.
+type DateTimeMap = HashMap<DateTime<Utc>, DateTime<Utc>>;
+
+fn parse<E: serde::de::Error>(text: &str) -> Result<DateTime<Utc>, E> {
+    datetime::parse(text).map_err(E::custom)
+}
+
 pub fn serialize_datetime_option<S>(
-    datetime: &Option<chrono::DateTime<Utc>>,
+    datetime: &Option<DateTime<Utc>>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     match datetime {
-        Some(dt) => serialize_datetime(&dt, serializer),
+        Some(dt) => serialize_datetime(dt, serializer),
         None => serializer.serialize_none(),
     }
 }
 
 pub fn deserialize_datetime_option<'de, D>(
     deserializer: D,
-) -> Result<Option<chrono::DateTime<Utc>>, D::Error>
+) -> Result<Option<DateTime<Utc>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    match deserialize_datetime(deserializer) {
-        Ok(result) => Ok(Some(result)),
-        Err(error) => Err(error),
+    match Option::<String>::deserialize(deserializer)? {
+        Some(text) => parse(&text).map(Some),
+        None => Ok(None),
     }
 }
 
-pub fn deserialize_datetime<'de, D>(deserializer: D) -> Result<chrono::DateTime<Utc>, D::Error>
+pub fn deserialize_datetime<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let datetime_str = String::deserialize(deserializer)?;
-    DateTime::parse_from_str(&datetime_str, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-        .map(|dt| dt.with_timezone(&Utc))
-        .map_err(serde::de::Error::custom)
+    parse(&String::deserialize(deserializer)?)
 }
 
-pub fn serialize_datetime<S>(
-    datetime: &chrono::DateTime<Utc>,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
+pub fn serialize_datetime<S>(datetime: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let datetime_str = datetime.format("%+").to_string();
-    serializer.serialize_str(&datetime_str)
+    serializer.serialize_str(&datetime::format(datetime))
 }
 
+// `serialize_with` hands over a reference to the field's own type.
+#[allow(clippy::ptr_arg)]
 pub fn serialize_datetime_array<S>(
-    datetime_array: &Vec<chrono::DateTime<Utc>>,
+    datetime_array: &Vec<DateTime<Utc>>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let datetime_strings: Vec<String> = datetime_array
-        .iter()
-        .map(|dt| dt.format("%+").to_string())
-        .collect();
+    let datetime_strings: Vec<String> = datetime_array.iter().map(datetime::format).collect();
     datetime_strings.serialize(serializer)
 }
 
-pub fn deserialize_datetime_array<'de, D>(
-    deserializer: D,
-) -> Result<Vec<chrono::DateTime<Utc>>, D::Error>
+pub fn deserialize_datetime_array<'de, D>(deserializer: D) -> Result<Vec<DateTime<Utc>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let datetime_strings = Vec::<String>::deserialize(deserializer)?;
-    datetime_strings
+    Vec::<String>::deserialize(deserializer)?
         .iter()
-        .map(|s| {
-            DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(serde::de::Error::custom)
-        })
+        .map(|text| parse(text))
         .collect()
 }
 
 pub fn serialize_datetime_array_option<S>(
-    datetime_array: &Option<Vec<chrono::DateTime<Utc>>>,
+    datetime_array: &Option<Vec<DateTime<Utc>>>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     match datetime_array {
-        Some(arr) => serialize_datetime_array(&arr, serializer),
+        Some(arr) => serialize_datetime_array(arr, serializer),
         None => serializer.serialize_none(),
     }
 }
 
 pub fn deserialize_datetime_array_option<'de, D>(
     deserializer: D,
-) -> Result<Option<Vec<chrono::DateTime<Utc>>>, D::Error>
+) -> Result<Option<Vec<DateTime<Utc>>>, D::Error>
 where
     D: Deserializer<'de>,
 {
     match Option::<Vec<String>>::deserialize(deserializer)? {
-        Some(datetime_strings) => {
-            let result: Result<Vec<_>, _> = datetime_strings
-                .iter()
-                .map(|s| {
-                    DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .map_err(serde::de::Error::custom)
-                })
-                .collect();
-            result.map(Some)
-        }
+        Some(texts) => texts
+            .iter()
+            .map(|text| parse(text))
+            .collect::<Result<_, _>>()
+            .map(Some),
         None => Ok(None),
     }
 }
 
 pub fn serialize_hashmap_datetime_key<S>(
-    hashmap: &std::collections::HashMap<chrono::DateTime<Utc>, String>,
+    hashmap: &HashMap<DateTime<Utc>, String>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let string_map: std::collections::HashMap<String, String> = hashmap
+    let string_map: HashMap<String, &String> = hashmap
         .iter()
-        .map(|(k, v)| (k.format("%+").to_string(), v.clone()))
+        .map(|(k, v)| (datetime::format(k), v))
         .collect();
     string_map.serialize(serializer)
 }
 
 pub fn deserialize_hashmap_datetime_key<'de, D>(
     deserializer: D,
-) -> Result<std::collections::HashMap<chrono::DateTime<Utc>, String>, D::Error>
+) -> Result<HashMap<DateTime<Utc>, String>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let string_map = std::collections::HashMap::<String, String>::deserialize(deserializer)?;
-    let mut result = std::collections::HashMap::new();
-    for (k, v) in string_map {
-        let datetime_key = DateTime::parse_from_str(&k, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-            .map(|dt| dt.with_timezone(&Utc))
-            .map_err(serde::de::Error::custom)?;
-        result.insert(datetime_key, v);
-    }
-    Ok(result)
+    HashMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(k, v)| Ok((parse(&k)?, v)))
+        .collect()
 }
 
 pub fn serialize_hashmap_datetime_value<S>(
-    hashmap: &std::collections::HashMap<String, chrono::DateTime<Utc>>,
+    hashmap: &HashMap<String, DateTime<Utc>>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let string_map: std::collections::HashMap<String, String> = hashmap
+    let string_map: HashMap<&String, String> = hashmap
         .iter()
-        .map(|(k, v)| (k.clone(), v.format("%+").to_string()))
+        .map(|(k, v)| (k, datetime::format(v)))
         .collect();
     string_map.serialize(serializer)
 }
 
 pub fn deserialize_hashmap_datetime_value<'de, D>(
     deserializer: D,
-) -> Result<std::collections::HashMap<String, chrono::DateTime<Utc>>, D::Error>
+) -> Result<HashMap<String, DateTime<Utc>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let string_map = std::collections::HashMap::<String, String>::deserialize(deserializer)?;
-    let mut result = std::collections::HashMap::new();
-    for (k, v) in string_map {
-        let datetime_value = DateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-            .map(|dt| dt.with_timezone(&Utc))
-            .map_err(serde::de::Error::custom)?;
-        result.insert(k, datetime_value);
-    }
-    Ok(result)
+    HashMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(k, v)| Ok((k, parse(&v)?)))
+        .collect()
 }
 
 pub fn serialize_hashmap_datetime_both<S>(
-    hashmap: &std::collections::HashMap<chrono::DateTime<Utc>, chrono::DateTime<Utc>>,
+    hashmap: &DateTimeMap,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let string_map: std::collections::HashMap<String, String> = hashmap
+    let string_map: HashMap<String, String> = hashmap
         .iter()
-        .map(|(k, v)| (k.format("%+").to_string(), v.format("%+").to_string()))
+        .map(|(k, v)| (datetime::format(k), datetime::format(v)))
         .collect();
     string_map.serialize(serializer)
 }
 
-pub fn deserialize_hashmap_datetime_both<'de, D>(
-    deserializer: D,
-) -> Result<std::collections::HashMap<chrono::DateTime<Utc>, chrono::DateTime<Utc>>, D::Error>
+pub fn deserialize_hashmap_datetime_both<'de, D>(deserializer: D) -> Result<DateTimeMap, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let string_map = std::collections::HashMap::<String, String>::deserialize(deserializer)?;
-    let mut result = std::collections::HashMap::new();
-    for (k, v) in string_map {
-        let datetime_key = DateTime::parse_from_str(&k, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-            .map(|dt| dt.with_timezone(&Utc))
-            .map_err(serde::de::Error::custom)?;
-        let datetime_value = DateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-            .map(|dt| dt.with_timezone(&Utc))
-            .map_err(serde::de::Error::custom)?;
-        result.insert(datetime_key, datetime_value);
-    }
-    Ok(result)
+    HashMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(k, v)| Ok((parse(&k)?, parse(&v)?)))
+        .collect()
 }
 
 pub fn serialize_hashmap_datetime_key_option<S>(
-    hashmap: &Option<std::collections::HashMap<chrono::DateTime<Utc>, String>>,
+    hashmap: &Option<HashMap<DateTime<Utc>, String>>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
@@ -224,27 +200,22 @@ where
 
 pub fn deserialize_hashmap_datetime_key_option<'de, D>(
     deserializer: D,
-) -> Result<Option<std::collections::HashMap<chrono::DateTime<Utc>, String>>, D::Error>
+) -> Result<Option<HashMap<DateTime<Utc>, String>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    match Option::<std::collections::HashMap<String, String>>::deserialize(deserializer)? {
-        Some(string_map) => {
-            let mut result = std::collections::HashMap::new();
-            for (k, v) in string_map {
-                let datetime_key = DateTime::parse_from_str(&k, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .map_err(serde::de::Error::custom)?;
-                result.insert(datetime_key, v);
-            }
-            Ok(Some(result))
-        }
+    match Option::<HashMap<String, String>>::deserialize(deserializer)? {
+        Some(string_map) => string_map
+            .into_iter()
+            .map(|(k, v)| Ok((parse(&k)?, v)))
+            .collect::<Result<_, _>>()
+            .map(Some),
         None => Ok(None),
     }
 }
 
 pub fn serialize_hashmap_datetime_value_option<S>(
-    hashmap: &Option<std::collections::HashMap<String, chrono::DateTime<Utc>>>,
+    hashmap: &Option<HashMap<String, DateTime<Utc>>>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
@@ -258,27 +229,22 @@ where
 
 pub fn deserialize_hashmap_datetime_value_option<'de, D>(
     deserializer: D,
-) -> Result<Option<std::collections::HashMap<String, chrono::DateTime<Utc>>>, D::Error>
+) -> Result<Option<HashMap<String, DateTime<Utc>>>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    match Option::<std::collections::HashMap<String, String>>::deserialize(deserializer)? {
-        Some(string_map) => {
-            let mut result = std::collections::HashMap::new();
-            for (k, v) in string_map {
-                let datetime_value = DateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .map_err(serde::de::Error::custom)?;
-                result.insert(k, datetime_value);
-            }
-            Ok(Some(result))
-        }
+    match Option::<HashMap<String, String>>::deserialize(deserializer)? {
+        Some(string_map) => string_map
+            .into_iter()
+            .map(|(k, v)| Ok((k, parse(&v)?)))
+            .collect::<Result<_, _>>()
+            .map(Some),
         None => Ok(None),
     }
 }
 
 pub fn serialize_hashmap_datetime_both_option<S>(
-    hashmap: &Option<std::collections::HashMap<chrono::DateTime<Utc>, chrono::DateTime<Utc>>>,
+    hashmap: &Option<DateTimeMap>,
     serializer: S,
 ) -> Result<S::Ok, S::Error>
 where
@@ -292,24 +258,43 @@ where
 
 pub fn deserialize_hashmap_datetime_both_option<'de, D>(
     deserializer: D,
-) -> Result<Option<std::collections::HashMap<chrono::DateTime<Utc>, chrono::DateTime<Utc>>>, D::Error>
+) -> Result<Option<DateTimeMap>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    match Option::<std::collections::HashMap<String, String>>::deserialize(deserializer)? {
-        Some(string_map) => {
-            let mut result = std::collections::HashMap::new();
-            for (k, v) in string_map {
-                let datetime_key = DateTime::parse_from_str(&k, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .map_err(serde::de::Error::custom)?;
-                let datetime_value = DateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.3f%Z")
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .map_err(serde::de::Error::custom)?;
-                result.insert(datetime_key, datetime_value);
-            }
-            Ok(Some(result))
-        }
+    match Option::<HashMap<String, String>>::deserialize(deserializer)? {
+        Some(string_map) => string_map
+            .into_iter()
+            .map(|(k, v)| Ok((parse(&k)?, parse(&v)?)))
+            .collect::<Result<_, _>>()
+            .map(Some),
         None => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Stamped {
+        #[serde(
+            serialize_with = "serialize_datetime",
+            deserialize_with = "deserialize_datetime"
+        )]
+        at: DateTime<Utc>,
+    }
+
+    #[test]
+    fn round_trips_through_the_canonical_form() {
+        let stamped: Stamped =
+            serde_json::from_str(r#"{ "at": "2024-03-01T10:30:00+01:00" }"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&stamped).unwrap(),
+            r#"{"at":"2024-03-01T09:30:00.000Z"}"#
+        );
+        assert!(serde_json::from_str::<Stamped>(r#"{ "at": "yesterday" }"#).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! Python values as JSON.
+//!
+//! Models and instances arrive either as JSON text or as the objects
+//! `json.load` would have produced: dicts with string keys, lists and tuples,
+//! strings, numbers, booleans and `None`. Anything else that behaves as an
+//! integer or a float, such as a NumPy scalar out of a data frame, is taken as
+//! one.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString, PyTuple};
+use serde_json::{Map, Number, Value};
+
+/// Reads a model or an instance: JSON text, or a value to convert.
+pub(crate) fn document(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    match value.cast::<PyString>() {
+        Ok(text) => serde_json::from_str(&text.to_cow()?)
+            .map_err(|error| PyValueError::new_err(format!("invalid JSON: {error}"))),
+        Err(_) => to_json(value),
+    }
+}
+
+/// Converts a Python value to JSON.
+pub(crate) fn to_json(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if value.is_none() {
+        return Ok(Value::Null);
+    }
+    if let Ok(flag) = value.cast::<PyBool>() {
+        return Ok(Value::Bool(flag.is_true()));
+    }
+    if let Ok(text) = value.cast::<PyString>() {
+        return Ok(Value::String(text.to_cow()?.into_owned()));
+    }
+    if let Ok(dict) = value.cast::<PyDict>() {
+        let mut object = Map::with_capacity(dict.len());
+        for (key, item) in dict.iter() {
+            let key = key.cast::<PyString>().map_err(|_| {
+                PyTypeError::new_err(format!(
+                    "object keys must be strings, not {}",
+                    type_name(&key)
+                ))
+            })?;
+            object.insert(key.to_cow()?.into_owned(), to_json(&item)?);
+        }
+        return Ok(Value::Object(object));
+    }
+    if let Ok(list) = value.cast::<PyList>() {
+        return list.iter().map(|item| to_json(&item)).collect();
+    }
+    if let Ok(tuple) = value.cast::<PyTuple>() {
+        return tuple.iter().map(|item| to_json(&item)).collect();
+    }
+    // Integers before floats, so that a NumPy integer, which converts to
+    // either, keeps its precision. A float is never taken as an integer.
+    if let Ok(integer) = value.extract::<i64>() {
+        return Ok(Value::from(integer));
+    }
+    if let Ok(integer) = value.extract::<u64>() {
+        return Ok(Value::from(integer));
+    }
+    if let Ok(float) = value.extract::<f64>() {
+        return Number::from_f64(float).map(Value::Number).ok_or_else(|| {
+            PyValueError::new_err(format!("{float} cannot be represented in JSON"))
+        });
+    }
+    Err(PyTypeError::new_err(format!(
+        "{} cannot be represented in JSON",
+        type_name(value)
+    )))
+}
+
+fn type_name(value: &Bound<'_, PyAny>) -> String {
+    value
+        .get_type()
+        .name()
+        .map_or_else(|_| "value".to_string(), |name| name.to_string())
+}
@@ -0,0 +1,310 @@
+//! Declarations as Python sees them.
+//!
+//! A Python object cannot borrow from the manager that loaded it, so each
+//! declaration crosses as a frozen snapshot: its names, with the types it
+//! mentions fully qualified, and the fields, members or key and value it
+//! declares. Unloading the model afterwards leaves the snapshot as it was.
+
+use concerto_core::model_util::{is_primitive_type, namespace_of, qualify};
+use concerto_core::{
+    ClassDeclaration, Declaration, ModelManager, Property, Result, ScalarDeclaration,
+};
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use pyo3::prelude::*;
+
+/// A concept, asset, participant, transaction or event.
+#[pyclass(name = "ClassDeclaration", module = "concerto", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyClassDeclaration {
+    /// The short name, such as `Person`.
+    pub name: String,
+    /// The fully-qualified name, such as `org.acme@1.0.0.Person`.
+    pub fully_qualified_name: String,
+    /// The metamodel `$class` short name, such as `ConceptDeclaration`.
+    pub kind: String,
+    /// Whether the class is abstract.
+    pub is_abstract: bool,
+    /// The type the class extends, fully qualified.
+    pub super_type: Option<String>,
+    /// The field the class is identified by.
+    pub identifier_field: Option<String>,
+    /// The fields the class declares itself, in order. Inherited fields
+    /// belong to the declarations they come from.
+    pub properties: Vec<PyProperty>,
+    /// The names of the decorators on the class.
+    pub decorators: Vec<String>,
+}
+
+/// A field of a class.
+#[pyclass(name = "Property", module = "concerto", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyProperty {
+    /// The field's name.
+    pub name: String,
+    /// The field's type: a primitive, or a declared type fully qualified.
+    pub type_name: String,
+    /// Whether the field holds an array.
+    pub is_array: bool,
+    /// Whether the field may be left out.
+    pub is_optional: bool,
+    /// Whether the field's type is a primitive.
+    pub is_primitive: bool,
+    /// Whether the field holds a relationship rather than a value.
+    pub is_relationship: bool,
+    /// The names of the decorators on the field.
+    pub decorators: Vec<String>,
+}
+
+/// A scalar: a primitive under a name of its own.
+#[pyclass(name = "ScalarDeclaration", module = "concerto", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyScalarDeclaration {
+    /// The short name.
+    pub name: String,
+    /// The fully-qualified name.
+    pub fully_qualified_name: String,
+    /// The metamodel `$class` short name, such as `StringScalar`.
+    pub kind: String,
+    /// The primitive the scalar wraps, such as `String`.
+    pub scalar_type: String,
+    /// The names of the decorators on the scalar.
+    pub decorators: Vec<String>,
+}
+
+/// An enum.
+#[pyclass(name = "EnumDeclaration", module = "concerto", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyEnumDeclaration {
+    /// The short name.
+    pub name: String,
+    /// The fully-qualified name.
+    pub fully_qualified_name: String,
+    /// The members, in order.
+    pub members: Vec<String>,
+    /// The names of the decorators on the enum.
+    pub decorators: Vec<String>,
+}
+
+/// A map.
+#[pyclass(name = "MapDeclaration", module = "concerto", frozen, get_all)]
+#[derive(Debug, Clone)]
+pub struct PyMapDeclaration {
+    /// The short name.
+    pub name: String,
+    /// The fully-qualified name.
+    pub fully_qualified_name: String,
+    /// The key type, fully qualified unless a primitive.
+    pub key_type: String,
+    /// The value type, fully qualified unless a primitive.
+    pub value_type: String,
+    /// The names of the decorators on the map.
+    pub decorators: Vec<String>,
+}
+
+#[pymethods]
+impl PyClassDeclaration {
+    /// The field the class itself declares with `name`, if any.
+    fn property(&self, name: &str) -> Option<PyProperty> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+            .cloned()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<ClassDeclaration {}>", self.fully_qualified_name)
+    }
+}
+
+#[pymethods]
+impl PyProperty {
+    fn __repr__(&self) -> String {
+        format!("<Property {}: {}>", self.name, self.type_name)
+    }
+}
+
+#[pymethods]
+impl PyScalarDeclaration {
+    fn __repr__(&self) -> String {
+        format!("<ScalarDeclaration {}>", self.fully_qualified_name)
+    }
+}
+
+#[pymethods]
+impl PyEnumDeclaration {
+    fn __repr__(&self) -> String {
+        format!("<EnumDeclaration {}>", self.fully_qualified_name)
+    }
+}
+
+#[pymethods]
+impl PyMapDeclaration {
+    fn __repr__(&self) -> String {
+        format!("<MapDeclaration {}>", self.fully_qualified_name)
+    }
+}
+
+/// The Python object for a declaration of `namespace`, resolving the names it
+/// mentions through `manager`.
+pub(crate) fn to_python(
+    py: Python<'_>,
+    manager: &ModelManager,
+    namespace: &str,
+    declaration: &Declaration,
+) -> PyResult<Py<PyAny>> {
+    let name = declaration.name().to_string();
+    let fully_qualified_name = qualify(namespace, &name);
+    let decorators = decorator_names(declaration.decorators());
+    let object = match declaration {
+        Declaration::Class(class) => {
+            Py::new(py, PyClassDeclaration::new(manager, namespace, class))?.into_any()
+        }
+        Declaration::Scalar(scalar) => {
+            Py::new(py, PyScalarDeclaration::new(namespace, scalar))?.into_any()
+        }
+        Declaration::Enum(declaration) => Py::new(
+            py,
+            PyEnumDeclaration {
+                name,
+                fully_qualified_name,
+                members: declaration
+                    .properties
+                    .iter()
+                    .map(|member| member.name.clone())
+                    .collect(),
+                decorators,
+            },
+        )?
+        .into_any(),
+        Declaration::Map(map) => Py::new(
+            py,
+            PyMapDeclaration {
+                name,
+                fully_qualified_name,
+                key_type: map_side(manager, namespace, map.key_kind(), map.key_type()),
+                value_type: map_side(manager, namespace, map.value_kind(), map.value_type()),
+                decorators,
+            },
+        )?
+        .into_any(),
+    };
+    Ok(object)
+}
+
+impl PyClassDeclaration {
+    fn new(manager: &ModelManager, namespace: &str, class: &ClassDeclaration) -> Self {
+        Self {
+            name: class.name().to_string(),
+            fully_qualified_name: qualify(namespace, class.name()),
+            kind: class.kind().declaration_kind().to_string(),
+            is_abstract: class.is_abstract(),
+            super_type: class
+                .super_type()
+                .map(|super_type| type_name(manager, namespace, super_type)),
+            identifier_field: class.identifier_field_name().map(str::to_string),
+            properties: class
+                .own_properties()
+                .iter()
+                .map(|property| PyProperty::new(manager, namespace, property))
+                .collect(),
+            decorators: decorator_names(class.decorators()),
+        }
+    }
+}
+
+impl PyProperty {
+    /// Describes a field declared in `namespace`.
+    pub(crate) fn new(manager: &ModelManager, namespace: &str, property: &Property) -> Self {
+        let type_name = match property.type_identifier() {
+            Some(type_identifier) => type_name(manager, namespace, type_identifier),
+            None => property.type_name().unwrap_or_default().to_string(),
+        };
+        Self {
+            name: property.name().to_string(),
+            type_name,
+            is_array: property.is_array(),
+            is_optional: property.is_optional(),
+            is_primitive: property.is_primitive(),
+            is_relationship: property.is_relationship(),
+            decorators: decorator_names(property.decorators()),
+        }
+    }
+}
+
+impl PyScalarDeclaration {
+    fn new(namespace: &str, scalar: &ScalarDeclaration) -> Self {
+        Self {
+            name: scalar.name().to_string(),
+            fully_qualified_name: qualify(namespace, scalar.name()),
+            kind: scalar.declaration_kind().to_string(),
+            scalar_type: scalar.scalar_type().to_string(),
+            decorators: decorator_names(scalar.decorators()),
+        }
+    }
+}
+
+/// Every field of the class `fqn`, each described in the namespace of the
+/// class that declares it.
+pub(crate) fn all_properties(manager: &ModelManager, fqn: &str) -> Result<Vec<PyProperty>> {
+    // Resolves the whole lineage first, so the walk below cannot fail.
+    let count = manager.get_all_properties(fqn)?.len();
+    let mut properties = Vec::with_capacity(count);
+    let mut next = Some(fqn.to_string());
+    while let Some(fqn) = next {
+        let Some(class) = manager.get_declaration(&fqn)?.as_class() else {
+            break;
+        };
+        let namespace = namespace_of(&fqn);
+        properties.extend(
+            class
+                .own_properties()
+                .iter()
+                .map(|property| PyProperty::new(manager, namespace, property)),
+        );
+        next = class
+            .super_type()
+            .map(|super_type| type_name(manager, namespace, super_type));
+    }
+    Ok(properties)
+}
+
+/// One side of a map: its declared type, or the primitive its `$class`
+/// names.
+fn map_side(
+    manager: &ModelManager,
+    namespace: &str,
+    kind: &str,
+    declared: Option<&mm::TypeIdentifier>,
+) -> String {
+    match declared {
+        Some(declared) => type_name(manager, namespace, declared),
+        None => ["MapKeyType", "MapValueType"]
+            .into_iter()
+            .find_map(|suffix| kind.strip_suffix(suffix))
+            .filter(|primitive| is_primitive_type(primitive))
+            .unwrap_or(kind)
+            .to_string(),
+    }
+}
+
+/// A type named in `namespace`, fully qualified if it resolves, and as
+/// written if not.
+fn type_name(
+    manager: &ModelManager,
+    namespace: &str,
+    type_identifier: &mm::TypeIdentifier,
+) -> String {
+    match &type_identifier.namespace {
+        Some(declared_in) => qualify(declared_in, &type_identifier.name),
+        None => manager
+            .resolve_type_name(namespace, &type_identifier.name)
+            .unwrap_or_else(|_| type_identifier.name.clone()),
+    }
+}
+
+fn decorator_names(decorators: &[mm::Decorator]) -> Vec<String> {
+    decorators
+        .iter()
+        .map(|decorator| decorator.name.clone())
+        .collect()
+}
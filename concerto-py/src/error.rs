@@ -0,0 +1,99 @@
+//! Errors as Python sees them.
+//!
+//! Every [`Error`] is raised as a subclass of `concerto.ConcertoError`, one
+//! per variant, so that a caller can catch exactly the failures it expects:
+//!
+//! ```python
+//! try:
+//!     manager.validate_instance(row)
+//! except concerto.InvalidInstanceError as error:
+//!     print(error.path, error.expected_type, error)
+//! ```
+//!
+//! The exception's message is the error's full text. The fields the variant
+//! carries are attributes of the exception, in snake_case, and `None` when
+//! they are unknown; the instance's `$class` is `class_name`, since `class`
+//! is a keyword.
+
+use concerto_core::ConcertoError as Error;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+
+create_exception!(
+    concerto,
+    ConcertoError,
+    PyException,
+    "The base class of the errors concerto raises."
+);
+create_exception!(
+    concerto,
+    TypeNotFoundError,
+    ConcertoError,
+    "A type could not be resolved. Its name is `type_name`."
+);
+create_exception!(
+    concerto,
+    NamespaceNotFoundError,
+    ConcertoError,
+    "A namespace is not loaded. Its name is `namespace`."
+);
+create_exception!(
+    concerto,
+    IllegalModelError,
+    ConcertoError,
+    "A model is malformed, or clashes with one already loaded. Where is in `file_name` and `location`."
+);
+create_exception!(
+    concerto,
+    ValidationFailedError,
+    ConcertoError,
+    "The loaded models do not pass semantic validation."
+);
+create_exception!(
+    concerto,
+    InvalidInstanceError,
+    ConcertoError,
+    "An instance does not conform to its type. The offending value is at the JSON Pointer `path`, and should be an `expected_type`."
+);
+
+/// Adds the exception classes to the module.
+pub(crate) fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add("ConcertoError", py.get_type::<ConcertoError>())?;
+    module.add("TypeNotFoundError", py.get_type::<TypeNotFoundError>())?;
+    module.add(
+        "NamespaceNotFoundError",
+        py.get_type::<NamespaceNotFoundError>(),
+    )?;
+    module.add("IllegalModelError", py.get_type::<IllegalModelError>())?;
+    module.add(
+        "ValidationFailedError",
+        py.get_type::<ValidationFailedError>(),
+    )?;
+    module.add(
+        "InvalidInstanceError",
+        py.get_type::<InvalidInstanceError>(),
+    )?;
+    Ok(())
+}
+
+/// The exception raised for a core error.
+pub(crate) fn raise(py: Python<'_>, error: Error) -> PyErr {
+    let message = error.to_string();
+    let raised = match error {
+        Error::TypeNotFound { .. } => TypeNotFoundError::new_err(message),
+        Error::NamespaceNotFound { .. } => NamespaceNotFoundError::new_err(message),
+        Error::IllegalModel { .. } => IllegalModelError::new_err(message),
+        Error::ValidationFailed { .. } => ValidationFailedError::new_err(message),
+        Error::InvalidInstance { .. } => InvalidInstanceError::new_err(message),
+    };
+    let value = raised.value(py);
+    for (name, field) in error.fields() {
+        let name = if name == "class" { "class_name" } else { name };
+        if let Err(failure) = value.setattr(name, field) {
+            return failure;
+        }
+    }
+    raised
+}
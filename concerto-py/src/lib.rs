@@ -0,0 +1,326 @@
+//! # concerto-py
+//!
+//! Python bindings for [`concerto_core`], built with PyO3 and packaged with
+//! maturin as the `concerto` module, so that a dataset can be checked against
+//! a Concerto model without leaving Python:
+//!
+//! ```python
+//! import concerto
+//!
+//! manager = concerto.ModelManager()
+//! manager.add_model(model_ast)        # a JSON AST, as a dict or as text
+//! manager.validate_models()
+//! for row in rows:
+//!     try:
+//!         manager.validate_instance(row)
+//!     except concerto.InvalidInstanceError as error:
+//!         print(error.path, error)
+//! manager.get_declaration("org.acme@1.0.0.Person").properties
+//! ```
+//!
+//! Errors are raised as subclasses of `concerto.ConcertoError`, one per
+//! [`ConcertoError`](concerto_core::ConcertoError) variant; see [`error`].
+//! Declarations come back as the frozen snapshots in [`declaration`].
+//! Validation lets go of the GIL, so threads can validate against one manager
+//! side by side.
+//!
+//! `pip install .` in this directory builds the extension with maturin.
+
+pub mod convert;
+pub mod declaration;
+pub mod error;
+
+use concerto_core::{ConcertoError, ModelManager};
+use pyo3::prelude::*;
+
+pub use declaration::{
+    PyClassDeclaration, PyEnumDeclaration, PyMapDeclaration, PyProperty, PyScalarDeclaration,
+};
+
+/// A set of loaded models, as Python's `concerto.ModelManager`.
+#[pyclass(name = "ModelManager", module = "concerto")]
+#[derive(Debug)]
+pub struct PyModelManager {
+    manager: ModelManager,
+}
+
+#[pymethods]
+impl PyModelManager {
+    /// A manager with only the built-in `concerto@1.0.0` system model loaded.
+    #[new]
+    fn new(py: Python<'_>) -> PyResult<Self> {
+        Ok(Self {
+            manager: ModelManager::new().map_err(|error| error::raise(py, error))?,
+        })
+    }
+
+    /// Loads a model from its JSON AST, given as a dict or as JSON text.
+    #[pyo3(signature = (model, file_name = None))]
+    fn add_model(
+        &mut self,
+        py: Python<'_>,
+        model: &Bound<'_, PyAny>,
+        file_name: Option<String>,
+    ) -> PyResult<()> {
+        let model = convert::document(model)?;
+        self.manager
+            .add_model(&model, file_name)
+            .map_err(|error| error::raise(py, error))
+    }
+
+    /// Unloads the model for a namespace. Returns `False` if it was not
+    /// loaded, or is the system model.
+    fn remove_model(&mut self, namespace: &str) -> bool {
+        self.manager.remove_model(namespace).is_some()
+    }
+
+    /// Checks that the loaded models hang together. Raises the first problem.
+    fn validate_models(&self, py: Python<'_>) -> PyResult<()> {
+        let manager = &self.manager;
+        py.detach(|| manager.validate_models())
+            .map_err(|error| error::raise(py, error))
+    }
+
+    /// Validates an instance, as a dict or as JSON text, against the type its
+    /// `$class` names. Raises the first problem.
+    fn validate_instance(&self, py: Python<'_>, instance: &Bound<'_, PyAny>) -> PyResult<()> {
+        let instance = convert::document(instance)?;
+        let manager = &self.manager;
+        py.detach(|| manager.validate_instance(&instance))
+            .map_err(|error| error::raise(py, error))
+    }
+
+    /// Validates an instance declared to be a `type_name`, which its `$class`
+    /// may narrow to a subtype. Raises the first problem.
+    fn validate_instance_as(
+        &self,
+        py: Python<'_>,
+        type_name: &str,
+        instance: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        let instance = convert::document(instance)?;
+        let manager = &self.manager;
+        py.detach(|| manager.validate_instance_as(type_name, &instance))
+            .map_err(|error| error::raise(py, error))
+    }
+
+    /// The namespaces of the loaded models, system model included, in order.
+    fn namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = self
+            .manager
+            .model_files()
+            .map(|model_file| model_file.namespace().to_string())
+            .collect();
+        namespaces.sort();
+        namespaces
+    }
+
+    /// Every declaration of a namespace, in the order the model declares
+    /// them.
+    fn declarations(&self, py: Python<'_>, namespace: &str) -> PyResult<Vec<Py<PyAny>>> {
+        let model_file = self.manager.model_file(namespace).ok_or_else(|| {
+            error::raise(
+                py,
+                ConcertoError::NamespaceNotFound {
+                    namespace: namespace.to_string(),
+                },
+            )
+        })?;
+        model_file
+            .declarations()
+            .iter()
+            .map(|declaration| declaration::to_python(py, &self.manager, namespace, declaration))
+            .collect()
+    }
+
+    /// The declaration with a fully-qualified name: a `ClassDeclaration`,
+    /// `ScalarDeclaration`, `EnumDeclaration` or `MapDeclaration`.
+    fn get_declaration(&self, py: Python<'_>, fqn: &str) -> PyResult<Py<PyAny>> {
+        let declaration = self
+            .manager
+            .get_declaration(fqn)
+            .map_err(|error| error::raise(py, error))?;
+        let namespace = concerto_core::model_util::namespace_of(fqn);
+        declaration::to_python(py, &self.manager, namespace, declaration)
+    }
+
+    /// Every field of a class: its own, then those it inherits, nearest
+    /// first.
+    fn get_all_properties(&self, py: Python<'_>, fqn: &str) -> PyResult<Vec<PyProperty>> {
+        declaration::all_properties(&self.manager, fqn).map_err(|error| error::raise(py, error))
+    }
+
+    /// Whether a value of `sub_type` is also a valid `super_type`.
+    fn is_assignable_to(&self, py: Python<'_>, sub_type: &str, super_type: &str) -> PyResult<bool> {
+        self.manager
+            .is_assignable_to(sub_type, super_type)
+            .map_err(|error| error::raise(py, error))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<ModelManager {}>", self.namespaces().join(", "))
+    }
+}
+
+/// The `concerto` Python module.
+#[pymodule]
+fn concerto(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyModelManager>()?;
+    module.add_class::<PyClassDeclaration>()?;
+    module.add_class::<PyProperty>()?;
+    module.add_class::<PyScalarDeclaration>()?;
+    module.add_class::<PyEnumDeclaration>()?;
+    module.add_class::<PyMapDeclaration>()?;
+    error::register(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+    use serde_json::json;
+
+    /// Runs a Python script with the module imported as `concerto` and the
+    /// test model's JSON text as `MODEL`.
+    fn run(script: &CStr) {
+        let model = json!({
+            "$class": "concerto.metamodel@1.0.0.Model",
+            "namespace": "org.acme@1.0.0",
+            "declarations": [
+                { "$class": "concerto.metamodel@1.0.0.StringScalar", "name": "Email" },
+                { "$class": "concerto.metamodel@1.0.0.EnumDeclaration", "name": "Grade",
+                  "properties": [
+                    { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "JUNIOR" },
+                    { "$class": "concerto.metamodel@1.0.0.EnumProperty", "name": "SENIOR" }
+                  ] },
+                { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Person",
+                  "isAbstract": false,
+                  "decorators": [ { "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Form" } ],
+                  "properties": [
+                    { "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "email",
+                      "isArray": false, "isOptional": false,
+                      "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Email" } },
+                    { "$class": "concerto.metamodel@1.0.0.IntegerProperty", "name": "age",
+                      "isArray": false, "isOptional": true }
+                  ] },
+                { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Employee",
+                  "isAbstract": false,
+                  "superType": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Person" },
+                  "properties": [
+                    { "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "grade",
+                      "isArray": false, "isOptional": false,
+                      "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Grade" } },
+                    { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "skills",
+                      "isArray": true, "isOptional": false }
+                  ] }
+            ]
+        });
+        Python::attach(|py| {
+            let globals = PyDict::new(py);
+            globals
+                .set_item("concerto", pyo3::wrap_pymodule!(super::concerto)(py))
+                .unwrap();
+            globals.set_item("MODEL", model.to_string()).unwrap();
+            if let Err(error) = py.run(script, Some(&globals), None) {
+                error.print(py);
+                panic!("the script failed: {error}");
+            }
+        });
+    }
+
+    #[test]
+    fn instances_are_validated() {
+        run(cr#"
+import json
+
+manager = concerto.ModelManager()
+manager.add_model(json.loads(MODEL), "acme.json")
+manager.validate_models()
+manager.validate_instance({"$class": "org.acme@1.0.0.Person", "email": "ada@acme.org", "age": 36})
+manager.validate_instance('{"$class": "org.acme@1.0.0.Person", "email": "ada@acme.org"}')
+manager.validate_instance_as("org.acme@1.0.0.Person", {
+    "$class": "org.acme@1.0.0.Employee", "email": "ada@acme.org",
+    "grade": "SENIOR", "skills": ("maths", "engines"),
+})
+
+try:
+    manager.validate_instance({"$class": "org.acme@1.0.0.Person", "email": 3})
+    raise AssertionError("expected an InvalidInstanceError")
+except concerto.InvalidInstanceError as error:
+    assert isinstance(error, concerto.ConcertoError)
+    assert error.path == "/email", error.path
+    assert error.class_name == "org.acme@1.0.0.Person", error.class_name
+    assert "/email" in str(error)
+
+try:
+    manager.validate_instance({"$class": "org.acme@1.0.0.Person", "email": object()})
+    raise AssertionError("expected a TypeError")
+except TypeError:
+    pass
+"#);
+    }
+
+    #[test]
+    fn errors_map_to_exception_subclasses() {
+        run(cr#"
+manager = concerto.ModelManager()
+manager.add_model(MODEL)
+
+try:
+    manager.add_model(MODEL, "again.json")
+    raise AssertionError("expected an IllegalModelError")
+except concerto.IllegalModelError as error:
+    assert error.file_name == "again.json", error.file_name
+
+try:
+    manager.get_declaration("org.acme@1.0.0.Ghost")
+    raise AssertionError("expected a TypeNotFoundError")
+except concerto.TypeNotFoundError as error:
+    assert error.type_name == "org.acme@1.0.0.Ghost", error.type_name
+
+try:
+    manager.declarations("org.nowhere@1.0.0")
+    raise AssertionError("expected a NamespaceNotFoundError")
+except concerto.NamespaceNotFoundError as error:
+    assert error.namespace == "org.nowhere@1.0.0"
+
+assert issubclass(concerto.ValidationFailedError, concerto.ConcertoError)
+assert manager.remove_model("org.acme@1.0.0")
+assert not manager.remove_model("concerto@1.0.0")
+"#);
+    }
+
+    #[test]
+    fn declarations_are_introspected() {
+        run(cr#"
+manager = concerto.ModelManager()
+manager.add_model(MODEL)
+assert "org.acme@1.0.0" in manager.namespaces()
+
+person = manager.get_declaration("org.acme@1.0.0.Person")
+assert isinstance(person, concerto.ClassDeclaration)
+assert person.kind == "ConceptDeclaration" and person.decorators == ["Form"]
+email = person.property("email")
+assert email.type_name == "org.acme@1.0.0.Email" and not email.is_primitive
+assert person.property("age").is_optional
+
+employee = manager.get_declaration("org.acme@1.0.0.Employee")
+assert employee.super_type == "org.acme@1.0.0.Person"
+assert [p.name for p in employee.properties] == ["grade", "skills"]
+assert [p.name for p in manager.get_all_properties("org.acme@1.0.0.Employee")] == [
+    "grade", "skills", "email", "age",
+]
+assert manager.is_assignable_to("org.acme@1.0.0.Employee", "org.acme@1.0.0.Person")
+
+scalar = manager.get_declaration("org.acme@1.0.0.Email")
+assert isinstance(scalar, concerto.ScalarDeclaration)
+assert scalar.scalar_type == "String" and scalar.kind == "StringScalar"
+
+kinds = [type(d).__name__ for d in manager.declarations("org.acme@1.0.0")]
+assert kinds == ["ScalarDeclaration", "EnumDeclaration", "ClassDeclaration", "ClassDeclaration"], kinds
+assert manager.get_declaration("org.acme@1.0.0.Grade").members == ["JUNIOR", "SENIOR"]
+"#);
+    }
+}
@@ -0,0 +1,229 @@
+//! Declarations as JavaScript sees them.
+//!
+//! The core's [`Declaration`] is a Rust sum type over the generated metamodel
+//! structs, which does not cross into JavaScript well. A [`DeclarationInfo`]
+//! is the summary a form builder needs instead: what kind of declaration it
+//! is, what it extends, and its fields with their types fully qualified, or
+//! the members of an enum, or the key and value of a map.
+
+use concerto_core::introspect::declaration::MapDeclaration;
+use concerto_core::model_util::{is_primitive_type, qualify};
+use concerto_core::{ClassDeclaration, Declaration, ModelManager, Property};
+use concerto_metamodel::concerto_metamodel_1_0_0 as mm;
+use serde::Serialize;
+
+/// The JavaScript shape of a declaration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeclarationInfo {
+    /// The short name, such as `Person`.
+    pub name: String,
+    /// The fully-qualified name, such as `org.acme@1.0.0.Person`.
+    pub fully_qualified_name: String,
+    /// The metamodel `$class` short name, such as `ConceptDeclaration`.
+    pub kind: &'static str,
+    /// Whether a class is abstract. Always `false` for other declarations.
+    pub is_abstract: bool,
+    /// The type a class extends, fully qualified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub super_type: Option<String>,
+    /// The field a class is identified by.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier_field: Option<String>,
+    /// The fields a class declares itself, in order. Inherited fields belong
+    /// to the declarations they come from.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<PropertyInfo>,
+    /// The members of an enum, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+    /// The primitive a scalar wraps.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scalar_type: Option<&'static str>,
+    /// The key type of a map, fully qualified unless a primitive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<String>,
+    /// The value type of a map, fully qualified unless a primitive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<String>,
+    /// The names of the decorators on the declaration itself.
+    pub decorators: Vec<String>,
+}
+
+/// The JavaScript shape of a field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyInfo {
+    /// The field's name.
+    pub name: String,
+    /// The field's type: a primitive, or a declared type fully qualified.
+    pub type_name: String,
+    /// Whether the field holds an array.
+    pub is_array: bool,
+    /// Whether the field may be left out.
+    pub is_optional: bool,
+    /// Whether the field holds a relationship rather than a value.
+    pub is_relationship: bool,
+    /// The names of the decorators on the field.
+    pub decorators: Vec<String>,
+}
+
+impl DeclarationInfo {
+    /// Summarizes a declaration of `namespace`, resolving the names it
+    /// mentions through `manager`.
+    pub fn new(manager: &ModelManager, namespace: &str, declaration: &Declaration) -> Self {
+        let mut info = Self {
+            name: declaration.name().to_string(),
+            fully_qualified_name: qualify(namespace, declaration.name()),
+            kind: declaration.declaration_kind(),
+            is_abstract: false,
+            super_type: None,
+            identifier_field: None,
+            properties: Vec::new(),
+            members: Vec::new(),
+            scalar_type: None,
+            key_type: None,
+            value_type: None,
+            decorators: decorator_names(declaration.decorators()),
+        };
+        match declaration {
+            Declaration::Class(class) => info.describe_class(manager, namespace, class),
+            Declaration::Enum(declaration) => {
+                info.members = declaration
+                    .properties
+                    .iter()
+                    .map(|member| member.name.clone())
+                    .collect();
+            }
+            Declaration::Scalar(scalar) => info.scalar_type = Some(scalar.scalar_type()),
+            Declaration::Map(map) => info.describe_map(manager, namespace, map),
+        }
+        info
+    }
+
+    fn describe_class(
+        &mut self,
+        manager: &ModelManager,
+        namespace: &str,
+        class: &ClassDeclaration,
+    ) {
+        self.is_abstract = class.is_abstract();
+        self.super_type = class
+            .super_type()
+            .map(|super_type| type_name(manager, namespace, super_type));
+        self.identifier_field = class.identifier_field_name().map(str::to_string);
+        self.properties = class
+            .own_properties()
+            .iter()
+            .map(|property| PropertyInfo::new(manager, namespace, property))
+            .collect();
+    }
+
+    fn describe_map(&mut self, manager: &ModelManager, namespace: &str, map: &MapDeclaration) {
+        let side = |kind: &str, declared: Option<&mm::TypeIdentifier>| match declared {
+            Some(declared) => type_name(manager, namespace, declared),
+            None => ["MapKeyType", "MapValueType"]
+                .into_iter()
+                .find_map(|suffix| kind.strip_suffix(suffix))
+                .filter(|primitive| is_primitive_type(primitive))
+                .unwrap_or(kind)
+                .to_string(),
+        };
+        self.key_type = Some(side(map.key_kind(), map.key_type()));
+        self.value_type = Some(side(map.value_kind(), map.value_type()));
+    }
+}
+
+impl PropertyInfo {
+    fn new(manager: &ModelManager, namespace: &str, property: &Property) -> Self {
+        let type_name = match property.type_identifier() {
+            Some(type_identifier) => type_name(manager, namespace, type_identifier),
+            None => property.type_name().unwrap_or_default().to_string(),
+        };
+        Self {
+            name: property.name().to_string(),
+            type_name,
+            is_array: property.is_array(),
+            is_optional: property.is_optional(),
+            is_relationship: property.is_relationship(),
+            decorators: decorator_names(property.decorators()),
+        }
+    }
+}
+
+/// A type named in `namespace`, fully qualified if it resolves, and as
+/// written if not.
+fn type_name(
+    manager: &ModelManager,
+    namespace: &str,
+    type_identifier: &mm::TypeIdentifier,
+) -> String {
+    match &type_identifier.namespace {
+        Some(declared_in) => qualify(declared_in, &type_identifier.name),
+        None => manager
+            .resolve_type_name(namespace, &type_identifier.name)
+            .unwrap_or_else(|_| type_identifier.name.clone()),
+    }
+}
+
+fn decorator_names(decorators: &[mm::Decorator]) -> Vec<String> {
+    decorators
+        .iter()
+        .map(|decorator| decorator.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::ModelManager;
+    use serde_json::json;
+
+    use super::DeclarationInfo;
+
+    #[test]
+    fn a_class_is_summarized_with_qualified_types() {
+        let mut manager = ModelManager::new().unwrap();
+        manager
+            .add_model(
+                &json!({
+                    "$class": "concerto.metamodel@1.0.0.Model",
+                    "namespace": "org.acme@1.0.0",
+                    "declarations": [
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Address",
+                          "isAbstract": false, "properties": [] },
+                        { "$class": "concerto.metamodel@1.0.0.ConceptDeclaration", "name": "Person",
+                          "isAbstract": true,
+                          "decorators": [ { "$class": "concerto.metamodel@1.0.0.Decorator", "name": "Form" } ],
+                          "properties": [
+                            { "$class": "concerto.metamodel@1.0.0.StringProperty", "name": "email",
+                              "isArray": false, "isOptional": false },
+                            { "$class": "concerto.metamodel@1.0.0.ObjectProperty", "name": "homes",
+                              "isArray": true, "isOptional": true,
+                              "type": { "$class": "concerto.metamodel@1.0.0.TypeIdentifier", "name": "Address" } }
+                          ] }
+                    ]
+                }),
+                None,
+            )
+            .unwrap();
+        let model_file = manager.model_file("org.acme@1.0.0").unwrap();
+        let person = &model_file.declarations()[1];
+        let info = DeclarationInfo::new(&manager, model_file.namespace(), person);
+        assert_eq!(
+            serde_json::to_value(info).unwrap(),
+            json!({
+                "name": "Person",
+                "fullyQualifiedName": "org.acme@1.0.0.Person",
+                "kind": "ConceptDeclaration",
+                "isAbstract": true,
+                "properties": [
+                    { "name": "email", "typeName": "String", "isArray": false,
+                      "isOptional": false, "isRelationship": false, "decorators": [] },
+                    { "name": "homes", "typeName": "org.acme@1.0.0.Address", "isArray": true,
+                      "isOptional": true, "isRelationship": false, "decorators": [] }
+                ],
+                "decorators": ["Form"]
+            })
+        );
+    }
+}
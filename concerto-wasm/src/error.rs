@@ -0,0 +1,59 @@
+//! Errors as JavaScript sees them.
+//!
+//! A [`ConcertoError`](concerto_core::ConcertoError) crosses into JavaScript as a plain object rather than
+//! a string, so that a form can put a message beside the field it is about
+//! without parsing it back out of the text. The object is the error as the
+//! core serializes it: a `kind`, naming the variant, a `message`, the error's
+//! full text, and the fields the variant carries, in camelCase, left out
+//! when they are unknown:
+//!
+//! ```json
+//! { "kind": "InvalidInstance",
+//!   "message": "invalid instance at /email: The field email of ...",
+//!   "path": "/email", "expectedType": "String", "class": "org.acme@1.0.0.Person" }
+//! ```
+//!
+//! An error the bindings raise themselves, such as a model that is not JSON,
+//! is an [`ErrorInfo`] of the same shape.
+
+use serde::Serialize;
+
+/// An error raised by the bindings rather than the core, as JavaScript sees
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorInfo {
+    /// What went wrong, such as `InvalidJson`.
+    pub kind: &'static str,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl ErrorInfo {
+    pub(crate) fn new(kind: &'static str, message: String) -> Self {
+        Self { kind, message }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use concerto_core::ConcertoError;
+    use serde_json::json;
+
+    use super::ErrorInfo;
+
+    #[test]
+    fn binding_errors_look_like_core_errors() {
+        let info = ErrorInfo::new("InvalidJson", "expected value at line 1".into());
+        assert_eq!(
+            serde_json::to_value(info).unwrap(),
+            json!({ "kind": "InvalidJson", "message": "expected value at line 1" })
+        );
+        let error = ConcertoError::ValidationFailed {
+            message: "no".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(error).unwrap(),
+            json!({ "kind": "ValidationFailed", "message": "validation failed: no" })
+        );
+    }
+}
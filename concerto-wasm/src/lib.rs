@@ -0,0 +1,161 @@
+//! # concerto-wasm
+//!
+//! WebAssembly bindings for [`concerto_core`], so that a browser validates
+//! instance data by exactly the rules the server does. Built with
+//! `wasm-bindgen`, the crate exposes one class to JavaScript:
+//!
+//! ```js
+//! import { ModelManager } from "concerto-wasm";
+//!
+//! const manager = new ModelManager();
+//! manager.addModel(modelAst);            // a JSON AST, as an object or a string
+//! manager.validateModels();
+//! manager.validateInstance({ $class: "org.acme@1.0.0.Person", email: "ada@acme.org" });
+//! manager.getDeclaration("org.acme@1.0.0.Person").properties;
+//! ```
+//!
+//! A method that fails throws a plain object describing the error, with a
+//! `kind`, a `message` and, for an invalid instance, the `path` to the
+//! offending value; see [`error`]. Declarations come back as the plain
+//! objects described by [`DeclarationInfo`].
+//!
+//! The conversions live in ordinary Rust modules, and this one only wraps
+//! them, so the crate builds and tests on any target and is deployed to
+//! `wasm32-unknown-unknown`.
+
+pub mod declaration;
+pub mod error;
+
+use concerto_core::ConcertoError;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use wasm_bindgen::prelude::*;
+
+pub use declaration::{DeclarationInfo, PropertyInfo};
+pub use error::ErrorInfo;
+
+/// A [`concerto_core::ModelManager`], as JavaScript's `ModelManager`.
+#[wasm_bindgen(js_name = ModelManager)]
+#[derive(Debug)]
+pub struct WasmModelManager {
+    manager: concerto_core::ModelManager,
+}
+
+#[wasm_bindgen(js_class = ModelManager)]
+impl WasmModelManager {
+    /// A manager with only the built-in `concerto@1.0.0` system model loaded.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<WasmModelManager, JsValue> {
+        Ok(Self {
+            manager: concerto_core::ModelManager::new().map_err(throw)?,
+        })
+    }
+
+    /// Loads a model from its JSON AST, given as an object or as JSON text.
+    #[wasm_bindgen(js_name = addModel)]
+    pub fn add_model(&mut self, ast: JsValue, file_name: Option<String>) -> Result<(), JsValue> {
+        let ast: serde_json::Value = from_js(ast)?;
+        self.manager.add_model(&ast, file_name).map_err(throw)
+    }
+
+    /// Unloads the model for a namespace. Returns `false` if it was not
+    /// loaded, or is the system model.
+    #[wasm_bindgen(js_name = removeModel)]
+    pub fn remove_model(&mut self, namespace: &str) -> bool {
+        self.manager.remove_model(namespace).is_some()
+    }
+
+    /// Checks that the loaded models hang together. Throws the first problem.
+    #[wasm_bindgen(js_name = validateModels)]
+    pub fn validate_models(&self) -> Result<(), JsValue> {
+        self.manager.validate_models().map_err(throw)
+    }
+
+    /// Validates an instance against the type its `$class` names. Throws the
+    /// first problem.
+    #[wasm_bindgen(js_name = validateInstance)]
+    pub fn validate_instance(&self, instance: JsValue) -> Result<(), JsValue> {
+        let instance: serde_json::Value = from_js(instance)?;
+        self.manager.validate_instance(&instance).map_err(throw)
+    }
+
+    /// Validates an instance declared to be a `typeName`, which its `$class`
+    /// may narrow to a subtype. Throws the first problem.
+    #[wasm_bindgen(js_name = validateInstanceAs)]
+    pub fn validate_instance_as(&self, type_name: &str, instance: JsValue) -> Result<(), JsValue> {
+        let instance: serde_json::Value = from_js(instance)?;
+        self.manager
+            .validate_instance_as(type_name, &instance)
+            .map_err(throw)
+    }
+
+    /// The namespaces of the loaded models, system model included, in order.
+    pub fn namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = self
+            .manager
+            .model_files()
+            .map(|model_file| model_file.namespace().to_string())
+            .collect();
+        namespaces.sort();
+        namespaces
+    }
+
+    /// Every declaration of a namespace, in the order the model declares
+    /// them.
+    pub fn declarations(&self, namespace: &str) -> Result<JsValue, JsValue> {
+        let model_file = self.manager.model_file(namespace).ok_or_else(|| {
+            throw(ConcertoError::NamespaceNotFound {
+                namespace: namespace.to_string(),
+            })
+        })?;
+        let declarations: Vec<DeclarationInfo> = model_file
+            .declarations()
+            .iter()
+            .map(|declaration| DeclarationInfo::new(&self.manager, namespace, declaration))
+            .collect();
+        to_js(&declarations)
+    }
+
+    /// The declaration with a fully-qualified name.
+    #[wasm_bindgen(js_name = getDeclaration)]
+    pub fn get_declaration(&self, fqn: &str) -> Result<JsValue, JsValue> {
+        let declaration = self.manager.get_declaration(fqn).map_err(throw)?;
+        let namespace = concerto_core::model_util::namespace_of(fqn);
+        to_js(&DeclarationInfo::new(&self.manager, namespace, declaration))
+    }
+
+    /// Whether a value of `subType` is also a valid `superType`.
+    #[wasm_bindgen(js_name = isAssignableTo)]
+    pub fn is_assignable_to(&self, sub_type: &str, super_type: &str) -> Result<bool, JsValue> {
+        self.manager
+            .is_assignable_to(sub_type, super_type)
+            .map_err(throw)
+    }
+}
+
+/// Reads a value passed in from JavaScript: JSON text, or anything else
+/// serde can take apart.
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsValue> {
+    match value.as_string() {
+        Some(text) => serde_json::from_str(&text)
+            .map_err(|error| raise(ErrorInfo::new("InvalidJson", error.to_string()))),
+        None => serde_wasm_bindgen::from_value(value)
+            .map_err(|error| raise(ErrorInfo::new("InvalidJson", error.to_string()))),
+    }
+}
+
+/// Hands a value back to JavaScript as plain objects and arrays.
+fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsValue> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|error| raise(ErrorInfo::new("InvalidJson", error.to_string())))
+}
+
+/// The value thrown for a core error.
+fn throw(error: ConcertoError) -> JsValue {
+    to_js(&error).unwrap_or_else(|_| JsValue::from_str(&error.to_string()))
+}
+
+fn raise(info: ErrorInfo) -> JsValue {
+    to_js(&info).unwrap_or_else(|_| JsValue::from_str(&info.message))
+}